@@ -0,0 +1,38 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Blocking HTTP helper (requires the `blocking` feature)
+//!
+//! For small synchronous programs that just need to fetch something from a
+//! local daemon. [`get`] runs a current-thread Tokio runtime for the length
+//! of one request, so it must not be called from within an async runtime.
+//!
+//! ```rust,ignore
+//! let (status, body) = rigging::blocking::get("http::unix///run/app.sock/api/status")?;
+//! ```
+
+use crate::composed::{http_error, ComposedConnector};
+use crate::types::TransportError;
+use crate::TransportUrl;
+use http_body_util::BodyExt;
+use hyper::StatusCode;
+
+/// GET `url_str` over its transport and return the status and body
+///
+/// Sends a single HTTP/1.1 request with `Connection: close`. Redirects are
+/// not followed and the status is returned as is, including errors.
+pub fn get(url_str: &str) -> Result<(StatusCode, Vec<u8>), TransportError> {
+    let url = TransportUrl::parse(url_str)?;
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    runtime.block_on(fetch(&url))
+}
+
+async fn fetch(url: &TransportUrl) -> Result<(StatusCode, Vec<u8>), TransportError> {
+    let response = ComposedConnector::new().send_get(url).await?;
+    let status = response.status();
+    let body = response.into_body().collect().await.map_err(http_error)?.to_bytes();
+    Ok((status, body.to_vec()))
+}
@@ -0,0 +1,323 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Write buffering for chatty protocols
+//!
+//! Connections pass every `poll_write` straight to the socket, so a protocol
+//! sending many tiny messages pays a syscall for each. [`BufferedConnection`]
+//! collects small writes and sends them together, as decided by its
+//! [`FlushOn`] policy. Flushing always sends everything buffered and then
+//! flushes the connection, so `flush().await` still means the bytes are on
+//! their way.
+//!
+//! ```rust,ignore
+//! let mut conn = connector
+//!     .connect("unix:///run/rpc.sock")
+//!     .await?
+//!     .with_write_buffer(FlushOn::PerMessage);
+//!
+//! conn.write_all(&(frame.len() as u32).to_be_bytes()).await?;
+//! conn.write_all(&frame).await?;
+//! conn.flush().await?; // one write for the whole message
+//! ```
+
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// Default buffer capacity
+pub const DEFAULT_CAPACITY: usize = 8 * 1024;
+
+/// When a [`BufferedConnection`] sends what it has buffered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FlushOn {
+    /// Only when flushed (typically once per message) or the buffer is full
+    #[default]
+    PerMessage,
+    /// Also as soon as this many bytes are buffered
+    Threshold(usize),
+}
+
+/// A connection that buffers writes
+pub struct BufferedConnection<S> {
+    inner: S,
+    buf: Vec<u8>,
+    /// Bytes at the front of `buf` already written to `inner`
+    written: usize,
+    capacity: usize,
+    flush_on: FlushOn,
+}
+
+impl<S> BufferedConnection<S> {
+    /// Wrap `inner`, buffering up to [`DEFAULT_CAPACITY`] bytes per message
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            buf: Vec::new(),
+            written: 0,
+            capacity: DEFAULT_CAPACITY,
+            flush_on: FlushOn::default(),
+        }
+    }
+
+    /// Buffer at most `capacity` bytes; larger writes go straight through
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Set when buffered bytes are sent
+    pub fn with_flush_on(mut self, flush_on: FlushOn) -> Self {
+        self.flush_on = flush_on;
+        self
+    }
+
+    /// The flush policy
+    pub fn flush_on(&self) -> FlushOn {
+        self.flush_on
+    }
+
+    /// Bytes written but not yet sent
+    pub fn buffered(&self) -> usize {
+        self.buf.len() - self.written
+    }
+
+    /// The wrapped connection
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// The wrapped connection, mutably
+    ///
+    /// Writing to it directly would skip anything still buffered.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Unwrap the connection, discarding unsent bytes
+    ///
+    /// Flush first to keep them.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    fn over_threshold(&self) -> bool {
+        matches!(self.flush_on, FlushOn::Threshold(threshold) if self.buffered() >= threshold)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> BufferedConnection<S> {
+    /// Send everything buffered, without flushing the connection
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        while self.written < self.buf.len() {
+            let n = ready!(Pin::new(&mut self.inner).poll_write(cx, &self.buf[self.written..]))?;
+            if n == 0 {
+                return Poll::Ready(Err(std::io::Error::new(
+                    std::io::ErrorKind::WriteZero,
+                    "connection closed with buffered data unsent",
+                )));
+            }
+            self.written += n;
+        }
+        self.buf.clear();
+        self.written = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for BufferedConnection<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_read(cx, buf)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for BufferedConnection<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        if this.over_threshold() || this.buffered() + buf.len() > this.capacity {
+            ready!(this.poll_drain(cx))?;
+        }
+        if buf.len() >= this.capacity {
+            return Pin::new(&mut this.inner).poll_write(cx, buf);
+        }
+        this.buf.extend_from_slice(buf);
+
+        if this.over_threshold() {
+            // Start sending now rather than on the next write. The bytes are
+            // already accepted, so a failure here is left for the next write
+            // or flush to report: the unsent bytes stay buffered.
+            let _ = this.poll_drain(cx);
+        }
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+impl<S: AsyncRead + Unpin> hyper::rt::Read for BufferedConnection<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        mut buf: hyper::rt::ReadBufCursor<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        let mut read_buf = tokio::io::ReadBuf::uninit(unsafe { buf.as_mut() });
+        match AsyncRead::poll_read(self, cx, &mut read_buf) {
+            Poll::Ready(Ok(())) => {
+                let filled = read_buf.filled().len();
+                unsafe { buf.advance(filled) };
+                Poll::Ready(Ok(()))
+            }
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> hyper::rt::Write for BufferedConnection<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, std::io::Error>> {
+        AsyncWrite::poll_write(self, cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), std::io::Error>> {
+        AsyncWrite::poll_flush(self, cx)
+    }
+
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        AsyncWrite::poll_shutdown(self, cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Stream that counts the writes reaching it
+    struct CountingStream {
+        inner: tokio::io::DuplexStream,
+        writes: usize,
+    }
+
+    impl AsyncWrite for CountingStream {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<std::io::Result<usize>> {
+            let poll = Pin::new(&mut self.inner).poll_write(cx, buf);
+            if poll.is_ready() {
+                self.writes += 1;
+            }
+            poll
+        }
+
+        fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Pin::new(&mut self.inner).poll_flush(cx)
+        }
+
+        fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Pin::new(&mut self.inner).poll_shutdown(cx)
+        }
+    }
+
+    fn counting() -> (CountingStream, tokio::io::DuplexStream) {
+        let (ours, peer) = tokio::io::duplex(1 << 20);
+        (CountingStream { inner: ours, writes: 0 }, peer)
+    }
+
+    /// Send 1000 length-prefixed 4-byte messages, flushing after each
+    async fn send_messages<W: AsyncWrite + Unpin>(conn: &mut W, flush_each: bool) {
+        for i in 0..1000u32 {
+            conn.write_all(&4u32.to_be_bytes()).await.unwrap();
+            conn.write_all(&i.to_be_bytes()).await.unwrap();
+            if flush_each {
+                conn.flush().await.unwrap();
+            }
+        }
+        conn.flush().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_fewer_writes_for_small_messages() {
+        let (mut direct, _peer) = counting();
+        send_messages(&mut direct, false).await;
+        assert_eq!(direct.writes, 2000);
+
+        // Flushing per message halves the writes: prefix and body go together
+        let (stream, _peer) = counting();
+        let mut per_message = BufferedConnection::new(stream);
+        send_messages(&mut per_message, true).await;
+        assert_eq!(per_message.get_ref().writes, 1000);
+
+        // A threshold batches many messages into one write
+        let (stream, mut peer) = counting();
+        let mut batched = BufferedConnection::new(stream).with_flush_on(FlushOn::Threshold(1024));
+        send_messages(&mut batched, false).await;
+        assert!(batched.get_ref().writes <= 8, "{} writes", batched.get_ref().writes);
+
+        // Nothing is lost or reordered
+        drop(batched);
+        let mut received = Vec::new();
+        peer.read_to_end(&mut received).await.unwrap();
+        assert_eq!(received.len(), 8000);
+        assert_eq!(&received[7992..], &[0, 0, 0, 4, 0, 0, 3, 231]);
+    }
+
+    #[tokio::test]
+    async fn test_flush_delivers_buffered_bytes() {
+        let (ours, mut peer) = tokio::io::duplex(64);
+        let mut conn = BufferedConnection::new(ours);
+
+        conn.write_all(b"ping").await.unwrap();
+        assert_eq!(conn.buffered(), 4);
+        conn.flush().await.unwrap();
+        assert_eq!(conn.buffered(), 0);
+
+        let mut buf = [0u8; 4];
+        peer.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+
+        // Reads pass straight through
+        peer.write_all(b"pong").await.unwrap();
+        conn.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"pong");
+    }
+
+    #[tokio::test]
+    async fn test_large_writes_bypass_buffer() {
+        let (stream, _peer) = counting();
+        let mut conn = BufferedConnection::new(stream).with_capacity(16);
+
+        conn.write_all(b"head").await.unwrap();
+        conn.write_all(&[7u8; 64]).await.unwrap();
+        assert_eq!(conn.buffered(), 0);
+        // The buffered prefix goes first, then the large write in one piece
+        assert_eq!(conn.get_ref().writes, 2);
+    }
+}
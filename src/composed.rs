@@ -6,12 +6,37 @@
 //!
 //! Allows chaining multiple transports together, e.g., Tor → Unix socket.
 
-use crate::types::{Transport, TransportChain, TransportError};
+use crate::buffered::{BufferedConnection, FlushOn};
+use crate::connector::{cancellable, AsyncReadWrite, Connector, CustomConnection};
+use crate::deadline;
+use crate::governor::{ConnectionGovernor, GovernedConnection, GovernorConfig};
+use crate::metrics::observe_connect;
+use crate::redact::RedactionPolicy;
+use crate::retry::RetryConfig;
+use crate::routing::{RoutingPolicy, RoutingRule};
+use crate::timeout::TimeoutStream;
+use crate::trace;
+use crate::types::{PeerInfo, Transport, TransportChain, TransportError};
 use crate::TransportUrl;
+use futures::future::BoxFuture;
+use hyper::{HeaderMap, Method, StatusCode, Uri};
+use hyper_util::client::legacy::connect::Connected;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+use std::collections::HashMap;
+use std::ffi::OsString;
 use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_util::sync::CancellationToken;
+use tower_layer::Layer;
+use tower_service::Service;
 
 #[cfg(feature = "unix")]
-use crate::unix_connector::UnixConnector;
+use crate::unix_connector::{SocketMapping, UnixConnector};
 
 #[cfg(feature = "tcp")]
 use crate::tcp_connector::TcpConnector;
@@ -19,79 +44,318 @@ use crate::tcp_connector::TcpConnector;
 #[cfg(feature = "tor")]
 use crate::tor_connector::TorConnector;
 
+#[cfg(feature = "i2p")]
+use crate::i2p_connector::I2pConnector;
+
+#[cfg(feature = "tls")]
+use crate::tls_connector::TlsTcpConnector;
+
+/// Environment variable overriding the default socket directory
+pub const ENV_SOCKET_DIR: &str = "RIGGING_SOCKET_DIR";
+
+/// Environment variable overriding the Tor socket path
+pub const ENV_TOR_SOCKET: &str = "RIGGING_TOR_SOCKET";
+
+/// Largest response body [`ComposedConnector::fetch`] reads (16 MiB)
+pub const DEFAULT_MAX_FETCH_BODY: usize = 16 * 1024 * 1024;
+
+/// Environment variable holding hostname to socket mappings
+///
+/// Format: "host1:/path1,host2:/path2"
+pub const ENV_SOCKET_MAP: &str = "RIGGING_SOCKET_MAP";
+
 /// Configuration for composed transports
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct ComposedConfig {
-    /// Default socket directory for Unix sockets
+    /// Default socket directory for Unix sockets (see
+    /// [`runtime_dir`](crate::runtime_dir) for the default)
     pub socket_dir: Option<PathBuf>,
     /// Path to Tor SOCKS proxy socket
     pub tor_socket: Option<PathBuf>,
+    /// Hostname to Unix socket mappings
+    ///
+    /// Hosts with an explicit mapping are routed to their socket even when
+    /// the URL does not name the Unix transport.
+    #[cfg(feature = "unix")]
+    pub socket_map: Option<SocketMapping>,
+    /// Address of the I2P router's SAM bridge (default `127.0.0.1:7656`)
+    #[cfg(feature = "i2p")]
+    pub i2p_sam: Option<std::net::SocketAddr>,
+    /// TLS settings for explicit `https::tcp`/`wss::tcp` URLs (default:
+    /// Mozilla root store)
+    #[cfg(feature = "tls")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub tls: Option<TlsTcpConnector>,
+    /// Connection limits per transport
+    ///
+    /// Limits apply to the transport a URL is actually dialed over, so a
+    /// host in the socket map counts against `Transport::Unix`.
+    pub limits: HashMap<Transport, GovernorConfig>,
+    /// Host suffixes (such as `bridge.onion`) exempt from automatic Tor
+    ///
+    /// A `.onion` URL without an explicit transport whose host is one of
+    /// these or ends in `.<suffix>` is routed like any other host name:
+    /// by the routing policy, then the socket map, then TCP. Explicit
+    /// `http::tor//` URLs always use Tor.
+    pub tor_bypass: Vec<String>,
 }
 
 impl Default for ComposedConfig {
     fn default() -> Self {
+        Self::defaults_in(crate::runtime_dir::default_socket_dir())
+    }
+}
+
+impl ComposedConfig {
+    /// The defaults, with sockets in `socket_dir`
+    fn defaults_in(socket_dir: PathBuf) -> Self {
         Self {
-            socket_dir: Some(PathBuf::from("/tmp/servo-sockets")),
-            tor_socket: Some(PathBuf::from("/tmp/servo-sockets/tor.sock")),
+            tor_socket: Some(socket_dir.join(crate::runtime_dir::TOR_SOCKET_NAME)),
+            socket_dir: Some(socket_dir),
+            #[cfg(feature = "unix")]
+            socket_map: None,
+            #[cfg(feature = "i2p")]
+            i2p_sam: None,
+            #[cfg(feature = "tls")]
+            tls: None,
+            limits: HashMap::new(),
+            tor_bypass: Vec::new(),
+        }
+    }
+
+    /// Load configuration from environment variables
+    ///
+    /// Reads `RIGGING_SOCKET_DIR`, `RIGGING_TOR_SOCKET` (or Corsair's own
+    /// `CORSAIR_SOCKET`) and `RIGGING_SOCKET_MAP`. Unset or empty variables
+    /// keep the defaults. The Tor socket is resolved as
+    /// [`TorConnector::from_env`](crate::tor_connector::TorConnector::from_env)
+    /// resolves it, so Tor routing and the direct connector agree.
+    pub fn from_env() -> Self {
+        Self::from_lookup(|name| std::env::var_os(name))
+    }
+
+    /// [`from_env`](Self::from_env), reading variables through `env`
+    pub(crate) fn from_lookup(env: impl Fn(&str) -> Option<OsString>) -> Self {
+        let env_var = |name: &str| env(name).filter(|v| !v.is_empty());
+        let mut config = Self::defaults_in(crate::runtime_dir::socket_dir_from(&env));
+
+        if let Some(dir) = env_var(ENV_SOCKET_DIR) {
+            config.socket_dir = Some(PathBuf::from(dir));
+        }
+        config.tor_socket = Some(crate::runtime_dir::tor_socket_from(&env));
+        #[cfg(feature = "unix")]
+        if let Some(map) = env_var(ENV_SOCKET_MAP) {
+            config.socket_map = Some(SocketMapping::from_env_string(&map.to_string_lossy()));
         }
+
+        config
+    }
+}
+
+/// Map a WebSocket handshake failure onto a transport error
+#[cfg(feature = "websocket")]
+fn websocket_error(e: tokio_tungstenite::tungstenite::Error) -> TransportError {
+    match e {
+        tokio_tungstenite::tungstenite::Error::Io(e) => TransportError::Io(e),
+        other => TransportError::ConnectionFailed(format!("WebSocket handshake failed: {}", other)),
     }
 }
 
+/// Map an HTTP exchange failure onto a transport error
+pub(crate) fn http_error(e: hyper::Error) -> TransportError {
+    TransportError::ConnectionFailed(format!("HTTP request failed: {}", e))
+}
+
 /// A composed connector that routes based on transport type
+///
+/// Connectors registered with [`register`](Self::register) take precedence
+/// over the built-in connector for their transport.
+///
+/// Also usable directly as a `Service<Uri>`: each URI is parsed as a
+/// [`TransportUrl`] and connected over its transport, so hosts in the
+/// socket map reach their Unix socket and everything else uses TCP (or Tor
+/// for `.onion`). Transport-prefixed URLs such as `http::unix//...` are not
+/// valid `Uri`s; use [`bound_connector`](Self::bound_connector) for those.
+#[derive(Clone)]
 pub struct ComposedConnector {
     config: ComposedConfig,
+    connectors: HashMap<Transport, Arc<dyn Connector>>,
+    /// Shared by clones, so limits hold across every copy of the connector
+    governors: HashMap<Transport, Arc<ConnectionGovernor>>,
+    policy: Option<Arc<RoutingPolicy>>,
+    redaction: RedactionPolicy,
 }
 
 impl ComposedConnector {
     /// Create a new composed connector with default config
     pub fn new() -> Self {
-        Self {
-            config: ComposedConfig::default(),
-        }
+        Self::with_config(ComposedConfig::default())
     }
 
     /// Create with custom configuration
+    ///
+    /// With the `test-util` feature the in-memory connector is registered
+    /// for [`Transport::Memory`].
     pub fn with_config(config: ComposedConfig) -> Self {
-        Self { config }
+        #[allow(unused_mut)]
+        let mut connectors: HashMap<Transport, Arc<dyn Connector>> = HashMap::new();
+        #[cfg(feature = "test-util")]
+        connectors.insert(Transport::Memory, Arc::new(crate::memory_connector::MemoryConnector::new()));
+
+        let governors = config
+            .limits
+            .iter()
+            .map(|(transport, limits)| (*transport, Arc::new(ConnectionGovernor::new(limits))))
+            .collect();
+
+        Self {
+            config,
+            connectors,
+            governors,
+            policy: None,
+            redaction: RedactionPolicy::default(),
+        }
+    }
+
+    /// Choose transports for URLs that do not name one by `policy`
+    ///
+    /// Explicit-transport URLs such as `http::unix//...` are left alone,
+    /// as are hosts no rule matches. Registered connectors still take
+    /// precedence for the URL's own transport.
+    pub fn with_policy(mut self, policy: RoutingPolicy) -> Self {
+        self.policy = Some(Arc::new(policy));
+        self
+    }
+
+    /// Set how hosts and socket paths appear in the built-in connectors' logs
+    ///
+    /// Each connect attempt is logged at `info` with its transport and
+    /// target. The default, [`RedactionPolicy::Truncate`], shortens onion
+    /// addresses and keeps only socket file names. Registered connectors
+    /// do their own logging.
+    pub fn with_redaction(mut self, redaction: RedactionPolicy) -> Self {
+        self.redaction = redaction;
+        self
+    }
+
+    /// The redaction applied to connect logs
+    pub fn redaction(&self) -> RedactionPolicy {
+        self.redaction
     }
 
     /// Create a Unix-only connector
     #[cfg(feature = "unix")]
     pub fn unix<P: Into<PathBuf>>(socket_path: P) -> Self {
-        Self {
-            config: ComposedConfig {
-                socket_dir: Some(socket_path.into()),
-                tor_socket: None,
-            },
-        }
+        Self::with_config(ComposedConfig {
+            socket_dir: Some(socket_path.into()),
+            tor_socket: None,
+            ..ComposedConfig::default()
+        })
     }
 
     /// Create a Tor connector
     #[cfg(feature = "tor")]
     pub fn tor() -> Self {
-        Self {
-            config: ComposedConfig {
-                socket_dir: None,
-                tor_socket: Some(PathBuf::from("/tmp/servo-sockets/tor.sock")),
-            },
-        }
+        Self::with_config(ComposedConfig {
+            socket_dir: None,
+            tor_socket: Some(crate::runtime_dir::default_tor_socket()),
+            ..ComposedConfig::default()
+        })
+    }
+
+    /// Register a connector for a transport
+    ///
+    /// The connector overrides the built-in one for that transport, or adds
+    /// support for a transport that has no built-in connector. Registering
+    /// twice for the same transport replaces the earlier connector.
+    pub fn register(&mut self, transport: Transport, connector: Box<dyn Connector>) {
+        self.connectors.insert(transport, Arc::from(connector));
     }
 
     /// Get the appropriate connector for a URL
+    ///
+    /// Consults the routing policy, if any, before the URL's own transport.
     pub fn connector_for_url(&self, url: &TransportUrl) -> Result<ConnectorType, TransportError> {
-        match url.transport() {
+        let connector = match self.policy_rule(url) {
+            Some(rule) => self.connector_for_rule(rule, url),
+            None => self.connector_for_transport(self.url_transport(url), url),
+        };
+        connector.map(|connector| connector.with_redaction(self.redaction))
+    }
+
+    /// The URL's transport, unless it is an automatic Tor URL for a host in
+    /// [`ComposedConfig::tor_bypass`], which is treated as TCP
+    fn url_transport(&self, url: &TransportUrl) -> Transport {
+        let transport = url.transport();
+        if transport != Transport::Tor || url.is_explicit_transport() {
+            return transport;
+        }
+        let Some(host) = url.host_str() else {
+            return transport;
+        };
+        let bypassed = self.config.tor_bypass.iter().any(|suffix| {
+            let suffix = suffix.trim_start_matches('.');
+            host.len() >= suffix.len()
+                && host[host.len() - suffix.len()..].eq_ignore_ascii_case(suffix)
+                && (host.len() == suffix.len() || host.as_bytes()[host.len() - suffix.len() - 1] == b'.')
+        });
+        if bypassed {
+            Transport::Tcp
+        } else {
+            transport
+        }
+    }
+
+    /// The routing rule for an implicit-transport URL's host
+    fn policy_rule(&self, url: &TransportUrl) -> Option<&RoutingRule> {
+        if url.is_explicit_transport() {
+            return None;
+        }
+        self.policy.as_ref()?.route(url.host_str()?)
+    }
+
+    /// Connector for a URL routed by a policy rule
+    fn connector_for_rule(&self, rule: &RoutingRule, url: &TransportUrl) -> Result<ConnectorType, TransportError> {
+        match (rule.transport(), rule.socket_path()) {
+            #[cfg(feature = "unix")]
+            (Transport::Unix, Some(path)) => Ok(ConnectorType::Unix(UnixConnector::new(path))),
+            #[cfg(feature = "tor")]
+            (Transport::Tor, Some(path)) => Ok(ConnectorType::Tor(TorConnector::with_socket(path))),
+            // The rule chose TCP, so the socket map does not apply
+            #[cfg(feature = "tcp")]
+            (Transport::Tcp, _) => Ok(ConnectorType::Tcp(TcpConnector::new())),
+            (transport, _) => self.connector_for_transport(transport, url),
+        }
+    }
+
+    /// Connector for a URL whose socket is overridden by the caller
+    ///
+    /// The socket is the Corsair socket for Tor URLs, and the Unix socket to
+    /// connect to otherwise.
+    fn connector_for_socket(&self, url: &TransportUrl, path: &std::path::Path) -> Result<ConnectorType, TransportError> {
+        match self.dialed_transport(url) {
+            #[cfg(feature = "tor")]
+            Transport::Tor => Ok(ConnectorType::Tor(TorConnector::with_socket(path).with_redaction(self.redaction))),
+            #[cfg(feature = "unix")]
+            Transport::Unix | Transport::Tcp => {
+                Ok(ConnectorType::Unix(UnixConnector::new(path).with_redaction(self.redaction)))
+            }
+            other => Err(TransportError::NotAvailable(format!(
+                "a socket path cannot be used with {} URLs",
+                other.display_name()
+            ))),
+        }
+    }
+
+    /// Built-in connector for `transport`
+    fn connector_for_transport(&self, transport: Transport, url: &TransportUrl) -> Result<ConnectorType, TransportError> {
+        match transport {
             Transport::Unix => {
                 #[cfg(feature = "unix")]
                 {
-                    let socket_path = url.unix_socket_path()
-                        .map(PathBuf::from)
-                        .or_else(|| {
-                            self.config.socket_dir.as_ref().and_then(|dir| {
-                                url.host_str().map(|h| dir.join(format!("{}.sock", h)))
-                            })
-                        })
-                        .ok_or(TransportError::SocketPathNotFound)?;
-
+                    let socket_path = self.unix_socket_path(url)?;
                     Ok(ConnectorType::Unix(UnixConnector::new(socket_path)))
                 }
                 #[cfg(not(feature = "unix"))]
@@ -100,6 +364,14 @@ impl ComposedConnector {
                 }
             }
             Transport::Tcp => {
+                #[cfg(feature = "unix")]
+                if let Some(socket_path) = self.mapped_socket_path(url) {
+                    return Ok(ConnectorType::Unix(UnixConnector::new(socket_path)));
+                }
+                #[cfg(feature = "tls")]
+                if Self::layers_tls(url) {
+                    return Ok(ConnectorType::Tls(self.config.tls.clone().unwrap_or_default()));
+                }
                 #[cfg(feature = "tcp")]
                 {
                     Ok(ConnectorType::Tcp(TcpConnector::new()))
@@ -121,6 +393,18 @@ impl ComposedConnector {
                     Err(TransportError::NotAvailable("Tor not compiled".to_string()))
                 }
             }
+            Transport::I2p => {
+                #[cfg(feature = "i2p")]
+                {
+                    Ok(ConnectorType::I2p(
+                        self.config.i2p_sam.map_or_else(I2pConnector::new, I2pConnector::with_sam_addr),
+                    ))
+                }
+                #[cfg(not(feature = "i2p"))]
+                {
+                    Err(TransportError::NotAvailable("I2P not compiled".to_string()))
+                }
+            }
             Transport::NamedPipe => {
                 Err(TransportError::NotAvailable("Named pipes not yet implemented".to_string()))
             }
@@ -130,7 +414,556 @@ impl ComposedConnector {
             Transport::Quic => {
                 Err(TransportError::NotAvailable("QUIC not yet implemented".to_string()))
             }
+            Transport::Memory => {
+                // Served by the MemoryConnector registered in `with_config`
+                Err(TransportError::NotAvailable("In-memory transport requires the test-util feature".to_string()))
+            }
+        }
+    }
+
+    /// Resolve the Unix socket path for a URL
+    ///
+    /// Uses the explicit socket path when present, then the socket map, and
+    /// finally falls back to `<socket_dir>/<host>.sock`.
+    #[cfg(feature = "unix")]
+    fn unix_socket_path(&self, url: &TransportUrl) -> Result<PathBuf, TransportError> {
+        url.unix_socket_pathbuf()
+            .or_else(|| {
+                let host = url.host_str()?;
+                self.config.socket_map.as_ref()
+                    .and_then(|map| map.get_socket_path(host))
+                    .or_else(|| self.config.socket_dir.as_ref().map(|dir| dir.join(format!("{}.sock", host))))
+            })
+            .ok_or(TransportError::SocketPathNotFound)
+    }
+
+    /// Socket explicitly mapped for an implicit-transport URL's host
+    #[cfg(feature = "unix")]
+    fn mapped_socket_path(&self, url: &TransportUrl) -> Option<PathBuf> {
+        if url.is_explicit_transport() {
+            return None;
+        }
+        let host = url.host_str()?;
+        self.config.socket_map.as_ref()?.mapped_path(host).map(PathBuf::from)
+    }
+
+    /// Establish every hop of a transport chain and tear it down again
+    ///
+    /// This is a boot-time readiness gate: hops are connected in order,
+    /// outermost first, and each must succeed before `deadline`. Connections
+    /// are dropped as soon as they are established. On failure the error is
+    /// a [`TransportError::ChainHopFailed`] naming the hop that failed.
+    pub async fn validate_chain_live(
+        &self,
+        chain: &TransportChain,
+        url: &TransportUrl,
+        deadline: Instant,
+    ) -> Result<(), TransportError> {
+        if chain.is_empty() {
+            return Err(TransportError::InvalidTransport("Empty transport chain".to_string()));
+        }
+
+        let deadline = tokio::time::Instant::from_std(deadline);
+        let last = chain.len() - 1;
+
+        for (index, &transport) in chain.transports().iter().enumerate() {
+            let result = tokio::time::timeout_at(deadline, self.probe_hop(transport, url, index == last))
+                .await
+                .unwrap_or_else(|_| {
+                    Err(TransportError::Timeout(format!(
+                        "{} hop not established before deadline",
+                        transport
+                    )))
+                });
+
+            result.map_err(|e| TransportError::ChainHopFailed {
+                index,
+                transport,
+                source: Box::new(e),
+            })?;
+
+            log::debug!("Chain {} hop {} ({}) is live", chain, index, transport);
+        }
+
+        Ok(())
+    }
+
+    /// Connect a single hop of a chain and drop the connection
+    ///
+    /// Intermediate Tor hops only check that Corsair accepts connections;
+    /// the innermost hop connects to the URL's target.
+    async fn probe_hop(
+        &self,
+        transport: Transport,
+        url: &TransportUrl,
+        is_last: bool,
+    ) -> Result<(), TransportError> {
+        match transport {
+            Transport::Unix => {
+                #[cfg(feature = "unix")]
+                {
+                    let socket_path = self.unix_socket_path(url)?;
+                    UnixConnector::new(socket_path).with_redaction(self.redaction).connect().await.map(drop)
+                }
+                #[cfg(not(feature = "unix"))]
+                {
+                    Err(TransportError::NotAvailable("Unix sockets not compiled".to_string()))
+                }
+            }
+            Transport::Tcp => {
+                #[cfg(feature = "tcp")]
+                {
+                    let host = url.host_str().ok_or_else(|| {
+                        TransportError::InvalidUrl("No host".to_string())
+                    })?;
+                    TcpConnector::new()
+                        .with_redaction(self.redaction)
+                        .connect(host, url.port_or_default())
+                        .await
+                        .map(drop)
+                }
+                #[cfg(not(feature = "tcp"))]
+                {
+                    Err(TransportError::NotAvailable("TCP not compiled".to_string()))
+                }
+            }
+            Transport::Tor => {
+                #[cfg(feature = "tor")]
+                {
+                    let socket_path = self.config.tor_socket.clone()
+                        .ok_or(TransportError::TorNotAvailable)?;
+                    let connector = TorConnector::with_socket(socket_path).with_redaction(self.redaction);
+                    if !is_last {
+                        return connector.probe().await;
+                    }
+                    let host = url.host_str().ok_or_else(|| {
+                        TransportError::InvalidUrl("No host".to_string())
+                    })?;
+                    connector.connect(host, url.port_or_default()).await.map(drop)
+                }
+                #[cfg(not(feature = "tor"))]
+                {
+                    let _ = is_last;
+                    Err(TransportError::NotAvailable("Tor not compiled".to_string()))
+                }
+            }
+            other => Err(TransportError::NotAvailable(format!(
+                "{} not yet implemented",
+                other.display_name()
+            ))),
+        }
+    }
+
+    /// Connect to the first reachable of several alternative endpoints
+    ///
+    /// Each URL is tried in order until one connects. This is for fallback
+    /// endpoints (e.g. a Unix socket, then TCP), not for layering transports.
+    /// If every attempt fails, the error is a
+    /// [`TransportError::AllEndpointsFailed`] listing each attempt; the last
+    /// failure is available via [`TransportError::last_attempt`].
+    pub async fn connect_with_fallback(
+        &self,
+        urls: &[TransportUrl],
+    ) -> Result<Connection, TransportError> {
+        if urls.is_empty() {
+            return Err(TransportError::InvalidUrl("No endpoints to connect to".to_string()));
+        }
+
+        let mut attempts = Vec::with_capacity(urls.len());
+        for url in urls {
+            match self.connect_url(url).await {
+                Ok(conn) => return Ok(conn),
+                Err(e) => {
+                    log::debug!("Endpoint {} failed, trying next: {}", url, e);
+                    attempts.push((url.to_string(), e));
+                }
+            }
+        }
+
+        Err(TransportError::AllEndpointsFailed { attempts })
+    }
+
+    /// Connect to a URL, retrying with exponential backoff
+    ///
+    /// Only errors for which [`TransportError::is_retryable`] is true are
+    /// retried, so a malformed URL fails immediately. On failure the error
+    /// is a [`TransportError::RetryFailed`] holding the number of attempts
+    /// made and the last error.
+    pub async fn connect_with_retry(
+        &self,
+        url_str: &str,
+        retry: &RetryConfig,
+    ) -> Result<Connection, TransportError> {
+        let url = TransportUrl::parse(url_str)?;
+        let max_attempts = retry.max_attempts.max(1);
+
+        let mut attempt = 1;
+        loop {
+            let error = match self.connect_url(&url).await {
+                Ok(conn) => return Ok(conn),
+                Err(e) => e,
+            };
+            if attempt >= max_attempts || !error.is_retryable() {
+                return Err(TransportError::RetryFailed {
+                    attempts: attempt,
+                    source: Box::new(error),
+                });
+            }
+
+            let delay = retry.backoff(attempt);
+            log::debug!(
+                "Connect to {} failed (attempt {}/{}), retrying in {:?}: {}",
+                url, attempt, max_attempts, delay, error
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Connect to a URL, giving up when `token` is cancelled
+    ///
+    /// Returns [`TransportError::Cancelled`] as soon as the token fires,
+    /// e.g. when the user stops a page load during a slow Tor connect. Any
+    /// socket opened by the abandoned attempt is closed.
+    pub async fn connect_with_cancel(
+        &self,
+        url_str: &str,
+        token: CancellationToken,
+    ) -> Result<Connection, TransportError> {
+        let url = TransportUrl::parse(url_str)?;
+        cancellable(&token, self.connect_url(&url)).await
+    }
+
+    /// Select the connector for a URL as a cloneable tower service
+    ///
+    /// Registered connectors take precedence over built-in ones, as in
+    /// [`connect_url`](Self::connect_url). Connections count against the
+    /// transport's [limits](ComposedConfig::limits), shared with this
+    /// connector and its clones, and are traced like any other connect.
+    pub fn bound_connector(&self, url: &TransportUrl) -> Result<BoundConnector, TransportError> {
+        let target = match self.connectors.get(&self.url_transport(url)) {
+            Some(registered) => BoundTarget::Registered {
+                connector: registered.clone(),
+                url: url.clone(),
+            },
+            None => BoundTarget::Builtin(self.connector_for_url(url)?),
+        };
+        Ok(BoundConnector {
+            target,
+            governor: self.governors.get(&self.dialed_transport(url)).cloned(),
+            plan: self.plan(url).ok(),
+        })
+    }
+
+    /// Build a hyper client that connects over the transport of `url`
+    ///
+    /// Requests sent through the client are routed over the selected
+    /// transport regardless of their URI. For Unix sockets every request goes
+    /// to the socket named by `url`; for TCP and Tor the request URI's host
+    /// and port are used.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let url = TransportUrl::parse("http::unix///tmp/app.sock/")?;
+    /// let client: HyperClient<Full<Bytes>> = ComposedConnector::new().into_hyper_client(&url)?;
+    /// let response = client.get("http://localhost/api".parse()?).await?;
+    /// ```
+    pub fn into_hyper_client<B>(&self, url: &TransportUrl) -> Result<HyperClient<B>, TransportError>
+    where
+        B: hyper::body::Body + Send + 'static,
+        B::Data: Send,
+        B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        let connector = self.bound_connector(url)?;
+        Ok(Client::builder(TokioExecutor::new()).build(connector))
+    }
+
+    /// Open the transport for a WebSocket URL
+    ///
+    /// Returns the request URL and connection to pass to a WebSocket
+    /// client's handshake, e.g. `tokio_tungstenite::client_async(request,
+    /// connection)`. For Unix sockets the request URL is the downgraded
+    /// `ws://localhost/...` form. `wss` is supported where the transport
+    /// itself is local, and for other explicit `wss::` URLs with the `tls`
+    /// feature, whose connection is already TLS.
+    pub async fn websocket_stream(
+        &self,
+        url: &TransportUrl,
+    ) -> Result<(String, Connection), TransportError> {
+        if !url.is_websocket() {
+            return Err(TransportError::InvalidUrl(format!("Not a WebSocket URL: {}", url)));
+        }
+        #[cfg(feature = "tls")]
+        if url.scheme() == "wss" && Self::layers_tls(url) {
+            let connection = self.connect_url(url).await?;
+            return Ok((url.url().to_string(), connection));
+        }
+        if url.scheme() == "wss" {
+            return Err(TransportError::NotAvailable(format!(
+                "TLS for wss over {} is not supported",
+                url.transport().display_name()
+            )));
+        }
+
+        let connection = self.connect_url(url).await?;
+        Ok((url.url().to_string(), connection))
+    }
+
+    /// Open the transport for a WebSocket URL and perform the handshake
+    ///
+    /// The upgrade request targets the URL's path and query. Its `Origin`
+    /// is the request's own origin, so servers that compare `Origin` with
+    /// `Host` accept it; over a Unix socket both name `localhost`, matching
+    /// what an embedded page served from the socket would send.
+    #[cfg(feature = "websocket")]
+    pub async fn connect_websocket(
+        &self,
+        url: &TransportUrl,
+    ) -> Result<tokio_tungstenite::WebSocketStream<Connection>, TransportError> {
+        use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+        use tokio_tungstenite::tungstenite::http::{header, HeaderValue};
+
+        let (request_url, connection) = self.websocket_stream(url).await?;
+        let mut request = request_url
+            .as_str()
+            .into_client_request()
+            .map_err(websocket_error)?;
+
+        // The Host header tungstenite derives from the request URL, as an
+        // http(s) origin
+        let target = url::Url::parse(&request_url).map_err(|e| TransportError::InvalidUrl(e.to_string()))?;
+        let origin_scheme = if target.scheme() == "wss" { "https" } else { "http" };
+        let host = target.host_str().unwrap_or("localhost");
+        let origin = match target.port() {
+            Some(port) => format!("{}://{}:{}", origin_scheme, host, port),
+            None => format!("{}://{}", origin_scheme, host),
+        };
+        let origin = HeaderValue::from_str(&origin).map_err(|e| TransportError::InvalidHeader(e.to_string()))?;
+        request.headers_mut().insert(header::ORIGIN, origin);
+
+        let (stream, _response) = tokio_tungstenite::client_async(request, connection)
+            .await
+            .map_err(websocket_error)?;
+        Ok(stream)
+    }
+
+    /// Open the transport for a URL and start an HTTP/2 session on it
+    ///
+    /// Uses prior knowledge (h2c): there is no HTTP/1.1 upgrade or ALPN, so
+    /// the server must already expect HTTP/2, as RPC services on Unix
+    /// sockets or onion services commonly do. TLS connections are not
+    /// offered `h2` via ALPN either, so this is meant for cleartext
+    /// transports. The connection is driven by a task on the current
+    /// runtime; clone the returned sender to issue concurrent requests
+    /// over the one connection.
+    #[cfg(feature = "http2")]
+    pub async fn connect_h2<B>(
+        &self,
+        url: &TransportUrl,
+    ) -> Result<hyper::client::conn::http2::SendRequest<B>, TransportError>
+    where
+        B: hyper::body::Body + Unpin + Send + 'static,
+        B::Data: Send,
+        B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        let connection = self.connect_url(url).await?;
+        let (sender, driver) = hyper::client::conn::http2::handshake(TokioExecutor::new(), connection)
+            .await
+            .map_err(|e| TransportError::ConnectionFailed(format!("HTTP/2 handshake failed: {}", e)))?;
+        tokio::spawn(async move {
+            if let Err(e) = driver.await {
+                log::debug!("HTTP/2 connection closed: {}", e);
+            }
+        });
+        Ok(sender)
+    }
+
+    /// Send one HTTP/1.1 `GET` for `url` over a new connection
+    ///
+    /// The `Host` header is the URL's host (`localhost` for socket URLs)
+    /// and the connection closes after the response.
+    pub(crate) async fn send_get(
+        &self,
+        url: &TransportUrl,
+    ) -> Result<hyper::Response<hyper::body::Incoming>, TransportError> {
+        self.send_http1(url, Method::GET, HeaderMap::new(), http_body_util::Empty::<hyper::body::Bytes>::new())
+            .await
+    }
+
+    /// Send a request with a streamed body and return the streamed response
+    ///
+    /// Sends one HTTP/1.1 request over a new connection, reading `body` as
+    /// it goes out, so large payloads are never held in memory. Without a
+    /// `Content-Length` in `headers` the body is sent with
+    /// `Transfer-Encoding: chunked`. `Host` defaults to the URL's host
+    /// (`localhost` for socket URLs) and `Connection` to `close`. The
+    /// response body is read from the connection as it is consumed.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let file = tokio::fs::File::open("backup.tar").await?;
+    /// let response = connector
+    ///     .send("http::unix///run/backupd.sock/upload", Method::POST, HeaderMap::new(), file)
+    ///     .await?;
+    /// ```
+    pub async fn send<R>(
+        &self,
+        url_str: &str,
+        method: Method,
+        mut headers: HeaderMap,
+        body: R,
+    ) -> Result<hyper::Response<hyper::body::Incoming>, TransportError>
+    where
+        R: AsyncRead + Send + 'static,
+    {
+        use futures::TryStreamExt;
+        use hyper::header::{HeaderValue, CONTENT_LENGTH, TRANSFER_ENCODING};
+
+        let url = TransportUrl::parse(url_str)?;
+        if !headers.contains_key(CONTENT_LENGTH) {
+            headers.insert(TRANSFER_ENCODING, HeaderValue::from_static("chunked"));
+        }
+        let body = http_body_util::StreamBody::new(
+            tokio_util::io::ReaderStream::new(body).map_ok(hyper::body::Frame::data),
+        );
+        self.send_http1(&url, method, headers, body).await
+    }
+
+    /// Send one HTTP/1.1 request for `url` over a new connection
+    ///
+    /// `Host` and `Connection: close` are added unless `headers` has them.
+    async fn send_http1<B>(
+        &self,
+        url: &TransportUrl,
+        method: Method,
+        headers: HeaderMap,
+        body: B,
+    ) -> Result<hyper::Response<hyper::body::Incoming>, TransportError>
+    where
+        B: hyper::body::Body + Send + 'static,
+        B::Data: Send,
+        B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        use hyper::header::{CONNECTION, HOST};
+
+        let connection = self.connect_url(url).await?;
+        let (mut sender, driver) = hyper::client::conn::http1::handshake(connection)
+            .await
+            .map_err(http_error)?;
+        tokio::spawn(async move {
+            if let Err(e) = driver.await {
+                log::debug!("HTTP connection closed: {}", e);
+            }
+        });
+
+        let host = url.host_str().unwrap_or("localhost");
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", host, port),
+            None => host.to_string(),
+        };
+        let mut request = hyper::Request::builder()
+            .method(method)
+            .uri(&url.url()[url::Position::BeforePath..url::Position::AfterQuery])
+            .body(body)
+            .map_err(|e| TransportError::InvalidUrl(e.to_string()))?;
+        *request.headers_mut() = headers;
+        if !request.headers().contains_key(HOST) {
+            let host = host.parse().map_err(|_| TransportError::InvalidHeader(format!("Host: {}", host)))?;
+            request.headers_mut().insert(HOST, host);
+        }
+        request
+            .headers_mut()
+            .entry(CONNECTION)
+            .or_insert(hyper::header::HeaderValue::from_static("close"));
+        sender.send_request(request).await.map_err(http_error)
+    }
+
+    /// GET `url_str` over its transport and return the whole response
+    ///
+    /// Sends a single HTTP/1.1 request with `Connection: close` and a `Host`
+    /// of the URL's host (`localhost` for socket URLs). Redirects are not
+    /// followed and the status is returned as is, including errors. Bodies
+    /// over [`DEFAULT_MAX_FETCH_BODY`] bytes fail; use
+    /// [`fetch_with_limit`](Self::fetch_with_limit) to change the limit.
+    pub async fn fetch(&self, url_str: &str) -> Result<(StatusCode, HeaderMap, Vec<u8>), TransportError> {
+        self.fetch_with_limit(url_str, DEFAULT_MAX_FETCH_BODY).await
+    }
+
+    /// [`fetch`](Self::fetch) with a body of at most `max_body` bytes
+    ///
+    /// A larger body fails with [`TransportError::ConnectionFailed`], as
+    /// soon as its `Content-Length` or the bytes read so far exceed the limit.
+    pub async fn fetch_with_limit(
+        &self,
+        url_str: &str,
+        max_body: usize,
+    ) -> Result<(StatusCode, HeaderMap, Vec<u8>), TransportError> {
+        use http_body_util::{BodyExt, Limited};
+
+        let url = TransportUrl::parse(url_str)?;
+        let response = self.send_get(&url).await?;
+        let too_large = || TransportError::ConnectionFailed(format!("response body exceeds {} bytes", max_body));
+
+        let declared = response
+            .headers()
+            .get(hyper::header::CONTENT_LENGTH)
+            .and_then(|len| len.to_str().ok()?.parse::<u64>().ok());
+        if declared.is_some_and(|len| len > max_body as u64) {
+            return Err(too_large());
+        }
+
+        let (parts, body) = response.into_parts();
+        let body = Limited::new(body, max_body).collect().await.map_err(|e| {
+            match e.downcast::<hyper::Error>() {
+                Ok(e) => http_error(*e),
+                Err(_) => too_large(),
+            }
+        })?;
+        Ok((parts.status, parts.headers, body.to_bytes().to_vec()))
+    }
+
+    /// Report how `url_str` would be connected, without connecting
+    ///
+    /// Applies the same routing as [`connect`](Self::connect): registered
+    /// connectors, the routing policy, the socket map and the socket
+    /// directory fallback. No socket is opened and nothing is checked for
+    /// existence, so a plan does not promise the connect will succeed.
+    pub fn resolve(&self, url_str: &str) -> Result<ResolutionPlan, TransportError> {
+        self.plan(&TransportUrl::parse(url_str)?)
+    }
+
+    /// [`resolve`](Self::resolve) for a parsed URL
+    fn plan(&self, url: &TransportUrl) -> Result<ResolutionPlan, TransportError> {
+        if self.connectors.contains_key(&self.url_transport(url)) {
+            return Ok(ResolutionPlan {
+                transport: self.url_transport(url),
+                endpoint: ResolvedEndpoint::Custom,
+                daemon: None,
+            });
         }
+
+        #[allow(unused_variables)]
+        let host = || -> Result<ResolvedEndpoint, TransportError> {
+            let host = url.host_str().ok_or_else(|| TransportError::InvalidUrl("No host".to_string()))?;
+            Ok(ResolvedEndpoint::Host { host: host.to_string(), port: url.port_or_default() })
+        };
+        let (transport, endpoint, daemon) = match self.connector_for_url(url)? {
+            #[cfg(feature = "unix")]
+            ConnectorType::Unix(c) => {
+                (Transport::Unix, ResolvedEndpoint::Socket(c.socket_path().to_path_buf()), None)
+            }
+            #[cfg(feature = "tcp")]
+            ConnectorType::Tcp(_) => (Transport::Tcp, host()?, None),
+            #[cfg(feature = "tls")]
+            ConnectorType::Tls(_) => (Transport::Tcp, host()?, None),
+            #[cfg(feature = "tor")]
+            ConnectorType::Tor(c) => (Transport::Tor, host()?, Some(c.endpoint().to_string())),
+            #[cfg(feature = "i2p")]
+            ConnectorType::I2p(c) => (Transport::I2p, host()?, Some(c.sam_addr().to_string())),
+        };
+        Ok(ResolutionPlan { transport, endpoint, daemon })
     }
 
     /// Connect to a URL using the appropriate transport
@@ -139,11 +972,104 @@ impl ComposedConnector {
         self.connect_url(&url).await
     }
 
+    /// Connect to a URL, returning the connection as a trait object
+    ///
+    /// For keeping connections over different transports in one collection,
+    /// or handing one across an API that should not name [`Connection`].
+    /// Use [`connect`](Self::connect) to keep access to
+    /// [`peer_info`](Connection::peer_info) and the other inherent methods.
+    pub async fn connect_boxed(&self, url_str: &str) -> Result<Box<dyn AsyncReadWrite>, TransportError> {
+        Ok(Box::new(self.connect(url_str).await?))
+    }
+
     /// Connect to a parsed URL
+    ///
+    /// Registered connectors are consulted first; the built-in connectors
+    /// handle any transport without one. If [`ComposedConfig::limits`] has
+    /// an entry for the transport, a slot is acquired from its governor
+    /// first and the connection is returned as [`Connection::Governed`].
     pub async fn connect_url(&self, url: &TransportUrl) -> Result<Connection, TransportError> {
-        let connector = self.connector_for_url(url)?;
+        self.connect_url_with(url, ConnectOverrides::default()).await
+    }
+
+    /// Connect to a parsed URL with per-call overrides
+    ///
+    /// As [`connect_url`](Self::connect_url), but the socket path, timeout
+    /// and Tor isolation tag can be set for this call only; see
+    /// [`ConnectOverrides`]. The timeout covers waiting for a governor slot.
+    /// Registered connectors ignore the socket path and isolation tag.
+    pub async fn connect_url_with(
+        &self,
+        url: &TransportUrl,
+        overrides: ConnectOverrides,
+    ) -> Result<Connection, TransportError> {
+        let connect = async {
+            let Some(governor) = self.governors.get(&self.dialed_transport(url)) else {
+                return self.dial(url, &overrides).await;
+            };
+            let permit = governor.acquire().await?;
+            let conn = self.dial(url, &overrides).await?;
+            Ok(Connection::Governed(GovernedConnection::new(conn, permit)))
+        };
+        let connect = async {
+            match overrides.timeout {
+                Some(timeout) => tokio::time::timeout(timeout, connect).await.map_err(|_| {
+                    TransportError::Timeout(format!("connecting to {} took longer than {:?}", url, timeout))
+                })?,
+                None => connect.await,
+            }
+        };
+        trace::instrument_connect(|| self.plan_with(url, &overrides), connect).await
+    }
+
+    /// [`plan`](Self::plan) for `url` with the socket path override applied
+    fn plan_with(&self, url: &TransportUrl, overrides: &ConnectOverrides) -> Option<ResolutionPlan> {
+        let mut plan = self.plan(url).ok()?;
+        if let Some(path) = &overrides.socket_path {
+            match plan.transport {
+                Transport::Tor => plan.daemon = Some(path.display().to_string()),
+                _ => plan.endpoint = ResolvedEndpoint::Socket(path.clone()),
+            }
+        }
+        Some(plan)
+    }
+
+    /// Transport a URL will be dialed over
+    fn dialed_transport(&self, url: &TransportUrl) -> Transport {
+        let transport = self.url_transport(url);
+        if let Some(rule) = self.policy_rule(url) {
+            if !self.connectors.contains_key(&transport) {
+                return rule.transport();
+            }
+        }
+        #[cfg(feature = "unix")]
+        if !self.connectors.contains_key(&transport) && self.mapped_socket_path(url).is_some() {
+            return Transport::Unix;
+        }
+        transport
+    }
+
+    /// Open a connection without consulting the governors
+    async fn dial(&self, url: &TransportUrl, overrides: &ConnectOverrides) -> Result<Connection, TransportError> {
+        let transport = self.url_transport(url);
+        if let Some(registered) = self.connectors.get(&transport) {
+            if !registered.allows_url(url) {
+                return Err(TransportError::NotAvailable(format!(
+                    "{} connector does not allow {}",
+                    transport,
+                    url
+                )));
+            }
+            let stream = observe_connect(transport, registered.connect(url)).await?;
+            return Ok(Connection::Custom(CustomConnection::new(stream)));
+        }
+
+        let connector = match &overrides.socket_path {
+            Some(path) => self.connector_for_socket(url, path)?,
+            None => self.connector_for_url(url)?,
+        };
 
-        match connector {
+        let conn = match connector {
             #[cfg(feature = "unix")]
             ConnectorType::Unix(c) => {
                 let conn = c.connect().await?;
@@ -158,18 +1084,58 @@ impl ComposedConnector {
                 let conn = c.connect(host, port).await?;
                 Ok(Connection::Tcp(conn))
             }
+            #[cfg(feature = "tls")]
+            ConnectorType::Tls(c) => {
+                let host = url.host_str().ok_or_else(|| {
+                    TransportError::InvalidUrl("No host".to_string())
+                })?;
+                let conn = c.connect(host, url.port_or_default()).await?;
+                Ok(Connection::Tls(conn))
+            }
             #[cfg(feature = "tor")]
             ConnectorType::Tor(c) => {
                 let host = url.host_str().ok_or_else(|| {
                     TransportError::InvalidUrl("No host".to_string())
                 })?;
                 let port = url.port_or_default();
-                let conn = c.connect(host, port).await?;
+                let conn = match &overrides.isolation {
+                    Some(tag) => c.connect_isolated(host, port, tag).await?,
+                    None => c.connect(host, port).await?,
+                };
                 Ok(Connection::Tor(conn))
             }
+            #[cfg(feature = "i2p")]
+            ConnectorType::I2p(c) => {
+                let host = url.host_str().ok_or_else(|| {
+                    TransportError::InvalidUrl("No host".to_string())
+                })?;
+                let conn = c.connect(host, url.port_or_default()).await?;
+                Ok(Connection::I2p(conn))
+            }
             #[allow(unreachable_patterns)]
             _ => Err(TransportError::NotAvailable("Transport not available".to_string())),
+        }?;
+
+        // TLS to a service behind Tor, I2P or a socket that was not downgraded
+        #[cfg(feature = "tls")]
+        if Self::layers_tls(url) && !matches!(conn, Connection::Tls(_)) {
+            let tls = self.config.tls.clone().unwrap_or_default();
+            let peer = conn.peer_info();
+            return Ok(Connection::Tls(tls.handshake_url(url, conn).await?.with_peer_info(peer)));
         }
+        Ok(conn)
+    }
+
+    /// Whether the connection for `url` gets a TLS layer
+    ///
+    /// Only explicit-transport URLs that still have a secure scheme: implicit
+    /// `https://` URLs are left to callers that do their own TLS, and local
+    /// sockets are downgraded to `http` at parse time unless
+    /// [`ParseOptions::downgrade_local_tls`](crate::transport_url::ParseOptions::downgrade_local_tls)
+    /// is off.
+    #[cfg(feature = "tls")]
+    fn layers_tls(url: &TransportUrl) -> bool {
+        url.is_explicit_transport() && matches!(url.scheme(), "https" | "wss")
     }
 }
 
@@ -179,35 +1145,539 @@ impl Default for ComposedConnector {
     }
 }
 
-/// Enum of connector types
-pub enum ConnectorType {
-    #[cfg(feature = "unix")]
-    Unix(UnixConnector),
-    #[cfg(feature = "tcp")]
-    Tcp(TcpConnector),
-    #[cfg(feature = "tor")]
-    Tor(TorConnector),
+impl Service<Uri> for ComposedConnector {
+    type Response = Connection;
+    type Error = TransportError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let connector = self.clone();
+        deadline::bounded(async move {
+            let url = TransportUrl::parse(&uri.to_string())?;
+            connector.connect_url(&url).await
+        })
+    }
 }
 
-/// Enum of connection types
-pub enum Connection {
-    #[cfg(feature = "unix")]
-    Unix(crate::unix_connector::UnixConnection),
-    #[cfg(feature = "tcp")]
-    Tcp(crate::tcp_connector::TcpConnection),
-    #[cfg(feature = "tor")]
-    Tor(crate::tor_connector::TorConnection),
+/// Tower layer that opens a transport connection for each request `Uri`
+///
+/// The wrapped service receives the [`Connection`] selected by a
+/// [`ComposedConnector`], e.g. to perform a protocol handshake on it.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let service = ServiceBuilder::new()
+///     .layer(ComposedLayer::new(ComposedConnector::new()))
+///     .service(handshake);
+/// let sender = service.oneshot("http://api/".parse()?).await?;
+/// ```
+#[derive(Clone)]
+pub struct ComposedLayer {
+    connector: ComposedConnector,
 }
 
-/// Builder for transport chains
-pub struct TransportChainBuilder {
-    transports: Vec<Transport>,
-    config: ComposedConfig,
+impl ComposedLayer {
+    /// Create a layer that connects with `connector`
+    pub fn new(connector: ComposedConnector) -> Self {
+        Self { connector }
+    }
 }
 
-impl TransportChainBuilder {
-    /// Create a new builder
-    pub fn new() -> Self {
+impl<S> Layer<S> for ComposedLayer {
+    type Service = ComposedService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ComposedService {
+            connector: self.connector.clone(),
+            inner,
+        }
+    }
+}
+
+/// Service produced by [`ComposedLayer`]
+///
+/// Connects to the request `Uri`, then calls the inner service with the
+/// connection. Connection errors are converted into the inner service's
+/// error type.
+#[derive(Clone)]
+pub struct ComposedService<S> {
+    connector: ComposedConnector,
+    inner: S,
+}
+
+impl<S> Service<Uri> for ComposedService<S>
+where
+    S: Service<Connection> + Clone + Send + 'static,
+    S::Error: From<TransportError>,
+    S::Future: Send,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let mut connector = self.connector.clone();
+        // Use the instance that was polled ready, leaving a fresh clone behind
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        Box::pin(async move {
+            let connection = connector.call(uri).await?;
+            inner.call(connection).await
+        })
+    }
+}
+
+/// Hyper client whose connections are opened by a [`BoundConnector`]
+///
+/// `B` is the request body type, e.g. `http_body_util::Full<Bytes>`.
+pub type HyperClient<B> = Client<BoundConnector, B>;
+
+/// Connector service bound to the transport selected for a single URL
+///
+/// Implements `Service<Uri>` so it can be plugged into hyper-util's
+/// legacy client. Obtain one with [`ComposedConnector::bound_connector`].
+#[derive(Clone)]
+pub struct BoundConnector {
+    target: BoundTarget,
+    /// Limits of the transport the URL is dialed over, if it has any
+    governor: Option<Arc<ConnectionGovernor>>,
+    /// How the bound URL resolves, for the trace span
+    plan: Option<ResolutionPlan>,
+}
+
+#[derive(Clone)]
+enum BoundTarget {
+    /// One of the built-in connectors
+    Builtin(ConnectorType),
+    /// A connector registered with [`ComposedConnector::register`]
+    Registered {
+        connector: Arc<dyn Connector>,
+        url: TransportUrl,
+    },
+}
+
+impl BoundConnector {
+    /// Open a connection for `uri`, without the governor
+    fn dial(&self, uri: Uri) -> BoxFuture<'static, Result<Connection, TransportError>> {
+        match self.target.clone() {
+            #[cfg(feature = "unix")]
+            BoundTarget::Builtin(ConnectorType::Unix(mut c)) => {
+                Box::pin(async move { c.call(uri).await.map(Connection::Unix) })
+            }
+            #[cfg(feature = "tcp")]
+            BoundTarget::Builtin(ConnectorType::Tcp(mut c)) => {
+                Box::pin(async move { c.call(uri).await.map(Connection::Tcp) })
+            }
+            #[cfg(feature = "tls")]
+            BoundTarget::Builtin(ConnectorType::Tls(mut c)) => {
+                Box::pin(async move { c.call(uri).await.map(Connection::Tls) })
+            }
+            #[cfg(feature = "tor")]
+            BoundTarget::Builtin(ConnectorType::Tor(mut c)) => {
+                Box::pin(async move { c.call(uri).await.map(Connection::Tor) })
+            }
+            #[cfg(feature = "i2p")]
+            BoundTarget::Builtin(ConnectorType::I2p(mut c)) => {
+                Box::pin(async move { c.call(uri).await.map(Connection::I2p) })
+            }
+            BoundTarget::Registered { connector, url } => Box::pin(async move {
+                let stream = observe_connect(url.transport(), connector.connect(&url)).await?;
+                Ok(Connection::Custom(CustomConnection::new(stream)))
+            }),
+            #[allow(unreachable_patterns)]
+            _ => Box::pin(async {
+                Err(TransportError::NotAvailable("Transport not available".to_string()))
+            }),
+        }
+    }
+
+    /// The bound plan, with the request's host and port for network transports
+    fn plan_for(&self, uri: &Uri) -> Option<ResolutionPlan> {
+        let mut plan = self.plan.clone()?;
+        if let (ResolvedEndpoint::Host { .. }, Some(host)) = (&plan.endpoint, uri.host()) {
+            let port = uri.port_u16().unwrap_or(if uri.scheme_str() == Some("https") { 443 } else { 80 });
+            plan.endpoint = ResolvedEndpoint::Host { host: host.to_string(), port };
+        }
+        Some(plan)
+    }
+}
+
+impl Service<Uri> for BoundConnector {
+    type Response = Connection;
+    type Error = TransportError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let plan = self.plan_for(&uri);
+        let governor = self.governor.clone();
+        let dial = self.dial(uri);
+        let connect = async move {
+            let Some(governor) = governor else {
+                return dial.await;
+            };
+            let permit = governor.acquire().await?;
+            let conn = dial.await?;
+            Ok(Connection::Governed(GovernedConnection::new(conn, permit)))
+        };
+        deadline::bounded(trace::instrument_connect(|| plan, connect))
+    }
+}
+
+/// Enum of connector types
+#[derive(Clone)]
+pub enum ConnectorType {
+    #[cfg(feature = "unix")]
+    Unix(UnixConnector),
+    #[cfg(feature = "tcp")]
+    Tcp(TcpConnector),
+    #[cfg(feature = "tls")]
+    Tls(TlsTcpConnector),
+    #[cfg(feature = "tor")]
+    Tor(TorConnector),
+    #[cfg(feature = "i2p")]
+    I2p(I2pConnector),
+}
+
+impl ConnectorType {
+    /// Apply `redaction` to the connector's logs
+    fn with_redaction(self, redaction: RedactionPolicy) -> Self {
+        match self {
+            #[cfg(feature = "unix")]
+            ConnectorType::Unix(connector) => ConnectorType::Unix(connector.with_redaction(redaction)),
+            #[cfg(feature = "tcp")]
+            ConnectorType::Tcp(connector) => ConnectorType::Tcp(connector.with_redaction(redaction)),
+            #[cfg(feature = "tls")]
+            ConnectorType::Tls(connector) => ConnectorType::Tls(connector.with_redaction(redaction)),
+            #[cfg(feature = "tor")]
+            ConnectorType::Tor(connector) => ConnectorType::Tor(connector.with_redaction(redaction)),
+            #[cfg(feature = "i2p")]
+            ConnectorType::I2p(connector) => ConnectorType::I2p(connector.with_redaction(redaction)),
+        }
+    }
+}
+
+/// Enum of connection types
+pub enum Connection {
+    #[cfg(feature = "unix")]
+    Unix(crate::unix_connector::UnixConnection),
+    #[cfg(feature = "tcp")]
+    Tcp(crate::tcp_connector::TcpConnection),
+    #[cfg(feature = "tls")]
+    Tls(crate::tls_connector::TlsConnection),
+    #[cfg(feature = "tor")]
+    Tor(crate::tor_connector::TorConnection),
+    #[cfg(feature = "i2p")]
+    I2p(crate::i2p_connector::I2pConnection),
+    /// Connection opened by a registered [`Connector`]
+    Custom(CustomConnection),
+    /// Connection counted against a transport's limits (see
+    /// [`ComposedConfig::limits`])
+    Governed(GovernedConnection),
+}
+
+/// How [`ComposedConnector::resolve`] would connect to a URL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolutionPlan {
+    /// Transport the connection would be made over
+    pub transport: Transport,
+    /// What would be connected to
+    pub endpoint: ResolvedEndpoint,
+    /// Daemon carrying the connection: the Corsair socket or Tor SOCKS port
+    /// for Tor, the SAM bridge for I2P
+    pub daemon: Option<String>,
+}
+
+impl ResolutionPlan {
+    /// Whether a separate daemon must be running for the connection
+    pub fn requires_daemon(&self) -> bool {
+        self.daemon.is_some()
+    }
+}
+
+impl std::fmt::Display for ResolutionPlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.transport.display_name(), self.endpoint)?;
+        match &self.daemon {
+            Some(daemon) => write!(f, " via {}", daemon),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Target of a [`ResolutionPlan`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolvedEndpoint {
+    /// A Unix domain socket
+    Socket(PathBuf),
+    /// A host and port (resolved by the daemon, if there is one)
+    Host { host: String, port: u16 },
+    /// Chosen by a connector registered with [`ComposedConnector::register`]
+    Custom,
+}
+
+impl std::fmt::Display for ResolvedEndpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResolvedEndpoint::Socket(path) => write!(f, "{}", path.display()),
+            ResolvedEndpoint::Host { host, port } => write!(f, "{}:{}", host, port),
+            ResolvedEndpoint::Custom => write!(f, "(custom connector)"),
+        }
+    }
+}
+
+/// Per-call settings for [`ComposedConnector::connect_url_with`]
+///
+/// Anything left unset behaves as in [`ComposedConnector::connect_url`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConnectOverrides {
+    socket_path: Option<PathBuf>,
+    timeout: Option<Duration>,
+    isolation: Option<String>,
+}
+
+impl ConnectOverrides {
+    /// No overrides
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Connect to this socket instead of the one the URL resolves to
+    ///
+    /// For Tor URLs it is the Corsair socket; for Unix and TCP URLs, the
+    /// Unix socket to connect to (as if the host were in the socket map).
+    pub fn with_socket_path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.socket_path = Some(path.into());
+        self
+    }
+
+    /// Give up if the connection is not established within `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Keep Tor connections with different tags on separate circuits
+    ///
+    /// Ignored by other transports; see `TorConnector::connect_isolated`.
+    pub fn with_isolation(mut self, tag: impl Into<String>) -> Self {
+        self.isolation = Some(tag.into());
+        self
+    }
+
+    /// The socket path override, if set
+    pub fn socket_path(&self) -> Option<&std::path::Path> {
+        self.socket_path.as_deref()
+    }
+
+    /// The connect timeout, if set
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// The isolation tag, if set
+    pub fn isolation(&self) -> Option<&str> {
+        self.isolation.as_deref()
+    }
+}
+
+/// Read half of a [`Connection`] (see [`Connection::into_split`])
+pub type ReadHalf = tokio::io::ReadHalf<Connection>;
+
+/// Write half of a [`Connection`] (see [`Connection::into_split`])
+pub type WriteHalf = tokio::io::WriteHalf<Connection>;
+
+/// Dispatch an expression to whichever connection variant is active
+macro_rules! with_connection {
+    ($conn:expr, $inner:ident => $body:expr) => {
+        match $conn {
+            #[cfg(feature = "unix")]
+            Connection::Unix($inner) => $body,
+            #[cfg(feature = "tcp")]
+            Connection::Tcp($inner) => $body,
+            #[cfg(feature = "tls")]
+            Connection::Tls($inner) => $body,
+            #[cfg(feature = "tor")]
+            Connection::Tor($inner) => $body,
+            #[cfg(feature = "i2p")]
+            Connection::I2p($inner) => $body,
+            Connection::Custom($inner) => $body,
+            Connection::Governed($inner) => $body,
+        }
+    };
+}
+
+impl Connection {
+    /// What this connection reached: the TCP peer address, Unix socket
+    /// path, or host and port asked of Tor or I2P
+    pub fn peer_info(&self) -> PeerInfo {
+        with_connection!(self, c => c.peer_info())
+    }
+
+    /// Wrap the connection so reads and writes that stall time out
+    ///
+    /// See [`TimeoutStream`] for when the clock runs.
+    pub fn with_io_timeout(self, read: Duration, write: Duration) -> TimeoutStream<Connection> {
+        TimeoutStream::new(self).with_read_timeout(read).with_write_timeout(write)
+    }
+
+    /// Wrap the connection so small writes are sent together
+    ///
+    /// See [`BufferedConnection`] for when buffered bytes go out.
+    pub fn with_write_buffer(self, flush_on: FlushOn) -> BufferedConnection<Connection> {
+        BufferedConnection::new(self).with_flush_on(flush_on)
+    }
+
+    /// Shut down the write side, signalling end of input to the peer
+    ///
+    /// The read side stays open, so a response sent after the half-close
+    /// can still be read. Over TLS this sends `close_notify` first.
+    pub async fn shutdown_write(&mut self) -> std::io::Result<()> {
+        use tokio::io::AsyncWriteExt;
+
+        with_connection!(self, c => AsyncWriteExt::shutdown(c).await)
+    }
+
+    /// Split into read and write halves that can move to separate tasks
+    ///
+    /// Works for every variant: TLS, multiplexed Tor and custom connections
+    /// have no owned split, so the halves share the connection through
+    /// tokio's [`split`](tokio::io::split) lock. Use the variant's own
+    /// `into_split` for a lock-free split of a Unix or TCP connection. The
+    /// halves implement the tokio I/O traits only; rejoin them with
+    /// [`ReadHalf::unsplit`] to hand the connection to hyper.
+    pub fn into_split(self) -> (ReadHalf, WriteHalf) {
+        tokio::io::split(self)
+    }
+
+    /// Transport label for byte counters
+    ///
+    /// `None` for governed connections, whose inner connection counts.
+    fn metrics_label(&self) -> Option<&'static str> {
+        match self {
+            #[cfg(feature = "unix")]
+            Connection::Unix(_) => Some(Transport::Unix.as_str()),
+            #[cfg(feature = "tcp")]
+            Connection::Tcp(_) => Some(Transport::Tcp.as_str()),
+            #[cfg(feature = "tls")]
+            Connection::Tls(_) => Some(Transport::Tcp.as_str()),
+            #[cfg(feature = "tor")]
+            Connection::Tor(_) => Some(Transport::Tor.as_str()),
+            #[cfg(feature = "i2p")]
+            Connection::I2p(_) => Some(Transport::I2p.as_str()),
+            Connection::Custom(_) => Some("custom"),
+            Connection::Governed(_) => None,
+        }
+    }
+}
+
+impl AsyncRead for Connection {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        let before = buf.filled().len();
+        let poll = with_connection!(&mut *this, c => AsyncRead::poll_read(Pin::new(c), cx, buf));
+        if let (Poll::Ready(Ok(())), Some(label)) = (&poll, this.metrics_label()) {
+            crate::metrics::bytes_received(label, buf.filled().len() - before);
+        }
+        poll
+    }
+}
+
+impl AsyncWrite for Connection {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        let poll = with_connection!(&mut *this, c => AsyncWrite::poll_write(Pin::new(c), cx, buf));
+        if let (Poll::Ready(Ok(written)), Some(label)) = (&poll, this.metrics_label()) {
+            crate::metrics::bytes_sent(label, *written);
+        }
+        poll
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        with_connection!(self.get_mut(), c => AsyncWrite::poll_flush(Pin::new(c), cx))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        with_connection!(self.get_mut(), c => AsyncWrite::poll_shutdown(Pin::new(c), cx))
+    }
+}
+
+impl hyper_util::client::legacy::connect::Connection for Connection {
+    fn connected(&self) -> Connected {
+        Connected::new()
+    }
+}
+
+impl hyper::rt::Read for Connection {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        mut buf: hyper::rt::ReadBufCursor<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        // Read through the tokio impl so bytes are counted once
+        let mut read_buf = tokio::io::ReadBuf::uninit(unsafe { buf.as_mut() });
+        match AsyncRead::poll_read(self, cx, &mut read_buf) {
+            Poll::Ready(Ok(())) => {
+                let filled = read_buf.filled().len();
+                unsafe { buf.advance(filled) };
+                Poll::Ready(Ok(()))
+            }
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl hyper::rt::Write for Connection {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, std::io::Error>> {
+        AsyncWrite::poll_write(self, cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), std::io::Error>> {
+        with_connection!(self.get_mut(), c => hyper::rt::Write::poll_flush(Pin::new(c), cx))
+    }
+
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        with_connection!(self.get_mut(), c => hyper::rt::Write::poll_shutdown(Pin::new(c), cx))
+    }
+}
+
+/// Builder for transport chains
+pub struct TransportChainBuilder {
+    transports: Vec<Transport>,
+    config: ComposedConfig,
+}
+
+impl TransportChainBuilder {
+    /// Create a new builder
+    pub fn new() -> Self {
         Self {
             transports: Vec::new(),
             config: ComposedConfig::default(),
@@ -248,8 +1718,12 @@ impl TransportChainBuilder {
     }
 
     /// Build the transport chain
-    pub fn build(self) -> (TransportChain, ComposedConfig) {
-        (TransportChain::new(self.transports), self.config)
+    ///
+    /// Fails if the chain does not pass [`TransportChain::validate`].
+    pub fn build(self) -> Result<(TransportChain, ComposedConfig), TransportError> {
+        let chain = TransportChain::new(self.transports);
+        chain.validate()?;
+        Ok((chain, self.config))
     }
 }
 
@@ -275,10 +1749,940 @@ mod tests {
             .tor()
             .unix()
             .socket_dir("/tmp/sockets")
-            .build();
+            .build()
+            .unwrap();
 
         assert_eq!(chain.len(), 2);
         assert_eq!(chain.first(), Some(&Transport::Tor));
         assert_eq!(chain.last(), Some(&Transport::Unix));
     }
+
+    #[test]
+    fn test_transport_chain_builder_rejects_duplicates() {
+        let result = TransportChainBuilder::new().tor().tor().build();
+        assert!(matches!(result, Err(TransportError::InvalidChain(_))));
+    }
+
+    #[test]
+    fn test_config_from_env() {
+        fn lookup(vars: &'static [(&'static str, &'static str)]) -> impl Fn(&str) -> Option<OsString> {
+            move |name| vars.iter().find(|(key, _)| *key == name).map(|(_, value)| OsString::from(value))
+        }
+
+        let config = ComposedConfig::from_lookup(lookup(&[
+            (ENV_SOCKET_DIR, "/run/rigging"),
+            (ENV_TOR_SOCKET, "/run/rigging/corsair.sock"),
+            (ENV_SOCKET_MAP, "api:/run/api.sock,web:/run/web.sock"),
+            (crate::runtime_dir::ENV_CORSAIR_SOCKET, "/run/corsair/other.sock"),
+        ]));
+        assert_eq!(config.socket_dir, Some(PathBuf::from("/run/rigging")));
+        // RIGGING_TOR_SOCKET wins over CORSAIR_SOCKET
+        assert_eq!(config.tor_socket, Some(PathBuf::from("/run/rigging/corsair.sock")));
+        #[cfg(feature = "unix")]
+        {
+            let map = config.socket_map.expect("socket map");
+            assert_eq!(map.get_socket_path("api"), Some(PathBuf::from("/run/api.sock")));
+            assert_eq!(map.get_socket_path("web"), Some(PathBuf::from("/run/web.sock")));
+        }
+
+        // Empty variables count as unset
+        let config = ComposedConfig::from_lookup(lookup(&[
+            (ENV_TOR_SOCKET, ""),
+            (crate::runtime_dir::ENV_CORSAIR_SOCKET, "/run/corsair/other.sock"),
+        ]));
+        assert_eq!(config.tor_socket, Some(PathBuf::from("/run/corsair/other.sock")));
+
+        let config = ComposedConfig::from_lookup(lookup(&[(ENV_SOCKET_DIR, ""), (ENV_SOCKET_MAP, "")]));
+        let defaults = ComposedConfig::defaults_in(PathBuf::from(crate::runtime_dir::FALLBACK_SOCKET_DIR));
+        assert_eq!(config.socket_dir, defaults.socket_dir);
+        assert_eq!(config.tor_socket, defaults.tor_socket);
+        #[cfg(feature = "unix")]
+        assert!(config.socket_map.is_none());
+    }
+
+    #[test]
+    fn test_routing_policy() {
+        let policy = RoutingPolicy::new()
+            .with_rule(RoutingRule::new("*.onion", Transport::Tor).unwrap())
+            .with_rule(RoutingRule::new("10.0.0.0/8", Transport::Tcp).unwrap())
+            .with_rule(RoutingRule::new("*", Transport::Unix).unwrap());
+        let connector = ComposedConnector::with_config(ComposedConfig {
+            socket_dir: Some(PathBuf::from("/run/apps")),
+            ..ComposedConfig::default()
+        })
+        .with_policy(policy);
+        let route = |url: &str| connector.connector_for_url(&TransportUrl::parse(url).unwrap()).unwrap();
+
+        #[cfg(feature = "tor")]
+        assert!(matches!(route("http://example.onion/"), ConnectorType::Tor(_)));
+        assert!(matches!(route("http://10.2.3.4:8080/"), ConnectorType::Tcp(_)));
+        match route("http://web/") {
+            ConnectorType::Unix(c) => assert_eq!(c.socket_path(), std::path::Path::new("/run/apps/web.sock")),
+            _ => panic!("expected the default socket directory"),
+        }
+
+        // Explicit transports are not re-routed
+        assert!(matches!(route("http::tcp//web/"), ConnectorType::Tcp(_)));
+    }
+
+    #[cfg(all(feature = "unix", feature = "tcp"))]
+    #[test]
+    fn test_resolve_plans() {
+        let mut map = SocketMapping::new();
+        map.add_mapping("api", "/run/api.sock");
+        let connector = ComposedConnector::with_config(ComposedConfig {
+            socket_dir: Some(PathBuf::from("/run/apps")),
+            tor_socket: Some(PathBuf::from("/run/corsair.sock")),
+            socket_map: Some(map),
+            ..ComposedConfig::default()
+        });
+
+        let plan = connector.resolve("http::unix///tmp/app.sock/index.html").unwrap();
+        assert_eq!(plan.transport, Transport::Unix);
+        assert_eq!(plan.endpoint, ResolvedEndpoint::Socket(PathBuf::from("/tmp/app.sock")));
+        assert!(!plan.requires_daemon());
+
+        // Hosts without a socket of their own fall back to the socket directory
+        let plan = connector.resolve("http::unix//web/").unwrap();
+        assert_eq!(plan.endpoint, ResolvedEndpoint::Socket(PathBuf::from("/run/apps/web.sock")));
+        assert_eq!(plan.to_string(), "Unix Socket /run/apps/web.sock");
+
+        // Mapped hosts reach their socket even without the unix transport
+        let plan = connector.resolve("http://api/v1").unwrap();
+        assert_eq!(plan.transport, Transport::Unix);
+        assert_eq!(plan.endpoint, ResolvedEndpoint::Socket(PathBuf::from("/run/api.sock")));
+
+        let plan = connector.resolve("https://example.com/").unwrap();
+        assert_eq!(plan.transport, Transport::Tcp);
+        assert_eq!(plan.endpoint, ResolvedEndpoint::Host { host: "example.com".to_string(), port: 443 });
+        assert!(!plan.requires_daemon());
+
+        #[cfg(feature = "tor")]
+        {
+            let plan = connector.resolve("http://example.onion:8080/").unwrap();
+            assert_eq!(plan.transport, Transport::Tor);
+            assert_eq!(plan.endpoint, ResolvedEndpoint::Host { host: "example.onion".to_string(), port: 8080 });
+            assert_eq!(plan.daemon.as_deref(), Some("/run/corsair.sock"));
+            assert!(plan.to_string().ends_with("example.onion:8080 via /run/corsair.sock"));
+        }
+
+        // Nothing was dialed, so missing sockets and daemons are no obstacle,
+        // but unknown transports still fail
+        assert!(connector.resolve("http::quic//example.com/").is_err());
+    }
+
+    #[cfg(all(feature = "unix", feature = "tcp", feature = "tor"))]
+    #[test]
+    fn test_tor_bypass() {
+        let mut map = SocketMapping::new();
+        map.add_mapping("svc.bridge.onion", "/run/bridge.sock");
+        let connector = ComposedConnector::with_config(ComposedConfig {
+            socket_map: Some(map),
+            tor_bypass: vec![".bridge.onion".to_string()],
+            ..ComposedConfig::default()
+        });
+        let transport = |url: &str| connector.resolve(url).unwrap().transport;
+
+        // Bypassed onions are routed like ordinary hosts
+        let plan = connector.resolve("http://svc.bridge.onion/").unwrap();
+        assert_eq!(plan.endpoint, ResolvedEndpoint::Socket(PathBuf::from("/run/bridge.sock")));
+        assert!(!plan.requires_daemon());
+        assert_eq!(transport("http://other.BRIDGE.onion/"), Transport::Tcp);
+        assert!(matches!(
+            connector.connector_for_url(&TransportUrl::parse("http://other.bridge.onion/").unwrap()),
+            Ok(ConnectorType::Tcp(_))
+        ));
+
+        // Other onions, lookalike suffixes and explicit Tor URLs still use Tor
+        assert_eq!(transport("http://example.onion/"), Transport::Tor);
+        assert_eq!(transport("http://notbridge.onion/"), Transport::Tor);
+        assert_eq!(transport("http::tor//svc.bridge.onion/"), Transport::Tor);
+    }
+
+    #[cfg(all(feature = "unix", feature = "tcp"))]
+    #[test]
+    fn test_socket_map_routes_host() {
+        let mut map = SocketMapping::new();
+        map.add_mapping("api", "/run/api.sock");
+        let connector = ComposedConnector::with_config(ComposedConfig {
+            socket_map: Some(map),
+            ..ComposedConfig::default()
+        });
+
+        let mapped = TransportUrl::parse("http://api/v1").unwrap();
+        match connector.connector_for_url(&mapped).unwrap() {
+            ConnectorType::Unix(c) => assert_eq!(c.socket_path(), std::path::Path::new("/run/api.sock")),
+            _ => panic!("expected Unix connector for mapped host"),
+        }
+
+        // Explicit TCP is never rerouted
+        let explicit = TransportUrl::parse("http::tcp//api/v1").unwrap();
+        assert!(!matches!(connector.connector_for_url(&explicit).unwrap(), ConnectorType::Unix(_)));
+    }
+
+    #[cfg(all(feature = "unix", feature = "tcp"))]
+    #[tokio::test]
+    async fn test_connect_with_fallback() {
+        let dir = tempfile::tempdir().unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let urls = [
+            TransportUrl::parse(&format!("http::unix//{}/dead.sock/", dir.path().display())).unwrap(),
+            TransportUrl::parse(&format!("http://127.0.0.1:{}/", port)).unwrap(),
+        ];
+
+        let connector = ComposedConnector::new();
+        let conn = connector.connect_with_fallback(&urls).await.unwrap();
+        assert!(matches!(conn, Connection::Tcp(_)));
+    }
+
+    #[cfg(feature = "unix")]
+    #[tokio::test]
+    async fn test_connect_with_fallback_all_fail() {
+        let dir = tempfile::tempdir().unwrap();
+        let urls = [
+            TransportUrl::parse(&format!("http::unix//{}/one.sock/", dir.path().display())).unwrap(),
+            TransportUrl::parse(&format!("http::unix//{}/two.sock/", dir.path().display())).unwrap(),
+        ];
+
+        let connector = ComposedConnector::new();
+        let err = match connector.connect_with_fallback(&urls).await {
+            Err(e) => e,
+            Ok(_) => panic!("expected all endpoints to fail"),
+        };
+        match &err {
+            TransportError::AllEndpointsFailed { attempts } => {
+                assert_eq!(attempts.len(), 2);
+                assert!(attempts[1].0.contains("two.sock"));
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+        assert!(matches!(err.last_attempt(), Some(TransportError::Io(_))));
+        assert!(err.to_string().contains("one.sock"));
+    }
+
+    #[cfg(feature = "unix")]
+    #[tokio::test]
+    async fn test_validate_chain_live_unix() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("app.sock");
+        let _listener = tokio::net::UnixListener::bind(&socket).unwrap();
+
+        let connector = ComposedConnector::new();
+        let url = TransportUrl::parse(&format!("http::unix//{}/", socket.display())).unwrap();
+        let deadline = Instant::now() + std::time::Duration::from_secs(2);
+
+        connector
+            .validate_chain_live(&TransportChain::single(Transport::Unix), &url, deadline)
+            .await
+            .unwrap();
+    }
+
+    #[cfg(feature = "unix")]
+    #[tokio::test]
+    async fn test_validate_chain_live_missing_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let connector = ComposedConnector::new();
+        let url = TransportUrl::parse(&format!("http::unix//{}/app.sock/", dir.path().display())).unwrap();
+        let deadline = Instant::now() + std::time::Duration::from_secs(2);
+
+        let err = connector
+            .validate_chain_live(&TransportChain::single(Transport::Unix), &url, deadline)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            TransportError::ChainHopFailed { index: 0, transport: Transport::Unix, .. }
+        ));
+    }
+
+    /// Write a ping through the connection and expect the peer's pong back
+    async fn assert_ping_pong<S>(mut conn: Connection, mut peer: S)
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        conn.write_all(b"ping").await.unwrap();
+        conn.flush().await.unwrap();
+
+        let mut buf = [0u8; 4];
+        peer.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+        peer.write_all(b"pong").await.unwrap();
+
+        conn.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"pong");
+        conn.shutdown().await.unwrap();
+    }
+
+    #[cfg(all(feature = "unix", feature = "tcp"))]
+    #[tokio::test]
+    async fn test_connect_url_with_socket_override() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("app.sock");
+        let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+        let connector = ComposedConnector::new();
+        let overrides = ConnectOverrides::new().with_socket_path(&socket);
+
+        // The URL's own socket does not exist; the override is used instead
+        for url in ["http::unix///nonexistent/rigging.sock/", "http://api.internal/v1"] {
+            let url = TransportUrl::parse(url).unwrap();
+            let (conn, accepted) =
+                tokio::join!(connector.connect_url_with(&url, overrides.clone()), listener.accept());
+            accepted.unwrap();
+            assert_eq!(conn.unwrap().peer_info(), PeerInfo::Unix(socket.clone()));
+        }
+
+        // Without the override the URL's socket is tried
+        let url = TransportUrl::parse("http::unix///nonexistent/rigging.sock/").unwrap();
+        let err = connector.connect_url_with(&url, ConnectOverrides::new()).await.err().unwrap();
+        assert!(matches!(err, TransportError::SocketPathNotFound));
+
+        // Overrides that cannot apply are rejected rather than ignored
+        let i2p = TransportUrl::parse("http::i2p//example.i2p/").unwrap();
+        assert!(connector.connect_url_with(&i2p, overrides).await.is_err());
+    }
+
+    #[cfg(all(feature = "unix", feature = "tcp"))]
+    #[tokio::test]
+    async fn test_connect_boxed_mixed_transports() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("app.sock");
+        let unix = tokio::net::UnixListener::bind(&socket).unwrap();
+        let tcp = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = tcp.local_addr().unwrap().port();
+        let echo = |mut stream: Box<dyn AsyncReadWrite>| async move {
+            let mut buf = [0u8; 4];
+            stream.read_exact(&mut buf).await.unwrap();
+            stream.write_all(&buf).await.unwrap();
+        };
+        tokio::spawn(async move {
+            let (stream, _) = unix.accept().await.unwrap();
+            echo(Box::new(stream)).await;
+        });
+        tokio::spawn(async move {
+            let (stream, _) = tcp.accept().await.unwrap();
+            echo(Box::new(stream)).await;
+        });
+
+        let connector = ComposedConnector::new();
+        let mut conns: Vec<Box<dyn AsyncReadWrite>> = Vec::new();
+        conns.push(connector.connect_boxed(&format!("http::unix//{}/", socket.display())).await.unwrap());
+        conns.push(connector.connect_boxed(&format!("http://127.0.0.1:{}/", port)).await.unwrap());
+
+        for (i, conn) in conns.iter_mut().enumerate() {
+            let message = format!("msg{}", i);
+            conn.write_all(message.as_bytes()).await.unwrap();
+            let mut buf = [0u8; 4];
+            conn.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, message.as_bytes());
+        }
+    }
+
+    #[cfg(feature = "unix")]
+    #[tokio::test]
+    async fn test_connect_url_with_timeout() {
+        // A governor with no free slots makes the connect wait
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("app.sock");
+        let _listener = tokio::net::UnixListener::bind(&socket).unwrap();
+        let mut config = ComposedConfig::default();
+        config.limits.insert(Transport::Unix, GovernorConfig::new().with_max_concurrent(1));
+        let connector = ComposedConnector::with_config(config);
+        let url = TransportUrl::parse(&format!("http::unix//{}/", socket.display())).unwrap();
+        let _held = connector.connect_url(&url).await.unwrap();
+
+        let overrides = ConnectOverrides::new().with_timeout(Duration::from_millis(50));
+        let err = connector.connect_url_with(&url, overrides).await.err().unwrap();
+        assert!(matches!(err, TransportError::Timeout(_)), "{}", err);
+    }
+
+    #[cfg(all(feature = "unix", feature = "tcp"))]
+    #[tokio::test]
+    async fn test_peer_info() {
+        let connector = ComposedConnector::new();
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (conn, _accepted) = tokio::join!(
+            connector.connect(&format!("http://127.0.0.1:{}/", addr.port())),
+            listener.accept()
+        );
+        assert_eq!(conn.unwrap().peer_info(), PeerInfo::Tcp(addr));
+
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("app.sock");
+        let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+        let (conn, _accepted) = tokio::join!(
+            connector.connect(&format!("http::unix//{}/", socket.display())),
+            listener.accept()
+        );
+        assert_eq!(conn.unwrap().peer_info(), PeerInfo::Unix(socket.clone()));
+        assert_eq!(
+            PeerInfo::Unix(socket.clone()).to_string(),
+            format!("unix {}", socket.display())
+        );
+
+        let (ours, _peer) = tokio::net::UnixStream::pair().unwrap();
+        let unnamed = Connection::Unix(crate::unix_connector::UnixConnection::new(ours));
+        assert_eq!(unnamed.peer_info(), PeerInfo::Unknown);
+    }
+
+    #[cfg(feature = "unix")]
+    #[tokio::test]
+    async fn test_connection_io_timeout() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let (ours, mut peer) = tokio::net::UnixStream::pair().unwrap();
+        let conn = Connection::Unix(crate::unix_connector::UnixConnection::new(ours));
+        let mut conn = conn.with_io_timeout(Duration::from_millis(50), Duration::from_secs(1));
+
+        peer.write_all(b"H").await.unwrap();
+        let mut buf = [0u8; 16];
+        assert_eq!(conn.read(&mut buf).await.unwrap(), 1);
+        let err = conn.read(&mut buf).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+    }
+
+    #[cfg(feature = "tcp")]
+    #[tokio::test]
+    async fn test_shutdown_write_then_read_response() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = async {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            stream.read_to_end(&mut request).await.unwrap();
+            stream.write_all(format!("got {} bytes", request.len()).as_bytes()).await.unwrap();
+        };
+        let client = async {
+            let mut conn = ComposedConnector::new()
+                .connect(&format!("http://127.0.0.1:{}/", port))
+                .await
+                .unwrap();
+            conn.write_all(b"hello").await.unwrap();
+            conn.shutdown_write().await.unwrap();
+            let mut response = String::new();
+            conn.read_to_string(&mut response).await.unwrap();
+            response
+        };
+
+        let ((), response) = tokio::join!(server, client);
+        assert_eq!(response, "got 5 bytes");
+    }
+
+    #[cfg(feature = "unix")]
+    #[tokio::test]
+    async fn test_into_split_concurrent_halves() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let (ours, peer) = tokio::net::UnixStream::pair().unwrap();
+        let conn = Connection::Unix(crate::unix_connector::UnixConnection::new(ours));
+        let (mut read_half, mut write_half) = conn.into_split();
+
+        // Echo everything back, so the write half fills the socket while
+        // the read half drains it
+        tokio::spawn(async move {
+            let (mut peer_read, mut peer_write) = peer.into_split();
+            tokio::io::copy(&mut peer_read, &mut peer_write).await.unwrap();
+        });
+
+        let payload: Vec<u8> = (0..256 * 1024).map(|i| i as u8).collect();
+        let expected = payload.clone();
+        let writer = tokio::spawn(async move {
+            write_half.write_all(&payload).await.unwrap();
+            write_half
+        });
+        let reader = tokio::spawn(async move {
+            let mut echoed = vec![0u8; expected.len()];
+            read_half.read_exact(&mut echoed).await.unwrap();
+            assert_eq!(echoed, expected);
+            read_half
+        });
+
+        let (write_half, read_half) = (writer.await.unwrap(), reader.await.unwrap());
+        let conn = read_half.unsplit(write_half);
+        assert!(matches!(conn.peer_info(), PeerInfo::Unknown));
+    }
+
+    #[cfg(feature = "unix")]
+    #[tokio::test]
+    async fn test_connection_io_unix() {
+        let (ours, peer) = tokio::net::UnixStream::pair().unwrap();
+        let conn = Connection::Unix(crate::unix_connector::UnixConnection::new(ours));
+        assert_ping_pong(conn, peer).await;
+    }
+
+    #[cfg(feature = "tcp")]
+    #[tokio::test]
+    async fn test_connection_io_tcp() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let connector = ComposedConnector::new();
+        let (conn, accepted) = tokio::join!(
+            connector.connect(&format!("http://127.0.0.1:{}/", addr.port())),
+            listener.accept()
+        );
+        assert!(matches!(conn, Ok(Connection::Tcp(_))));
+        assert_ping_pong(conn.unwrap(), accepted.unwrap().0).await;
+    }
+
+    #[cfg(feature = "tor")]
+    #[tokio::test]
+    async fn test_connection_io_tor() {
+        use crate::tor_connector::ConnectResponse;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let dir = tempfile::tempdir().unwrap();
+        let corsair = dir.path().join("corsair.sock");
+        let listener = tokio::net::UnixListener::bind(&corsair).unwrap();
+
+        // Minimal Corsair: accept the connect request, then hand over the stream
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut len = [0u8; 4];
+            stream.read_exact(&mut len).await.unwrap();
+            let mut request = vec![0u8; u32::from_be_bytes(len) as usize];
+            stream.read_exact(&mut request).await.unwrap();
+
+            let response = bincode::serialize(&ConnectResponse { success: true, error: None }).unwrap();
+            stream.write_all(&(response.len() as u32).to_be_bytes()).await.unwrap();
+            stream.write_all(&response).await.unwrap();
+            stream
+        });
+
+        let connector = ComposedConnector::with_config(ComposedConfig {
+            socket_dir: None,
+            tor_socket: Some(corsair),
+            ..ComposedConfig::default()
+        });
+        let conn = connector.connect("http://example.onion/").await.unwrap();
+        assert!(matches!(conn, Connection::Tor(_)));
+        assert_ping_pong(conn, server.await.unwrap()).await;
+    }
+
+    /// In-memory connector that echoes back whatever is written to it
+    struct EchoConnector;
+
+    impl Connector for EchoConnector {
+        fn allows_url(&self, url: &TransportUrl) -> bool {
+            url.host_str() == Some("echo")
+        }
+
+        fn connect<'a>(
+            &'a self,
+            _url: &'a TransportUrl,
+        ) -> futures::future::BoxFuture<'a, Result<Box<dyn crate::connector::AsyncReadWrite>, TransportError>> {
+            Box::pin(async move {
+                let (client, server) = tokio::io::duplex(64);
+                tokio::spawn(async move {
+                    let (mut reader, mut writer) = tokio::io::split(server);
+                    let _ = tokio::io::copy(&mut reader, &mut writer).await;
+                });
+                Ok(Box::new(client) as Box<dyn crate::connector::AsyncReadWrite>)
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_registered_connector() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let mut connector = ComposedConnector::new();
+        connector.register(Transport::Ssh, Box::new(EchoConnector));
+
+        let mut conn = connector.connect("http::ssh//echo/").await.unwrap();
+        assert!(matches!(conn, Connection::Custom(_)));
+
+        conn.write_all(b"ping").await.unwrap();
+        let mut buf = [0u8; 4];
+        conn.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+    }
+
+    #[tokio::test]
+    async fn test_registered_connector_rejects_url() {
+        let mut connector = ComposedConnector::new();
+        connector.register(Transport::Ssh, Box::new(EchoConnector));
+
+        let result = connector.connect("http::ssh//elsewhere/").await;
+        assert!(matches!(result, Err(TransportError::NotAvailable(_))));
+    }
+
+    #[cfg(feature = "unix")]
+    #[tokio::test]
+    async fn test_connect_with_retry_waits_for_socket() {
+        use std::time::Duration;
+
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("late.sock");
+
+        // The daemon comes up between the second and third attempts (at 100ms and 300ms)
+        let late_socket = socket.clone();
+        let daemon = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            let listener = tokio::net::UnixListener::bind(&late_socket).unwrap();
+            listener.accept().await.unwrap()
+        });
+
+        let retry = RetryConfig::new()
+            .with_max_attempts(5)
+            .with_initial_backoff(Duration::from_millis(100))
+            .with_jitter(0.0);
+        let conn = ComposedConnector::new()
+            .connect_with_retry(&format!("http::unix//{}/", socket.display()), &retry)
+            .await
+            .unwrap();
+        assert!(matches!(conn, Connection::Unix(_)));
+        daemon.await.unwrap();
+    }
+
+    #[cfg(feature = "unix")]
+    #[tokio::test]
+    async fn test_connect_with_retry_gives_up() {
+        use std::time::Duration;
+
+        let dir = tempfile::tempdir().unwrap();
+        let retry = RetryConfig::new()
+            .with_max_attempts(3)
+            .with_initial_backoff(Duration::from_millis(1))
+            .with_jitter(0.0);
+        let url = format!("http::unix//{}/", dir.path().join("never.sock").display());
+
+        let err = ComposedConnector::new().connect_with_retry(&url, &retry).await.unwrap_err();
+        assert!(matches!(err, TransportError::RetryFailed { attempts: 3, .. }));
+
+        // Permanent errors are not retried
+        let err = ComposedConnector::new()
+            .connect_with_retry("http::ssh//host/", &retry)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, TransportError::RetryFailed { attempts: 1, .. }));
+
+        // Nor is a path that will never be a socket
+        let err = ComposedConnector::new()
+            .connect_with_retry(&format!("http::unix//{}:/", dir.path().display()), &retry)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, TransportError::RetryFailed { attempts: 1, ref source } if matches!(**source, TransportError::ConnectionFailed(_))),
+            "{:?}",
+            err
+        );
+    }
+
+    #[cfg(feature = "unix")]
+    #[tokio::test]
+    async fn test_governor_serializes_unix_connects() {
+        use crate::governor::GovernorMode;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::time::Duration;
+
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("daemon.sock");
+        let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                drop(stream);
+            }
+        });
+
+        let mut config = ComposedConfig::default();
+        config.limits.insert(Transport::Unix, GovernorConfig::new().with_max_concurrent(1));
+        let connector = ComposedConnector::with_config(config);
+        let url = format!("http::unix//{}/", socket.display());
+
+        let open = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let started = Instant::now();
+        let tasks: Vec<_> = (0..3)
+            .map(|_| {
+                let (connector, url) = (connector.clone(), url.clone());
+                let (open, peak) = (open.clone(), peak.clone());
+                tokio::spawn(async move {
+                    let conn = connector.connect(&url).await.unwrap();
+                    assert!(matches!(conn, Connection::Governed(_)));
+                    peak.fetch_max(open.fetch_add(1, Ordering::SeqCst) + 1, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    open.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        assert_eq!(peak.load(Ordering::SeqCst), 1);
+        assert!(started.elapsed() >= Duration::from_millis(150));
+
+        // Fail-fast mode refuses instead of waiting
+        let mut config = ComposedConfig::default();
+        config.limits.insert(
+            Transport::Unix,
+            GovernorConfig::new().with_max_concurrent(1).with_mode(GovernorMode::FailFast),
+        );
+        let connector = ComposedConnector::with_config(config);
+        let held = connector.connect(&url).await.unwrap();
+        let refused = connector.connect(&url).await;
+        assert!(matches!(refused, Err(TransportError::LimitReached(_))));
+        drop(held);
+        assert!(connector.connect(&url).await.is_ok());
+    }
+
+    #[cfg(feature = "unix")]
+    #[tokio::test]
+    async fn test_hyper_client_waits_for_governor() {
+        use http_body_util::Empty;
+        use hyper::body::Bytes;
+
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("app.sock");
+        let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let service = hyper::service::service_fn(|_| async {
+                    Ok::<_, std::convert::Infallible>(hyper::Response::new(Empty::<Bytes>::new()))
+                });
+                let io = hyper_util::rt::TokioIo::new(stream);
+                tokio::spawn(hyper::server::conn::http1::Builder::new().serve_connection(io, service));
+            }
+        });
+
+        let mut config = ComposedConfig::default();
+        config.limits.insert(Transport::Unix, GovernorConfig::new().with_max_concurrent(1));
+        let connector = ComposedConnector::with_config(config);
+        let url = TransportUrl::parse(&format!("http::unix//{}/", socket.display())).unwrap();
+        let client: HyperClient<Empty<Bytes>> = connector.into_hyper_client(&url).unwrap();
+
+        // The client shares the connector's only slot, so it waits for it
+        let held = connector.connect_url(&url).await.unwrap();
+        let request = client.get(Uri::from_static("http://localhost/"));
+        tokio::pin!(request);
+        assert!(tokio::time::timeout(Duration::from_millis(100), &mut request).await.is_err());
+
+        drop(held);
+        let response = tokio::time::timeout(Duration::from_secs(5), request).await.unwrap().unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    /// Connector that opens a pipe and then never finishes connecting
+    struct StalledConnector {
+        peer: std::sync::Mutex<Option<tokio::io::DuplexStream>>,
+    }
+
+    impl Connector for StalledConnector {
+        fn allows_url(&self, _url: &TransportUrl) -> bool {
+            true
+        }
+
+        fn connect<'a>(
+            &'a self,
+            _url: &'a TransportUrl,
+        ) -> futures::future::BoxFuture<'a, Result<Box<dyn crate::connector::AsyncReadWrite>, TransportError>> {
+            Box::pin(async move {
+                let (half_open, peer) = tokio::io::duplex(64);
+                *self.peer.lock().unwrap() = Some(peer);
+                futures::future::pending::<()>().await;
+                Ok(Box::new(half_open) as Box<dyn crate::connector::AsyncReadWrite>)
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_connect_with_cancel() {
+        use std::time::Duration;
+        use tokio::io::AsyncReadExt;
+
+        let stalled = Arc::new(StalledConnector { peer: Default::default() });
+        let mut connector = ComposedConnector::new();
+        connector.connectors.insert(Transport::Ssh, stalled.clone());
+
+        let token = CancellationToken::new();
+        let cancel = token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            cancel.cancel();
+        });
+
+        let started = Instant::now();
+        let result = connector.connect_with_cancel("http::ssh//slow/", token).await;
+        assert!(matches!(result, Err(TransportError::Cancelled)));
+        assert!(started.elapsed() < Duration::from_secs(2));
+
+        // The abandoned attempt's pipe was closed
+        let mut peer = stalled.peer.lock().unwrap().take().unwrap();
+        let mut buf = [0u8; 1];
+        assert_eq!(peer.read(&mut buf).await.unwrap(), 0);
+
+        // An already-cancelled token never starts connecting
+        let token = CancellationToken::new();
+        token.cancel();
+        let result = ComposedConnector::new().connect_with_cancel("http://127.0.0.1:1/", token).await;
+        assert!(matches!(result, Err(TransportError::Cancelled)));
+    }
+
+    #[tokio::test]
+    async fn test_request_deadline_aborts_connect() {
+        use crate::deadline::{self, Deadline};
+        use http_body_util::Empty;
+        use hyper::body::Bytes;
+
+        let stalled = Arc::new(StalledConnector { peer: Default::default() });
+        let mut connector = ComposedConnector::new();
+        connector.connectors.insert(Transport::Ssh, stalled);
+
+        // As a plain Service<Uri>
+        let started = Instant::now();
+        let connect = deadline::with_deadline(Deadline::after(Duration::from_millis(50)), async {
+            connector.clone().call("http::ssh//slow/".parse::<Uri>().unwrap()).await
+        });
+        assert!(matches!(connect.await, Err(TransportError::Timeout(_))));
+        assert!(started.elapsed() < Duration::from_secs(2));
+
+        // Through hyper, with the deadline in the request's extensions
+        let url = TransportUrl::parse("http::ssh//slow/").unwrap();
+        let client: HyperClient<Empty<Bytes>> = connector.into_hyper_client(&url).unwrap();
+        let mut request = hyper::Request::get("http://slow/").body(Empty::new()).unwrap();
+        request.extensions_mut().insert(Deadline::after(Duration::from_millis(50)));
+
+        let started = Instant::now();
+        let err = deadline::request(&client, request).await.unwrap_err();
+        assert!(err.is_connect());
+        let source = std::error::Error::source(&err).and_then(|e| e.downcast_ref::<TransportError>());
+        assert!(matches!(source, Some(TransportError::Timeout(_))), "{:?}", err);
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[cfg(feature = "unix")]
+    #[tokio::test]
+    async fn test_service_routes_mapped_uri_to_unix() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("api.sock");
+        let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+
+        let mut map = SocketMapping::new();
+        map.add_mapping("api", &socket);
+        let mut connector = ComposedConnector::with_config(ComposedConfig {
+            socket_map: Some(map),
+            ..ComposedConfig::default()
+        });
+
+        let (conn, accepted) = tokio::join!(
+            connector.call("http://api/v1".parse().unwrap()),
+            listener.accept()
+        );
+        assert!(matches!(conn, Ok(Connection::Unix(_))));
+        assert_ping_pong(conn.unwrap(), accepted.unwrap().0).await;
+    }
+
+    #[cfg(feature = "tcp")]
+    #[tokio::test]
+    async fn test_service_routes_uri_to_tcp() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let mut connector = ComposedConnector::new();
+        let uri: Uri = format!("http://127.0.0.1:{}/", addr.port()).parse().unwrap();
+        let (conn, accepted) = tokio::join!(connector.call(uri), listener.accept());
+        assert!(matches!(conn, Ok(Connection::Tcp(_))));
+        assert_ping_pong(conn.unwrap(), accepted.unwrap().0).await;
+    }
+
+    /// Inner service that reports which transport it was handed
+    #[derive(Clone)]
+    struct TransportName;
+
+    impl Service<Connection> for TransportName {
+        type Response = &'static str;
+        type Error = TransportError;
+        type Future = futures::future::Ready<Result<Self::Response, Self::Error>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, conn: Connection) -> Self::Future {
+            futures::future::ready(Ok(match conn {
+                #[cfg(feature = "unix")]
+                Connection::Unix(_) => "unix",
+                #[cfg(feature = "tcp")]
+                Connection::Tcp(_) => "tcp",
+                #[allow(unreachable_patterns)]
+                _ => "other",
+            }))
+        }
+    }
+
+    #[cfg(feature = "tcp")]
+    #[tokio::test]
+    async fn test_composed_layer() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let mut service = ComposedLayer::new(ComposedConnector::new()).layer(TransportName);
+        let uri: Uri = format!("http://127.0.0.1:{}/", addr.port()).parse().unwrap();
+        let (name, _accepted) = tokio::join!(service.call(uri), listener.accept());
+        assert_eq!(name.unwrap(), "tcp");
+
+        // URIs that are not transport URLs fail before reaching the inner service
+        let relative = Uri::from_static("/no-host");
+        assert!(matches!(service.call(relative).await, Err(TransportError::InvalidUrl(_))));
+    }
+
+    #[cfg(all(feature = "unix", feature = "tor"))]
+    #[tokio::test]
+    async fn test_validate_chain_live_tor_unix() {
+        let dir = tempfile::tempdir().unwrap();
+        let corsair = dir.path().join("corsair.sock");
+        let socket = dir.path().join("app.sock");
+        let _corsair = tokio::net::UnixListener::bind(&corsair).unwrap();
+        let _app = tokio::net::UnixListener::bind(&socket).unwrap();
+
+        let connector = ComposedConnector::with_config(ComposedConfig {
+            socket_dir: Some(dir.path().to_path_buf()),
+            tor_socket: Some(corsair),
+            ..ComposedConfig::default()
+        });
+        let url = TransportUrl::parse(&format!("http::unix//{}/", socket.display())).unwrap();
+        let chain = TransportChain::parse("tor+unix").unwrap();
+        let deadline = Instant::now() + std::time::Duration::from_secs(2);
+
+        connector.validate_chain_live(&chain, &url, deadline).await.unwrap();
+    }
+
+    #[cfg(all(feature = "unix", feature = "tor"))]
+    #[tokio::test]
+    async fn test_validate_chain_live_missing_corsair() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("app.sock");
+        let _app = tokio::net::UnixListener::bind(&socket).unwrap();
+
+        let connector = ComposedConnector::with_config(ComposedConfig {
+            socket_dir: Some(dir.path().to_path_buf()),
+            tor_socket: Some(dir.path().join("corsair.sock")),
+            ..ComposedConfig::default()
+        });
+        let url = TransportUrl::parse(&format!("http::unix//{}/", socket.display())).unwrap();
+        let chain = TransportChain::parse("tor+unix").unwrap();
+        let started = Instant::now();
+        let deadline = started + std::time::Duration::from_secs(2);
+
+        let err = connector.validate_chain_live(&chain, &url, deadline).await.unwrap_err();
+        assert!(started.elapsed() < std::time::Duration::from_secs(2));
+        match err {
+            TransportError::ChainHopFailed { index, transport, source } => {
+                assert_eq!(index, 0);
+                assert_eq!(transport, Transport::Tor);
+                assert!(matches!(*source, TransportError::TorNotAvailable));
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+    }
 }
@@ -0,0 +1,161 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Pluggable connector trait
+//!
+//! The `Connector` trait is the extension point for transports. The built-in
+//! connectors implement it, and applications can register their own with
+//! [`ComposedConnector::register`](crate::composed::ComposedConnector::register)
+//! to add a transport or override a built-in one.
+
+use crate::types::{PeerInfo, TransportError};
+use crate::TransportUrl;
+use futures::future::BoxFuture;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_util::sync::CancellationToken;
+
+/// A bidirectional byte stream usable as a connection
+pub trait AsyncReadWrite: AsyncRead + AsyncWrite + Send + Unpin {}
+
+impl<T: AsyncRead + AsyncWrite + Send + Unpin> AsyncReadWrite for T {}
+
+/// A connector that can open connections for transport-aware URLs
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use rigging::connector::{AsyncReadWrite, Connector};
+///
+/// struct MyConnector;
+///
+/// impl Connector for MyConnector {
+///     fn allows_url(&self, url: &TransportUrl) -> bool {
+///         url.host_str() == Some("internal")
+///     }
+///
+///     fn connect<'a>(
+///         &'a self,
+///         url: &'a TransportUrl,
+///     ) -> BoxFuture<'a, Result<Box<dyn AsyncReadWrite>, TransportError>> {
+///         Box::pin(async move { /* ... */ })
+///     }
+/// }
+/// ```
+pub trait Connector: Send + Sync {
+    /// Check if this connector allows the given URL
+    fn allows_url(&self, url: &TransportUrl) -> bool;
+
+    /// Connect to the given URL
+    fn connect<'a>(
+        &'a self,
+        url: &'a TransportUrl,
+    ) -> BoxFuture<'a, Result<Box<dyn AsyncReadWrite>, TransportError>>;
+}
+
+/// Run a connect future until it finishes or `token` is cancelled
+///
+/// On cancellation the future is dropped, which closes any socket it had
+/// opened, and [`TransportError::Cancelled`] is returned.
+pub(crate) async fn cancellable<T>(
+    token: &CancellationToken,
+    connect: impl Future<Output = Result<T, TransportError>>,
+) -> Result<T, TransportError> {
+    tokio::select! {
+        biased;
+        _ = token.cancelled() => Err(TransportError::Cancelled),
+        result = connect => result,
+    }
+}
+
+/// A connection opened by a registered [`Connector`]
+pub struct CustomConnection {
+    stream: Box<dyn AsyncReadWrite>,
+}
+
+impl CustomConnection {
+    pub fn new(stream: Box<dyn AsyncReadWrite>) -> Self {
+        Self { stream }
+    }
+
+    /// Unwrap the underlying stream
+    pub fn into_inner(self) -> Box<dyn AsyncReadWrite> {
+        self.stream
+    }
+
+    /// Always [`PeerInfo::Unknown`]: registered connectors do not report it
+    pub fn peer_info(&self) -> PeerInfo {
+        PeerInfo::Unknown
+    }
+}
+
+impl AsyncRead for CustomConnection {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for CustomConnection {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}
+
+impl hyper::rt::Read for CustomConnection {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        mut buf: hyper::rt::ReadBufCursor<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        let mut read_buf = tokio::io::ReadBuf::uninit(unsafe { buf.as_mut() });
+        match Pin::new(&mut self.get_mut().stream).poll_read(cx, &mut read_buf) {
+            Poll::Ready(Ok(())) => {
+                let filled = read_buf.filled().len();
+                unsafe { buf.advance(filled) };
+                Poll::Ready(Ok(()))
+            }
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl hyper::rt::Write for CustomConnection {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, std::io::Error>> {
+        Pin::new(&mut self.get_mut().stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), std::io::Error>> {
+        Pin::new(&mut self.get_mut().stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        Pin::new(&mut self.get_mut().stream).poll_shutdown(cx)
+    }
+}
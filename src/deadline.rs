@@ -0,0 +1,167 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Per-request connect deadlines
+//!
+//! hyper hands a connector only the request `Uri`, so a deadline on the
+//! request cannot reach it directly. Instead the deadline is carried in a
+//! task-local scope: every `Service<Uri>` impl in this crate reads it when
+//! called (or when its future is first polled) and fails the connect with
+//! [`TransportError::Timeout`] once it passes. Outside a scope connects are
+//! not limited.
+//!
+//! Callers either scope a future themselves, or put a [`Deadline`] in the
+//! request's extensions and send it with [`request`]:
+//!
+//! ```rust,ignore
+//! let client = ComposedConnector::new().into_hyper_client(&url)?;
+//!
+//! let mut req = Request::get("http://localhost/api").body(Empty::new())?;
+//! req.extensions_mut().insert(Deadline::after(Duration::from_secs(2)));
+//! let response = deadline::request(&client, req).await?;
+//!
+//! // Or for any future, e.g. inside a tower timeout layer's inner service
+//! let conn = deadline::with_deadline(Deadline::after(timeout), connector.call(uri)).await?;
+//! ```
+//!
+//! Nested scopes keep the earlier deadline. The deadline bounds only
+//! establishing the connection; sending the request and reading the
+//! response are up to the caller's own timeout.
+
+use crate::types::TransportError;
+use futures::future::BoxFuture;
+use hyper::body::Incoming;
+use hyper::{Request, Response};
+use hyper_util::client::legacy::connect::Connect;
+use hyper_util::client::legacy::Client;
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::Instant;
+
+tokio::task_local! {
+    static DEADLINE: Deadline;
+}
+
+/// Point in time by which a connection must be established
+///
+/// Insert it into a request's extensions and send the request with
+/// [`request`], or scope a future with [`with_deadline`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Deadline(Instant);
+
+impl Deadline {
+    /// Deadline at `instant`
+    pub fn at(instant: Instant) -> Self {
+        Deadline(instant)
+    }
+
+    /// Deadline `timeout` from now
+    pub fn after(timeout: Duration) -> Self {
+        Deadline(Instant::now() + timeout)
+    }
+
+    /// When the deadline passes
+    pub fn instant(&self) -> Instant {
+        self.0
+    }
+
+    /// Time left before the deadline (zero once it has passed)
+    pub fn remaining(&self) -> Duration {
+        self.0.saturating_duration_since(Instant::now())
+    }
+}
+
+/// The deadline in scope for the current task, if any
+pub fn current() -> Option<Deadline> {
+    DEADLINE.try_with(|deadline| *deadline).ok()
+}
+
+/// Run `future` with `deadline` applied to any connects it makes
+///
+/// Inside an existing scope the earlier of the two deadlines applies.
+pub async fn with_deadline<F: Future>(deadline: Deadline, future: F) -> F::Output {
+    let deadline = current().map_or(deadline, |outer| outer.min(deadline));
+    DEADLINE.scope(deadline, future).await
+}
+
+/// Send `req` with `client`, honouring a [`Deadline`] in its extensions
+///
+/// Without one this is the same as `client.request(req)`.
+pub async fn request<C, B>(
+    client: &Client<C, B>,
+    req: Request<B>,
+) -> Result<Response<Incoming>, hyper_util::client::legacy::Error>
+where
+    C: Connect + Clone + Send + Sync + 'static,
+    B: hyper::body::Body + Send + Unpin + 'static,
+    B::Data: Send,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    match req.extensions().get::<Deadline>().copied() {
+        Some(deadline) => with_deadline(deadline, client.request(req)).await,
+        None => client.request(req).await,
+    }
+}
+
+/// Box a connect future, bounded by the deadline in scope
+///
+/// The deadline is read when this is called, since hyper may poll the
+/// connect on another task, and failing that when first polled.
+pub(crate) fn bounded<'a, T: 'a>(
+    connect: impl Future<Output = Result<T, TransportError>> + Send + 'a,
+) -> BoxFuture<'a, Result<T, TransportError>> {
+    let called_in = current();
+    Box::pin(async move {
+        let Some(deadline) = called_in.or_else(current) else {
+            return connect.await;
+        };
+        tokio::time::timeout_at(deadline.instant(), connect).await.unwrap_or_else(|_| {
+            Err(TransportError::Timeout("request deadline passed while connecting".to_string()))
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_scopes_keep_earliest_deadline() {
+        assert_eq!(current(), None);
+
+        let soon = Deadline::after(Duration::from_secs(1));
+        let later = Deadline::after(Duration::from_secs(60));
+        with_deadline(soon, async {
+            assert_eq!(current(), Some(soon));
+            with_deadline(later, async { assert_eq!(current(), Some(soon)) }).await;
+        })
+        .await;
+        with_deadline(later, async {
+            with_deadline(soon, async { assert_eq!(current(), Some(soon)) }).await;
+        })
+        .await;
+
+        assert_eq!(current(), None);
+    }
+
+    #[tokio::test]
+    async fn test_bounded_times_out() {
+        let pending = futures::future::pending::<Result<(), TransportError>>();
+        let started = Instant::now();
+        let deadline = Deadline::after(Duration::from_millis(50));
+        let result = with_deadline(deadline, async { bounded(pending).await }).await;
+        assert!(matches!(result, Err(TransportError::Timeout(_))));
+        assert!(started.elapsed() < Duration::from_secs(2));
+
+        // A future created outside the scope picks it up when first polled
+        let pending = futures::future::pending::<Result<(), TransportError>>();
+        let late = bounded(pending);
+        let result = with_deadline(Deadline::after(Duration::from_millis(50)), late).await;
+        assert!(matches!(result, Err(TransportError::Timeout(_))));
+
+        // Outside a scope the future runs to completion
+        let ready = bounded(async { Ok::<_, TransportError>(7) });
+        assert_eq!(ready.await.unwrap(), 7);
+    }
+}
@@ -15,24 +15,114 @@
 //! - `servo` feature: Will use embedded Servo engine (future)
 
 use super::config::BrowserConfig;
+#[cfg(feature = "webview")]
+use super::config::CookieStore;
 use super::events::{BrowserEvent, EventCallback};
+use super::handle::CommandReceiver;
 use super::EmbedError;
 use log::{info, warn};
 
+#[cfg(feature = "webview")]
+use super::events::{ConsoleLevel, LoadState, NavigationEvent};
+#[cfg(feature = "webview")]
+use super::handle::BrowserCommand;
+#[cfg(feature = "webview")]
+use log::debug;
+
+/// Forwards `console.*` calls to the ipc handler
+///
+/// Messages are `console:<level>:<line>:<source>`, a newline, then the text.
+/// The location comes from the calling stack frame and is left empty when
+/// the engine does not report one (for example in evaluated scripts).
+#[cfg(feature = "webview")]
+const CONSOLE_BRIDGE_SCRIPT: &str = r#"(function () {
+    function caller() {
+        const frame = (new Error().stack || '').split('\n')[2] || '';
+        const at = frame.match(/(\S+):(\d+):\d+\)?$/);
+        return at ? at[2] + ':' + at[1].replace(/^.*?@|^\(/, '') : ':';
+    }
+    for (const level of ['debug', 'info', 'log', 'warn', 'error']) {
+        const original = console[level];
+        console[level] = function (...args) {
+            try {
+                window.ipc.postMessage('console:' + level + ':' + caller() + '\n' + args.map(String).join(' '));
+            } catch (e) {}
+            return original.apply(console, args);
+        };
+    }
+})();"#;
+
+/// Reports the page's `<link rel=icon>` to the ipc handler
+///
+/// Messages are `favicon:` followed by the icon's absolute URL, or nothing
+/// when the page has none. Only changes are posted, starting with the state
+/// once the document is parsed.
+#[cfg(feature = "webview")]
+const FAVICON_BRIDGE_SCRIPT: &str = r#"(function () {
+    let last = null;
+    function report() {
+        const link = document.querySelector('link[rel~="icon" i][href]');
+        const href = link ? link.href : '';
+        if (href === last) return;
+        last = href;
+        try {
+            window.ipc.postMessage('favicon:' + href);
+        } catch (e) {}
+    }
+    function watch() {
+        report();
+        new MutationObserver(report).observe(document.documentElement, {
+            subtree: true,
+            childList: true,
+            attributes: true,
+            attributeFilter: ['rel', 'href'],
+        });
+    }
+    if (document.readyState === 'loading') {
+        document.addEventListener('DOMContentLoaded', watch);
+    } else {
+        watch();
+    }
+})();"#;
+
+/// Events delivered to the webview event loop
+#[cfg(feature = "webview")]
+#[derive(Debug)]
+enum UserEvent {
+    /// Command from a `BrowserHandle`
+    Command(BrowserCommand),
+    /// The page finished loading
+    PageLoaded,
+    /// Screenshot capture finished
+    ScreenshotSaved(Result<(), String>),
+}
+
 /// Run the browser with the given configuration
 ///
-/// This is the main entry point for the browser backend.
+/// This is the main entry point for the browser backend. When `commands` is
+/// set, commands from a `BrowserHandle` are applied inside the event loop.
+///
+/// When `screenshot_path` is set, the visible page is saved as a PNG once it
+/// has finished loading and the browser then shuts down. Headless windows are
+/// never shown, but on Linux WebKitGTK still needs an X11 or Wayland display
+/// to render into; use `xvfb-run` on machines without one.
 #[cfg(feature = "webview")]
 pub fn run_browser(
     config: BrowserConfig,
     event_callback: Option<EventCallback>,
+    commands: Option<CommandReceiver>,
 ) -> Result<(), EmbedError> {
     use tao::{
         event::{Event, WindowEvent},
-        event_loop::{ControlFlow, EventLoop},
+        event_loop::{ControlFlow, EventLoopBuilder},
+        platform::run_return::EventLoopExtRunReturn,
         window::WindowBuilder,
     };
-    use wry::WebViewBuilder;
+    use std::rc::Rc;
+    use wry::{PageLoadEvent, WebViewBuilder};
+
+    // Shared between the webview handlers and the event loop (all on this thread)
+    let event_callback = Rc::new(event_callback);
 
     // Emit initialization event
     emit_event(&event_callback, BrowserEvent::Initialized);
@@ -41,18 +131,56 @@ pub fn run_browser(
     debug!("Window: {}x{}", config.width, config.height);
     debug!("URL: {}", config.url);
 
-    // Check for transport-aware URLs - webview backend does not support them
-    if config.url.contains("::unix//") || config.url.contains("::tor//") || config.url.contains("::pipe//") {
-        return Err(EmbedError::InvalidUrl(
-            format!("WebView backend does not support transport-aware URLs (found: {}). Use Servo backend with 'servo' feature.", config.url)
+    #[cfg(target_os = "linux")]
+    if std::env::var_os("DISPLAY").is_none() && std::env::var_os("WAYLAND_DISPLAY").is_none() {
+        return Err(EmbedError::InitFailed(
+            "No display available for WebKitGTK (headless mode still needs one; try xvfb-run)".into(),
         ));
     }
 
-    let url = &config.url;
+    // Unix socket URLs are served through a localhost proxy
+    let (mut url, mut proxy) = convert_transport_url(&config.url, &config.extra_headers, config.socket_map.as_ref())?;
+    if let Some(ref proxy) = proxy {
+        emit_event(&event_callback, proxy_started_event(proxy));
+    }
+
+    // wry only sets headers on the first request; the Unix proxy adds them
+    // to every request, and for TCP pages a custom protocol does
+    let mut header_protocol = None;
+    if proxy.is_none() && !config.extra_headers.is_empty() {
+        if let Some(protocol_url) = super::header_protocol::protocol_url(&url) {
+            if config.proxy.is_some() {
+                return Err(EmbedError::ConfigError(
+                    "extra_headers on TCP URLs cannot be combined with proxy".into(),
+                ));
+            }
+            header_protocol = Some(super::header_protocol::HeaderProtocol::start(&config.extra_headers)?);
+            url = protocol_url;
+        }
+    }
+    let url = &url;
     info!("Loading URL: {}", url);
 
-    // Create event loop
-    let event_loop = EventLoop::new();
+    // Create event loop; handle commands arrive as user events
+    let mut builder = EventLoopBuilder::<UserEvent>::with_user_event();
+    // spawn() and run_async() drive the loop from a dedicated thread
+    #[cfg(target_os = "linux")]
+    {
+        use tao::platform::unix::EventLoopBuilderExtUnix;
+        builder.with_any_thread(true);
+    }
+    let mut event_loop = builder.build();
+
+    if let Some(commands) = commands {
+        let proxy = event_loop.create_proxy();
+        std::thread::spawn(move || {
+            for command in commands {
+                if proxy.send_event(UserEvent::Command(command)).is_err() {
+                    break;
+                }
+            }
+        });
+    }
 
     // Build window
     let window = WindowBuilder::new()
@@ -60,6 +188,7 @@ pub fn run_browser(
         .with_inner_size(tao::dpi::LogicalSize::new(config.width as f64, config.height as f64))
         .with_resizable(config.resizable)
         .with_decorations(config.decorated)
+        .with_visible(!config.headless)
         .build(&event_loop)
         .map_err(|e| EmbedError::WindowFailed(e.to_string()))?;
 
@@ -69,25 +198,135 @@ pub fn run_browser(
         BrowserEvent::WindowCreated { window_id: 1 },
     );
 
+    // Persistent and shared stores need a web context that outlives the webview
+    let mut web_context = match &config.cookie_store {
+        CookieStore::Ephemeral => None,
+        CookieStore::Persistent(dir) => {
+            std::fs::create_dir_all(dir).map_err(|e| {
+                EmbedError::ConfigError(format!("Failed to create cookie store {}: {}", dir.display(), e))
+            })?;
+            Some(wry::WebContext::new(Some(dir.clone())))
+        }
+        CookieStore::Shared => Some(wry::WebContext::default()),
+    };
+    let mut webview_builder = match web_context.as_mut() {
+        Some(context) => WebViewBuilder::new_with_web_context(context),
+        None => WebViewBuilder::new(),
+    };
+
+    if let Some(proxy_config) = webview_proxy(&config, proxy.as_ref())? {
+        webview_builder = webview_builder.with_proxy_config(proxy_config);
+    }
+    if let Some(protocol) = header_protocol {
+        for scheme in super::header_protocol::SCHEMES {
+            let protocol = protocol.clone();
+            webview_builder = webview_builder
+                .with_asynchronous_custom_protocol(scheme.to_string(), move |_, request, responder| {
+                    protocol.handle(request, responder)
+                });
+        }
+    }
+
+    // The console bridge runs first so userscripts can log
+    webview_builder = webview_builder
+        .with_initialization_script(CONSOLE_BRIDGE_SCRIPT)
+        .with_initialization_script(FAVICON_BRIDGE_SCRIPT);
+    if let Some(ref dir) = config.userscripts_dir {
+        for script in super::config::load_userscripts(dir)? {
+            webview_builder = webview_builder.with_initialization_script(&script);
+        }
+    }
+
     // Build webview
-    let _webview = WebViewBuilder::new()
+    let page_load_callback = event_callback.clone();
+    let title_callback = event_callback.clone();
+    let page_load_proxy = event_loop.create_proxy();
+    let ipc_callback = event_callback.clone();
+    let webview = webview_builder
         .with_url(url)
+        .with_incognito(config.cookie_store == CookieStore::Ephemeral)
         .with_devtools(config.devtools)
+        .with_ipc_handler(move |request: wry::http::Request<String>| {
+            let body = request.body();
+            match parse_console_message(body).or_else(|| parse_favicon_message(body)) {
+                Some(event) => emit_event(&ipc_callback, event),
+                None => debug!("Ignoring ipc message: {}", body),
+            }
+        })
+        .with_on_page_load_handler(move |event, url| {
+            let url = super::header_protocol::origin_url(&url).to_string();
+            let (navigation, state) = match event {
+                PageLoadEvent::Started => (NavigationEvent::Started { url: url.clone() }, LoadState::Loading),
+                // wry reports the final URL, after any redirects
+                PageLoadEvent::Finished => (NavigationEvent::Completed { url: url.clone() }, LoadState::Complete),
+            };
+            let fraction = if state == LoadState::Complete { 1.0 } else { 0.0 };
+            emit_event(&page_load_callback, BrowserEvent::Navigation(navigation));
+            emit_event(&page_load_callback, BrowserEvent::load_progress(url.clone(), fraction));
+            emit_event(&page_load_callback, BrowserEvent::LoadStateChanged { state, url });
+            if state == LoadState::Complete {
+                let _ = page_load_proxy.send_event(UserEvent::PageLoaded);
+            }
+        })
+        .with_document_title_changed_handler(move |title| {
+            emit_event(&title_callback, BrowserEvent::TitleChanged { title });
+        })
         .build(&window)
         .map_err(|e| EmbedError::InitFailed(e.to_string()))?;
 
+    // wry only reports start and finish; WebKitGTK can fill in the steps between
+    #[cfg(target_os = "linux")]
+    {
+        use webkit2gtk::WebViewExt;
+        use wry::WebViewExtUnix;
+
+        let progress_callback = event_callback.clone();
+        webview.webview().connect_estimated_load_progress_notify(move |view| {
+            let fraction = view.estimated_load_progress() as f32;
+            // Start and finish are already reported by the page load handler
+            if fraction > 0.0 && fraction < 1.0 {
+                let url = view
+                    .uri()
+                    .map(|uri| super::header_protocol::origin_url(&uri).to_string())
+                    .unwrap_or_default();
+                emit_event(&progress_callback, BrowserEvent::load_progress(url, fraction));
+            }
+        });
+
+        // With a socket map the Unix proxy is also the webview's HTTP proxy,
+        // and asks for its token as proxy credentials
+        if let Some(unix_proxy) = proxy.as_ref().filter(|_| config.socket_map.is_some()) {
+            use webkit2gtk::{AuthenticationRequestExt, Credential, CredentialPersistence};
+
+            let token = unix_proxy.token().to_string();
+            webview.webview().connect_authenticate(move |_, request| {
+                if !request.is_for_proxy() {
+                    return false;
+                }
+                let credential = Credential::new(crate::proxy::PROXY_USER, &token, CredentialPersistence::ForSession);
+                request.authenticate(Some(&credential));
+                true
+            });
+        }
+    }
+
+    if config.zoom != 1.0 {
+        webview
+            .zoom(config.zoom as f64)
+            .map_err(|e| EmbedError::InitFailed(format!("Failed to set zoom: {}", e)))?;
+    }
+    if let Some(ratio) = config.device_pixel_ratio {
+        warn!("WebView backend cannot override the device pixel ratio ({})", ratio);
+    }
+
     info!("Browser window created, entering event loop");
 
-    // Emit load started
-    emit_event(
-        &event_callback,
-        BrowserEvent::LoadStarted {
-            url: url.to_string(),
-        },
-    );
+    // Only the first completed load is captured
+    let mut screenshot_path = config.screenshot_path.clone();
+    let screenshot_proxy = event_loop.create_proxy();
 
-    // Run event loop
-    event_loop.run(move |event, _, control_flow| {
+    // Run event loop until the window closes
+    event_loop.run_return(|event, _, control_flow| {
         *control_flow = ControlFlow::Wait;
 
         match event {
@@ -96,7 +335,9 @@ pub fn run_browser(
                 ..
             } => {
                 info!("Window close requested");
-                emit_event(&event_callback, BrowserEvent::Shutdown);
+                emit_event(&event_callback, BrowserEvent::CloseRequested);
+                proxy.take();
+                emit_window_closed(&event_callback);
                 *control_flow = ControlFlow::Exit;
             }
             Event::WindowEvent {
@@ -105,9 +346,262 @@ pub fn run_browser(
             } => {
                 debug!("Window resized to {}x{}", size.width, size.height);
             }
+            Event::UserEvent(UserEvent::PageLoaded) => {
+                if let Some(path) = screenshot_path.take() {
+                    info!("Capturing screenshot to {}", path.display());
+                    capture_screenshot(&webview, path, screenshot_proxy.clone());
+                }
+            }
+            Event::UserEvent(UserEvent::ScreenshotSaved(result)) => {
+                if let Err(message) = result {
+                    warn!("Screenshot failed: {}", message);
+                    emit_event(
+                        &event_callback,
+                        BrowserEvent::Error {
+                            message,
+                            recoverable: false,
+                        },
+                    );
+                }
+                proxy.take();
+                emit_window_closed(&event_callback);
+                *control_flow = ControlFlow::Exit;
+            }
+            Event::UserEvent(UserEvent::Command(command)) => {
+                debug!("Browser command: {:?}", command);
+                let result = match command {
+                    BrowserCommand::Navigate(url) => {
+                        emit_event(
+                            &event_callback,
+                            BrowserEvent::Navigation(NavigationEvent::Started { url: url.clone() }),
+                        );
+                        webview.load_url(&url)
+                    }
+                    BrowserCommand::Reload => webview.reload(),
+                    BrowserCommand::GoBack => webview.evaluate_script("history.back()"),
+                    BrowserCommand::EvaluateScript(js) => webview.evaluate_script(&js),
+                    BrowserCommand::Close | BrowserCommand::Terminate(_) => {
+                        proxy.take();
+                        emit_window_closed(&event_callback);
+                        *control_flow = ControlFlow::Exit;
+                        Ok(())
+                    }
+                };
+                if let Err(e) = result {
+                    emit_event(
+                        &event_callback,
+                        BrowserEvent::Error {
+                            message: e.to_string(),
+                            recoverable: true,
+                        },
+                    );
+                }
+            }
             _ => {}
         }
     });
+
+    Ok(())
+}
+
+/// Translate a message posted by `CONSOLE_BRIDGE_SCRIPT` into an event
+#[cfg(feature = "webview")]
+fn parse_console_message(body: &str) -> Option<BrowserEvent> {
+    let (header, message) = body.strip_prefix("console:")?.split_once('\n')?;
+    let mut fields = header.splitn(3, ':');
+    let level = match fields.next()? {
+        "debug" => ConsoleLevel::Debug,
+        "info" | "log" => ConsoleLevel::Info,
+        "warn" => ConsoleLevel::Warn,
+        "error" => ConsoleLevel::Error,
+        _ => return None,
+    };
+    let line = fields.next()?.parse().ok();
+    let source = fields.next().filter(|source| !source.is_empty());
+    Some(BrowserEvent::ConsoleMessage {
+        level,
+        message: message.to_string(),
+        source: source.map(str::to_string),
+        line,
+    })
+}
+
+/// Translate a message posted by `FAVICON_BRIDGE_SCRIPT` into an event
+#[cfg(feature = "webview")]
+fn parse_favicon_message(body: &str) -> Option<BrowserEvent> {
+    let href = body.strip_prefix("favicon:")?;
+    Some(BrowserEvent::FaviconChanged {
+        url: (!href.is_empty()).then(|| href.to_string()),
+    })
+}
+
+/// Snapshot the visible page and write it to `path` as a PNG
+///
+/// Completion is reported to the event loop as `UserEvent::ScreenshotSaved`.
+#[cfg(all(feature = "webview", target_os = "linux"))]
+fn capture_screenshot(
+    webview: &wry::WebView,
+    path: std::path::PathBuf,
+    proxy: tao::event_loop::EventLoopProxy<UserEvent>,
+) {
+    use webkit2gtk::{SnapshotOptions, SnapshotRegion, WebViewExt};
+    use wry::WebViewExtUnix;
+
+    webview.webview().snapshot(
+        SnapshotRegion::Visible,
+        SnapshotOptions::NONE,
+        None::<&gio::Cancellable>,
+        move |result| {
+            let saved = result
+                .map_err(|e| format!("Snapshot failed: {}", e))
+                .and_then(|surface| write_png(surface, &path));
+            let _ = proxy.send_event(UserEvent::ScreenshotSaved(saved));
+        },
+    );
+}
+
+#[cfg(all(feature = "webview", target_os = "linux"))]
+fn write_png(surface: cairo::Surface, path: &std::path::Path) -> Result<(), String> {
+    let image = cairo::ImageSurface::try_from(surface)
+        .map_err(|_| "Snapshot is not an image surface".to_string())?;
+    let mut file = std::fs::File::create(path)
+        .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    image
+        .write_to_png(&mut file)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Screenshots need WebKitGTK; other platforms report an error instead
+#[cfg(all(feature = "webview", not(target_os = "linux")))]
+fn capture_screenshot(
+    _webview: &wry::WebView,
+    _path: std::path::PathBuf,
+    proxy: tao::event_loop::EventLoopProxy<UserEvent>,
+) {
+    let _ = proxy.send_event(UserEvent::ScreenshotSaved(Err(
+        "Screenshots are only supported by the webview backend on Linux".into(),
+    )));
+}
+
+/// Convert a transport-aware URL into one the webview can load
+///
+/// Unix socket URLs start a [`UnixProxy`](crate::proxy::UnixProxy) and are
+/// rewritten to its localhost address; the proxy must be kept alive for as
+/// long as the page is in use. The URL is the proxy's login URL, which sets
+/// its token cookie. The proxy adds `headers` to every request and routes
+/// hosts in `socket_map` to their own sockets.
+/// `data:` URLs are checked against the basic `data:[<mediatype>][;base64],<data>`
+/// grammar and passed through. Other transport-aware URLs are rejected.
+#[cfg(feature = "webview")]
+fn convert_transport_url(
+    url: &str,
+    headers: &[(String, String)],
+    socket_map: Option<&crate::unix_connector::SocketMapping>,
+) -> Result<(String, Option<crate::proxy::UnixProxy>), EmbedError> {
+    use crate::types::Transport;
+    use crate::{TransportUrl, UnixConnector};
+
+    if url.get(..5).is_some_and(|scheme| scheme.eq_ignore_ascii_case("data:")) {
+        check_data_url(url)?;
+        return Ok((url.to_string(), None));
+    }
+
+    let transport_url = match TransportUrl::parse(url) {
+        Ok(parsed) => parsed,
+        // Not a URL Rigging understands (e.g. about:blank); let the webview decide
+        Err(_) => return Ok((url.to_string(), None)),
+    };
+
+    match transport_url.transport() {
+        Transport::Unix => {
+            let socket_path = transport_url.unix_socket_path().ok_or_else(|| {
+                EmbedError::InvalidUrl(format!("No socket path in {}", url))
+            })?;
+            let primary = UnixConnector::new(socket_path);
+            let proxy = match socket_map {
+                Some(map) => crate::proxy::UnixProxy::start_with_mapping(primary, map.clone(), headers),
+                None => crate::proxy::UnixProxy::start_with_headers(primary, headers),
+            }
+            .map_err(|e| EmbedError::InitFailed(format!("Failed to start Unix proxy: {}", e)))?;
+            let proxied = proxy.login_url_for(&transport_url);
+            debug!("Proxying {} via {}", url, proxied);
+            Ok((proxied, Some(proxy)))
+        }
+        Transport::Tcp if !transport_url.is_explicit_transport() => Ok((url.to_string(), None)),
+        Transport::Tcp => Ok((transport_url.url().to_string(), None)),
+        other => Err(EmbedError::InvalidUrl(format!(
+            "WebView backend does not support {} URLs (found: {}). Use Servo backend with 'servo' feature.",
+            other.display_name(),
+            url
+        ))),
+    }
+}
+
+/// Proxy the webview sends its requests through, if any
+///
+/// With a socket map every request goes to the Unix proxy, which routes it
+/// by host; the webview would otherwise look mapped hosts up in DNS and
+/// never reach it. Otherwise this is the configured `proxy`, if set.
+#[cfg(feature = "webview")]
+fn webview_proxy(
+    config: &BrowserConfig,
+    unix_proxy: Option<&crate::proxy::UnixProxy>,
+) -> Result<Option<wry::ProxyConfig>, EmbedError> {
+    use wry::{ProxyConfig, ProxyEndpoint};
+
+    if let Some(unix_proxy) = unix_proxy.filter(|_| config.socket_map.is_some()) {
+        if config.proxy.is_some() {
+            return Err(EmbedError::ConfigError("proxy cannot be combined with socket_map".into()));
+        }
+        let addr = unix_proxy.local_addr();
+        return Ok(Some(ProxyConfig::Http(ProxyEndpoint {
+            host: addr.ip().to_string(),
+            port: addr.port().to_string(),
+        })));
+    }
+
+    let Some(ref proxy) = config.proxy else {
+        return Ok(None);
+    };
+    let (scheme, host, port) = super::config::parse_proxy(proxy)?;
+    let endpoint = ProxyEndpoint {
+        host,
+        port: port.to_string(),
+    };
+    // The webview resolves names through SOCKS proxies either way
+    Ok(Some(match scheme.as_str() {
+        "http" => ProxyConfig::Http(endpoint),
+        _ => ProxyConfig::Socks5(endpoint),
+    }))
+}
+
+/// Event announcing where `proxy` is listening
+#[cfg(feature = "webview")]
+fn proxy_started_event(proxy: &crate::proxy::UnixProxy) -> BrowserEvent {
+    BrowserEvent::ProxyStarted {
+        port: proxy.local_addr().port(),
+        socket_path: proxy.socket_path().display().to_string(),
+    }
+}
+
+/// Check `url` is a well-formed `data:` URL
+#[cfg(feature = "webview")]
+fn check_data_url(url: &str) -> Result<(), EmbedError> {
+    let invalid = |reason: &str| EmbedError::InvalidUrl(format!("Malformed data URL ({}): {}", reason, url));
+
+    let (header, data) = url[5..].split_once(',').ok_or_else(|| invalid("missing `,`"))?;
+    let (media_type, base64) = match header.strip_suffix(";base64") {
+        Some(media_type) => (media_type, true),
+        None => (header, false),
+    };
+    if media_type.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err(invalid("media type contains whitespace"));
+    }
+    let base64_char = |c: char| c.is_ascii_alphanumeric() || "+/=%".contains(c);
+    if base64 && !data.chars().all(base64_char) {
+        return Err(invalid("invalid base64 data"));
+    }
+    Ok(())
 }
 
 /// Run browser with Servo engine
@@ -115,8 +609,9 @@ pub fn run_browser(
 pub fn run_browser(
     config: BrowserConfig,
     event_callback: Option<EventCallback>,
+    commands: Option<CommandReceiver>,
 ) -> Result<(), EmbedError> {
-    super::servo_backend::run_browser(config, event_callback)
+    super::servo_backend::run_browser(config, event_callback, commands)
 }
 
 /// Run browser - fallback when no backend is available
@@ -124,6 +619,7 @@ pub fn run_browser(
 pub fn run_browser(
     config: BrowserConfig,
     event_callback: Option<EventCallback>,
+    _commands: Option<CommandReceiver>,
 ) -> Result<(), EmbedError> {
     emit_event(&event_callback, BrowserEvent::Initialized);
 
@@ -131,6 +627,7 @@ pub fn run_browser(
     warn!("Enable 'webview' or 'servo' feature in Cargo.toml");
     info!("URL would be loaded: {}", config.url);
 
+    emit_event(&event_callback, BrowserEvent::Shutdown);
     Err(EmbedError::ServoNotAvailable)
 }
 
@@ -141,3 +638,325 @@ fn emit_event(callback: &Option<EventCallback>, event: BrowserEvent) {
     }
 }
 
+/// Report the window destroyed, then the engine shut down
+///
+/// The webview backend has a single window, so closing it ends the browser.
+#[cfg(feature = "webview")]
+fn emit_window_closed(callback: &Option<EventCallback>) {
+    emit_event(callback, BrowserEvent::WindowClosed { window_id: 1 });
+    emit_event(callback, BrowserEvent::Shutdown);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "webview")]
+    #[test]
+    #[ignore = "requires a display for the webview backend"]
+    fn test_webview_emits_title_changed() {
+        use std::sync::mpsc;
+
+        let (tx, rx) = mpsc::channel();
+        let handle = super::super::BrowserBuilder::new()
+            .url("data:text/html,<title>Rigging Test</title><p>hello</p>")
+            .headless()
+            .on_event(move |event| {
+                let _ = tx.send(event);
+            })
+            .spawn()
+            .unwrap();
+
+        let title = rx.iter().find_map(|event| match event {
+            BrowserEvent::TitleChanged { title } => Some(title),
+            _ => None,
+        });
+        assert_eq!(title.as_deref(), Some("Rigging Test"));
+
+        handle.close().unwrap();
+        assert!(handle.join().is_ok());
+    }
+
+    #[cfg(feature = "webview")]
+    #[test]
+    #[ignore = "requires a display for the webview backend"]
+    fn test_evaluate_script_changes_title() {
+        use std::sync::mpsc;
+
+        let (tx, rx) = mpsc::channel();
+        let handle = super::super::BrowserBuilder::new()
+            .url("data:text/html,<title>before</title>")
+            .headless()
+            .on_event(move |event| {
+                let _ = tx.send(event);
+            })
+            .spawn()
+            .unwrap();
+
+        // Scripts sent before the page has loaded may be dropped
+        rx.iter()
+            .find(|event| matches!(event, BrowserEvent::LoadStateChanged { state: LoadState::Complete, .. }))
+            .unwrap();
+
+        handle.evaluate_script("document.title = 'hi'; console.log('title set')").unwrap();
+
+        let mut title = None;
+        let mut console = None;
+        for event in rx.iter() {
+            match event {
+                BrowserEvent::TitleChanged { title: t } if t == "hi" => title = Some(t),
+                BrowserEvent::ConsoleMessage { message, .. } => console = Some(message),
+                _ => {}
+            }
+            if title.is_some() && console.is_some() {
+                break;
+            }
+        }
+        assert_eq!(title.as_deref(), Some("hi"));
+        assert_eq!(console.as_deref(), Some("title set"));
+
+        handle.close().unwrap();
+        assert!(handle.join().is_ok());
+    }
+
+    #[cfg(feature = "webview")]
+    #[test]
+    #[ignore = "requires a display for the webview backend"]
+    fn test_console_warn_emits_console_message() {
+        use std::sync::mpsc;
+
+        let (tx, rx) = mpsc::channel();
+        let handle = super::super::BrowserBuilder::new()
+            .url("data:text/html,<p>console</p>")
+            .headless()
+            .on_event(move |event| {
+                let _ = tx.send(event);
+            })
+            .spawn()
+            .unwrap();
+
+        rx.iter()
+            .find(|event| matches!(event, BrowserEvent::LoadStateChanged { state: LoadState::Complete, .. }))
+            .unwrap();
+
+        handle.evaluate_script("console.warn('x')").unwrap();
+
+        let warning = rx.iter().find_map(|event| match event {
+            BrowserEvent::ConsoleMessage { level, message, .. } => Some((level, message)),
+            _ => None,
+        });
+        assert_eq!(warning, Some((ConsoleLevel::Warn, "x".to_string())));
+
+        handle.close().unwrap();
+        assert!(handle.join().is_ok());
+    }
+
+    #[cfg(feature = "webview")]
+    #[test]
+    #[ignore = "requires a display for the webview backend"]
+    fn test_webview_emits_progress_before_complete() {
+        use std::sync::mpsc;
+
+        let (tx, rx) = mpsc::channel();
+        let handle = super::super::BrowserBuilder::new()
+            .url("data:text/html,<p>progress</p>")
+            .headless()
+            .on_event(move |event| {
+                let _ = tx.send(event);
+            })
+            .spawn()
+            .unwrap();
+
+        let mut progress = Vec::new();
+        for event in rx.iter() {
+            match event {
+                BrowserEvent::LoadProgress { fraction, .. } => progress.push(fraction),
+                BrowserEvent::LoadStateChanged { state: LoadState::Complete, .. } => break,
+                _ => {}
+            }
+        }
+        assert!(!progress.is_empty());
+        assert!(progress.iter().all(|f| (0.0..=1.0).contains(f)));
+
+        handle.close().unwrap();
+        assert!(handle.join().is_ok());
+    }
+
+    #[cfg(feature = "webview")]
+    #[test]
+    fn test_parse_console_message() {
+        match parse_console_message("console:warn:12:http://localhost/app.js\nlow disk: 5%") {
+            Some(BrowserEvent::ConsoleMessage { level, message, source, line }) => {
+                assert_eq!(level, ConsoleLevel::Warn);
+                assert_eq!(message, "low disk: 5%");
+                assert_eq!(source.as_deref(), Some("http://localhost/app.js"));
+                assert_eq!(line, Some(12));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(matches!(
+            parse_console_message("console:log::\nhello"),
+            Some(BrowserEvent::ConsoleMessage { level: ConsoleLevel::Info, source: None, line: None, .. })
+        ));
+        assert!(parse_console_message("console:trace::\nx").is_none());
+        assert!(parse_console_message("console:log:hello").is_none());
+        assert!(parse_console_message("something else").is_none());
+    }
+
+    #[cfg(feature = "webview")]
+    #[test]
+    fn test_parse_favicon_message() {
+        assert_eq!(
+            parse_favicon_message("favicon:http://localhost/icon.png"),
+            Some(BrowserEvent::FaviconChanged { url: Some("http://localhost/icon.png".to_string()) })
+        );
+        assert_eq!(parse_favicon_message("favicon:"), Some(BrowserEvent::FaviconChanged { url: None }));
+        assert_eq!(parse_favicon_message("console:log::\nfavicon:x"), None);
+    }
+
+    #[cfg(feature = "webview")]
+    #[test]
+    #[ignore = "requires a display for the webview backend"]
+    fn test_link_icon_emits_favicon_changed() {
+        use std::sync::mpsc;
+
+        let (tx, rx) = mpsc::channel();
+        let handle = super::super::BrowserBuilder::new()
+            .url("data:text/html,<link rel=icon href=http://localhost/icon.png><p>icon</p>")
+            .headless()
+            .on_event(move |event| {
+                let _ = tx.send(event);
+            })
+            .spawn()
+            .unwrap();
+
+        let favicon = rx.iter().find_map(|event| match event {
+            BrowserEvent::FaviconChanged { url } => Some(url),
+            _ => None,
+        });
+        assert_eq!(favicon, Some(Some("http://localhost/icon.png".to_string())));
+
+        handle.close().unwrap();
+        assert!(handle.join().is_ok());
+    }
+
+    #[cfg(feature = "webview")]
+    #[test]
+    fn test_proxy_started_event_reports_bound_port() {
+        let (_, proxy) = convert_transport_url("http::unix///tmp/rigging-test.sock/", &[], None).unwrap();
+        let proxy = proxy.expect("Unix URLs are proxied");
+        match proxy_started_event(&proxy) {
+            BrowserEvent::ProxyStarted { port, socket_path } => {
+                assert_ne!(port, 0);
+                assert_eq!(port, proxy.local_addr().port());
+                assert_eq!(socket_path, "/tmp/rigging-test.sock");
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "webview")]
+    #[test]
+    fn test_socket_map_sends_webview_through_unix_proxy() {
+        use crate::unix_connector::SocketMapping;
+        use wry::{ProxyConfig, ProxyEndpoint};
+
+        let mut map = SocketMapping::new();
+        map.add_mapping("api.local", "/tmp/rigging-test-api.sock");
+        let url = "http::unix///tmp/rigging-test.sock/";
+        let config = BrowserConfig::new(url).with_socket_map(map);
+        let (_, proxy) = convert_transport_url(url, &[], config.socket_map.as_ref()).unwrap();
+        let proxy = proxy.expect("Unix URLs are proxied");
+
+        match webview_proxy(&config, Some(&proxy)).unwrap() {
+            Some(ProxyConfig::Http(ProxyEndpoint { host, port })) => {
+                assert_eq!(host, "127.0.0.1");
+                assert_eq!(port, proxy.local_addr().port().to_string());
+            }
+            _ => panic!("webview should use the Unix proxy"),
+        }
+
+        // Without a map only the configured proxy applies
+        let plain = BrowserConfig::new(url);
+        assert!(webview_proxy(&plain, Some(&proxy)).unwrap().is_none());
+        let socks = BrowserConfig::new("http://localhost/").with_proxy("socks5://127.0.0.1:9050");
+        assert!(matches!(webview_proxy(&socks, None).unwrap(), Some(ProxyConfig::Socks5(_))));
+
+        let both = config.with_proxy("http://proxy.local:3128");
+        assert!(matches!(webview_proxy(&both, Some(&proxy)), Err(EmbedError::ConfigError(_))));
+    }
+
+    #[cfg(feature = "webview")]
+    #[test]
+    #[ignore = "requires a display for the webview backend"]
+    fn test_proxy_started_precedes_navigation() {
+        use std::sync::mpsc;
+
+        let (tx, rx) = mpsc::channel();
+        let handle = super::super::BrowserBuilder::new()
+            .url("http::unix///tmp/rigging-proxy-event.sock/")
+            .headless()
+            .on_event(move |event| {
+                let _ = tx.send(event);
+            })
+            .spawn()
+            .unwrap();
+
+        // The socket does not exist; the navigation still starts
+        let mut proxy_port = None;
+        for event in rx.iter() {
+            match event {
+                BrowserEvent::ProxyStarted { port, .. } => proxy_port = Some(port),
+                BrowserEvent::Navigation(NavigationEvent::Started { url }) => {
+                    let port = proxy_port.expect("ProxyStarted before navigation");
+                    assert_ne!(port, 0);
+                    assert!(url.starts_with(&format!("http://127.0.0.1:{}/", port)), "{}", url);
+                    break;
+                }
+                _ => {}
+            }
+        }
+        assert!(proxy_port.is_some());
+
+        handle.close().unwrap();
+        assert!(handle.join().is_ok());
+    }
+
+    #[cfg(feature = "webview")]
+    #[test]
+    fn test_convert_transport_url_keeps_path_and_query() {
+        let (url, proxy) = convert_transport_url("http::unix///tmp/rigging-test.sock/api/v1?x=1", &[], None).unwrap();
+        let proxy = proxy.expect("Unix URLs are proxied");
+        assert_eq!(url, format!("http://{}/.rigging-login/{}/api/v1?x=1", proxy.local_addr(), proxy.token()));
+
+        let (url, proxy) = convert_transport_url("http://localhost:8080/page", &[], None).unwrap();
+        assert!(proxy.is_none());
+        assert_eq!(url, "http://localhost:8080/page");
+
+        assert!(convert_transport_url("http::tor//example.onion/", &[], None).is_err());
+    }
+
+    #[cfg(feature = "webview")]
+    #[test]
+    fn test_convert_transport_url_data() {
+        for url in ["data:text/html,<h1>hi</h1>", "data:,plain", "DATA:text/plain;base64,aGk="] {
+            let (converted, proxy) = convert_transport_url(url, &[], None).unwrap();
+            assert_eq!(converted, url);
+            assert!(proxy.is_none());
+        }
+
+        for url in ["data:text/html", "data:text/plain;base64,not base64!", "data:text html,<p>x</p>"] {
+            assert!(
+                matches!(convert_transport_url(url, &[], None), Err(EmbedError::InvalidUrl(_))),
+                "{}",
+                url
+            );
+        }
+    }
+
+    #[test]
+    fn test_emit_event_without_callback() {
+        // Must be a no-op rather than a panic
+        emit_event(&None, BrowserEvent::Initialized);
+    }
+}
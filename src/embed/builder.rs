@@ -8,10 +8,18 @@
 //! browser instances. This is the main entry point for embedding Servo.
 
 use super::backend;
-use super::config::BrowserConfig;
+use super::config::{self, BrowserConfig};
 use super::events::{BrowserEvent, EventCallback};
+use super::handle::BrowserHandle;
 use super::EmbedError;
+use crate::transport_url::TransportUrl;
+use hyper::header::{HeaderName, HeaderValue};
 use log::{debug, info};
+use std::future::Future;
+use std::ops::RangeInclusive;
+
+/// Zoom factors accepted by `validate_config`
+const ZOOM_RANGE: RangeInclusive<f32> = 0.25..=5.0;
 
 /// Builder for creating and running browser instances
 ///
@@ -110,7 +118,8 @@ impl BrowserBuilder {
     /// - Window creation fails
     /// - URL loading fails
     /// - Event loop encounters an error
-    pub fn run(self) -> Result<(), EmbedError> {
+    pub fn run(mut self) -> Result<(), EmbedError> {
+        self.resolve_start_url();
         info!("Starting browser with URL: {}", self.config.url);
         debug!("Browser config: {:?}", self.config);
 
@@ -118,7 +127,84 @@ impl BrowserBuilder {
         self.validate_config()?;
 
         // Run the backend implementation
-        backend::run_browser(self.config, self.event_callback)
+        backend::run_browser(self.config, self.event_callback, None)
+    }
+
+    /// Build and start the browser, returning a handle to control it
+    ///
+    /// The browser runs on a dedicated thread, as with
+    /// [`run_async`](Self::run_async). The returned [`BrowserHandle`] sends
+    /// navigation and close commands into its event loop. Commands are
+    /// currently applied by the webview backend only.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration is invalid or the browser
+    /// thread cannot be spawned. Errors from the running browser are
+    /// returned by [`BrowserHandle::join`].
+    pub fn spawn(mut self) -> Result<BrowserHandle, EmbedError> {
+        self.resolve_start_url();
+        info!("Spawning browser with URL: {}", self.config.url);
+        debug!("Browser config: {:?}", self.config);
+
+        self.validate_config()?;
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let thread = std::thread::Builder::new()
+            .name("rigging-browser".to_string())
+            .spawn(move || backend::run_browser(self.config, self.event_callback, Some(rx)))
+            .map_err(|e| {
+                EmbedError::EventLoopError(format!("Failed to spawn browser thread: {}", e))
+            })?;
+
+        Ok(BrowserHandle::new(tx, thread))
+    }
+
+    /// Build and run the browser without blocking the caller
+    ///
+    /// The browser runs exactly as with [`run`](Self::run), but on a
+    /// dedicated thread named `rigging-browser`. The returned future resolves
+    /// with the browser's result once it shuts down, so it can be awaited or
+    /// raced against other tasks in an async application. The future does
+    /// not need to be polled for the browser to make progress, and dropping
+    /// it does not stop the browser.
+    ///
+    /// # Threading model
+    ///
+    /// Event callbacks are invoked on the browser thread, not on the task
+    /// awaiting the future. The Servo subprocess backend can run on any
+    /// thread. The webview backend's tao event loop must be created on the
+    /// main thread on macOS and Windows; on those platforms call `run()` from
+    /// `main` and move your async runtime to another thread instead.
+    pub fn run_async(self) -> impl Future<Output = Result<(), EmbedError>> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+
+        let spawned = std::thread::Builder::new()
+            .name("rigging-browser".to_string())
+            .spawn(move || {
+                let _ = tx.send(self.run());
+            });
+
+        async move {
+            spawned.map_err(|e| {
+                EmbedError::EventLoopError(format!("Failed to spawn browser thread: {}", e))
+            })?;
+
+            rx.await.unwrap_or_else(|_| {
+                Err(EmbedError::EventLoopError("Browser thread exited without a result".into()))
+            })
+        }
+    }
+
+    /// Replace a blank URL with the homepage (see [`BrowserConfig::start_url`])
+    ///
+    /// Backends only ever see the resolved URL, so they report a single
+    /// navigation to it rather than one to `about:blank` first.
+    fn resolve_start_url(&mut self) {
+        if self.config.start_url() != self.config.url {
+            debug!("Loading homepage in place of {:?}", self.config.url);
+            self.config.url = self.config.start_url().to_string();
+        }
     }
 
     /// Validate the configuration before running
@@ -128,6 +214,14 @@ impl BrowserBuilder {
             return Err(EmbedError::InvalidUrl("URL cannot be empty".into()));
         }
 
+        // Catch malformed transport URLs here rather than deep in the backend
+        let special = ["about:", "file:"].iter().any(|prefix| self.config.url.starts_with(prefix));
+        if !special {
+            TransportUrl::parse(&self.config.url).map_err(|e| {
+                EmbedError::InvalidUrl(format!("{:?}: {}", self.config.url, e))
+            })?;
+        }
+
         // Check window size is reasonable
         if self.config.width == 0 || self.config.height == 0 {
             return Err(EmbedError::WindowFailed(
@@ -146,6 +240,43 @@ impl BrowserBuilder {
             }
         }
 
+        // Check zoom and pixel ratio are usable
+        if !ZOOM_RANGE.contains(&self.config.zoom) {
+            return Err(EmbedError::ConfigError(format!(
+                "Zoom {} is outside {}..={}",
+                self.config.zoom,
+                ZOOM_RANGE.start(),
+                ZOOM_RANGE.end()
+            )));
+        }
+        if let Some(ratio) = self.config.device_pixel_ratio {
+            if !(ratio.is_finite() && ratio > 0.0) {
+                return Err(EmbedError::ConfigError(format!(
+                    "Device pixel ratio must be positive, got {}",
+                    ratio
+                )));
+            }
+        }
+
+        if let Some(ref proxy) = self.config.proxy {
+            config::parse_proxy(proxy)?;
+            // A socket map sends every request through the Unix proxy
+            #[cfg(feature = "unix")]
+            if self.config.socket_map.is_some() {
+                return Err(EmbedError::ConfigError("proxy cannot be combined with socket_map".into()));
+            }
+        }
+
+        // Check extra headers are valid HTTP
+        for (name, value) in &self.config.extra_headers {
+            if HeaderName::from_bytes(name.as_bytes()).is_err() {
+                return Err(EmbedError::ConfigError(format!("Invalid header name: {:?}", name)));
+            }
+            if HeaderValue::from_str(value).is_err() {
+                return Err(EmbedError::ConfigError(format!("Invalid value for header {}", name)));
+            }
+        }
+
         Ok(())
     }
 }
@@ -184,10 +315,172 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_blank_url_falls_back_to_homepage() {
+        let mut builder = BrowserBuilder::new()
+            .config(BrowserConfig::default().with_homepage("http://localhost/home"));
+        builder.resolve_start_url();
+        assert_eq!(builder.config.url, "http://localhost/home");
+
+        let mut builder = BrowserBuilder::new()
+            .config(BrowserConfig::new("").with_homepage("http://localhost/home"));
+        builder.resolve_start_url();
+        assert_eq!(builder.config.url, "http://localhost/home");
+        assert!(builder.validate_config().is_ok());
+    }
+
+    #[test]
+    fn test_explicit_url_overrides_homepage() {
+        let mut builder = BrowserBuilder::new()
+            .config(BrowserConfig::new("http://localhost/page").with_homepage("http://localhost/home"));
+        builder.resolve_start_url();
+        assert_eq!(builder.config.url, "http://localhost/page");
+    }
+
+    #[test]
+    fn test_blank_url_without_homepage() {
+        let mut builder = BrowserBuilder::new();
+        builder.resolve_start_url();
+        assert_eq!(builder.config.url, "about:blank");
+
+        // An empty URL is still rejected
+        let mut builder = BrowserBuilder::new().url("");
+        builder.resolve_start_url();
+        assert!(matches!(builder.validate_config(), Err(EmbedError::InvalidUrl(_))));
+    }
+
+    #[test]
+    fn test_validate_transport_url() {
+        for url in ["http::unix///tmp/app.sock/", "http://localhost/", "about:blank", "file:///tmp/index.html"] {
+            assert!(BrowserBuilder::new().url(url).validate_config().is_ok(), "{}", url);
+        }
+
+        for url in ["http::unxi///tmp/app.sock/", "http::unix::tor//x/", "not a url"] {
+            assert!(
+                matches!(BrowserBuilder::new().url(url).validate_config(), Err(EmbedError::InvalidUrl(_))),
+                "{}",
+                url
+            );
+        }
+    }
+
     #[test]
     fn test_validate_zero_size() {
         let builder = BrowserBuilder::new().size(0, 600);
         let result = builder.validate_config();
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_validate_zoom() {
+        let config = BrowserConfig::new("http://localhost/").with_zoom(1.5);
+        assert!(BrowserBuilder::new().config(config).validate_config().is_ok());
+
+        for zoom in [0.0, 10.0, f32::NAN] {
+            let config = BrowserConfig::new("http://localhost/").with_zoom(zoom);
+            assert!(BrowserBuilder::new().config(config).validate_config().is_err());
+        }
+
+        let config = BrowserConfig::new("http://localhost/").with_device_pixel_ratio(-1.0);
+        assert!(BrowserBuilder::new().config(config).validate_config().is_err());
+    }
+
+    #[test]
+    fn test_validate_proxy() {
+        let config = BrowserConfig::new("http://localhost/").with_proxy("socks5://127.0.0.1:9050");
+        assert!(BrowserBuilder::new().config(config).validate_config().is_ok());
+
+        let config = BrowserConfig::new("http://localhost/").with_proxy("gopher://proxy:70");
+        assert!(matches!(
+            BrowserBuilder::new().config(config).validate_config(),
+            Err(EmbedError::ConfigError(_))
+        ));
+    }
+
+    #[cfg(feature = "unix")]
+    #[test]
+    fn test_validate_proxy_with_socket_map() {
+        use crate::unix_connector::SocketMapping;
+
+        let config = BrowserConfig::new("http::unix///tmp/app.sock/")
+            .with_socket_map(SocketMapping::new())
+            .with_proxy("socks5://127.0.0.1:9050");
+        assert!(matches!(
+            BrowserBuilder::new().config(config).validate_config(),
+            Err(EmbedError::ConfigError(_))
+        ));
+    }
+
+    #[test]
+    fn test_validate_extra_headers() {
+        let valid = BrowserConfig::new("http://localhost/").with_header("X-Rigging-Token", "abc123");
+        assert!(BrowserBuilder::new().config(valid).validate_config().is_ok());
+
+        let bad_name = BrowserConfig::new("http://localhost/").with_header("Bad Header", "value");
+        assert!(matches!(
+            BrowserBuilder::new().config(bad_name).validate_config(),
+            Err(EmbedError::ConfigError(_))
+        ));
+
+        let bad_value = BrowserConfig::new("http://localhost/").with_header("X-Token", "line\nbreak");
+        assert!(matches!(
+            BrowserBuilder::new().config(bad_value).validate_config(),
+            Err(EmbedError::ConfigError(_))
+        ));
+    }
+
+    #[cfg(feature = "webview")]
+    #[test]
+    #[ignore = "requires a display for the webview backend"]
+    fn test_spawn_navigate_emits_started() {
+        use super::super::events::NavigationEvent;
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let handle = BrowserBuilder::new()
+            .url("about:blank")
+            .headless()
+            .on_event(move |event| {
+                let _ = tx.send(event);
+            })
+            .spawn()
+            .unwrap();
+
+        handle.navigate("data:text/html,<p>second</p>").unwrap();
+
+        let started = rx.iter().find(|event| {
+            matches!(event, BrowserEvent::Navigation(NavigationEvent::Started { url }) if url.starts_with("data:"))
+        });
+        assert!(started.is_some());
+
+        handle.close().unwrap();
+        assert!(handle.join().is_ok());
+    }
+
+    #[cfg(not(any(feature = "webview", feature = "servo")))]
+    #[test]
+    fn test_spawn_without_backend() {
+        let handle = BrowserBuilder::new().url("http://localhost/").spawn().unwrap();
+        assert!(matches!(handle.join(), Err(EmbedError::ServoNotAvailable)));
+    }
+
+    #[cfg(not(any(feature = "webview", feature = "servo")))]
+    #[tokio::test]
+    async fn test_run_async_resolves_on_shutdown() {
+        let (tx, rx) = std::sync::mpsc::channel();
+
+        let result = BrowserBuilder::new()
+            .url("http://localhost/")
+            .headless()
+            .on_event(move |event| {
+                let _ = tx.send(event);
+            })
+            .run_async()
+            .await;
+
+        // No backend is compiled in, so the browser shuts down immediately
+        assert!(matches!(result, Err(EmbedError::ServoNotAvailable)));
+        let events: Vec<_> = rx.try_iter().collect();
+        assert!(matches!(events.first(), Some(BrowserEvent::Initialized)));
+        assert!(matches!(events.last(), Some(BrowserEvent::Shutdown)));
+    }
 }
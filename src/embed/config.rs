@@ -9,16 +9,41 @@
 
 use std::path::PathBuf;
 
+use super::EmbedError;
+#[cfg(feature = "serde")]
+use std::path::Path;
+
 #[cfg(feature = "servo")]
 use crate::composed::ComposedConfig;
+#[cfg(feature = "unix")]
+use crate::unix_connector::SocketMapping;
 #[cfg(feature = "servo")]
 use crate::types::Transport;
 
+/// Where the browser keeps cookies and other site data
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum CookieStore {
+    /// In memory only; discarded when the browser exits
+    #[default]
+    Ephemeral,
+    /// Stored in the given directory, which is created if missing
+    Persistent(PathBuf),
+    /// The engine's default profile, shared with other instances
+    Shared,
+}
+
 /// Configuration for a browser window
 ///
 /// This struct is part of the **stable API**. Fields should not be removed,
 /// only added with appropriate defaults via `#[serde(default)]`.
+///
+/// With the `serde` feature it can be loaded from a file. Missing fields
+/// take their values from `BrowserConfig::default()`.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct BrowserConfig {
     /// URL to load (supports transport-aware URLs like `http::unix:///path/`)
     pub url: String,
@@ -50,6 +75,12 @@ pub struct BrowserConfig {
     /// Whether to enable developer tools (F12)
     pub devtools: bool,
 
+    /// Page zoom factor (1.0 = 100%)
+    pub zoom: f32,
+
+    /// Device pixel ratio override (None = use the display's)
+    pub device_pixel_ratio: Option<f32>,
+
     /// Custom user agent string (None = default Servo user agent)
     pub user_agent: Option<String>,
 
@@ -62,9 +93,26 @@ pub struct BrowserConfig {
     /// Path to output screenshot on exit (headless mode)
     pub screenshot_path: Option<PathBuf>,
 
-    /// Homepage URL (for new tabs, etc.)
+    /// Homepage URL, loaded at startup when `url` is blank (and for new tabs, etc.)
     pub homepage: Option<String>,
 
+    /// Extra HTTP headers sent with every request, as (name, value) pairs
+    pub extra_headers: Vec<(String, String)>,
+
+    /// Cookie and site data storage
+    pub cookie_store: CookieStore,
+
+    /// Proxy for plain TCP requests (`http://`, `socks5://` or `socks5h://` URL)
+    pub proxy: Option<String>,
+
+    /// Hostnames served by other Unix sockets (webview backend)
+    ///
+    /// When the start URL is a Unix socket URL, the webview sends every
+    /// request through its proxy, and requests for a mapped host go to that
+    /// host's socket instead. Cannot be combined with `proxy`.
+    #[cfg(feature = "unix")]
+    pub socket_map: Option<SocketMapping>,
+
     /// Allowed transport types (None = allow all)
     #[cfg(feature = "servo")]
     pub allowed_transports: Option<Vec<Transport>>,
@@ -72,6 +120,10 @@ pub struct BrowserConfig {
     /// Connector configuration (for Servo backend with transport-aware URLs)
     #[cfg(feature = "servo")]
     pub connector_config: Option<ComposedConfig>,
+
+    /// Servo binary to launch (None = `RIGGING_SERVO_BINARY`, then `PATH`)
+    #[cfg(feature = "servo")]
+    pub servo_binary: Option<PathBuf>,
 }
 
 impl Default for BrowserConfig {
@@ -87,15 +139,24 @@ impl Default for BrowserConfig {
             decorated: true,
             fullscreen: false,
             devtools: false,
+            zoom: 1.0,
+            device_pixel_ratio: None,
             user_agent: None,
             userscripts_dir: None,
             headless: false,
             screenshot_path: None,
             homepage: None,
+            extra_headers: Vec::new(),
+            cookie_store: CookieStore::Ephemeral,
+            proxy: None,
+            #[cfg(feature = "unix")]
+            socket_map: None,
             #[cfg(feature = "servo")]
             allowed_transports: None,
             #[cfg(feature = "servo")]
             connector_config: None,
+            #[cfg(feature = "servo")]
+            servo_binary: None,
         }
     }
 }
@@ -109,6 +170,24 @@ impl BrowserConfig {
         }
     }
 
+    /// Load a configuration from a TOML file
+    ///
+    /// Fields missing from the file keep their default values.
+    ///
+    /// ```toml
+    /// url = "http::unix///tmp/app.sock/"
+    /// title = "My App"
+    /// width = 1200
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn from_toml_file(path: impl AsRef<Path>) -> Result<Self, EmbedError> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| EmbedError::ConfigError(format!("{}: {}", path.display(), e)))?;
+        toml::from_str(&contents)
+            .map_err(|e| EmbedError::ConfigError(format!("{}: {}", path.display(), e)))
+    }
+
     /// Set window title
     pub fn with_title(mut self, title: impl Into<String>) -> Self {
         self.title = title.into();
@@ -158,6 +237,18 @@ impl BrowserConfig {
         self
     }
 
+    /// Set the page zoom factor
+    pub fn with_zoom(mut self, zoom: f32) -> Self {
+        self.zoom = zoom;
+        self
+    }
+
+    /// Override the device pixel ratio
+    pub fn with_device_pixel_ratio(mut self, ratio: f32) -> Self {
+        self.device_pixel_ratio = Some(ratio);
+        self
+    }
+
     /// Set custom user agent
     pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
         self.user_agent = Some(user_agent.into());
@@ -189,6 +280,43 @@ impl BrowserConfig {
         self
     }
 
+    /// The URL the browser loads first
+    ///
+    /// This is `url`, unless it is empty or `about:blank` and a homepage is
+    /// set, in which case it is the homepage.
+    pub fn start_url(&self) -> &str {
+        let blank = matches!(self.url.trim(), "" | "about:blank");
+        match self.homepage.as_deref() {
+            Some(homepage) if blank && !homepage.trim().is_empty() => homepage,
+            _ => &self.url,
+        }
+    }
+
+    /// Set where cookies and site data are stored
+    pub fn with_cookie_store(mut self, store: CookieStore) -> Self {
+        self.cookie_store = store;
+        self
+    }
+
+    /// Route plain TCP requests through an HTTP or SOCKS5 proxy
+    pub fn with_proxy(mut self, proxy: impl Into<String>) -> Self {
+        self.proxy = Some(proxy.into());
+        self
+    }
+
+    /// Route requests for mapped hostnames to their own Unix sockets
+    #[cfg(feature = "unix")]
+    pub fn with_socket_map(mut self, socket_map: SocketMapping) -> Self {
+        self.socket_map = Some(socket_map);
+        self
+    }
+
+    /// Add an HTTP header to send with every request
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.extra_headers.push((name.into(), value.into()));
+        self
+    }
+
     /// Restrict to specific transports (Servo backend only)
     #[cfg(feature = "servo")]
     pub fn with_transport_restriction(mut self, transports: Vec<Transport>) -> Self {
@@ -209,6 +337,68 @@ impl BrowserConfig {
         self.connector_config = Some(config);
         self
     }
+
+    /// Set the Servo binary to launch (Servo backend only)
+    #[cfg(feature = "servo")]
+    pub fn with_servo_binary(mut self, path: impl Into<PathBuf>) -> Self {
+        self.servo_binary = Some(path.into());
+        self
+    }
+}
+
+/// List the userscripts in `dir`: `*.js` files, sorted by path
+///
+/// Both backends inject them at document start in this order.
+pub(crate) fn userscript_files(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == "js") {
+            files.push(path);
+        }
+    }
+    files.sort_unstable();
+    Ok(files)
+}
+
+/// Read the userscripts in `dir` (see [`userscript_files`])
+pub(crate) fn load_userscripts(dir: &Path) -> Result<Vec<String>, EmbedError> {
+    let error = |e: std::io::Error| EmbedError::ConfigError(format!("userscripts {}: {}", dir.display(), e));
+    userscript_files(dir)
+        .map_err(error)?
+        .iter()
+        .map(|file| {
+            std::fs::read_to_string(file)
+                .map_err(|e| EmbedError::ConfigError(format!("userscript {}: {}", file.display(), e)))
+        })
+        .collect()
+}
+
+/// Port assumed for SOCKS proxies without one
+const DEFAULT_SOCKS_PORT: u16 = 1080;
+
+/// Parse and check a `BrowserConfig::proxy` URL
+///
+/// Returns the scheme, host and port. SOCKS proxies default to port 1080.
+pub(crate) fn parse_proxy(proxy: &str) -> Result<(String, String, u16), EmbedError> {
+    let invalid = |reason: &str| EmbedError::ConfigError(format!("Invalid proxy {:?}: {}", proxy, reason));
+
+    let url = url::Url::parse(proxy).map_err(|e| invalid(&e.to_string()))?;
+    let default_port = match url.scheme() {
+        "http" => 80,
+        "socks5" | "socks5h" => DEFAULT_SOCKS_PORT,
+        _ => return Err(invalid("expected an http://, socks5:// or socks5h:// URL")),
+    };
+    let host = url.host_str().ok_or_else(|| invalid("missing host"))?;
+    if !matches!(url.path(), "" | "/") || url.query().is_some() {
+        return Err(invalid("proxy URLs cannot have a path or query"));
+    }
+
+    Ok((
+        url.scheme().to_string(),
+        host.to_string(),
+        url.port().unwrap_or(default_port),
+    ))
 }
 
 #[cfg(test)]
@@ -242,5 +432,100 @@ mod tests {
         assert!(!config.fullscreen);
         assert!(!config.devtools);
         assert!(!config.headless);
+        assert_eq!(config.cookie_store, CookieStore::Ephemeral);
+        assert_eq!(config.zoom, 1.0);
+        assert_eq!(config.device_pixel_ratio, None);
+    }
+
+    #[test]
+    fn test_parse_proxy() {
+        assert_eq!(
+            parse_proxy("http://proxy.local:3128").unwrap(),
+            ("http".to_string(), "proxy.local".to_string(), 3128)
+        );
+        assert_eq!(
+            parse_proxy("socks5h://127.0.0.1").unwrap(),
+            ("socks5h".to_string(), "127.0.0.1".to_string(), 1080)
+        );
+
+        assert!(parse_proxy("ftp://proxy.local:21").is_err());
+        assert!(parse_proxy("https://proxy.local").is_err());
+        assert!(parse_proxy("socks5://proxy.local/path").is_err());
+        assert!(parse_proxy("not a url").is_err());
+    }
+
+    #[test]
+    fn test_load_userscripts() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        std::fs::create_dir_all(dir.join("nested.js")).unwrap();
+        std::fs::write(dir.join("b.js"), "second").unwrap();
+        std::fs::write(dir.join("a.js"), "first").unwrap();
+        std::fs::write(dir.join("notes.txt"), "skipped").unwrap();
+        std::fs::write(dir.join("c.js.bak"), "skipped").unwrap();
+
+        assert_eq!(load_userscripts(dir).unwrap(), vec!["first", "second"]);
+        assert!(matches!(
+            load_userscripts(&dir.join("missing")),
+            Err(EmbedError::ConfigError(_))
+        ));
+    }
+
+    #[test]
+    fn test_config_zoom_and_pixel_ratio() {
+        let config = BrowserConfig::new("http://localhost/")
+            .with_zoom(1.5)
+            .with_device_pixel_ratio(2.0);
+
+        assert_eq!(config.zoom, 1.5);
+        assert_eq!(config.device_pixel_ratio, Some(2.0));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_config_toml_round_trip() {
+        let config = BrowserConfig::new("http::unix///tmp/app.sock/")
+            .with_title("Round Trip")
+            .with_size(800, 600)
+            .with_min_size(400, 300)
+            .with_user_agent("Rigging/1.0")
+            .with_screenshot("/tmp/shot.png");
+
+        let text = toml::to_string(&config).unwrap();
+        let parsed: BrowserConfig = toml::from_str(&text).unwrap();
+
+        assert_eq!(parsed.url, config.url);
+        assert_eq!(parsed.title, config.title);
+        assert_eq!((parsed.width, parsed.height), (800, 600));
+        assert_eq!(parsed.min_size, Some((400, 300)));
+        assert_eq!(parsed.user_agent.as_deref(), Some("Rigging/1.0"));
+        assert_eq!(parsed.screenshot_path, Some(PathBuf::from("/tmp/shot.png")));
+        assert!(parsed.headless);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_config_from_partial_toml_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("browser.toml");
+        std::fs::write(&path, "url = \"http://localhost/\"\nwidth = 1280\ndevtools = true\n").unwrap();
+
+        let config = BrowserConfig::from_toml_file(&path).unwrap();
+        let defaults = BrowserConfig::default();
+
+        assert_eq!(config.url, "http://localhost/");
+        assert_eq!(config.width, 1280);
+        assert!(config.devtools);
+        assert_eq!(config.height, defaults.height);
+        assert_eq!(config.title, defaults.title);
+        assert_eq!(config.resizable, defaults.resizable);
+        assert_eq!(config.decorated, defaults.decorated);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_config_from_missing_toml_file() {
+        let result = BrowserConfig::from_toml_file("/nonexistent/rigging/browser.toml");
+        assert!(matches!(result, Err(EmbedError::ConfigError(_))));
     }
 }
@@ -6,12 +6,27 @@
 //!
 //! This module defines events that can occur during browser operation.
 //! These types are part of the stable API.
+//!
+//! With the `serde` feature the events can be serialized, for example to
+//! forward them from a browser subprocess. `BrowserEvent` is tagged by an
+//! `event` field and `NavigationEvent` by a `kind` field, so a navigation
+//! event is a single flat map:
+//!
+//! ```toml
+//! event = "Navigation"
+//! kind = "Started"
+//! url = "http://localhost/"
+//! ```
+//!
+//! Variants added in later versions deserialize to the `Unknown` catch-all.
 
 /// Events emitted by the browser during operation
 ///
 /// This enum is part of the **stable API**. Variants should not be removed,
 /// only added. Applications should handle unknown variants gracefully.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "event"))]
 #[non_exhaustive]
 pub enum BrowserEvent {
     /// Browser engine initialized successfully
@@ -23,12 +38,29 @@ pub enum BrowserEvent {
         window_id: u64,
     },
 
-    /// Window was closed
+    /// Window was destroyed
+    ///
+    /// Follows [`CloseRequested`](Self::CloseRequested) when the user closes
+    /// the window. After the last window closes the engine shuts down and
+    /// [`Shutdown`](Self::Shutdown) follows.
     WindowClosed {
         /// Window ID
         window_id: u64,
     },
 
+    /// The localhost proxy for a Unix socket URL is listening
+    ///
+    /// Sent by the webview backend, which cannot load Unix socket URLs
+    /// itself, before the first navigation starts. The page is served at
+    /// `http://127.0.0.1:<port>/`. The Servo backend connects to sockets
+    /// directly and does not send this event.
+    ProxyStarted {
+        /// Port the proxy bound on `127.0.0.1`
+        port: u16,
+        /// Unix socket the proxy forwards to
+        socket_path: String,
+    },
+
     /// Navigation event occurred
     Navigation(NavigationEvent),
 
@@ -40,6 +72,14 @@ pub enum BrowserEvent {
         url: String,
     },
 
+    /// Page load progress
+    LoadProgress {
+        /// URL being loaded
+        url: String,
+        /// Estimated fraction loaded, from 0.0 to 1.0
+        fraction: f32,
+    },
+
     /// Page title changed
     TitleChanged {
         /// New title
@@ -47,14 +87,25 @@ pub enum BrowserEvent {
     },
 
     /// Favicon changed
+    ///
+    /// Sent by the webview backend from the page's `<link rel=icon>`. Servo
+    /// only hands its embedder the decoded image, never the URL, so the
+    /// Servo backend does not send this event.
     FaviconChanged {
-        /// URL of the new favicon
+        /// Absolute URL of the new favicon, or `None` if the page has none
         url: Option<String>,
     },
 
     /// User requested to close the browser
     CloseRequested,
 
+    /// The browser engine process exited
+    ///
+    /// Sent by the Servo backend, which runs Servo as a subprocess, before
+    /// the window is reported closed. An unclean exit (a crash) is followed
+    /// by a non-recoverable [`Error`](Self::Error).
+    Exited(ExitInfo),
+
     /// Browser encountered an error
     Error {
         /// Error message
@@ -80,10 +131,56 @@ pub enum BrowserEvent {
         /// Alert message
         message: String,
     },
+
+    /// Browser engine has shut down, after its last window closed; no
+    /// further events will be emitted
+    Shutdown,
+
+    /// An event this version does not know, received from a newer one
+    ///
+    /// Only produced by deserialization; backends never emit it.
+    #[cfg_attr(feature = "serde", serde(other))]
+    Unknown,
+}
+
+impl BrowserEvent {
+    /// Create a `LoadProgress` event, clamping `fraction` to `0.0..=1.0`
+    pub fn load_progress(url: impl Into<String>, fraction: f32) -> Self {
+        let fraction = if fraction.is_nan() { 0.0 } else { fraction.clamp(0.0, 1.0) };
+        BrowserEvent::LoadProgress {
+            url: url.into(),
+            fraction,
+        }
+    }
+}
+
+/// How the browser engine process ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExitInfo {
+    /// Exit code, if the process exited on its own
+    pub code: Option<i32>,
+    /// Signal that killed the process (Unix only)
+    pub signal: Option<i32>,
+    /// Whether the exit was expected: a zero exit code, or the browser
+    /// being closed through its handle
+    pub clean: bool,
+}
+
+impl std::fmt::Display for ExitInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.code, self.signal) {
+            (Some(code), _) => write!(f, "exit code {}", code),
+            (None, Some(signal)) => write!(f, "signal {}", signal),
+            (None, None) => write!(f, "unknown exit status"),
+        }
+    }
 }
 
 /// Navigation events
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "kind"))]
 #[non_exhaustive]
 pub enum NavigationEvent {
     /// Navigation started
@@ -111,10 +208,15 @@ pub enum NavigationEvent {
         /// URL that was being loaded
         url: String,
     },
+
+    /// A navigation event this version does not know (deserialization only)
+    #[cfg_attr(feature = "serde", serde(other))]
+    Unknown,
 }
 
 /// Page load states
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum LoadState {
     /// Initial state, nothing loaded
     Initial,
@@ -130,6 +232,7 @@ pub enum LoadState {
 
 /// Console message log levels
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ConsoleLevel {
     /// Debug message
     Debug,
@@ -158,6 +261,72 @@ mod tests {
         assert!(debug_str.contains("Test Page"));
     }
 
+    #[test]
+    fn test_load_progress_is_clamped() {
+        for (input, expected) in [(0.5, 0.5), (1.7, 1.0), (-0.2, 0.0), (f32::NAN, 0.0)] {
+            match BrowserEvent::load_progress("http://localhost/", input) {
+                BrowserEvent::LoadProgress { fraction, .. } => assert_eq!(fraction, expected),
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_event_round_trip() {
+        let events = vec![
+            BrowserEvent::Initialized,
+            BrowserEvent::WindowCreated { window_id: 1 },
+            BrowserEvent::WindowClosed { window_id: 1 },
+            BrowserEvent::Navigation(NavigationEvent::Started { url: "http://localhost/".into() }),
+            BrowserEvent::Navigation(NavigationEvent::Completed { url: "http://localhost/".into() }),
+            BrowserEvent::Navigation(NavigationEvent::Failed {
+                url: "http://localhost/".into(),
+                error: "refused".into(),
+            }),
+            BrowserEvent::Navigation(NavigationEvent::Cancelled { url: "http://localhost/".into() }),
+            BrowserEvent::LoadStateChanged { state: LoadState::Interactive, url: "http://localhost/".into() },
+            BrowserEvent::load_progress("http://localhost/", 0.5),
+            BrowserEvent::ProxyStarted { port: 41234, socket_path: "/tmp/app.sock".into() },
+            BrowserEvent::TitleChanged { title: "Test Page".into() },
+            BrowserEvent::FaviconChanged { url: Some("http://localhost/favicon.ico".into()) },
+            BrowserEvent::FaviconChanged { url: None },
+            BrowserEvent::CloseRequested,
+            BrowserEvent::Exited(ExitInfo { code: Some(0), signal: None, clean: true }),
+            BrowserEvent::Exited(ExitInfo { code: None, signal: Some(9), clean: false }),
+            BrowserEvent::Error { message: "boom".into(), recoverable: true },
+            BrowserEvent::ConsoleMessage {
+                level: ConsoleLevel::Warn,
+                message: "x".into(),
+                source: Some("http://localhost/app.js".into()),
+                line: Some(3),
+            },
+            BrowserEvent::ConsoleMessage { level: ConsoleLevel::Debug, message: "y".into(), source: None, line: None },
+            BrowserEvent::Alert { message: "hi".into() },
+            BrowserEvent::Shutdown,
+        ];
+
+        for event in events {
+            let text = toml::to_string(&event).unwrap();
+            let parsed: BrowserEvent = toml::from_str(&text).unwrap();
+            assert_eq!(parsed, event, "serialized as:\n{}", text);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_unknown_variants_deserialize_to_catch_all() {
+        let event: BrowserEvent = toml::from_str("event = \"ZoomChanged\"\nzoom = 1.5\n").unwrap();
+        assert_eq!(event, BrowserEvent::Unknown);
+
+        let event: BrowserEvent =
+            toml::from_str("event = \"Navigation\"\nkind = \"Prerendered\"\nurl = \"http://localhost/\"\n").unwrap();
+        assert_eq!(event, BrowserEvent::Navigation(NavigationEvent::Unknown));
+
+        // The tag is still required
+        assert!(toml::from_str::<BrowserEvent>("title = \"x\"\n").is_err());
+    }
+
     #[test]
     fn test_load_state_equality() {
         assert_eq!(LoadState::Loading, LoadState::Loading);
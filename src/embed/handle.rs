@@ -0,0 +1,173 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Browser handle
+//!
+//! This module provides the `BrowserHandle` for controlling a browser that
+//! is running on another thread. The handle is part of the stable API.
+
+use super::EmbedError;
+use std::sync::mpsc::{Receiver, Sender};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// Commands sent from a `BrowserHandle` into the backend event loop
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum BrowserCommand {
+    /// Load a new URL
+    Navigate(String),
+    /// Reload the current page
+    Reload,
+    /// Go back in session history
+    GoBack,
+    /// Run JavaScript in the current page
+    EvaluateScript(String),
+    /// Close the browser
+    Close,
+    /// Stop the browser, forcibly once the grace period has passed
+    Terminate(Duration),
+}
+
+/// Receiving end of the command channel, owned by the backend
+pub(crate) type CommandReceiver = Receiver<BrowserCommand>;
+
+/// Handle for controlling a running browser
+///
+/// Returned by [`BrowserBuilder::spawn`](super::BrowserBuilder::spawn).
+/// Commands are delivered to the browser's event loop asynchronously; their
+/// effects are reported through the event callback.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let handle = BrowserBuilder::new()
+///     .url("http://localhost/")
+///     .spawn()?;
+///
+/// handle.navigate("http://localhost/settings")?;
+/// handle.close()?;
+/// handle.join()?;
+/// ```
+pub struct BrowserHandle {
+    commands: Sender<BrowserCommand>,
+    thread: JoinHandle<Result<(), EmbedError>>,
+}
+
+impl BrowserHandle {
+    pub(crate) fn new(
+        commands: Sender<BrowserCommand>,
+        thread: JoinHandle<Result<(), EmbedError>>,
+    ) -> Self {
+        Self { commands, thread }
+    }
+
+    /// Navigate to a URL
+    pub fn navigate(&self, url: impl Into<String>) -> Result<(), EmbedError> {
+        self.send(BrowserCommand::Navigate(url.into()))
+    }
+
+    /// Reload the current page
+    pub fn reload(&self) -> Result<(), EmbedError> {
+        self.send(BrowserCommand::Reload)
+    }
+
+    /// Go back in session history
+    pub fn go_back(&self) -> Result<(), EmbedError> {
+        self.send(BrowserCommand::GoBack)
+    }
+
+    /// Run JavaScript in the current page
+    ///
+    /// The script runs asynchronously and its result is discarded; observe
+    /// its effects through events such as `TitleChanged` or `ConsoleMessage`.
+    pub fn evaluate_script(&self, js: &str) -> Result<(), EmbedError> {
+        self.send(BrowserCommand::EvaluateScript(js.to_string()))
+    }
+
+    /// Close the browser
+    ///
+    /// Use [`join`](Self::join) to wait for it to finish shutting down.
+    pub fn close(&self) -> Result<(), EmbedError> {
+        self.send(BrowserCommand::Close)
+    }
+
+    /// Stop the browser, forcing it after `grace` if it has not exited
+    ///
+    /// For the Servo subprocess backend this sends SIGTERM, then SIGKILL
+    /// once `grace` has elapsed. `close()` does the same with a default
+    /// grace period. Other backends treat this like `close()`.
+    pub fn terminate(&self, grace: Duration) -> Result<(), EmbedError> {
+        self.send(BrowserCommand::Terminate(grace))
+    }
+
+    /// Check whether the browser thread has finished
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Wait for the browser to exit and return its result
+    pub fn join(self) -> Result<(), EmbedError> {
+        self.thread.join().unwrap_or_else(|_| {
+            Err(EmbedError::EventLoopError("Browser thread panicked".into()))
+        })
+    }
+
+    fn send(&self, command: BrowserCommand) -> Result<(), EmbedError> {
+        self.commands
+            .send(command)
+            .map_err(|_| EmbedError::EventLoopError("Browser is not running".into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_handle_sends_commands() {
+        let (tx, rx) = mpsc::channel();
+        let (done_tx, done_rx) = mpsc::channel::<()>();
+        let thread = std::thread::spawn(move || {
+            let _ = done_rx.recv();
+            Ok(())
+        });
+        let handle = BrowserHandle::new(tx, thread);
+
+        handle.navigate("http://localhost/next").unwrap();
+        handle.reload().unwrap();
+        handle.go_back().unwrap();
+        handle.evaluate_script("document.title").unwrap();
+        handle.close().unwrap();
+        handle.terminate(Duration::from_secs(1)).unwrap();
+
+        let received: Vec<_> = rx.try_iter().collect();
+        assert_eq!(
+            received,
+            vec![
+                BrowserCommand::Navigate("http://localhost/next".to_string()),
+                BrowserCommand::Reload,
+                BrowserCommand::GoBack,
+                BrowserCommand::EvaluateScript("document.title".to_string()),
+                BrowserCommand::Close,
+                BrowserCommand::Terminate(Duration::from_secs(1)),
+            ]
+        );
+
+        done_tx.send(()).unwrap();
+        assert!(handle.join().is_ok());
+    }
+
+    #[test]
+    fn test_handle_reports_stopped_browser() {
+        let (tx, rx) = mpsc::channel();
+        drop(rx);
+        let handle = BrowserHandle::new(tx, std::thread::spawn(|| Ok(())));
+
+        assert!(matches!(
+            handle.navigate("http://localhost/"),
+            Err(EmbedError::EventLoopError(_))
+        ));
+    }
+}
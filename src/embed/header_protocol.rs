@@ -0,0 +1,234 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Custom protocol adding `extra_headers` to every webview request
+//!
+//! wry can only attach headers to the first navigation. With headers
+//! configured, a TCP start URL is loaded as `rigging+http://...` (or
+//! `rigging+https://...`) instead, so the page and everything it loads by
+//! relative URL is handed to [`HeaderProtocol`], which sends each request to
+//! the real `http(s)://` origin with the headers set. Redirects within the
+//! origin stay on the protocol.
+//!
+//! Requests the page makes for absolute `http(s)://` URLs bypass the
+//! protocol and go out without the headers. Unix socket URLs do not need
+//! it: their proxy adds the headers itself.
+
+use crate::composed::ComposedConnector;
+use crate::types::TransportError;
+use http_body_util::{BodyExt, Full};
+use hyper::body::Bytes;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+use tokio::sync::mpsc;
+use wry::http::header::{self, HeaderName, HeaderValue};
+use wry::http::{Request, Response, StatusCode, Uri};
+use wry::RequestAsyncResponder;
+
+use super::EmbedError;
+
+/// Schemes to register the protocol for
+pub(crate) const SCHEMES: [&str; 2] = ["rigging+http", "rigging+https"];
+
+/// Prefix turning an `http(s)` URL into a protocol URL
+const PREFIX: &str = "rigging+";
+
+/// A request waiting for its response
+type Job = (Request<Vec<u8>>, RequestAsyncResponder);
+
+/// Handler for the [`SCHEMES`]
+///
+/// Requests are sent from a thread with its own Tokio runtime, which stops
+/// once every clone of the handler has been dropped.
+#[derive(Clone)]
+pub(crate) struct HeaderProtocol {
+    jobs: mpsc::UnboundedSender<Job>,
+}
+
+impl HeaderProtocol {
+    /// Start sending requests with `headers` added
+    pub(crate) fn start(headers: &[(String, String)]) -> Result<Self, EmbedError> {
+        let headers = headers
+            .iter()
+            .map(|(name, value)| {
+                let name = HeaderName::from_bytes(name.as_bytes())
+                    .map_err(|e| EmbedError::ConfigError(format!("Invalid header name {:?}: {}", name, e)))?;
+                let value = HeaderValue::from_str(value)
+                    .map_err(|e| EmbedError::ConfigError(format!("Invalid value for header {}: {}", name, e)))?;
+                Ok((name, value))
+            })
+            .collect::<Result<Vec<_>, EmbedError>>()?;
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| EmbedError::InitFailed(format!("Failed to start header protocol: {}", e)))?;
+        let (jobs, mut queued) = mpsc::unbounded_channel::<Job>();
+        std::thread::Builder::new()
+            .name("rigging-header-protocol".to_string())
+            .spawn(move || {
+                runtime.block_on(async move {
+                    let client: Client<ComposedConnector, Full<Bytes>> =
+                        Client::builder(TokioExecutor::new()).build(ComposedConnector::new());
+                    let headers = std::sync::Arc::new(headers);
+                    while let Some((request, responder)) = queued.recv().await {
+                        let (client, headers) = (client.clone(), headers.clone());
+                        tokio::spawn(async move {
+                            responder.respond(forward(&client, &headers, request).await);
+                        });
+                    }
+                });
+            })
+            .map_err(|e| EmbedError::InitFailed(format!("Failed to start header protocol: {}", e)))?;
+
+        Ok(Self { jobs })
+    }
+
+    /// Answer `request` once its origin has
+    pub(crate) fn handle(&self, request: Request<Vec<u8>>, responder: RequestAsyncResponder) {
+        if let Err(mpsc::error::SendError((_, responder))) = self.jobs.send((request, responder)) {
+            responder.respond(error_response(StatusCode::SERVICE_UNAVAILABLE, "Header protocol stopped"));
+        }
+    }
+}
+
+/// Protocol URL for an `http(s)` URL, or `None` for other schemes
+pub(crate) fn protocol_url(url: &str) -> Option<String> {
+    let scheme = url.split_once("://")?.0;
+    ["http", "https"]
+        .iter()
+        .any(|http| scheme.eq_ignore_ascii_case(http))
+        .then(|| format!("{}{}", PREFIX, url))
+}
+
+/// The `http(s)` URL behind a protocol URL; other URLs are returned as is
+pub(crate) fn origin_url(url: &str) -> &str {
+    url.strip_prefix(PREFIX).unwrap_or(url)
+}
+
+/// Send `request` to its origin, answering 502 if that fails
+async fn forward(
+    client: &Client<ComposedConnector, Full<Bytes>>,
+    headers: &[(HeaderName, HeaderValue)],
+    request: Request<Vec<u8>>,
+) -> Response<Vec<u8>> {
+    match send(client, headers, request).await {
+        Ok(response) => response,
+        Err(e) => {
+            log::warn!("Header protocol request failed: {}", e);
+            error_response(StatusCode::BAD_GATEWAY, &format!("Bad gateway: {}", e))
+        }
+    }
+}
+
+async fn send(
+    client: &Client<ComposedConnector, Full<Bytes>>,
+    headers: &[(HeaderName, HeaderValue)],
+    request: Request<Vec<u8>>,
+) -> Result<Response<Vec<u8>>, TransportError> {
+    let (mut parts, body) = request.into_parts();
+    let uri = parts.uri.to_string();
+    parts.uri = origin_url(&uri)
+        .parse::<Uri>()
+        .map_err(|e| TransportError::InvalidUrl(format!("{}: {}", uri, e)))?;
+    let origin = (parts.uri.scheme().cloned(), parts.uri.authority().cloned());
+    for (name, value) in headers {
+        parts.headers.insert(name.clone(), value.clone());
+    }
+
+    let response = client
+        .request(Request::from_parts(parts, Full::new(Bytes::from(body))))
+        .await
+        .map_err(|e| TransportError::ConnectionFailed(format!("HTTP request failed: {}", e)))?;
+    let (mut parts, body) = response.into_parts();
+    let body = body
+        .collect()
+        .await
+        .map_err(|e| TransportError::ConnectionFailed(format!("Failed to read response: {}", e)))?
+        .to_bytes();
+
+    // Keep redirects within the origin on the protocol; others leave it
+    // without the headers
+    let location = parts.headers.get(header::LOCATION).and_then(|value| value.to_str().ok());
+    let same_origin = location
+        .and_then(|location| location.parse::<Uri>().ok())
+        .is_some_and(|target| (target.scheme().cloned(), target.authority().cloned()) == origin);
+    if let Some(redirect) = location.filter(|_| same_origin).and_then(protocol_url) {
+        if let Ok(value) = HeaderValue::from_str(&redirect) {
+            parts.headers.insert(header::LOCATION, value);
+        }
+    }
+    Ok(Response::from_parts(parts, body.to_vec()))
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response<Vec<u8>> {
+    let mut response = Response::new(message.as_bytes().to_vec());
+    *response.status_mut() = status;
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_protocol_urls() {
+        assert_eq!(protocol_url("http://app.local/a?b=1").as_deref(), Some("rigging+http://app.local/a?b=1"));
+        assert_eq!(protocol_url("HTTPS://app.local/").as_deref(), Some("rigging+HTTPS://app.local/"));
+        assert_eq!(protocol_url("data:text/plain,hi"), None);
+        assert_eq!(protocol_url("about:blank"), None);
+
+        assert_eq!(origin_url("rigging+https://app.local/"), "https://app.local/");
+        assert_eq!(origin_url("https://app.local/"), "https://app.local/");
+    }
+    #[tokio::test]
+    async fn test_send_adds_headers_to_each_request() {
+        use hyper::server::conn::http1;
+        use hyper::service::service_fn;
+        use hyper_util::rt::TokioIo;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let service = service_fn(move |req: hyper::Request<hyper::body::Incoming>| async move {
+                    let mut response = hyper::Response::new(Full::new(Bytes::new()));
+                    match req.uri().path() {
+                        "/moved" => {
+                            *response.status_mut() = StatusCode::FOUND;
+                            let location = format!("http://{}/page", addr).parse().unwrap();
+                            response.headers_mut().insert(header::LOCATION, location);
+                        }
+                        "/away" => {
+                            *response.status_mut() = StatusCode::FOUND;
+                            let location = HeaderValue::from_static("http://elsewhere.local/");
+                            response.headers_mut().insert(header::LOCATION, location);
+                        }
+                        _ => {
+                            let token = req.headers().get("x-app-token").cloned();
+                            *response.body_mut() = Full::new(Bytes::from(token.map_or(Vec::new(), |t| t.as_bytes().to_vec())));
+                        }
+                    }
+                    Ok::<_, std::convert::Infallible>(response)
+                });
+                tokio::spawn(http1::Builder::new().serve_connection(TokioIo::new(stream), service));
+            }
+        });
+
+        let client = Client::builder(TokioExecutor::new()).build(ComposedConnector::new());
+        let headers = [(HeaderName::from_static("x-app-token"), HeaderValue::from_static("secret"))];
+        let get = |path: &str| Request::get(format!("rigging+http://{}{}", addr, path)).body(Vec::new()).unwrap();
+
+        for path in ["/", "/api/data"] {
+            let response = send(&client, &headers, get(path)).await.unwrap();
+            assert_eq!(response.body(), b"secret");
+        }
+
+        let moved = send(&client, &headers, get("/moved")).await.unwrap();
+        assert_eq!(moved.headers()[header::LOCATION], format!("rigging+http://{}/page", addr).as_str());
+        let away = send(&client, &headers, get("/away")).await.unwrap();
+        assert_eq!(away.headers()[header::LOCATION], "http://elsewhere.local/");
+    }
+}
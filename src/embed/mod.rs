@@ -85,6 +85,7 @@
 //!     .with_connector_config(ComposedConfig {
 //!         socket_dir: Some(PathBuf::from("/tmp")),
 //!         tor_socket: None,  // Disable Tor
+//!         ..Default::default()
 //!     });
 //!
 //! BrowserBuilder::new()
@@ -96,12 +97,17 @@ mod config;
 mod events;
 mod builder;
 mod backend;
+mod handle;
+#[cfg(feature = "webview")]
+mod header_protocol;
 #[cfg(feature = "servo")]
 mod servo_backend;
 
-pub use config::BrowserConfig;
-pub use events::{BrowserEvent, NavigationEvent, LoadState};
+pub use config::{BrowserConfig, CookieStore};
+pub(crate) use config::userscript_files;
+pub use events::{BrowserEvent, ExitInfo, NavigationEvent, LoadState};
 pub use builder::BrowserBuilder;
+pub use handle::BrowserHandle;
 
 // Re-export transport types for convenience (servo feature only)
 #[cfg(feature = "servo")]
@@ -131,6 +137,21 @@ pub enum EmbedError {
 
     #[error("Servo engine not available (feature not enabled)")]
     ServoNotAvailable,
+
+    #[error("Invalid configuration: {0}")]
+    ConfigError(String),
+
+    #[error("Servo binary not found (tried: {})", format_paths(.0))]
+    ServoBinaryNotFound(Vec<std::path::PathBuf>),
+}
+
+/// Render a list of paths as "a, b, c"
+fn format_paths(paths: &[std::path::PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|p| p.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 /// Check if any browser engine is available
@@ -14,48 +14,272 @@
 //! approach as an interim solution while full embedding is developed.
 
 use super::config::BrowserConfig;
-use super::events::{BrowserEvent, EventCallback};
+use super::events::{BrowserEvent, ConsoleLevel, EventCallback, ExitInfo, LoadState, NavigationEvent};
+use super::handle::{BrowserCommand, CommandReceiver};
 use super::EmbedError;
 use log::{debug, info, warn};
-use std::process::{Command, Stdio};
-use std::path::PathBuf;
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+/// Environment variable naming the Servo binary to launch
+pub const ENV_SERVO_BINARY: &str = "RIGGING_SERVO_BINARY";
+
+/// Well-known install locations, checked last
+const WELL_KNOWN_SERVO_PATHS: &[&str] = &["/usr/local/bin/servo", "/usr/bin/servo"];
 
 /// Find the servo binary
-fn find_servo_binary() -> Option<PathBuf> {
-    // Check common locations
-    let candidates = [
-        // Development builds
-        PathBuf::from("/home/marc/servo/target/release/servo"),
-        PathBuf::from("/home/marc/servo/target/debug/servo"),
-        // System-wide
-        PathBuf::from("/usr/local/bin/servo"),
-        PathBuf::from("/usr/bin/servo"),
-    ];
-
-    for path in &candidates {
-        if path.exists() {
-            return Some(path.clone());
-        }
-    }
-
-    // Try PATH
-    if let Ok(output) = Command::new("which").arg("servo").output() {
-        if output.status.success() {
-            let path_str = String::from_utf8_lossy(&output.stdout);
-            let path = PathBuf::from(path_str.trim());
-            if path.exists() {
-                return Some(path);
-            }
+///
+/// Checks, in order: the configured path, `RIGGING_SERVO_BINARY`, `servo`
+/// on `PATH`, then well-known install locations. On failure returns every
+/// path that was tried.
+fn find_servo_binary(configured: Option<&Path>) -> Result<PathBuf, Vec<PathBuf>> {
+    find_servo_binary_from(configured, |name| std::env::var_os(name))
+}
+
+/// [`find_servo_binary`], reading variables through `env`
+fn find_servo_binary_from(
+    configured: Option<&Path>,
+    env: impl Fn(&str) -> Option<std::ffi::OsString>,
+) -> Result<PathBuf, Vec<PathBuf>> {
+    find_servo_binary_in(
+        configured,
+        env(ENV_SERVO_BINARY).filter(|v| !v.is_empty()).map(PathBuf::from),
+        env("PATH"),
+    )
+}
+
+fn find_servo_binary_in(
+    configured: Option<&Path>,
+    from_env: Option<PathBuf>,
+    search_path: Option<std::ffi::OsString>,
+) -> Result<PathBuf, Vec<PathBuf>> {
+    let on_path = search_path
+        .iter()
+        .flat_map(std::env::split_paths)
+        .map(|dir| dir.join("servo"));
+
+    let candidates = configured
+        .map(Path::to_path_buf)
+        .into_iter()
+        .chain(from_env)
+        .chain(on_path)
+        .chain(WELL_KNOWN_SERVO_PATHS.iter().map(PathBuf::from));
+
+    let mut tried = Vec::new();
+    for path in candidates {
+        if is_executable(&path) {
+            return Ok(path);
         }
+        tried.push(path);
     }
 
-    None
+    Err(tried)
+}
+
+/// Check that a path is a file we can execute
+fn is_executable(path: &Path) -> bool {
+    match std::fs::metadata(path) {
+        #[cfg(unix)]
+        Ok(meta) => {
+            use std::os::unix::fs::PermissionsExt;
+            meta.is_file() && meta.permissions().mode() & 0o111 != 0
+        }
+        #[cfg(not(unix))]
+        Ok(meta) => meta.is_file(),
+        Err(_) => false,
+    }
+}
+
+/// Build the Servo command line for a configuration
+///
+/// | `BrowserConfig` field | Servo flag |
+/// |-----------------------|------------|
+/// | `width`, `height`     | `--screen-size=WxH` |
+/// | `user_agent`          | `--user-agent=UA` |
+/// | `headless`            | `--headless` |
+/// | `devtools`            | `--devtools` |
+/// | `screenshot_path`     | `--output=PATH` (exit after the first frame) |
+/// | `device_pixel_ratio`  | `--device-pixel-ratio=R` |
+/// | `proxy`               | `--pref=network_http_proxy_uri=URL`, `--pref=network_https_proxy_uri=URL` |
+/// | `userscripts_dir`     | `--userscripts=DIR` |
+/// | `url`                 | positional URL (last) |
+///
+/// Fields Servo has no flag for (`title`, `resizable`, `decorated`,
+/// `fullscreen`, size limits, `zoom`) are skipped with a debug log.
+fn build_command(servo_path: &Path, config: &BrowserConfig) -> Command {
+    let mut cmd = Command::new(servo_path);
+
+    // Set screen size (WIDTHxHEIGHT format)
+    cmd.arg(format!("--screen-size={}x{}", config.width, config.height));
+
+    if let Some(ref user_agent) = config.user_agent {
+        cmd.arg(format!("--user-agent={}", user_agent));
+    }
+    if config.headless {
+        cmd.arg("--headless");
+    }
+    if config.devtools {
+        cmd.arg("--devtools");
+    }
+    if let Some(ref screenshot) = config.screenshot_path {
+        cmd.arg(format!("--output={}", screenshot.display()));
+    }
+    if let Some(ratio) = config.device_pixel_ratio {
+        cmd.arg(format!("--device-pixel-ratio={}", ratio));
+    }
+    if let Some(ref proxy) = config.proxy {
+        cmd.arg(format!("--pref=network_http_proxy_uri={}", proxy));
+        cmd.arg(format!("--pref=network_https_proxy_uri={}", proxy));
+    }
+    if let Some(ref dir) = config.userscripts_dir {
+        cmd.arg(format!("--userscripts={}", dir.display()));
+    }
+    if config.zoom != 1.0 {
+        debug!("Servo subprocess ignores zoom {}", config.zoom);
+    }
+
+    if config.fullscreen || !config.decorated || !config.resizable
+        || config.min_size.is_some() || config.max_size.is_some()
+    {
+        debug!("Servo subprocess ignores window fullscreen/decoration/resize settings");
+    }
+
+    // Add the URL
+    cmd.arg(&config.url);
+
+    cmd
+}
+
+/// Translates Servo's stdout into browser events
+///
+/// Servo has no machine-readable output, so this recognises the embedder
+/// message names it logs:
+///
+/// | Line contains                   | Events |
+/// |---------------------------------|--------|
+/// | `LoadStart`                     | `Navigation(Started)`, `LoadStateChanged(Loading)`, `LoadProgress(0.0)` |
+/// | `HeadParsed`                    | `LoadStateChanged(Interactive)`, `LoadProgress(0.5)` |
+/// | `LoadComplete`                  | `LoadProgress(1.0)`, `LoadStateChanged(Complete)`, `Navigation(Completed)` |
+/// | `ChangePageTitle(Some("..."))`  | `TitleChanged` |
+/// | `ShowConsoleApiMessage(Level, "...")` | `ConsoleMessage` (no source or line) |
+/// | `panicked at`                   | `Error` (not recoverable) |
+/// | starts with `ERROR`             | `Error` (recoverable) |
+///
+/// A `url: "..."` field on any line updates the URL reported by later
+/// events. Unrecognised lines produce no events.
+///
+/// Servo reports favicons to its embedder as decoded images without a URL
+/// and logs nothing for them, so `FaviconChanged` is never produced.
+struct ServoOutputParser {
+    current_url: String,
+}
+
+impl ServoOutputParser {
+    fn new(initial_url: &str) -> Self {
+        Self {
+            current_url: initial_url.to_string(),
+        }
+    }
+
+    fn parse_line(&mut self, line: &str) -> Vec<BrowserEvent> {
+        if let Some(url) = quoted_after(line, "url: ") {
+            self.current_url = url;
+        }
+        let url = self.current_url.clone();
+
+        if line.contains("panicked at") {
+            return vec![BrowserEvent::Error {
+                message: line.trim().to_string(),
+                recoverable: false,
+            }];
+        }
+        if line.starts_with("ERROR") {
+            return vec![BrowserEvent::Error {
+                message: line.trim().to_string(),
+                recoverable: true,
+            }];
+        }
+        if let Some(event) = console_message(line) {
+            return vec![event];
+        }
+        if let Some(title) = quoted_after(line, "ChangePageTitle(Some(") {
+            return vec![BrowserEvent::TitleChanged { title }];
+        }
+        if line.contains("LoadStart") {
+            return vec![
+                BrowserEvent::Navigation(NavigationEvent::Started { url: url.clone() }),
+                BrowserEvent::LoadStateChanged { state: LoadState::Loading, url: url.clone() },
+                BrowserEvent::load_progress(url, 0.0),
+            ];
+        }
+        if line.contains("HeadParsed") {
+            return vec![
+                BrowserEvent::LoadStateChanged { state: LoadState::Interactive, url: url.clone() },
+                BrowserEvent::load_progress(url, 0.5),
+            ];
+        }
+        if line.contains("LoadComplete") {
+            return vec![
+                BrowserEvent::load_progress(url.clone(), 1.0),
+                BrowserEvent::LoadStateChanged { state: LoadState::Complete, url: url.clone() },
+                BrowserEvent::Navigation(NavigationEvent::Completed { url }),
+            ];
+        }
+
+        Vec::new()
+    }
+}
+
+/// Translate Servo's `ShowConsoleApiMessage(Level, "text")` log line
+fn console_message(line: &str) -> Option<BrowserEvent> {
+    let (_, rest) = line.split_once("ShowConsoleApiMessage(")?;
+    let (level, _) = rest.split_once(',')?;
+    let level = match level.trim() {
+        "Debug" | "Trace" => ConsoleLevel::Debug,
+        "Log" | "Info" => ConsoleLevel::Info,
+        "Warn" => ConsoleLevel::Warn,
+        "Error" => ConsoleLevel::Error,
+        _ => return None,
+    };
+    Some(BrowserEvent::ConsoleMessage {
+        level,
+        message: quoted_after(rest, ", ")?,
+        source: None,
+        line: None,
+    })
+}
+
+/// Extract the double-quoted string following `key` in `line`
+fn quoted_after(line: &str, key: &str) -> Option<String> {
+    let rest = &line[line.find(key)? + key.len()..];
+    let rest = rest.strip_prefix('"')?;
+    let end = rest.find('"')?;
+    Some(rest[..end].to_string())
+}
+
+/// Grace period between SIGTERM and SIGKILL when closing the browser
+const DEFAULT_SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+
+/// How often to check for Servo exiting while waiting for input
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Input to the subprocess supervision loop
+enum Input {
+    Event(BrowserEvent),
+    Command(BrowserCommand),
 }
 
 /// Run the browser with Servo engine (subprocess approach)
+///
+/// `Close` and `Terminate` commands stop the subprocess; other handle
+/// commands cannot be applied to a running subprocess and are ignored.
 pub fn run_browser(
     config: BrowserConfig,
     event_callback: Option<EventCallback>,
+    commands: Option<CommandReceiver>,
 ) -> Result<(), EmbedError> {
     // Emit initialization event
     if let Some(ref cb) = event_callback {
@@ -67,8 +291,8 @@ pub fn run_browser(
     debug!("URL: {}", config.url);
 
     // Find the servo binary
-    let servo_path = find_servo_binary()
-        .ok_or_else(|| EmbedError::ServoNotAvailable)?;
+    let servo_path = find_servo_binary(config.servo_binary.as_deref())
+        .map_err(EmbedError::ServoBinaryNotFound)?;
 
     info!("Using Servo binary: {}", servo_path.display());
 
@@ -77,44 +301,456 @@ pub fn run_browser(
         cb(BrowserEvent::WindowCreated { window_id: 1 });
     }
 
-    // Build the command
-    let mut cmd = Command::new(&servo_path);
-
-    // Set screen size (WIDTHxHEIGHT format)
-    cmd.arg(format!("--screen-size={}x{}", config.width, config.height));
-
-    // Add the URL
-    cmd.arg(&config.url);
+    let mut cmd = build_command(&servo_path, &config);
 
-    // Inherit stdout/stderr for debugging
-    cmd.stdout(Stdio::inherit());
+    // Capture stdout to translate it into events; stderr stays inherited
+    cmd.stdout(Stdio::piped());
     cmd.stderr(Stdio::inherit());
 
     // Emit load started
     if let Some(ref cb) = event_callback {
         cb(BrowserEvent::LoadStateChanged {
-            state: super::events::LoadState::Loading,
+            state: LoadState::Loading,
             url: config.url.clone(),
         });
     }
 
     info!("Launching Servo with URL: {}", config.url);
 
-    // Run Servo and wait for it to exit
-    let status = cmd.status()
+    let mut child = cmd.spawn()
         .map_err(|e| EmbedError::InitFailed(format!("Failed to run Servo: {}", e)))?;
 
-    // Emit close/shutdown
-    if let Some(ref cb) = event_callback {
-        cb(BrowserEvent::CloseRequested);
+    let (tx, rx) = mpsc::channel();
+
+    // Echo Servo's output and translate it into events until it closes stdout
+    if let Some(stdout) = child.stdout.take() {
+        let tx = tx.clone();
+        let mut parser = ServoOutputParser::new(&config.url);
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                println!("{}", line);
+                for event in parser.parse_line(&line) {
+                    if tx.send(Input::Event(event)).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+    }
+
+    if let Some(commands) = commands {
+        let tx = tx.clone();
+        std::thread::spawn(move || {
+            for command in commands {
+                if tx.send(Input::Command(command)).is_err() {
+                    break;
+                }
+            }
+        });
     }
+    drop(tx);
+
+    let mut close_requested = false;
+    let wait_error = |e: std::io::Error| EmbedError::InitFailed(format!("Failed to wait for Servo: {}", e));
+
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(wait_error)? {
+            break status;
+        }
+
+        let grace = match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(Input::Event(event)) => {
+                if let Some(ref cb) = event_callback {
+                    cb(event);
+                }
+                continue;
+            }
+            Ok(Input::Command(BrowserCommand::Close)) => DEFAULT_SHUTDOWN_GRACE,
+            Ok(Input::Command(BrowserCommand::Terminate(grace))) => grace,
+            Ok(Input::Command(other)) => {
+                warn!("{:?} is not supported by the Servo subprocess backend", other);
+                continue;
+            }
+            Err(RecvTimeoutError::Timeout) => continue,
+            // Stdout closed and no handle: nothing left to do but wait
+            Err(RecvTimeoutError::Disconnected) => break child.wait().map_err(wait_error)?,
+        };
+
+        info!("Stopping Servo (grace period {:?})", grace);
+        close_requested = true;
+        if let Some(ref cb) = event_callback {
+            cb(BrowserEvent::CloseRequested);
+        }
+        break terminate_child(&mut child, grace).map_err(wait_error)?;
+    };
 
-    if status.success() {
-        info!("Servo exited successfully");
-        Ok(())
+    let exit = exit_info(status, close_requested);
+    if exit.clean {
+        info!("Servo exited ({})", exit);
     } else {
-        warn!("Servo exited with status: {:?}", status.code());
-        // Don't treat non-zero exit as an error - user might have closed the window
-        Ok(())
+        warn!("Servo exited unexpectedly ({})", exit);
+    }
+
+    // Emit exit/close/shutdown, after any events Servo printed before
+    // exiting. Servo's one window goes away with the process.
+    if let Some(ref cb) = event_callback {
+        for input in rx.try_iter() {
+            if let Input::Event(event) = input {
+                cb(event);
+            }
+        }
+        if !close_requested {
+            cb(BrowserEvent::CloseRequested);
+        }
+        cb(BrowserEvent::Exited(exit));
+        if !exit.clean {
+            cb(BrowserEvent::Error {
+                message: format!("Servo exited unexpectedly ({})", exit),
+                recoverable: false,
+            });
+        }
+        cb(BrowserEvent::WindowClosed { window_id: 1 });
+        cb(BrowserEvent::Shutdown);
+    }
+
+    // Crashes are reported through events; the browser has still finished
+    Ok(())
+}
+
+/// Describe how Servo exited
+///
+/// `stopped` is set when the backend stopped Servo on request, in which
+/// case dying from its SIGTERM (or the SIGKILL after the grace period) is
+/// a clean exit rather than a crash.
+fn exit_info(status: ExitStatus, stopped: bool) -> ExitInfo {
+    #[cfg(unix)]
+    let (signal, stop_signal) = {
+        use std::os::unix::process::ExitStatusExt;
+        let signal = status.signal();
+        (signal, matches!(signal, Some(libc::SIGTERM | libc::SIGKILL)))
+    };
+    // Elsewhere the child is killed outright, with no signal to tell apart
+    #[cfg(not(unix))]
+    let (signal, stop_signal) = (None, true);
+
+    ExitInfo {
+        code: status.code(),
+        signal,
+        clean: status.success() || (stopped && stop_signal),
+    }
+}
+
+/// Stop a child process, politely first
+///
+/// Sends SIGTERM and waits up to `grace` for the child to exit, then
+/// SIGKILLs it. The child is always reaped before returning. On non-Unix
+/// platforms the child is killed immediately.
+fn terminate_child(child: &mut Child, grace: Duration) -> std::io::Result<ExitStatus> {
+    if let Some(status) = child.try_wait()? {
+        return Ok(status);
+    }
+
+    #[cfg(unix)]
+    {
+        // SAFETY: `kill` has no memory-safety requirements; the pid belongs to
+        // our child, which has not been reaped yet so cannot have been reused.
+        if unsafe { libc::kill(child.id() as libc::pid_t, libc::SIGTERM) } == 0 {
+            let deadline = Instant::now() + grace;
+            while Instant::now() < deadline {
+                if let Some(status) = child.try_wait()? {
+                    return Ok(status);
+                }
+                std::thread::sleep(Duration::from_millis(20));
+            }
+            warn!("Servo still running {:?} after SIGTERM, killing", grace);
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = grace;
+
+    child.kill()?;
+    child.wait()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_command_args() {
+        let config = BrowserConfig::new("http://localhost/")
+            .with_size(800, 600)
+            .with_user_agent("Rigging/1.0")
+            .with_devtools(true)
+            .with_screenshot("/tmp/shot.png")
+            .with_device_pixel_ratio(1.5)
+            .with_userscripts_dir("/etc/rigging/scripts");
+
+        let cmd = build_command(Path::new("/usr/bin/servo"), &config);
+        let args: Vec<_> = cmd.get_args().map(|a| a.to_string_lossy().into_owned()).collect();
+
+        assert_eq!(cmd.get_program(), "/usr/bin/servo");
+        assert_eq!(
+            args,
+            vec![
+                "--screen-size=800x600",
+                "--user-agent=Rigging/1.0",
+                "--headless",
+                "--devtools",
+                "--output=/tmp/shot.png",
+                "--device-pixel-ratio=1.5",
+                "--userscripts=/etc/rigging/scripts",
+                "http://localhost/",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_output_parser_events() {
+        let mut parser = ServoOutputParser::new("http://localhost/");
+
+        let events = parser.parse_line("EmbedderMsg::LoadStart { url: \"http://localhost/next\" }");
+        assert!(matches!(
+            &events[..],
+            [
+                BrowserEvent::Navigation(NavigationEvent::Started { url }),
+                BrowserEvent::LoadStateChanged { state: LoadState::Loading, .. },
+                BrowserEvent::LoadProgress { fraction, .. },
+            ] if url == "http://localhost/next" && *fraction == 0.0
+        ));
+
+        let events = parser.parse_line("EmbedderMsg::HeadParsed");
+        assert!(matches!(
+            &events[..],
+            [
+                BrowserEvent::LoadStateChanged { state: LoadState::Interactive, url },
+                BrowserEvent::LoadProgress { fraction, .. },
+            ] if url == "http://localhost/next" && *fraction == 0.5
+        ));
+
+        let events = parser.parse_line("EmbedderMsg::ChangePageTitle(Some(\"Hello\"))");
+        assert!(matches!(&events[..], [BrowserEvent::TitleChanged { title }] if title == "Hello"));
+
+        let events = parser.parse_line("EmbedderMsg::ShowConsoleApiMessage(Warn, \"x\")");
+        assert!(matches!(
+            &events[..],
+            [BrowserEvent::ConsoleMessage { level: ConsoleLevel::Warn, message, source: None, line: None }]
+                if message == "x"
+        ));
+        assert!(parser.parse_line("EmbedderMsg::ShowConsoleApiMessage(Loud, \"x\")").is_empty());
+
+        let events = parser.parse_line("EmbedderMsg::LoadComplete");
+        assert!(matches!(
+            &events[..],
+            [
+                BrowserEvent::LoadProgress { fraction, .. },
+                BrowserEvent::LoadStateChanged { state: LoadState::Complete, .. },
+                BrowserEvent::Navigation(NavigationEvent::Completed { url }),
+            ] if url == "http://localhost/next" && *fraction == 1.0
+        ));
+
+        let events = parser.parse_line("ERROR script: failed to compile");
+        assert!(matches!(&events[..], [BrowserEvent::Error { recoverable: true, .. }]));
+
+        let events = parser.parse_line("thread 'main' panicked at src/main.rs:1:1");
+        assert!(matches!(&events[..], [BrowserEvent::Error { recoverable: false, .. }]));
+
+        assert!(parser.parse_line("some unrelated output").is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_terminate_child_sigterm() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+        let started = Instant::now();
+
+        let status = terminate_child(&mut child, Duration::from_secs(5)).unwrap();
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(status.signal(), Some(libc::SIGTERM));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_terminate_child_kills_after_grace() {
+        use std::os::unix::process::ExitStatusExt;
+
+        // Ignores SIGTERM, so must be SIGKILLed once the grace period ends
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg("trap '' TERM; while true; do sleep 1; done")
+            .spawn()
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        let started = Instant::now();
+
+        let status = terminate_child(&mut child, Duration::from_millis(300)).unwrap();
+
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(300));
+        assert!(elapsed < Duration::from_secs(5));
+        assert_eq!(status.signal(), Some(libc::SIGKILL));
+    }
+
+    /// Write an executable stub script into a fresh temp directory
+    #[cfg(unix)]
+    fn stub_servo() -> (tempfile::TempDir, PathBuf) {
+        stub_servo_running("exit 0")
+    }
+
+    /// Write an executable stub that runs `script` with `sh`
+    #[cfg(unix)]
+    fn stub_servo_running(script: &str) -> (tempfile::TempDir, PathBuf) {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let stub = dir.path().join("servo");
+        std::fs::write(&stub, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        std::fs::set_permissions(&stub, std::fs::Permissions::from_mode(0o755)).unwrap();
+        (dir, stub)
+    }
+
+    /// Run the browser on a stub running `script` and collect its events
+    #[cfg(unix)]
+    fn run_stub(script: &str) -> Vec<BrowserEvent> {
+        let (_dir, stub) = stub_servo_running(script);
+        let (tx, rx) = mpsc::channel();
+        let config = BrowserConfig::new("http://localhost/").with_servo_binary(stub);
+        let callback: EventCallback = Box::new(move |event| {
+            let _ = tx.send(event);
+        });
+        run_browser(config, Some(callback), None).unwrap();
+        rx.try_iter().collect()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exit_reason_reported() {
+        let exited = |events: &[BrowserEvent]| {
+            events.iter().find_map(|event| match event {
+                BrowserEvent::Exited(info) => Some(*info),
+                _ => None,
+            })
+        };
+        let crash_reported = |events: &[BrowserEvent]| {
+            events.iter().any(|event| matches!(event, BrowserEvent::Error { recoverable: false, .. }))
+        };
+
+        let events = run_stub("exit 0");
+        assert_eq!(exited(&events), Some(ExitInfo { code: Some(0), signal: None, clean: true }));
+        assert!(!crash_reported(&events));
+
+        let events = run_stub("exit 1");
+        assert_eq!(exited(&events), Some(ExitInfo { code: Some(1), signal: None, clean: false }));
+        assert!(crash_reported(&events));
+
+        let events = run_stub("kill -KILL $$");
+        let info = exited(&events).unwrap();
+        assert_eq!(info, ExitInfo { code: None, signal: Some(libc::SIGKILL), clean: false });
+        assert_eq!(info.to_string(), format!("signal {}", libc::SIGKILL));
+        assert!(crash_reported(&events));
+
+        // The exit comes before the window closes and the engine shuts down
+        let position = |wanted: &BrowserEvent| events.iter().position(|event| event == wanted).unwrap();
+        assert!(position(&BrowserEvent::Exited(info)) < position(&BrowserEvent::WindowClosed { window_id: 1 }));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exit_info_when_stopped() {
+        use std::os::unix::process::ExitStatusExt;
+
+        // Raw wait statuses: a signal number alone means killed by it
+        let terminated = ExitStatus::from_raw(libc::SIGTERM);
+        assert!(exit_info(terminated, true).clean);
+        assert!(!exit_info(terminated, false).clean);
+        let segfault = ExitStatus::from_raw(libc::SIGSEGV);
+        assert!(!exit_info(segfault, true).clean);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_window_closed_precedes_shutdown() {
+        // The stub exits at once: its window opens, then closes, then the engine stops
+        let events = run_stub("exit 0");
+        let position = |wanted: &BrowserEvent| events.iter().position(|event| event == wanted).unwrap();
+        let created = position(&BrowserEvent::WindowCreated { window_id: 1 });
+        let close_requested = position(&BrowserEvent::CloseRequested);
+        let closed = position(&BrowserEvent::WindowClosed { window_id: 1 });
+        assert!(created < close_requested && close_requested < closed);
+        assert_eq!(closed, events.len() - 2);
+        assert_eq!(events.last(), Some(&BrowserEvent::Shutdown));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_find_servo_binary_from_env() {
+        let (_dir, stub) = stub_servo();
+        let env = |name: &str| (name == ENV_SERVO_BINARY).then(|| stub.clone().into_os_string());
+        assert_eq!(find_servo_binary_from(None, env), Ok(stub));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_find_servo_binary_precedence() {
+        let (_configured_dir, configured) = stub_servo();
+        let (_from_env_dir, from_env) = stub_servo();
+        let (_on_path_dir, on_path) = stub_servo();
+        let search_path = Some(on_path.parent().unwrap().as_os_str().to_owned());
+
+        assert_eq!(
+            find_servo_binary_in(Some(&configured), Some(from_env.clone()), search_path.clone()),
+            Ok(configured)
+        );
+        assert_eq!(
+            find_servo_binary_in(None, Some(from_env.clone()), search_path.clone()),
+            Ok(from_env)
+        );
+        assert_eq!(find_servo_binary_in(None, None, search_path), Ok(on_path));
+    }
+
+    #[test]
+    fn test_find_servo_binary_reports_tried_paths() {
+        let missing = PathBuf::from("/nonexistent/rigging/servo");
+        let tried = match find_servo_binary_in(Some(&missing), None, Some("/nonexistent/bin".into())) {
+            Err(tried) => tried,
+            Ok(path) if WELL_KNOWN_SERVO_PATHS.iter().any(|p| path == Path::new(p)) => return,
+            Ok(path) => panic!("unexpected binary: {}", path.display()),
+        };
+
+        assert_eq!(tried[0], missing);
+        assert_eq!(tried[1], PathBuf::from("/nonexistent/bin/servo"));
+        assert_eq!(tried.len(), 2 + WELL_KNOWN_SERVO_PATHS.len());
+
+        let message = EmbedError::ServoBinaryNotFound(tried).to_string();
+        assert!(message.contains("/nonexistent/rigging/servo"));
+    }
+
+    #[test]
+    fn test_build_command_proxy() {
+        let config = BrowserConfig::new("http://localhost/").with_proxy("socks5h://127.0.0.1:9050");
+        let cmd = build_command(Path::new("servo"), &config);
+        let args: Vec<_> = cmd.get_args().map(|a| a.to_string_lossy().into_owned()).collect();
+
+        assert_eq!(
+            args,
+            vec![
+                "--screen-size=1024x768",
+                "--pref=network_http_proxy_uri=socks5h://127.0.0.1:9050",
+                "--pref=network_https_proxy_uri=socks5h://127.0.0.1:9050",
+                "http://localhost/",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_command_minimal() {
+        let config = BrowserConfig::new("http://localhost/");
+        let cmd = build_command(Path::new("servo"), &config);
+        let args: Vec<_> = cmd.get_args().map(|a| a.to_string_lossy().into_owned()).collect();
+
+        assert_eq!(args, vec!["--screen-size=1024x768", "http://localhost/"]);
     }
 }
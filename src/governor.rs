@@ -0,0 +1,334 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Per-transport limits on outbound connections
+//!
+//! A [`ConnectionGovernor`] caps how many connections are open at once (a
+//! semaphore whose permit lives as long as the connection) and how fast new
+//! ones are dialed (a token bucket). [`ComposedConnector`] builds one for
+//! each transport listed in [`ComposedConfig::limits`] and consults it
+//! before dialing.
+//!
+//! ```rust,ignore
+//! let mut config = ComposedConfig::default();
+//! config.limits.insert(
+//!     Transport::Unix,
+//!     GovernorConfig::new().with_max_concurrent(4).with_rate(20.0),
+//! );
+//! let connector = ComposedConnector::with_config(config);
+//! ```
+//!
+//! [`ComposedConnector`]: crate::composed::ComposedConnector
+//! [`ComposedConfig::limits`]: crate::composed::ComposedConfig::limits
+
+use crate::composed::Connection;
+use crate::types::{PeerInfo, TransportError};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// What to do when a limit is reached
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum GovernorMode {
+    /// Wait for a connection slot or rate token
+    #[default]
+    Wait,
+    /// Fail immediately with [`TransportError::LimitReached`]
+    FailFast,
+}
+
+/// Limits for one transport
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct GovernorConfig {
+    /// Maximum number of connections open at once
+    pub max_concurrent: Option<usize>,
+    /// Sustained rate of new connections per second
+    pub rate_per_second: Option<f64>,
+    /// Connections that may be dialed back to back before the rate applies
+    pub burst: u32,
+    /// Whether to wait or fail when a limit is reached
+    pub mode: GovernorMode,
+}
+
+impl Default for GovernorConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent: None,
+            rate_per_second: None,
+            burst: 1,
+            mode: GovernorMode::Wait,
+        }
+    }
+}
+
+impl GovernorConfig {
+    /// Create a config with no limits
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cap the number of open connections
+    pub fn with_max_concurrent(mut self, max: usize) -> Self {
+        self.max_concurrent = Some(max);
+        self
+    }
+
+    /// Limit new connections to `per_second` on average
+    pub fn with_rate(mut self, per_second: f64) -> Self {
+        self.rate_per_second = Some(per_second);
+        self
+    }
+
+    /// Set how many connections may be dialed in a burst
+    pub fn with_burst(mut self, burst: u32) -> Self {
+        self.burst = burst;
+        self
+    }
+
+    /// Set whether to wait or fail when a limit is reached
+    pub fn with_mode(mut self, mode: GovernorMode) -> Self {
+        self.mode = mode;
+        self
+    }
+}
+
+/// Token bucket refilled at `rate` tokens per second up to `capacity`
+#[derive(Debug)]
+struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: f64, burst: u32) -> Self {
+        let capacity = f64::from(burst.max(1));
+        Self {
+            rate,
+            capacity,
+            tokens: capacity,
+            updated: Instant::now(),
+        }
+    }
+
+    /// Take a token, returning how long the caller must wait for it
+    ///
+    /// With `reserve` the token is taken even when it has not accrued yet
+    /// (the balance goes negative), so waiters are served in order.
+    fn take(&mut self, reserve: bool) -> Option<Duration> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.updated = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Some(Duration::ZERO);
+        }
+        if !reserve {
+            return None;
+        }
+        let wait = Duration::from_secs_f64((1.0 - self.tokens) / self.rate);
+        self.tokens -= 1.0;
+        Some(wait)
+    }
+}
+
+/// Enforces a [`GovernorConfig`] for one transport
+#[derive(Debug)]
+pub struct ConnectionGovernor {
+    slots: Option<Arc<Semaphore>>,
+    bucket: Option<Mutex<TokenBucket>>,
+    mode: GovernorMode,
+}
+
+impl ConnectionGovernor {
+    /// Create a governor enforcing `config`
+    ///
+    /// A non-positive or non-finite rate is treated as unlimited.
+    pub fn new(config: &GovernorConfig) -> Self {
+        Self {
+            slots: config.max_concurrent.map(|max| Arc::new(Semaphore::new(max))),
+            bucket: config
+                .rate_per_second
+                .filter(|rate| rate.is_finite() && *rate > 0.0)
+                .map(|rate| Mutex::new(TokenBucket::new(rate, config.burst))),
+            mode: config.mode,
+        }
+    }
+
+    /// Number of connections that can be opened right now without waiting
+    /// for one to close (`None` if uncapped)
+    pub fn available(&self) -> Option<usize> {
+        self.slots.as_ref().map(|slots| slots.available_permits())
+    }
+
+    /// Wait for (or, in fail-fast mode, check for) a connection slot and a
+    /// rate token
+    ///
+    /// The returned permit holds the slot until it is dropped.
+    pub async fn acquire(&self) -> Result<GovernorPermit, TransportError> {
+        let slot = match &self.slots {
+            Some(slots) => Some(match self.mode {
+                GovernorMode::Wait => slots.clone().acquire_owned().await.map_err(|_| {
+                    TransportError::LimitReached("connection governor closed".to_string())
+                })?,
+                GovernorMode::FailFast => slots.clone().try_acquire_owned().map_err(|_| {
+                    TransportError::LimitReached("too many open connections".to_string())
+                })?,
+            }),
+            None => None,
+        };
+
+        if let Some(bucket) = &self.bucket {
+            let reserve = self.mode == GovernorMode::Wait;
+            let wait = bucket
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .take(reserve)
+                .ok_or_else(|| TransportError::LimitReached("connection rate exceeded".to_string()))?;
+            if !wait.is_zero() {
+                tokio::time::sleep(wait).await;
+            }
+        }
+
+        Ok(GovernorPermit { _slot: slot })
+    }
+}
+
+/// A connection slot granted by a [`ConnectionGovernor`]
+#[derive(Debug)]
+pub struct GovernorPermit {
+    _slot: Option<OwnedSemaphorePermit>,
+}
+
+/// A connection holding a [`GovernorPermit`] for as long as it is open
+pub struct GovernedConnection {
+    inner: Box<Connection>,
+    _permit: GovernorPermit,
+}
+
+impl GovernedConnection {
+    /// Wrap `inner`, releasing `permit` when the connection is dropped
+    pub fn new(inner: Connection, permit: GovernorPermit) -> Self {
+        Self {
+            inner: Box::new(inner),
+            _permit: permit,
+        }
+    }
+
+    /// The wrapped connection
+    pub fn get_ref(&self) -> &Connection {
+        &self.inner
+    }
+
+    /// What the wrapped connection is connected to
+    pub fn peer_info(&self) -> PeerInfo {
+        self.inner.peer_info()
+    }
+}
+
+impl AsyncRead for GovernedConnection {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        AsyncRead::poll_read(Pin::new(&mut *self.inner), cx, buf)
+    }
+}
+
+impl AsyncWrite for GovernedConnection {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        AsyncWrite::poll_write(Pin::new(&mut *self.inner), cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        AsyncWrite::poll_flush(Pin::new(&mut *self.inner), cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        AsyncWrite::poll_shutdown(Pin::new(&mut *self.inner), cx)
+    }
+}
+
+impl hyper::rt::Read for GovernedConnection {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: hyper::rt::ReadBufCursor<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        hyper::rt::Read::poll_read(Pin::new(&mut *self.inner), cx, buf)
+    }
+}
+
+impl hyper::rt::Write for GovernedConnection {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, std::io::Error>> {
+        hyper::rt::Write::poll_write(Pin::new(&mut *self.inner), cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), std::io::Error>> {
+        hyper::rt::Write::poll_flush(Pin::new(&mut *self.inner), cx)
+    }
+
+    fn poll_shutdown(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        hyper::rt::Write::poll_shutdown(Pin::new(&mut *self.inner), cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_concurrency_cap_fail_fast() {
+        let governor = ConnectionGovernor::new(
+            &GovernorConfig::new()
+                .with_max_concurrent(1)
+                .with_mode(GovernorMode::FailFast),
+        );
+        let permit = governor.acquire().await.unwrap();
+        assert_eq!(governor.available(), Some(0));
+        assert!(matches!(governor.acquire().await, Err(TransportError::LimitReached(_))));
+
+        drop(permit);
+        assert!(governor.acquire().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit() {
+        let waiting = ConnectionGovernor::new(&GovernorConfig::new().with_rate(20.0).with_burst(2));
+        let started = Instant::now();
+        for _ in 0..4 {
+            waiting.acquire().await.unwrap();
+        }
+        // Two from the burst, then two more at 50ms intervals
+        assert!(started.elapsed() >= Duration::from_millis(90));
+
+        let failing = ConnectionGovernor::new(
+            &GovernorConfig::new().with_rate(1.0).with_mode(GovernorMode::FailFast),
+        );
+        assert!(failing.acquire().await.is_ok());
+        assert!(matches!(failing.acquire().await, Err(TransportError::LimitReached(_))));
+    }
+}
@@ -0,0 +1,454 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! I2P connector via the SAMv3 bridge
+//!
+//! This connector talks to a local I2P router (i2pd or Java I2P) over its
+//! SAM bridge, a line-based text protocol on TCP port 7656.
+//!
+//! # Protocol
+//!
+//! 1. Control socket: `HELLO VERSION`, then `SESSION CREATE STYLE=STREAM`
+//!    with a transient destination. The session lives as long as this socket.
+//! 2. `NAMING LOOKUP` resolves `.b32.i2p` and address-book names to a full
+//!    destination.
+//! 3. Data socket: `HELLO VERSION`, then `STREAM CONNECT` to the destination.
+//!    Once the router replies `RESULT=OK` the socket carries the stream.
+//!
+//! Each connection uses its own transient session, which is closed with the
+//! connection.
+
+use crate::connector::{cancellable, AsyncReadWrite, Connector};
+use crate::deadline;
+use crate::metrics::observe_connect;
+use crate::redact::RedactionPolicy;
+use crate::types::{PeerInfo, Transport, TransportError};
+use crate::TransportUrl;
+use futures::future::BoxFuture;
+use hyper::Uri;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_util::sync::CancellationToken;
+use tower_service::Service;
+
+/// Default address of the router's SAM bridge
+pub const DEFAULT_SAM_ADDR: &str = "127.0.0.1:7656";
+
+/// SAM versions this connector speaks
+const SAM_HELLO: &str = "HELLO VERSION MIN=3.0 MAX=3.3\n";
+
+/// Longest reply line accepted from the bridge (destinations are ~600 bytes)
+const MAX_REPLY_LEN: usize = 8 * 1024;
+
+/// Makes session IDs unique within the process
+static NEXT_SESSION: AtomicU64 = AtomicU64::new(0);
+
+/// A stream to an I2P destination
+pub struct I2pConnection {
+    stream: TcpStream,
+    /// Control socket keeping the SAM session alive
+    _session: TcpStream,
+    host: String,
+    port: u16,
+}
+
+impl I2pConnection {
+    fn new(stream: TcpStream, session: TcpStream, host: &str, port: u16) -> Self {
+        Self {
+            stream,
+            _session: session,
+            host: host.to_string(),
+            port,
+        }
+    }
+
+    /// Host and port asked of the router
+    pub fn peer_info(&self) -> PeerInfo {
+        PeerInfo::I2p {
+            host: self.host.clone(),
+            port: self.port,
+        }
+    }
+}
+
+impl AsyncRead for I2pConnection {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for I2pConnection {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}
+
+impl hyper::rt::Read for I2pConnection {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        mut buf: hyper::rt::ReadBufCursor<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        let mut read_buf = tokio::io::ReadBuf::uninit(unsafe { buf.as_mut() });
+        match Pin::new(&mut self.get_mut().stream).poll_read(cx, &mut read_buf) {
+            Poll::Ready(Ok(())) => {
+                let filled = read_buf.filled().len();
+                unsafe { buf.advance(filled) };
+                Poll::Ready(Ok(()))
+            }
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl hyper::rt::Write for I2pConnection {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, std::io::Error>> {
+        Pin::new(&mut self.get_mut().stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), std::io::Error>> {
+        Pin::new(&mut self.get_mut().stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        Pin::new(&mut self.get_mut().stream).poll_shutdown(cx)
+    }
+}
+
+/// I2P connector that opens streams through a SAM bridge
+#[derive(Clone)]
+pub struct I2pConnector {
+    /// Address of the SAM bridge
+    sam_addr: SocketAddr,
+    /// How hosts appear in logs
+    redaction: RedactionPolicy,
+}
+
+impl I2pConnector {
+    /// Create a new I2P connector using the default SAM address
+    pub fn new() -> Self {
+        Self::with_sam_addr(DEFAULT_SAM_ADDR.parse().expect("default SAM address is valid"))
+    }
+
+    /// Create an I2P connector with a custom SAM bridge address
+    pub fn with_sam_addr(sam_addr: SocketAddr) -> Self {
+        Self {
+            sam_addr,
+            redaction: RedactionPolicy::default(),
+        }
+    }
+
+    /// Set how hosts appear in logs
+    pub fn with_redaction(mut self, redaction: RedactionPolicy) -> Self {
+        self.redaction = redaction;
+        self
+    }
+
+    /// Get the SAM bridge address
+    pub fn sam_addr(&self) -> SocketAddr {
+        self.sam_addr
+    }
+
+    /// Connect to an I2P host
+    ///
+    /// `host` is a `.b32.i2p` address, a name the router's address book can
+    /// resolve, or a full base64 destination. The port is passed to the
+    /// destination as `TO_PORT` (SAM 3.2+ routers honour it, older ones
+    /// ignore it).
+    pub async fn connect(&self, host: &str, port: u16) -> Result<I2pConnection, TransportError> {
+        observe_connect(Transport::I2p, self.establish(host, port)).await
+    }
+
+    async fn establish(&self, host: &str, port: u16) -> Result<I2pConnection, TransportError> {
+        self.redaction.log_connect(Transport::I2p, host, port);
+        let mut session = TcpStream::connect(self.sam_addr).await?;
+        hello(&mut session).await?;
+
+        let id = format!(
+            "rigging-{}-{}",
+            std::process::id(),
+            NEXT_SESSION.fetch_add(1, Ordering::Relaxed)
+        );
+        send(&mut session, &format!("SESSION CREATE STYLE=STREAM ID={} DESTINATION=TRANSIENT\n", id)).await?;
+        expect_ok(&mut session, "SESSION STATUS").await?;
+
+        let destination = if host.ends_with(".i2p") {
+            send(&mut session, &format!("NAMING LOOKUP NAME={}\n", host)).await?;
+            let reply = expect_ok(&mut session, "NAMING REPLY").await?;
+            reply.get("VALUE").cloned().ok_or_else(|| {
+                TransportError::ConnectionFailed(format!("SAM lookup of {} returned no destination", host))
+            })?
+        } else {
+            host.to_string()
+        };
+
+        let mut stream = TcpStream::connect(self.sam_addr).await?;
+        hello(&mut stream).await?;
+        send(
+            &mut stream,
+            &format!("STREAM CONNECT ID={} DESTINATION={} TO_PORT={} SILENT=false\n", id, destination, port),
+        )
+        .await?;
+        expect_ok(&mut stream, "STREAM STATUS").await?;
+
+        log::debug!("I2P stream established to {}:{}", self.redaction.host(host), port);
+        Ok(I2pConnection::new(stream, session, host, port))
+    }
+
+    /// Connect to an I2P host, giving up when `token` is cancelled
+    ///
+    /// Cancelling closes both SAM sockets, which ends the transient session.
+    pub async fn connect_with_cancel(
+        &self,
+        host: &str,
+        port: u16,
+        token: CancellationToken,
+    ) -> Result<I2pConnection, TransportError> {
+        cancellable(&token, self.connect(host, port)).await
+    }
+}
+
+impl Default for I2pConnector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+async fn hello(stream: &mut TcpStream) -> Result<(), TransportError> {
+    send(stream, SAM_HELLO).await?;
+    expect_ok(stream, "HELLO REPLY").await.map(drop)
+}
+
+async fn send(stream: &mut TcpStream, command: &str) -> Result<(), TransportError> {
+    stream.write_all(command.as_bytes()).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+/// Read a reply line, check its prefix and `RESULT=OK`, and return its fields
+async fn expect_ok(stream: &mut TcpStream, prefix: &str) -> Result<HashMap<String, String>, TransportError> {
+    let line = read_line(stream).await?;
+    let rest = line.strip_prefix(prefix).ok_or_else(|| {
+        TransportError::ConnectionFailed(format!("Unexpected SAM reply (wanted {}): {}", prefix, line))
+    })?;
+
+    let fields = parse_fields(rest);
+    match fields.get("RESULT").map(String::as_str) {
+        Some("OK") => Ok(fields),
+        result => Err(TransportError::ConnectionFailed(format!(
+            "SAM {} failed: {}{}",
+            prefix,
+            result.unwrap_or("no result"),
+            fields.get("MESSAGE").map(|m| format!(" ({})", m)).unwrap_or_default()
+        ))),
+    }
+}
+
+/// Read one `\n`-terminated line without consuming any stream data after it
+async fn read_line(stream: &mut TcpStream) -> Result<String, TransportError> {
+    let mut line = Vec::new();
+    loop {
+        let byte = stream.read_u8().await?;
+        if byte == b'\n' {
+            break;
+        }
+        if line.len() >= MAX_REPLY_LEN {
+            return Err(TransportError::ConnectionFailed("SAM reply too long".to_string()));
+        }
+        line.push(byte);
+    }
+    String::from_utf8(line)
+        .map(|line| line.trim_end_matches('\r').to_string())
+        .map_err(|_| TransportError::ConnectionFailed("SAM reply is not UTF-8".to_string()))
+}
+
+/// Parse `KEY=VALUE` pairs; values may be double-quoted
+fn parse_fields(input: &str) -> HashMap<String, String> {
+    let mut fields = HashMap::new();
+    let mut rest = input.trim_start();
+    while !rest.is_empty() {
+        let Some((key, after_key)) = rest.split_once('=') else {
+            break;
+        };
+        let (value, after_value) = match after_key.strip_prefix('"') {
+            Some(quoted) => match quoted.split_once('"') {
+                Some((value, after)) => (value, after),
+                None => (quoted, ""),
+            },
+            None => after_key.split_once(' ').unwrap_or((after_key, "")),
+        };
+        fields.insert(key.trim().to_string(), value.to_string());
+        rest = after_value.trim_start();
+    }
+    fields
+}
+
+impl Service<Uri> for I2pConnector {
+    type Response = I2pConnection;
+    type Error = TransportError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let connector = self.clone();
+        deadline::bounded(async move {
+            let host = uri.host().ok_or_else(|| {
+                TransportError::InvalidUrl("No host in URI".to_string())
+            })?;
+
+            let port = uri.port_u16().unwrap_or_else(|| {
+                match uri.scheme_str() {
+                    Some("https") => 443,
+                    Some("http") => 80,
+                    _ => 80,
+                }
+            });
+
+            connector.connect(host, port).await
+        })
+    }
+}
+
+impl Connector for I2pConnector {
+    fn allows_url(&self, url: &TransportUrl) -> bool {
+        url.transport() == Transport::I2p
+    }
+
+    fn connect<'a>(
+        &'a self,
+        url: &'a TransportUrl,
+    ) -> BoxFuture<'a, Result<Box<dyn AsyncReadWrite>, TransportError>> {
+        Box::pin(async move {
+            let host = url.host_str().ok_or_else(|| {
+                TransportError::InvalidUrl("No host".to_string())
+            })?;
+            let conn = I2pConnector::connect(self, host, url.port_or_default()).await?;
+            Ok(Box::new(conn) as Box<dyn AsyncReadWrite>)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, BufReader};
+    use tokio::net::TcpListener;
+
+    const B32: &str = "ukeu3k5oycgaauneqgtnvselmt4yemvoilkln7jpvamvfx7dnkdq.b32.i2p";
+    const DESTINATION: &str = "AAAAdestinationBBBB~";
+
+    /// Answer one SAM command line, returning the reply to send
+    fn mock_reply(command: &str) -> String {
+        if command.starts_with("HELLO VERSION") {
+            "HELLO REPLY RESULT=OK VERSION=3.3\n".to_string()
+        } else if command.starts_with("SESSION CREATE") {
+            "SESSION STATUS RESULT=OK DESTINATION=TRANSIENTKEY\n".to_string()
+        } else if command == format!("NAMING LOOKUP NAME={}", B32) {
+            format!("NAMING REPLY RESULT=OK NAME={} VALUE={}\n", B32, DESTINATION)
+        } else if command.starts_with("NAMING LOOKUP") {
+            "NAMING REPLY RESULT=KEY_NOT_FOUND NAME=unknown.i2p\n".to_string()
+        } else if command.starts_with("STREAM CONNECT ID=") && command.contains(DESTINATION) {
+            "STREAM STATUS RESULT=OK\n".to_string()
+        } else {
+            "STREAM STATUS RESULT=CANT_REACH_PEER MESSAGE=\"no such peer\"\n".to_string()
+        }
+    }
+
+    /// Minimal SAM bridge: answers commands, then echoes once a stream is open
+    async fn mock_sam_bridge() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                tokio::spawn(async move {
+                    let (reader, mut writer) = stream.into_split();
+                    let mut reader = BufReader::new(reader);
+                    let mut line = String::new();
+                    while reader.read_line(&mut line).await.unwrap_or(0) > 0 {
+                        let reply = mock_reply(line.trim_end());
+                        writer.write_all(reply.as_bytes()).await.unwrap();
+                        let connected = line.starts_with("STREAM CONNECT") && reply.contains("RESULT=OK");
+                        line.clear();
+                        if connected {
+                            let _ = tokio::io::copy(&mut reader, &mut writer).await;
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+        addr
+    }
+
+    #[test]
+    fn test_parse_fields() {
+        let fields = parse_fields(" RESULT=I2P_ERROR MESSAGE=\"tunnel build failed\" VERSION=3.1");
+        assert_eq!(fields["RESULT"], "I2P_ERROR");
+        assert_eq!(fields["MESSAGE"], "tunnel build failed");
+        assert_eq!(fields["VERSION"], "3.1");
+    }
+
+    #[tokio::test]
+    async fn test_connect_through_mock_sam() {
+        let connector = I2pConnector::with_sam_addr(mock_sam_bridge().await);
+        let mut conn = connector.connect(B32, 80).await.unwrap();
+        assert_eq!(conn.peer_info(), PeerInfo::I2p { host: B32.to_string(), port: 80 });
+
+        conn.write_all(b"ping").await.unwrap();
+        let mut buf = [0u8; 4];
+        conn.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+    }
+
+    #[tokio::test]
+    async fn test_connect_reports_sam_errors() {
+        let connector = I2pConnector::with_sam_addr(mock_sam_bridge().await);
+
+        let err = connector.connect("unknown.i2p", 80).await.err().unwrap();
+        assert!(err.to_string().contains("KEY_NOT_FOUND"), "{}", err);
+
+        let err = connector.connect("UnknownDestination~", 80).await.err().unwrap();
+        assert!(err.to_string().contains("CANT_REACH_PEER (no such peer)"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_service_call() {
+        let mut connector = I2pConnector::with_sam_addr(mock_sam_bridge().await);
+        let uri: Uri = format!("http://{}/", B32).parse().unwrap();
+        assert!(connector.call(uri).await.is_ok());
+    }
+}
@@ -17,6 +17,7 @@
 //! - **Unix Domain Sockets** - Local IPC on Unix systems
 //! - **Named Pipes** - Local IPC on Windows
 //! - **Tor** - Anonymous connections via Corsair daemon
+//! - **I2P** - Anonymous connections via an I2P router's SAM bridge
 //!
 //! ## Transport URL Syntax
 //!
@@ -25,6 +26,7 @@
 //! http::unix//var/run/app.sock          # Unix socket (relative path)
 //! http::tcp//localhost:8080             # Explicit TCP
 //! http::tor//example.onion              # Tor network
+//! http::i2p//example.b32.i2p            # I2P network
 //! ```
 //!
 //! ## Transport Example
@@ -70,9 +72,19 @@
 //!
 //! - `unix` - Unix Domain Socket support (default)
 //! - `tcp` - TCP transport support (default)
+//! - `tls` - TLS for explicit `https::tcp` URLs via rustls
 //! - `tor` - Tor transport via Corsair daemon
+//! - `i2p` - I2P transport via a SAMv3 bridge
 //! - `named-pipe` - Windows Named Pipe support
 //! - `servo` - Enable embedded Servo browser engine
+//! - `serde` - Serialize/Deserialize for `BrowserConfig`, loading it from TOML
+//! - `reqwest` - `reqwest` 0.12 client bound to a transport URL
+//! - `metrics` - Per-transport connection and byte counters via the `metrics` facade
+//! - `tracing` - A `tracing` span around each `ComposedConnector` connect
+//! - `websocket` - `ComposedConnector::connect_websocket` via tokio-tungstenite
+//! - `blocking` - `blocking::get` for synchronous programs
+//! - `http2` - `ComposedConnector::connect_h2` (HTTP/2 with prior knowledge)
+//! - `test-util` - In-memory `http::memory//<key>/` transport for tests
 
 // Transport layer modules
 pub mod transport_url;
@@ -87,7 +99,43 @@ pub mod tcp_connector;
 #[cfg(feature = "tor")]
 pub mod tor_connector;
 
+#[cfg(feature = "tor")]
+pub mod tor_mux;
+
+#[cfg(feature = "i2p")]
+pub mod i2p_connector;
+
+#[cfg(feature = "tls")]
+pub mod tls_connector;
+
+#[cfg(feature = "test-util")]
+pub mod memory_connector;
+
+pub mod buffered;
 pub mod composed;
+pub mod connector;
+pub mod deadline;
+pub mod governor;
+pub mod listener;
+pub mod metrics;
+pub mod redact;
+pub mod redirect;
+pub mod retry;
+pub mod routing;
+pub mod runtime_dir;
+pub mod timeout;
+pub mod trace;
+
+mod base64;
+
+#[cfg(feature = "unix")]
+pub mod proxy;
+
+#[cfg(feature = "reqwest")]
+pub mod reqwest_client;
+
+#[cfg(feature = "blocking")]
+pub mod blocking;
 
 // Embedding API module
 pub mod embed;
@@ -97,8 +145,9 @@ pub mod embed;
 pub mod servoshell;
 
 // Transport layer re-exports
-pub use transport_url::TransportUrl;
-pub use types::{Transport, TransportChain, TransportError};
+pub use transport_url::{ParseOptions, TransportUrl, TransportUrlBuilder};
+pub use types::{available_transports, PeerInfo, Transport, TransportChain, TransportError};
+pub use connector::Connector;
 
 #[cfg(feature = "unix")]
 pub use unix_connector::UnixConnector;
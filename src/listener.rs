@@ -0,0 +1,522 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Server side of the transport layer
+//!
+//! `TransportListener` binds the endpoint a `TransportUrl` describes and
+//! accepts connections as the same [`Connection`] type that
+//! [`ComposedConnector`](crate::composed::ComposedConnector) dials, so a
+//! daemon and its clients can share one URL. Unix and TCP URLs are
+//! supported, plus `http::memory//<key>/` with the `test-util` feature.
+//!
+//! ```rust,ignore
+//! use rigging::listener::TransportListener;
+//!
+//! let url = TransportUrl::parse("http::unix///tmp/app.sock/")?;
+//! let listener = TransportListener::bind(&url).await?;
+//! loop {
+//!     let connection = listener.accept().await?;
+//!     tokio::spawn(handle(connection));
+//! }
+//! ```
+
+use crate::composed::Connection;
+use crate::types::{Transport, TransportError};
+use crate::TransportUrl;
+use futures::Stream;
+use std::path::PathBuf;
+
+#[cfg(feature = "unix")]
+use crate::unix_connector::UnixConnection;
+#[cfg(feature = "unix")]
+use std::path::Path;
+
+#[cfg(feature = "tcp")]
+use crate::tcp_connector::TcpConnection;
+
+/// Options for binding a [`TransportListener`]
+///
+/// Neither option applies to `@name` abstract sockets, which have no file.
+#[derive(Debug, Clone, Default)]
+pub struct ListenerOptions {
+    /// Remove a leftover Unix socket file that no process is listening on
+    pub unlink_stale: bool,
+    /// Permission bits for the Unix socket file (e.g. `0o660`)
+    pub mode: Option<u32>,
+}
+
+impl ListenerOptions {
+    /// Create options with the defaults (no unlinking, default mode)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remove a stale Unix socket before binding
+    pub fn with_unlink_stale(mut self, unlink_stale: bool) -> Self {
+        self.unlink_stale = unlink_stale;
+        self
+    }
+
+    /// Set the Unix socket's permission bits
+    ///
+    /// The socket is never reachable at its path with other permissions.
+    pub fn with_mode(mut self, mode: u32) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+}
+
+/// A listener bound to the endpoint of a `TransportUrl`
+///
+/// Unix socket listeners remove their socket file when dropped. On Linux,
+/// `http::unix//@name/` binds `name` in the abstract namespace instead.
+pub struct TransportListener {
+    inner: Inner,
+}
+
+enum Inner {
+    #[cfg(feature = "unix")]
+    Unix {
+        listener: tokio::net::UnixListener,
+        path: PathBuf,
+        /// Bound in the abstract namespace, so there is no file to clean up
+        is_abstract: bool,
+    },
+    #[cfg(feature = "tcp")]
+    Tcp(tokio::net::TcpListener),
+    #[cfg(feature = "test-util")]
+    Memory(crate::memory_connector::MemoryListener),
+}
+
+impl TransportListener {
+    /// Bind the endpoint `url` describes with default options
+    pub async fn bind(url: &TransportUrl) -> Result<Self, TransportError> {
+        Self::bind_with_options(url, &ListenerOptions::default()).await
+    }
+
+    /// Bind the endpoint `url` describes
+    ///
+    /// Unix URLs bind their socket path; TCP URLs bind their host and port
+    /// (port 0 picks an ephemeral port). Other transports cannot be served.
+    pub async fn bind_with_options(
+        url: &TransportUrl,
+        options: &ListenerOptions,
+    ) -> Result<Self, TransportError> {
+        let inner = match url.transport() {
+            #[cfg(feature = "unix")]
+            Transport::Unix => {
+                let path = url.unix_socket_pathbuf().ok_or(TransportError::SocketPathNotFound)?;
+                let is_abstract = url.is_abstract_socket();
+                let listener = if is_abstract {
+                    bind_abstract(&path)?
+                } else {
+                    if options.unlink_stale {
+                        remove_stale_socket(&path).await?;
+                    }
+                    bind_unix(&path, options.mode)?
+                };
+                log::debug!("Listening on Unix socket {}", path.display());
+                Inner::Unix { listener, path, is_abstract }
+            }
+            #[cfg(feature = "tcp")]
+            Transport::Tcp => {
+                let host = url
+                    .host_str()
+                    .ok_or_else(|| TransportError::InvalidUrl("No host".to_string()))?;
+                // Url keeps IPv6 hosts bracketed, which is also what bind expects
+                let listener = tokio::net::TcpListener::bind(format!("{}:{}", host, url.port_or_default())).await?;
+                log::debug!("Listening on {}", listener.local_addr()?);
+                Inner::Tcp(listener)
+            }
+            #[cfg(feature = "test-util")]
+            Transport::Memory => {
+                let key = url
+                    .host_str()
+                    .ok_or_else(|| TransportError::InvalidUrl("No memory key".to_string()))?;
+                Inner::Memory(crate::memory_connector::MemoryListener::bind(key)?)
+            }
+            transport => {
+                return Err(TransportError::NotAvailable(format!(
+                    "Cannot listen on {} transport",
+                    transport.display_name()
+                )))
+            }
+        };
+        Ok(Self { inner })
+    }
+
+    /// Accept the next connection
+    pub async fn accept(&self) -> Result<Connection, TransportError> {
+        match &self.inner {
+            #[cfg(feature = "unix")]
+            Inner::Unix { listener, .. } => {
+                let (stream, _) = listener.accept().await?;
+                Ok(Connection::Unix(UnixConnection::new(stream)))
+            }
+            #[cfg(feature = "tcp")]
+            Inner::Tcp(listener) => {
+                let (stream, _) = listener.accept().await?;
+                Ok(Connection::Tcp(TcpConnection::new(stream)))
+            }
+            #[cfg(feature = "test-util")]
+            Inner::Memory(listener) => {
+                let conn = listener.accept().await?;
+                Ok(Connection::Custom(crate::connector::CustomConnection::new(Box::new(conn))))
+            }
+        }
+    }
+
+    /// Accepted connections as a stream
+    ///
+    /// A connection is only accepted when the stream is polled, so a
+    /// consumer that falls behind leaves clients waiting in the listen
+    /// backlog rather than piling up accepted connections. Accept errors
+    /// (e.g. running out of file descriptors) are yielded without ending
+    /// the stream.
+    ///
+    /// ```rust,ignore
+    /// listener
+    ///     .incoming()
+    ///     .for_each_concurrent(64, |conn| async move {
+    ///         match conn {
+    ///             Ok(conn) => serve(conn).await,
+    ///             Err(e) => log::warn!("accept failed: {}", e),
+    ///         }
+    ///     })
+    ///     .await;
+    /// ```
+    pub fn incoming(&self) -> impl Stream<Item = Result<Connection, TransportError>> + '_ {
+        futures::stream::unfold(self, |listener| async move { Some((listener.accept().await, listener)) })
+    }
+
+    /// Transport this listener accepts connections on
+    pub fn transport(&self) -> Transport {
+        match &self.inner {
+            #[cfg(feature = "unix")]
+            Inner::Unix { .. } => Transport::Unix,
+            #[cfg(feature = "tcp")]
+            Inner::Tcp(_) => Transport::Tcp,
+            #[cfg(feature = "test-util")]
+            Inner::Memory(_) => Transport::Memory,
+        }
+    }
+
+    /// Local address for TCP listeners
+    pub fn local_addr(&self) -> Option<std::net::SocketAddr> {
+        match &self.inner {
+            #[cfg(feature = "tcp")]
+            Inner::Tcp(listener) => listener.local_addr().ok(),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    }
+
+    /// Socket path for Unix listeners
+    pub fn socket_path(&self) -> Option<&std::path::Path> {
+        match &self.inner {
+            #[cfg(feature = "unix")]
+            Inner::Unix { path, .. } => Some(path),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    }
+}
+
+impl Drop for TransportListener {
+    fn drop(&mut self) {
+        #[cfg(feature = "unix")]
+        if let Inner::Unix { path, is_abstract: false, .. } = &self.inner {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Bind a Unix socket at `path`, with permission bits `mode` if given
+///
+/// Setting the mode after binding would leave a window in which anyone
+/// could connect, and changing the umask affects every thread. Instead the
+/// socket is bound in a private staging directory, given its mode there and
+/// then hard-linked into place. Linking fails if `path` exists, just as
+/// binding it directly would.
+#[cfg(feature = "unix")]
+fn bind_unix(path: &Path, mode: Option<u32>) -> std::io::Result<tokio::net::UnixListener> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static STAGING: AtomicUsize = AtomicUsize::new(0);
+
+    let Some(mode) = mode else {
+        return tokio::net::UnixListener::bind(path);
+    };
+    let parent = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let staging = parent.join(format!(
+        ".rigging-{}-{}",
+        std::process::id(),
+        STAGING.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::DirBuilder::new().mode(0o700).create(&staging)?;
+
+    let staged = staging.join("s");
+    let bound = tokio::net::UnixListener::bind(&staged).and_then(|listener| {
+        std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(mode))?;
+        std::fs::hard_link(&staged, path)?;
+        Ok(listener)
+    });
+    let _ = std::fs::remove_file(&staged);
+    let _ = std::fs::remove_dir(&staging);
+    bound
+}
+
+/// Bind the `@name` abstract socket `path` names
+#[cfg(all(feature = "unix", target_os = "linux"))]
+fn bind_abstract(path: &Path) -> Result<tokio::net::UnixListener, TransportError> {
+    use std::os::linux::net::SocketAddrExt;
+
+    let name = path.to_str().and_then(|p| p.strip_prefix('@')).ok_or(TransportError::SocketPathNotFound)?;
+    let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+    let listener = std::os::unix::net::UnixListener::bind_addr(&addr)?;
+    listener.set_nonblocking(true)?;
+    Ok(tokio::net::UnixListener::from_std(listener)?)
+}
+
+#[cfg(all(feature = "unix", not(target_os = "linux")))]
+fn bind_abstract(path: &Path) -> Result<tokio::net::UnixListener, TransportError> {
+    Err(TransportError::NotAvailable(format!(
+        "Abstract socket {} is only supported on Linux",
+        path.display()
+    )))
+}
+
+/// Remove `path` if it is a socket nothing is listening on
+///
+/// A socket that still accepts connections is left alone, so binding fails
+/// with "address in use" instead of stealing it from a running daemon.
+#[cfg(feature = "unix")]
+async fn remove_stale_socket(path: &Path) -> Result<(), TransportError> {
+    use std::os::unix::fs::FileTypeExt;
+
+    let Ok(metadata) = std::fs::symlink_metadata(path) else {
+        return Ok(());
+    };
+    if !metadata.file_type().is_socket() {
+        return Ok(());
+    }
+    if tokio::net::UnixStream::connect(path).await.is_err() {
+        log::info!("Removing stale socket {}", path.display());
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::composed::ComposedConnector;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[cfg(feature = "unix")]
+    fn test_socket() -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("app.sock");
+        (dir, socket)
+    }
+
+    /// Echo one message on the next accepted connection
+    async fn echo_once(listener: &TransportListener) {
+        let mut conn = listener.accept().await.unwrap();
+        let mut buf = [0u8; 5];
+        conn.read_exact(&mut buf).await.unwrap();
+        conn.write_all(&buf).await.unwrap();
+    }
+
+    #[cfg(feature = "unix")]
+    #[tokio::test]
+    async fn test_unix_listener_echo() {
+        let (_dir, socket) = test_socket();
+        let url = TransportUrl::parse(&format!("http::unix//{}/", socket.display())).unwrap();
+        let listener = TransportListener::bind(&url).await.unwrap();
+        assert_eq!(listener.transport(), Transport::Unix);
+        assert_eq!(listener.socket_path(), Some(socket.as_path()));
+
+        let client = async {
+            let mut conn = ComposedConnector::new().connect_url(&url).await.unwrap();
+            conn.write_all(b"hello").await.unwrap();
+            let mut buf = [0u8; 5];
+            conn.read_exact(&mut buf).await.unwrap();
+            buf
+        };
+        let (echoed, ()) = tokio::join!(client, echo_once(&listener));
+        assert_eq!(&echoed, b"hello");
+
+        drop(listener);
+        assert!(!socket.exists());
+    }
+
+    #[cfg(all(feature = "unix", target_os = "linux"))]
+    #[tokio::test]
+    async fn test_unix_listener_abstract_socket() {
+        let url = TransportUrl::parse(&format!("http::unix//@rigging-listener-{}/", std::process::id())).unwrap();
+        assert!(url.is_abstract_socket());
+        // File options are ignored rather than touching a path named `@...`
+        let options = ListenerOptions::new().with_unlink_stale(true).with_mode(0o600);
+        let listener = TransportListener::bind_with_options(&url, &options).await.unwrap();
+
+        let client = async {
+            let mut conn = ComposedConnector::new().connect_url(&url).await.unwrap();
+            conn.write_all(b"hello").await.unwrap();
+            let mut buf = [0u8; 5];
+            conn.read_exact(&mut buf).await.unwrap();
+            buf
+        };
+        let (echoed, ()) = tokio::join!(client, echo_once(&listener));
+        assert_eq!(&echoed, b"hello");
+
+        // The name is free again once the listener is gone
+        assert!(TransportListener::bind(&url).await.is_err());
+        drop(listener);
+        TransportListener::bind(&url).await.unwrap();
+    }
+
+    #[cfg(feature = "unix")]
+    #[tokio::test]
+    async fn test_unix_listener_stale_socket_and_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let (_dir, socket) = test_socket();
+        // A bound-then-dropped std listener leaves its socket file behind
+        drop(std::os::unix::net::UnixListener::bind(&socket).unwrap());
+        assert!(socket.exists());
+
+        let url = TransportUrl::parse(&format!("http::unix//{}/", socket.display())).unwrap();
+        assert!(TransportListener::bind(&url).await.is_err());
+
+        let options = ListenerOptions::new().with_unlink_stale(true).with_mode(0o600);
+        let listener = TransportListener::bind_with_options(&url, &options).await.unwrap();
+        let mode = std::fs::metadata(&socket).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        // A live socket is never unlinked
+        assert!(TransportListener::bind_with_options(&url, &options).await.is_err());
+        drop(listener);
+    }
+
+    #[cfg(feature = "unix")]
+    #[tokio::test]
+    async fn test_unix_listener_mode_applied_at_bind() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let (_dir, socket) = test_socket();
+        let url = TransportUrl::parse(&format!("http::unix//{}/", socket.display())).unwrap();
+        let options = ListenerOptions::new().with_mode(0o660);
+        let listener = TransportListener::bind_with_options(&url, &options).await.unwrap();
+        let mode = std::fs::metadata(&socket).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o660);
+
+        // The socket works at its final path and no staging directory is left
+        let client = async {
+            let mut conn = ComposedConnector::new().connect_url(&url).await.unwrap();
+            conn.write_all(b"hello").await.unwrap();
+        };
+        let server = async {
+            let mut conn = listener.accept().await.unwrap();
+            let mut buf = [0u8; 5];
+            conn.read_exact(&mut buf).await.unwrap();
+        };
+        tokio::join!(client, server);
+        let entries: Vec<_> = std::fs::read_dir(socket.parent().unwrap()).unwrap().collect();
+        assert_eq!(entries.len(), 1);
+
+        // An existing file is left alone
+        drop(listener);
+        std::fs::write(&socket, b"keep").unwrap();
+        assert!(TransportListener::bind_with_options(&url, &options).await.is_err());
+        assert_eq!(std::fs::read(&socket).unwrap(), b"keep");
+    }
+
+    #[cfg(feature = "unix")]
+    #[tokio::test]
+    async fn test_incoming_yields_each_connection() {
+        use futures::StreamExt;
+        use std::sync::Mutex;
+
+        let (_dir, socket) = test_socket();
+        let url = TransportUrl::parse(&format!("http::unix//{}/", socket.display())).unwrap();
+        let listener = TransportListener::bind(&url).await.unwrap();
+
+        let clients = futures::future::join_all((0..4u8).map(|id| {
+            let url = url.clone();
+            async move {
+                let mut conn = ComposedConnector::new().connect_url(&url).await.unwrap();
+                conn.write_all(&[id]).await.unwrap();
+                let mut ack = [0u8; 1];
+                conn.read_exact(&mut ack).await.unwrap();
+                assert_eq!(ack, [id]);
+            }
+        }));
+
+        let seen = Mutex::new(Vec::new());
+        let server = listener.incoming().take(4).for_each_concurrent(None, |conn| {
+            let seen = &seen;
+            async move {
+                let mut conn = conn.unwrap();
+                let mut id = [0u8; 1];
+                conn.read_exact(&mut id).await.unwrap();
+                seen.lock().unwrap().push(id[0]);
+                conn.write_all(&id).await.unwrap();
+            }
+        });
+        tokio::join!(clients, server);
+
+        let mut seen = seen.into_inner().unwrap();
+        seen.sort();
+        assert_eq!(seen, vec![0, 1, 2, 3]);
+    }
+
+    #[cfg(feature = "tcp")]
+    #[tokio::test]
+    async fn test_tcp_listener_echo() {
+        let url = TransportUrl::parse("http://127.0.0.1:0/").unwrap();
+        let listener = TransportListener::bind(&url).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let client = async {
+            let mut conn = ComposedConnector::new()
+                .connect(&format!("http://127.0.0.1:{}/", addr.port()))
+                .await
+                .unwrap();
+            assert!(matches!(conn, Connection::Tcp(_)));
+            conn.write_all(b"hello").await.unwrap();
+            let mut buf = [0u8; 5];
+            conn.read_exact(&mut buf).await.unwrap();
+            buf
+        };
+        let (echoed, ()) = tokio::join!(client, echo_once(&listener));
+        assert_eq!(&echoed, b"hello");
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn test_memory_listener_echo() {
+        let url = TransportUrl::parse("http::memory//listener-echo/").unwrap();
+        let listener = TransportListener::bind(&url).await.unwrap();
+        assert_eq!(listener.transport(), Transport::Memory);
+
+        let client = async {
+            let mut conn = ComposedConnector::new().connect_url(&url).await.unwrap();
+            conn.write_all(b"hello").await.unwrap();
+            let mut buf = [0u8; 5];
+            conn.read_exact(&mut buf).await.unwrap();
+            buf
+        };
+        let (echoed, ()) = tokio::join!(client, echo_once(&listener));
+        assert_eq!(&echoed, b"hello");
+    }
+
+    #[tokio::test]
+    async fn test_listener_rejects_tor() {
+        let url = TransportUrl::parse("http::tor//example.onion/").unwrap();
+        let result = TransportListener::bind(&url).await;
+        assert!(matches!(result, Err(TransportError::NotAvailable(_))));
+    }
+}
@@ -0,0 +1,246 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! In-memory loopback transport for tests (requires the `test-util` feature)
+//!
+//! `http::memory//<key>/path` URLs connect to the [`MemoryListener`] bound
+//! to `<key>` in the same process, over a `tokio::io::duplex` pipe. Nothing
+//! touches the filesystem or the network, so tests can run where sockets
+//! are not allowed.
+//!
+//! ```rust,ignore
+//! let listener = MemoryListener::bind("api")?;
+//! let conn = ComposedConnector::new().connect("http::memory//api/status").await?;
+//! let server_side = listener.accept().await?;
+//! ```
+
+use crate::connector::{AsyncReadWrite, Connector};
+use crate::types::{Transport, TransportError};
+use crate::TransportUrl;
+use futures::future::BoxFuture;
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::pin::Pin;
+use std::sync::{Mutex, OnceLock};
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, DuplexStream};
+use tokio::sync::mpsc;
+
+/// Bytes buffered in each direction of a memory connection
+const BUFFER_SIZE: usize = 64 * 1024;
+
+type Registry = Mutex<HashMap<String, mpsc::UnboundedSender<DuplexStream>>>;
+
+/// Listeners bound in this process, by key
+fn registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+/// One end of an in-memory connection
+pub struct MemoryConnection {
+    stream: DuplexStream,
+}
+
+impl MemoryConnection {
+    /// Wrap one end of an in-memory duplex pipe
+    pub fn new(stream: DuplexStream) -> Self {
+        Self { stream }
+    }
+}
+
+impl AsyncRead for MemoryConnection {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for MemoryConnection {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}
+
+/// Accepts in-memory connections addressed to a key
+///
+/// The key is released when the listener is dropped.
+pub struct MemoryListener {
+    key: String,
+    incoming: tokio::sync::Mutex<mpsc::UnboundedReceiver<DuplexStream>>,
+}
+
+impl MemoryListener {
+    /// Listen for connections to `key`
+    ///
+    /// Fails with `AddrInUse` if another listener holds the key.
+    pub fn bind(key: impl Into<String>) -> Result<Self, TransportError> {
+        let key = key.into();
+        let mut listeners = registry().lock().unwrap_or_else(|e| e.into_inner());
+        if listeners.get(&key).is_some_and(|sender| !sender.is_closed()) {
+            return Err(TransportError::Io(std::io::Error::new(
+                ErrorKind::AddrInUse,
+                format!("memory key {:?} is already bound", key),
+            )));
+        }
+
+        let (sender, incoming) = mpsc::unbounded_channel();
+        listeners.insert(key.clone(), sender);
+        Ok(Self {
+            key,
+            incoming: tokio::sync::Mutex::new(incoming),
+        })
+    }
+
+    /// Key this listener is bound to
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Accept the next connection
+    pub async fn accept(&self) -> Result<MemoryConnection, TransportError> {
+        self.incoming
+            .lock()
+            .await
+            .recv()
+            .await
+            .map(MemoryConnection::new)
+            .ok_or_else(|| TransportError::ConnectionFailed(format!("memory listener {:?} closed", self.key)))
+    }
+}
+
+impl Drop for MemoryListener {
+    fn drop(&mut self) {
+        // Closing our receiver marks our sender closed, so a newer listener
+        // bound to the same key is left registered
+        self.incoming.get_mut().close();
+        let mut listeners = registry().lock().unwrap_or_else(|e| e.into_inner());
+        if listeners.get(&self.key).is_some_and(|sender| sender.is_closed()) {
+            listeners.remove(&self.key);
+        }
+    }
+}
+
+/// Connector for `http::memory//<key>/` URLs
+#[derive(Clone, Default)]
+pub struct MemoryConnector;
+
+impl MemoryConnector {
+    /// Create a new memory connector
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Connect to the listener bound to `key`
+    ///
+    /// Fails with `ConnectionRefused` if nothing is listening.
+    pub fn connect_key(&self, key: &str) -> Result<MemoryConnection, TransportError> {
+        let (client, server) = tokio::io::duplex(BUFFER_SIZE);
+        let listeners = registry().lock().unwrap_or_else(|e| e.into_inner());
+        listeners
+            .get(key)
+            .and_then(|sender| sender.send(server).ok())
+            .ok_or_else(|| {
+                TransportError::Io(std::io::Error::new(
+                    ErrorKind::ConnectionRefused,
+                    format!("no memory listener bound to {:?}", key),
+                ))
+            })?;
+        Ok(MemoryConnection::new(client))
+    }
+}
+
+impl Connector for MemoryConnector {
+    fn allows_url(&self, url: &TransportUrl) -> bool {
+        url.transport() == Transport::Memory
+    }
+
+    fn connect<'a>(
+        &'a self,
+        url: &'a TransportUrl,
+    ) -> BoxFuture<'a, Result<Box<dyn AsyncReadWrite>, TransportError>> {
+        Box::pin(async move {
+            let key = url.host_str().ok_or_else(|| {
+                TransportError::InvalidUrl("No memory key".to_string())
+            })?;
+            let conn = self.connect_key(key)?;
+            Ok(Box::new(conn) as Box<dyn AsyncReadWrite>)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::composed::{ComposedConnector, Connection};
+    use http_body_util::{BodyExt, Empty, Full};
+    use hyper::body::Bytes;
+    use hyper::{Request, Response};
+    use hyper_util::rt::TokioIo;
+    use std::convert::Infallible;
+
+    #[tokio::test]
+    async fn test_memory_http_round_trip() {
+        let listener = MemoryListener::bind("memory-http").unwrap();
+        let server = tokio::spawn(async move {
+            let conn = listener.accept().await.unwrap();
+            let service = hyper::service::service_fn(|req: Request<hyper::body::Incoming>| async move {
+                let body = format!("hello from {}", req.uri().path());
+                Ok::<_, Infallible>(Response::new(Full::new(Bytes::from(body))))
+            });
+            hyper::server::conn::http1::Builder::new()
+                .serve_connection(TokioIo::new(conn), service)
+                .await
+                .unwrap();
+        });
+
+        let conn = ComposedConnector::new().connect("http::memory//memory-http/greeting").await.unwrap();
+        assert!(matches!(conn, Connection::Custom(_)));
+
+        let (mut sender, connection) = hyper::client::conn::http1::handshake(conn).await.unwrap();
+        tokio::spawn(connection);
+        let request = Request::get("/greeting")
+            .header("host", "memory-http")
+            .body(Empty::<Bytes>::new())
+            .unwrap();
+        let response = sender.send_request(request).await.unwrap();
+        assert!(response.status().is_success());
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], b"hello from /greeting");
+
+        drop(sender);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_memory_key_lifecycle() {
+        let connector = MemoryConnector::new();
+        let refused = connector.connect_key("memory-lifecycle").err().unwrap();
+        assert!(matches!(refused, TransportError::Io(ref e) if e.kind() == ErrorKind::ConnectionRefused));
+
+        let listener = MemoryListener::bind("memory-lifecycle").unwrap();
+        assert!(MemoryListener::bind("memory-lifecycle").is_err());
+        assert!(connector.connect_key("memory-lifecycle").is_ok());
+
+        drop(listener);
+        assert!(connector.connect_key("memory-lifecycle").is_err());
+        assert!(MemoryListener::bind("memory-lifecycle").is_ok());
+    }
+}
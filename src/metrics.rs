@@ -0,0 +1,192 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Connection counters (requires the `metrics` feature to record anything)
+//!
+//! With the `metrics` feature, connectors and [`Connection`](crate::composed::Connection)
+//! report to the [`metrics`](https://docs.rs/metrics) facade; install any
+//! recorder (Prometheus exporter, etc.) to collect them. Every counter has a
+//! `transport` label holding [`Transport::as_str`] (`"custom"` for bytes on
+//! connections opened by registered connectors).
+//!
+//! Without the feature these hooks are empty and compile away.
+
+use crate::types::{Transport, TransportError};
+use std::future::Future;
+
+/// Connection attempts started
+pub const CONNECTIONS_ATTEMPTED: &str = "rigging_connections_attempted_total";
+
+/// Connection attempts that produced a connection
+pub const CONNECTIONS_SUCCEEDED: &str = "rigging_connections_succeeded_total";
+
+/// Connection attempts that failed
+pub const CONNECTIONS_FAILED: &str = "rigging_connections_failed_total";
+
+/// Bytes read from connections
+pub const BYTES_RECEIVED: &str = "rigging_bytes_received_total";
+
+/// Bytes written to connections
+pub const BYTES_SENT: &str = "rigging_bytes_sent_total";
+
+/// Label holding the transport name
+pub const TRANSPORT_LABEL: &str = "transport";
+
+/// Count a connection attempt and its outcome
+#[inline]
+pub(crate) async fn observe_connect<T>(
+    transport: Transport,
+    connect: impl Future<Output = Result<T, TransportError>>,
+) -> Result<T, TransportError> {
+    #[cfg(feature = "metrics")]
+    {
+        let label = transport.as_str();
+        ::metrics::counter!(CONNECTIONS_ATTEMPTED, TRANSPORT_LABEL => label).increment(1);
+        let result = connect.await;
+        let outcome = if result.is_ok() { CONNECTIONS_SUCCEEDED } else { CONNECTIONS_FAILED };
+        ::metrics::counter!(outcome, TRANSPORT_LABEL => label).increment(1);
+        result
+    }
+    #[cfg(not(feature = "metrics"))]
+    {
+        let _ = transport;
+        connect.await
+    }
+}
+
+/// Count bytes read from a connection
+#[inline]
+pub(crate) fn bytes_received(label: &'static str, bytes: usize) {
+    #[cfg(feature = "metrics")]
+    if bytes > 0 {
+        ::metrics::counter!(BYTES_RECEIVED, TRANSPORT_LABEL => label).increment(bytes as u64);
+    }
+    #[cfg(not(feature = "metrics"))]
+    let _ = (label, bytes);
+}
+
+/// Count bytes written to a connection
+#[inline]
+pub(crate) fn bytes_sent(label: &'static str, bytes: usize) {
+    #[cfg(feature = "metrics")]
+    if bytes > 0 {
+        ::metrics::counter!(BYTES_SENT, TRANSPORT_LABEL => label).increment(bytes as u64);
+    }
+    #[cfg(not(feature = "metrics"))]
+    let _ = (label, bytes);
+}
+
+#[cfg(all(test, feature = "metrics", feature = "tcp"))]
+mod tests {
+    use super::*;
+    use crate::composed::ComposedConnector;
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Value of a counter with the given transport label, if recorded
+    fn counter(recorder: &DebuggingRecorder, name: &str, transport: &str) -> Option<u64> {
+        recorder
+            .snapshotter()
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .find_map(|(key, _, _, value)| {
+                let key = key.key();
+                let labelled = key
+                    .labels()
+                    .any(|l| l.key() == TRANSPORT_LABEL && l.value() == transport);
+                match value {
+                    DebugValue::Counter(n) if key.name() == name && labelled => Some(n),
+                    _ => None,
+                }
+            })
+    }
+
+    #[test]
+    fn test_tcp_connect_metrics() {
+        let recorder = DebuggingRecorder::new();
+        ::metrics::with_local_recorder(&recorder, || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+            runtime.block_on(async {
+                let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+                let port = listener.local_addr().unwrap().port();
+                let server = async {
+                    let (mut stream, _) = listener.accept().await.unwrap();
+                    let mut buf = [0u8; 4];
+                    stream.read_exact(&mut buf).await.unwrap();
+                    stream.write_all(b"pong!").await.unwrap();
+                };
+                let client = async {
+                    let mut conn = ComposedConnector::new()
+                        .connect(&format!("http://127.0.0.1:{}/", port))
+                        .await
+                        .unwrap();
+                    conn.write_all(b"ping").await.unwrap();
+                    let mut buf = [0u8; 5];
+                    conn.read_exact(&mut buf).await.unwrap();
+                };
+                tokio::join!(server, client);
+
+                // Nothing listens on the port once the listener is gone
+                drop(listener);
+                let refused = ComposedConnector::new()
+                    .connect(&format!("http://127.0.0.1:{}/", port))
+                    .await;
+                assert!(refused.is_err());
+            });
+        });
+
+        assert_eq!(counter(&recorder, CONNECTIONS_ATTEMPTED, "tcp"), Some(2));
+        assert_eq!(counter(&recorder, CONNECTIONS_SUCCEEDED, "tcp"), Some(1));
+        assert_eq!(counter(&recorder, CONNECTIONS_FAILED, "tcp"), Some(1));
+        assert_eq!(counter(&recorder, BYTES_SENT, "tcp"), Some(4));
+        assert_eq!(counter(&recorder, BYTES_RECEIVED, "tcp"), Some(5));
+    }
+
+    #[test]
+    fn test_service_connect_metrics() {
+        use tower_service::Service;
+
+        let recorder = DebuggingRecorder::new();
+        ::metrics::with_local_recorder(&recorder, || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+            runtime.block_on(async {
+                let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+                let uri: hyper::Uri = format!("http://127.0.0.1:{}/", listener.local_addr().unwrap().port())
+                    .parse()
+                    .unwrap();
+
+                // As hyper calls connectors: directly and through a bound connector
+                let mut tcp = crate::tcp_connector::TcpConnector::new();
+                let (conn, accepted) = tokio::join!(tcp.call(uri.clone()), listener.accept());
+                conn.unwrap();
+                accepted.unwrap();
+
+                let url = crate::TransportUrl::parse(&uri.to_string()).unwrap();
+                let mut bound = ComposedConnector::new().bound_connector(&url).unwrap();
+                let (conn, accepted) = tokio::join!(bound.call(uri.clone()), listener.accept());
+                conn.unwrap();
+                accepted.unwrap();
+
+                #[cfg(feature = "unix")]
+                {
+                    let missing = std::env::temp_dir().join("rigging-metrics-missing.sock");
+                    let mut unix = crate::unix_connector::UnixConnector::new(missing);
+                    assert!(unix.call(uri).await.is_err());
+                }
+            });
+        });
+
+        assert_eq!(counter(&recorder, CONNECTIONS_ATTEMPTED, "tcp"), Some(2));
+        assert_eq!(counter(&recorder, CONNECTIONS_SUCCEEDED, "tcp"), Some(2));
+        #[cfg(feature = "unix")]
+        assert_eq!(counter(&recorder, CONNECTIONS_FAILED, "unix"), Some(1));
+    }
+}
@@ -0,0 +1,482 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Localhost proxy for Unix socket URLs
+//!
+//! Browser backends that only speak TCP (e.g. the system webview) cannot
+//! reach `http::unix//...` URLs directly. `UnixProxy` listens on an
+//! ephemeral localhost port and forwards every HTTP request it receives to a
+//! Unix socket, so the URL can be rewritten to `http://127.0.0.1:<port>/...`.
+//!
+//! With a [`SocketMapping`], requests are routed by host instead: a request
+//! for `api.local` (from its `Host` header, or its target when the proxy is
+//! used as an HTTP proxy) goes to the socket mapped for `api.local`, and
+//! anything unmapped to the primary socket.
+//!
+//! # Access
+//!
+//! Anything on the machine can connect to a localhost port, so the proxy
+//! only forwards requests that carry its per-instance token, and would
+//! otherwise give every local process (and any page a browser runs) the
+//! socket's access and the configured headers. The token is accepted as:
+//!
+//! - a cookie, which [`login_url_for`](UnixProxy::login_url_for) URLs set
+//!   before redirecting to the page, for browsers loading the proxy directly
+//! - `Proxy-Authorization: Basic` credentials for [`PROXY_USER`] with the
+//!   token as password, for clients using it as an HTTP proxy
+//!
+//! Requests sent straight to the proxy must also name `localhost`,
+//! `127.0.0.1` or a mapped host in their `Host` header, which keeps pages
+//! on other sites from reaching it through DNS rebinding.
+
+use crate::types::TransportError;
+use crate::unix_connector::{SocketMapping, UnixConnector};
+use crate::TransportUrl;
+use http_body_util::combinators::BoxBody;
+use http_body_util::{BodyExt, Full};
+use hyper::body::{Bytes, Incoming};
+use hyper::header::{self, HeaderName, HeaderValue};
+use hyper::service::service_fn;
+use hyper::{Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use std::convert::Infallible;
+use std::io::Read;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::oneshot;
+
+/// Headers added to every forwarded request
+type ExtraHeaders = Arc<Vec<(HeaderName, HeaderValue)>>;
+
+/// Body type of proxied responses
+type ProxyBody = BoxBody<Bytes, hyper::Error>;
+
+/// User name for `Proxy-Authorization` credentials; the password is the token
+pub const PROXY_USER: &str = "rigging";
+
+/// Path prefix of [`UnixProxy::login_url_for`] URLs, followed by the token
+const LOGIN_PREFIX: &str = "/.rigging-login/";
+
+/// Checks a request carries the proxy's token
+struct Gate {
+    token: String,
+    /// Includes the port, so proxies on the same host keep separate cookies
+    cookie_name: String,
+    /// Expected `Proxy-Authorization` value
+    proxy_authorization: String,
+}
+
+impl Gate {
+    fn new(port: u16) -> std::io::Result<Self> {
+        let token = random_token()?;
+        let credentials = crate::base64::encode(format!("{}:{}", PROXY_USER, token).as_bytes());
+        Ok(Self {
+            cookie_name: format!("rigging-proxy-{}", port),
+            proxy_authorization: format!("Basic {}", credentials),
+            token,
+        })
+    }
+
+    /// Admit `request`, removing its credentials, or answer it here
+    ///
+    /// Answers login URLs with a redirect that sets the cookie, and requests
+    /// without a valid token or with a foreign `Host` with an error.
+    fn check(&self, request: &mut Request<Incoming>, upstream: &Upstream) -> Option<Response<ProxyBody>> {
+        let proxied = request.uri().authority().is_some();
+        if !proxied && !upstream.serves_host(request_host(request).as_deref()) {
+            return Some(reply(StatusCode::FORBIDDEN, "Host not allowed"));
+        }
+
+        if let Some(login) = request.uri().path().strip_prefix(LOGIN_PREFIX) {
+            let (token, rest) = login.split_once('/').unwrap_or((login, ""));
+            if !same_secret(token.as_bytes(), self.token.as_bytes()) {
+                return Some(reply(StatusCode::FORBIDDEN, "Invalid proxy token"));
+            }
+            let location = match request.uri().query() {
+                Some(query) => format!("/{}?{}", rest, query),
+                None => format!("/{}", rest),
+            };
+            let cookie = format!("{}={}; Path=/; HttpOnly; SameSite=Strict", self.cookie_name, self.token);
+            let mut response = reply(StatusCode::SEE_OTHER, "");
+            let headers = response.headers_mut();
+            headers.insert(header::LOCATION, location.parse().expect("path and query is a valid header value"));
+            headers.insert(header::SET_COOKIE, cookie.parse().expect("token is a valid header value"));
+            return Some(response);
+        }
+
+        // Check both so neither credential is forwarded
+        let by_header = self.take_proxy_authorization(request);
+        let by_cookie = self.take_cookie(request);
+        if by_header || by_cookie {
+            return None;
+        }
+        if proxied {
+            let mut response = reply(StatusCode::PROXY_AUTHENTICATION_REQUIRED, "Proxy token required");
+            response.headers_mut().insert(
+                header::PROXY_AUTHENTICATE,
+                HeaderValue::from_static("Basic realm=\"rigging\""),
+            );
+            return Some(response);
+        }
+        Some(reply(StatusCode::FORBIDDEN, "Proxy token required"))
+    }
+
+    fn take_proxy_authorization(&self, request: &mut Request<Incoming>) -> bool {
+        request
+            .headers_mut()
+            .remove(header::PROXY_AUTHORIZATION)
+            .is_some_and(|value| same_secret(value.as_bytes(), self.proxy_authorization.as_bytes()))
+    }
+
+    /// Remove this proxy's cookie, keeping any others
+    fn take_cookie(&self, request: &mut Request<Incoming>) -> bool {
+        let (found, kept) = {
+            let mut found = false;
+            let mut kept = Vec::new();
+            for value in request.headers().get_all(header::COOKIE) {
+                let Ok(value) = value.to_str() else { continue };
+                for pair in value.split(';').map(str::trim).filter(|pair| !pair.is_empty()) {
+                    match pair.split_once('=') {
+                        Some((name, token)) if name == self.cookie_name => {
+                            found |= same_secret(token.as_bytes(), self.token.as_bytes());
+                        }
+                        _ => kept.push(pair),
+                    }
+                }
+            }
+            (found, kept.join("; "))
+        };
+        let headers = request.headers_mut();
+        headers.remove(header::COOKIE);
+        if !kept.is_empty() {
+            if let Ok(value) = HeaderValue::from_str(&kept) {
+                headers.insert(header::COOKIE, value);
+            }
+        }
+        found
+    }
+}
+
+/// Where requests are forwarded
+#[derive(Clone)]
+struct Upstream {
+    /// Socket for hosts without a mapping
+    primary: UnixConnector,
+    mapping: Option<Arc<SocketMapping>>,
+}
+
+impl Upstream {
+    /// Connector for a request to `host` (without port)
+    fn connector_for(&self, host: Option<&str>) -> UnixConnector {
+        let routed = host.zip(self.mapping.as_deref()).and_then(|(host, mapping)| {
+            // A guess in the socket directory only counts if the socket is there
+            mapping
+                .get_socket_path(host)
+                .filter(|path| mapping.mapped_path(host).is_some() || path.exists())
+        });
+        routed.map_or_else(|| self.primary.clone(), UnixConnector::new)
+    }
+
+    /// Whether a request sent straight to the proxy may name `host`
+    fn serves_host(&self, host: Option<&str>) -> bool {
+        let Some(host) = host else { return false };
+        host.eq_ignore_ascii_case("localhost")
+            || host == "127.0.0.1"
+            || self.mapping.as_deref().is_some_and(|mapping| mapping.mapped_path(host).is_some())
+    }
+}
+
+/// A localhost TCP listener that forwards connections to a Unix socket
+///
+/// The proxy runs on its own thread with a private Tokio runtime, so it can
+/// be used from synchronous code such as a GUI event loop. It stops when
+/// [`shutdown`](Self::shutdown) is called or when it is dropped.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use rigging::proxy::UnixProxy;
+///
+/// let url = TransportUrl::parse("http::unix///tmp/app.sock/index.html")?;
+/// let proxy = UnixProxy::start(UnixConnector::new("/tmp/app.sock"))?;
+/// // http://127.0.0.1:<port>/.rigging-login/<token>/index.html
+/// let login_url = proxy.login_url_for(&url);
+/// ```
+pub struct UnixProxy {
+    addr: SocketAddr,
+    socket_path: PathBuf,
+    gate: Arc<Gate>,
+    shutdown: Option<oneshot::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl UnixProxy {
+    /// Start a proxy forwarding to the connector's socket
+    ///
+    /// Binds `127.0.0.1` on an ephemeral port before returning, so the
+    /// proxy is ready to accept connections as soon as this succeeds. Each
+    /// proxy has its own random token (see the [module docs](self)).
+    pub fn start(connector: UnixConnector) -> Result<Self, TransportError> {
+        Self::start_with_headers(connector, &[])
+    }
+
+    /// Start a proxy that adds `headers` to every forwarded request
+    ///
+    /// A configured header replaces any header of the same name sent by the
+    /// browser. Fails if a header name or value is not valid HTTP.
+    pub fn start_with_headers(
+        connector: UnixConnector,
+        headers: &[(String, String)],
+    ) -> Result<Self, TransportError> {
+        Self::spawn(Upstream { primary: connector, mapping: None }, headers)
+    }
+
+    /// Start a proxy that routes each request by host using `mapping`
+    ///
+    /// Hosts `mapping` resolves (via [`SocketMapping::get_socket_path`]) go
+    /// to their socket; a host that would only map into the socket directory
+    /// must have a socket there. Everything else goes to `primary`, as do
+    /// requests addressed to the proxy itself.
+    pub fn start_with_mapping(
+        primary: UnixConnector,
+        mapping: SocketMapping,
+        headers: &[(String, String)],
+    ) -> Result<Self, TransportError> {
+        Self::spawn(Upstream { primary, mapping: Some(Arc::new(mapping)) }, headers)
+    }
+
+    fn spawn(upstream: Upstream, headers: &[(String, String)]) -> Result<Self, TransportError> {
+        let headers: ExtraHeaders = Arc::new(parse_headers(headers)?);
+
+        let listener = std::net::TcpListener::bind(("127.0.0.1", 0))?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+        let socket_path = upstream.primary.socket_path().to_path_buf();
+        let gate = Arc::new(Gate::new(addr.port())?);
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .build()?;
+
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let serve_gate = gate.clone();
+        let thread = std::thread::Builder::new()
+            .name("rigging-unix-proxy".to_string())
+            .spawn(move || {
+                runtime.block_on(async move {
+                    match TcpListener::from_std(listener) {
+                        Ok(listener) => serve(listener, upstream, headers, serve_gate, shutdown_rx).await,
+                        Err(e) => log::error!("Unix proxy failed to start: {}", e),
+                    }
+                });
+            })?;
+
+        log::info!("Unix proxy listening on {}", addr);
+        Ok(Self {
+            addr,
+            socket_path,
+            gate,
+            shutdown: Some(shutdown_tx),
+            thread: Some(thread),
+        })
+    }
+
+    /// Address the proxy is listening on
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Socket requests are forwarded to, unless routed elsewhere by host
+    pub fn socket_path(&self) -> &Path {
+        &self.socket_path
+    }
+
+    /// Rewrite a Unix socket URL to the equivalent URL on this proxy
+    ///
+    /// Keeps the URL's path, query and fragment. Requests for it still need
+    /// the token; browsers should be given [`login_url_for`](Self::login_url_for).
+    pub fn url_for(&self, url: &TransportUrl) -> String {
+        format!("http://{}{}", self.addr, &url.url()[url::Position::BeforePath..])
+    }
+
+    /// Like [`url_for`](Self::url_for), but sets the token cookie first
+    ///
+    /// The proxy answers with a redirect to the `url_for` URL that stores
+    /// the cookie, so the page and everything it loads from the proxy are
+    /// let through.
+    pub fn login_url_for(&self, url: &TransportUrl) -> String {
+        format!("http://{}{}{}{}", self.addr, LOGIN_PREFIX, self.gate.token, &url.url()[url::Position::BeforePath..])
+    }
+
+    /// Token requests must carry (see the [module docs](self))
+    pub fn token(&self) -> &str {
+        &self.gate.token
+    }
+
+    /// `Proxy-Authorization` header value carrying the token
+    pub fn proxy_authorization(&self) -> &str {
+        &self.gate.proxy_authorization
+    }
+
+    /// Stop accepting connections and wait for the proxy thread to exit
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for UnixProxy {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Parse configured headers, rejecting invalid names and values
+fn parse_headers(headers: &[(String, String)]) -> Result<Vec<(HeaderName, HeaderValue)>, TransportError> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| TransportError::InvalidHeader(format!("invalid header name {:?}", name)))?;
+            let value = HeaderValue::from_str(value)
+                .map_err(|_| TransportError::InvalidHeader(format!("invalid value for header {}", name)))?;
+            Ok((name, value))
+        })
+        .collect()
+}
+
+/// Accept loop: serve HTTP on each TCP connection
+async fn serve(
+    listener: TcpListener,
+    upstream: Upstream,
+    headers: ExtraHeaders,
+    gate: Arc<Gate>,
+    mut shutdown: oneshot::Receiver<()>,
+) {
+    loop {
+        tokio::select! {
+            _ = &mut shutdown => break,
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    tokio::spawn(relay(stream, upstream.clone(), headers.clone(), gate.clone()));
+                }
+                Err(e) => log::warn!("Unix proxy accept failed: {}", e),
+            },
+        }
+    }
+    log::debug!("Unix proxy on {:?} stopped", listener.local_addr());
+}
+
+async fn relay(client: TcpStream, upstream: Upstream, headers: ExtraHeaders, gate: Arc<Gate>) {
+    let service = service_fn(move |request| forward(request, upstream.clone(), headers.clone(), gate.clone()));
+
+    if let Err(e) = hyper::server::conn::http1::Builder::new()
+        .serve_connection(TokioIo::new(client), service)
+        .await
+    {
+        log::debug!("Unix proxy connection closed: {}", e);
+    }
+}
+
+/// Forward one request to its Unix socket, answering 502 if it is unreachable
+async fn forward(
+    mut request: Request<Incoming>,
+    upstream: Upstream,
+    headers: ExtraHeaders,
+    gate: Arc<Gate>,
+) -> Result<Response<ProxyBody>, Infallible> {
+    if let Some(response) = gate.check(&mut request, &upstream) {
+        return Ok(response);
+    }
+    let connector = upstream.connector_for(request_host(&request).as_deref());
+    if request.uri().authority().is_some() {
+        // Absolute-form, from a client using us as its HTTP proxy
+        let origin_form = request.uri().path_and_query().map_or("/", |pq| pq.as_str());
+        *request.uri_mut() = origin_form.parse().expect("path and query is a valid URI");
+    }
+    for (name, value) in headers.iter() {
+        request.headers_mut().insert(name.clone(), value.clone());
+    }
+
+    match send_upstream(request, &connector).await {
+        Ok(response) => Ok(response.map(|body| body.boxed())),
+        Err(e) => {
+            log::warn!("Unix proxy could not reach {}: {}", connector.socket_path().display(), e);
+            Ok(reply(StatusCode::BAD_GATEWAY, format!("Bad gateway: {}", e)))
+        }
+    }
+}
+
+/// A response generated by the proxy itself
+fn reply(status: StatusCode, body: impl Into<Bytes>) -> Response<ProxyBody> {
+    let mut response = Response::new(Full::new(body.into()).map_err(|never| match never {}).boxed());
+    *response.status_mut() = status;
+    response
+}
+
+/// Host a request is addressed to, without any port
+fn request_host(request: &Request<Incoming>) -> Option<String> {
+    if let Some(host) = request.uri().host() {
+        return Some(host.to_string());
+    }
+    let header = request.headers().get(hyper::header::HOST)?.to_str().ok()?;
+    let authority: hyper::http::uri::Authority = header.parse().ok()?;
+    Some(authority.host().to_string())
+}
+
+async fn send_upstream(
+    request: Request<Incoming>,
+    connector: &UnixConnector,
+) -> Result<Response<Incoming>, TransportError> {
+    let upstream = connector.connect().await?;
+    let (mut sender, connection) = hyper::client::conn::http1::handshake(upstream)
+        .await
+        .map_err(|e| TransportError::ConnectionFailed(e.to_string()))?;
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            log::debug!("Unix proxy upstream connection closed: {}", e);
+        }
+    });
+
+    sender
+        .send_request(request)
+        .await
+        .map_err(|e| TransportError::ConnectionFailed(e.to_string()))
+}
+
+/// 128 random bits, hex encoded
+fn random_token() -> std::io::Result<String> {
+    let mut bytes = [0u8; 16];
+    std::fs::File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Compare secrets without revealing through timing where they differ
+fn same_secret(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_random_token() {
+        let token = random_token().unwrap();
+        assert_eq!(token.len(), 32);
+        assert!(token.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(token, random_token().unwrap());
+    }
+}
@@ -0,0 +1,145 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Redaction of connect targets in logs
+//!
+//! Connectors log each connect attempt at `info` with its transport and
+//! target, which is handy in support bundles but can reveal which onion
+//! services or local daemons were used. The host and socket path pass
+//! through a [`RedactionPolicy`] first:
+//!
+//! | Policy       | `http::tor//<56 chars>.onion/` | `/run/user/1000/app.sock` |
+//! |--------------|--------------------------------|---------------------------|
+//! | `None`       | unchanged                      | unchanged                 |
+//! | `Hash`       | `#` and 16 hex digits          | `#` and 16 hex digits     |
+//! | `Truncate`   | `pg6mmj….onion`                | `…/app.sock`              |
+//!
+//! `Truncate` is the default and leaves other host names alone. Set the
+//! policy with [`ComposedConnector::with_redaction`](crate::composed::ComposedConnector::with_redaction)
+//! or on each connector.
+
+use crate::types::Transport;
+use std::path::Path;
+
+/// Characters of an onion label kept by [`RedactionPolicy::Truncate`]
+pub const ONION_PREFIX_LEN: usize = 6;
+
+/// How hosts and socket paths appear in connect logs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum RedactionPolicy {
+    /// Log targets as they are
+    None,
+    /// Log a stable hash, so attempts to one target can still be correlated
+    Hash,
+    /// Shorten onion service labels and keep only the socket file name
+    #[default]
+    Truncate,
+}
+
+impl RedactionPolicy {
+    /// `host` as it should be logged
+    pub fn host(&self, host: &str) -> String {
+        match self {
+            RedactionPolicy::None => host.to_string(),
+            RedactionPolicy::Hash => hash(host.as_bytes()),
+            RedactionPolicy::Truncate => truncate_onion(host),
+        }
+    }
+
+    /// `path` as it should be logged
+    pub fn socket_path(&self, path: &Path) -> String {
+        match self {
+            RedactionPolicy::None => path.display().to_string(),
+            RedactionPolicy::Hash => hash(path.as_os_str().as_encoded_bytes()),
+            RedactionPolicy::Truncate => match (path.parent(), path.file_name()) {
+                (Some(parent), Some(name)) if !parent.as_os_str().is_empty() => {
+                    format!("…/{}", name.to_string_lossy())
+                }
+                // A bare name or an `@name` abstract socket
+                _ => path.display().to_string(),
+            },
+        }
+    }
+
+    /// Log a connect attempt to host:port at `info`
+    pub(crate) fn log_connect(&self, transport: Transport, host: &str, port: u16) {
+        log::info!("Connecting over {} to {}:{}", transport.as_str(), self.host(host), port);
+    }
+
+    /// Log a connect attempt to a Unix socket at `info`
+    pub(crate) fn log_socket_connect(&self, path: &Path) {
+        log::info!("Connecting over {} to {}", Transport::Unix.as_str(), self.socket_path(path));
+    }
+}
+
+/// Shorten the onion service label of a `.onion` host, keeping subdomains
+fn truncate_onion(host: &str) -> String {
+    let labels: Vec<&str> = host.split('.').collect();
+    let is_onion = labels.len() >= 2 && labels[labels.len() - 1].eq_ignore_ascii_case("onion");
+    if !is_onion {
+        return host.to_string();
+    }
+
+    let service = labels.len() - 2;
+    labels
+        .iter()
+        .enumerate()
+        .map(|(i, label)| match label.char_indices().nth(ONION_PREFIX_LEN) {
+            Some((end, _)) if i == service => format!("{}…", &label[..end]),
+            _ => label.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// `#` and the 64-bit FNV-1a hash of `bytes`, stable across runs and builds
+fn hash(bytes: &[u8]) -> String {
+    let hash = bytes.iter().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    format!("#{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ONION: &str = "pg6mmjiyjmcrsslvykfwnntlaru7p5svn6y2ymmju6nubxndf4pscryd.onion";
+
+    #[test]
+    fn test_redacted_onion_hosts() {
+        let policy = RedactionPolicy::default();
+        assert_eq!(policy, RedactionPolicy::Truncate);
+        assert_eq!(policy.host(ONION), "pg6mmj….onion");
+        assert_eq!(policy.host(&format!("www.{}", ONION)), "www.pg6mmj….onion");
+        assert_eq!(policy.host(&ONION.to_uppercase()), "PG6MMJ….ONION");
+        // Other hosts are not sensitive enough to truncate
+        assert_eq!(policy.host("example.com"), "example.com");
+        assert_eq!(policy.host("onion"), "onion");
+
+        assert_eq!(RedactionPolicy::None.host(ONION), ONION);
+
+        let hashed = RedactionPolicy::Hash.host(ONION);
+        assert_eq!(hashed.len(), 17);
+        assert!(hashed.starts_with('#') && !hashed.contains("pg6mmj"));
+        assert_eq!(RedactionPolicy::Hash.host(ONION), hashed);
+        assert_ne!(RedactionPolicy::Hash.host("example.com"), hashed);
+        assert_eq!(RedactionPolicy::Hash.host(""), "#cbf29ce484222325");
+    }
+
+    #[test]
+    fn test_redacted_unix_paths() {
+        let path = Path::new("/run/user/1000/rigging/app.sock");
+        assert_eq!(RedactionPolicy::Truncate.socket_path(path), "…/app.sock");
+        assert_eq!(RedactionPolicy::Truncate.socket_path(Path::new("app.sock")), "app.sock");
+        assert_eq!(RedactionPolicy::Truncate.socket_path(Path::new("@rigging")), "@rigging");
+        assert_eq!(RedactionPolicy::None.socket_path(path), "/run/user/1000/rigging/app.sock");
+
+        let hashed = RedactionPolicy::Hash.socket_path(path);
+        assert!(hashed.starts_with('#') && !hashed.contains("app"));
+        assert_eq!(hashed, RedactionPolicy::Hash.host("/run/user/1000/rigging/app.sock"));
+    }
+}
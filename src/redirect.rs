@@ -0,0 +1,126 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Transport-aware redirect following
+//!
+//! [`follow_redirects`] issues `GET` requests and follows redirect
+//! responses, resolving each target with [`TransportUrl::resolve`] so the
+//! transport context is kept: a relative `Location` from a server behind a
+//! Unix socket stays on that socket, while a transport-aware target such as
+//! `http::tor//example.onion/` switches transports.
+//!
+//! Redirects that leave Tor or I2P, or that move from the network onto a
+//! local socket, pipe or in-memory transport, are refused unless
+//! [`RedirectOptions::allow_unsafe_transport_change`] is set; see
+//! [`TransportUrl::check_redirect`].
+//!
+//! Browsers and other clients reject transport-aware URLs in `Location`,
+//! so servers can send one in [`TRANSPORT_LOCATION`] instead; it takes
+//! precedence over `Location` when both are present.
+//!
+//! ```rust,ignore
+//! let start = TransportUrl::parse("http::unix///run/app.sock/login")?;
+//! let (url, response) = follow_redirects(&connector, start.clone(), 5).await?;
+//!
+//! // Let a clearnet server send the client on to a local socket
+//! let options = RedirectOptions::new().with_unsafe_transport_change(true);
+//! let (url, response) = follow_redirects_with_options(&connector, start, 5, options).await?;
+//! ```
+
+use crate::composed::ComposedConnector;
+use crate::types::TransportError;
+use crate::TransportUrl;
+use hyper::body::Incoming;
+use hyper::header::LOCATION;
+use hyper::{Response, StatusCode};
+use std::collections::HashSet;
+
+/// Header carrying a transport-aware redirect target
+pub const TRANSPORT_LOCATION: &str = "x-transport-location";
+
+/// Options for [`follow_redirects_with_options`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RedirectOptions {
+    /// Follow redirects that [`TransportUrl::check_redirect`] refuses
+    pub allow_unsafe_transport_change: bool,
+}
+
+impl RedirectOptions {
+    /// Create options with the defaults (unsafe transport changes refused)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Follow redirects out of Tor/I2P or onto local transports
+    pub fn with_unsafe_transport_change(mut self, allow: bool) -> Self {
+        self.allow_unsafe_transport_change = allow;
+        self
+    }
+}
+
+/// `GET` `initial`, following up to `max_hops` redirects
+///
+/// Returns the final URL with its response, which may still be a redirect
+/// if it had no target. Fails with [`TransportError::TooManyRedirects`]
+/// after `max_hops` redirects, with [`TransportError::RedirectLoop`] as
+/// soon as a target repeats and with [`TransportError::RedirectDenied`] for
+/// a redirect that would leave Tor/I2P or reach a local transport.
+pub async fn follow_redirects(
+    connector: &ComposedConnector,
+    initial: TransportUrl,
+    max_hops: usize,
+) -> Result<(TransportUrl, Response<Incoming>), TransportError> {
+    follow_redirects_with_options(connector, initial, max_hops, RedirectOptions::default()).await
+}
+
+/// [`follow_redirects`], with `options`
+pub async fn follow_redirects_with_options(
+    connector: &ComposedConnector,
+    initial: TransportUrl,
+    max_hops: usize,
+    options: RedirectOptions,
+) -> Result<(TransportUrl, Response<Incoming>), TransportError> {
+    let mut url = initial;
+    let mut visited = HashSet::new();
+    let mut hops = 0;
+    loop {
+        visited.insert(url.to_string());
+        let response = connector.send_get(&url).await?;
+
+        let Some(target) = redirect_target(&response) else {
+            return Ok((url, response));
+        };
+        if hops == max_hops {
+            return Err(TransportError::TooManyRedirects(max_hops));
+        }
+        hops += 1;
+
+        let next = url.resolve(&target)?;
+        if !options.allow_unsafe_transport_change {
+            url.check_redirect(&next)?;
+        }
+        if visited.contains(&next.to_string()) {
+            return Err(TransportError::RedirectLoop(next.to_string()));
+        }
+        log::debug!("Following {} redirect from {} to {}", response.status(), url, next);
+        url = next;
+    }
+}
+
+/// The target of a redirect response, if it is one
+fn redirect_target(response: &Response<Incoming>) -> Option<String> {
+    if !matches!(
+        response.status(),
+        StatusCode::MOVED_PERMANENTLY
+            | StatusCode::FOUND
+            | StatusCode::SEE_OTHER
+            | StatusCode::TEMPORARY_REDIRECT
+            | StatusCode::PERMANENT_REDIRECT
+    ) {
+        return None;
+    }
+    let headers = response.headers();
+    let value = headers.get(TRANSPORT_LOCATION).or_else(|| headers.get(LOCATION))?;
+    value.to_str().ok().map(str::to_string)
+}
@@ -0,0 +1,123 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! reqwest adapter (requires the `reqwest` feature, supports reqwest 0.12)
+//!
+//! reqwest does not accept custom connectors, so `TransportClient` pairs a
+//! configured `reqwest::Client` with whatever it needs to reach a
+//! `TransportUrl`. Unix socket URLs go through a localhost
+//! [`UnixProxy`](crate::proxy::UnixProxy) that lives as long as the client;
+//! TCP URLs use reqwest directly. Tor and registered custom connectors are
+//! not supported; use [`ComposedConnector::into_hyper_client`] for those.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use rigging::reqwest_client::TransportClient;
+//!
+//! let url = TransportUrl::parse("http::unix///tmp/app.sock/")?;
+//! let client = TransportClient::new(&ComposedConnector::new(), &url)?;
+//! let body = client.get("/api/status")?.send().await?.text().await?;
+//! ```
+
+use crate::composed::{ComposedConnector, ConnectorType};
+use crate::proxy::{UnixProxy, PROXY_USER};
+use crate::types::TransportError;
+use crate::TransportUrl;
+use url::Url;
+
+/// A reqwest client bound to one transport endpoint
+pub struct TransportClient {
+    client: reqwest::Client,
+    base_url: Url,
+    _proxy: Option<UnixProxy>,
+}
+
+impl TransportClient {
+    /// Build a client for the endpoint `url` points at
+    pub fn new(connector: &ComposedConnector, url: &TransportUrl) -> Result<Self, TransportError> {
+        Self::with_builder(connector, url, reqwest::Client::builder())
+    }
+
+    /// Build a client from a customised `reqwest::ClientBuilder`
+    ///
+    /// For Unix socket URLs the builder's proxy settings are replaced: the
+    /// client sends its requests through the localhost proxy as an HTTP
+    /// proxy, with the proxy's token as credentials.
+    pub fn with_builder(
+        connector: &ComposedConnector,
+        url: &TransportUrl,
+        builder: reqwest::ClientBuilder,
+    ) -> Result<Self, TransportError> {
+        let build = |builder: reqwest::ClientBuilder| {
+            builder
+                .build()
+                .map_err(|e| TransportError::ConnectionFailed(format!("Failed to build reqwest client: {}", e)))
+        };
+
+        match connector.connector_for_url(url)? {
+            ConnectorType::Unix(unix) => {
+                let proxy = UnixProxy::start(unix)?;
+                let base_url = match url.host_str() {
+                    // Host mapped to a socket: keep the URL (and Host header)
+                    Some(_) if !url.is_explicit_transport() => url.url().clone(),
+                    _ => Url::parse(&proxy.url_for(url)).map_err(|e| TransportError::InvalidUrl(e.to_string()))?,
+                };
+                let via = reqwest::Proxy::http(format!("http://{}", proxy.local_addr()))
+                    .map_err(|e| TransportError::ConnectionFailed(format!("Invalid proxy: {}", e)))?
+                    .basic_auth(PROXY_USER, proxy.token());
+                Ok(Self {
+                    client: build(builder.no_proxy().proxy(via))?,
+                    base_url,
+                    _proxy: Some(proxy),
+                })
+            }
+            // reqwest performs TLS itself with whichever backend the application enabled
+            #[cfg(feature = "tcp")]
+            ConnectorType::Tcp(_) => Ok(Self {
+                client: build(builder)?,
+                base_url: url.url().clone(),
+                _proxy: None,
+            }),
+            #[cfg(feature = "tls")]
+            ConnectorType::Tls(_) => Ok(Self {
+                client: build(builder)?,
+                base_url: url.url().clone(),
+                _proxy: None,
+            }),
+            #[allow(unreachable_patterns)]
+            _ => Err(TransportError::NotAvailable(format!(
+                "reqwest adapter does not support {} URLs",
+                url.transport().display_name()
+            ))),
+        }
+    }
+
+    /// The underlying reqwest client
+    pub fn client(&self) -> &reqwest::Client {
+        &self.client
+    }
+
+    /// URL requests are resolved against
+    pub fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+
+    /// Resolve `path` against the base URL
+    pub fn url(&self, path: &str) -> Result<Url, TransportError> {
+        self.base_url
+            .join(path)
+            .map_err(|e| TransportError::InvalidUrl(e.to_string()))
+    }
+
+    /// Start a GET request for `path`
+    pub fn get(&self, path: &str) -> Result<reqwest::RequestBuilder, TransportError> {
+        Ok(self.client.get(self.url(path)?))
+    }
+
+    /// Start a POST request for `path`
+    pub fn post(&self, path: &str) -> Result<reqwest::RequestBuilder, TransportError> {
+        Ok(self.client.post(self.url(path)?))
+    }
+}
@@ -0,0 +1,123 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Retry policy for connecting to daemons that may not be up yet
+//!
+//! Used by [`ComposedConnector::connect_with_retry`](crate::composed::ComposedConnector::connect_with_retry).
+
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::time::Duration;
+
+/// Exponential backoff settings for connection retries
+///
+/// The delay before attempt `n + 1` is `initial_backoff * 2^(n - 1)`, capped
+/// at `max_backoff`, then randomly shortened by up to `jitter` (a fraction
+/// between 0 and 1) so that many clients do not retry in lockstep.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct RetryConfig {
+    /// Total number of attempts, including the first (at least 1)
+    pub max_attempts: u32,
+    /// Delay before the first retry
+    pub initial_backoff: Duration,
+    /// Upper bound on any single delay
+    pub max_backoff: Duration,
+    /// Fraction of each delay that may be randomly removed (0.0 - 1.0)
+    pub jitter: f64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+            jitter: 0.2,
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Create a config with the defaults (5 attempts, 100ms doubling to 5s)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the total number of attempts
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Set the delay before the first retry
+    pub fn with_initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// Set the upper bound on any single delay
+    pub fn with_max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// Set the jitter fraction (clamped to 0.0 - 1.0)
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Delay to wait after failed attempt number `attempt` (1-based)
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(31);
+        let delay = self
+            .initial_backoff
+            .saturating_mul(1u32 << exponent)
+            .min(self.max_backoff);
+
+        let jitter = if self.jitter.is_finite() { self.jitter.clamp(0.0, 1.0) } else { 0.0 };
+        if jitter == 0.0 {
+            return delay;
+        }
+        delay.mul_f64(1.0 - jitter * random_fraction())
+    }
+}
+
+/// A random number in [0, 1), good enough to spread out retries
+fn random_fraction() -> f64 {
+    let bits = RandomState::new().hash_one(std::time::Instant::now());
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let config = RetryConfig::new()
+            .with_initial_backoff(Duration::from_millis(100))
+            .with_max_backoff(Duration::from_millis(350))
+            .with_jitter(0.0);
+
+        assert_eq!(config.backoff(1), Duration::from_millis(100));
+        assert_eq!(config.backoff(2), Duration::from_millis(200));
+        assert_eq!(config.backoff(3), Duration::from_millis(350));
+        assert_eq!(config.backoff(40), Duration::from_millis(350));
+    }
+
+    #[test]
+    fn test_backoff_jitter_stays_in_range() {
+        let config = RetryConfig::new()
+            .with_initial_backoff(Duration::from_millis(100))
+            .with_jitter(0.5);
+
+        for _ in 0..100 {
+            let delay = config.backoff(1);
+            assert!(delay > Duration::from_millis(50) && delay <= Duration::from_millis(100));
+        }
+    }
+}
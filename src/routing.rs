@@ -0,0 +1,250 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Rule-based transport selection
+//!
+//! A [`RoutingPolicy`] picks the transport for URLs that do not name one,
+//! in the spirit of a proxy auto-config file: rules are tried top to bottom
+//! and the first whose pattern matches the host wins.
+//!
+//! ```rust,ignore
+//! let policy = RoutingPolicy::new()
+//!     .with_rule(RoutingRule::new("*.onion", Transport::Tor)?)
+//!     .with_rule(RoutingRule::new("10.0.0.0/8", Transport::Tcp)?)
+//!     .with_rule(RoutingRule::new("*", Transport::Unix)?);
+//! let connector = ComposedConnector::new().with_policy(policy);
+//! ```
+//!
+//! Patterns are a host glob (`*` matches any run of characters, compared
+//! case-insensitively), an IPv4 or IPv6 CIDR block (matched against IP
+//! literal hosts only), or `*` alone for every host.
+
+use crate::types::{Transport, TransportError};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+/// What a [`RoutingRule`] matches
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HostPattern {
+    /// Every host
+    Any,
+    /// Host glob such as `*.onion` (stored lowercase)
+    Glob(String),
+    /// IP literals within `network/prefix`
+    Cidr { network: IpAddr, prefix: u8 },
+}
+
+impl HostPattern {
+    /// Parse `*`, a CIDR block such as `10.0.0.0/8`, or a host glob
+    pub fn parse(pattern: &str) -> Result<Self, TransportError> {
+        let pattern = pattern.trim();
+        if pattern.is_empty() {
+            return Err(TransportError::ConfigError("empty host pattern".to_string()));
+        }
+        if pattern == "*" {
+            return Ok(HostPattern::Any);
+        }
+        let Some((network, prefix)) = pattern.split_once('/') else {
+            return Ok(HostPattern::Glob(pattern.to_ascii_lowercase()));
+        };
+
+        let invalid = || TransportError::ConfigError(format!("invalid CIDR block {:?}", pattern));
+        let network: IpAddr = network.parse().map_err(|_| invalid())?;
+        let prefix: u8 = prefix.parse().map_err(|_| invalid())?;
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        if prefix > max {
+            return Err(invalid());
+        }
+        Ok(HostPattern::Cidr { network, prefix })
+    }
+
+    /// Whether `host` matches (IPv6 hosts may keep their brackets)
+    pub fn matches(&self, host: &str) -> bool {
+        match self {
+            HostPattern::Any => true,
+            HostPattern::Glob(glob) => glob_matches(glob.as_bytes(), host.to_ascii_lowercase().as_bytes()),
+            HostPattern::Cidr { network, prefix } => {
+                let literal = host.trim_start_matches('[').trim_end_matches(']');
+                literal.parse().is_ok_and(|ip| in_block(ip, *network, *prefix))
+            }
+        }
+    }
+}
+
+/// Whether `ip` lies within `network/prefix` (never across address families)
+fn in_block(ip: IpAddr, network: IpAddr, prefix: u8) -> bool {
+    match (ip, network) {
+        (IpAddr::V4(ip), IpAddr::V4(network)) => {
+            let mask = u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0);
+            u32::from(ip) & mask == u32::from(network) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(network)) => {
+            let mask = u128::MAX.checked_shl(128 - u32::from(prefix)).unwrap_or(0);
+            u128::from(ip) & mask == u128::from(network) & mask
+        }
+        _ => false,
+    }
+}
+
+/// Match `text` against a glob where `*` matches any run of bytes
+fn glob_matches(glob: &[u8], text: &[u8]) -> bool {
+    let (mut g, mut t) = (0, 0);
+    // Position after the last `*` and the text position it was tried at
+    let mut retry = None;
+    while t < text.len() {
+        if g < glob.len() && glob[g] == b'*' {
+            g += 1;
+            retry = Some((g, t));
+        } else if g < glob.len() && glob[g] == text[t] {
+            g += 1;
+            t += 1;
+        } else if let Some((after_star, tried)) = retry {
+            // Let the last `*` swallow one more byte
+            g = after_star;
+            t = tried + 1;
+            retry = Some((after_star, tried + 1));
+        } else {
+            return false;
+        }
+    }
+    glob[g..].iter().all(|&b| b == b'*')
+}
+
+/// One routing rule: hosts matching a pattern use a transport
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoutingRule {
+    pattern: HostPattern,
+    transport: Transport,
+    socket: Option<PathBuf>,
+}
+
+impl RoutingRule {
+    /// Route hosts matching `pattern` (see [`HostPattern::parse`]) over `transport`
+    pub fn new(pattern: &str, transport: Transport) -> Result<Self, TransportError> {
+        Ok(Self {
+            pattern: HostPattern::parse(pattern)?,
+            transport,
+            socket: None,
+        })
+    }
+
+    /// Use this socket instead of the connector's default
+    ///
+    /// For [`Transport::Unix`] it is the socket to connect to; for
+    /// [`Transport::Tor`], the Corsair socket.
+    pub fn with_socket<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.socket = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// The host pattern
+    pub fn pattern(&self) -> &HostPattern {
+        &self.pattern
+    }
+
+    /// The transport matching hosts use
+    pub fn transport(&self) -> Transport {
+        self.transport
+    }
+
+    /// The socket override, if any
+    pub fn socket_path(&self) -> Option<&Path> {
+        self.socket.as_deref()
+    }
+}
+
+/// Ordered routing rules; the first match wins
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RoutingPolicy {
+    rules: Vec<RoutingRule>,
+}
+
+impl RoutingPolicy {
+    /// Create a policy with no rules
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a rule, tried after those already added
+    pub fn with_rule(mut self, rule: RoutingRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// The rules in evaluation order
+    pub fn rules(&self) -> &[RoutingRule] {
+        &self.rules
+    }
+
+    /// The first rule matching `host`
+    pub fn route(&self, host: &str) -> Option<&RoutingRule> {
+        self.rules.iter().find(|rule| rule.pattern.matches(host))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RoutingPolicy {
+        RoutingPolicy::new()
+            .with_rule(RoutingRule::new("*.onion", Transport::Tor).unwrap())
+            .with_rule(RoutingRule::new("10.0.0.0/8", Transport::Tcp).unwrap())
+            .with_rule(RoutingRule::new("fd00::/8", Transport::Tcp).unwrap())
+            .with_rule(RoutingRule::new("db.internal", Transport::Unix).unwrap().with_socket("/run/db.sock"))
+            .with_rule(RoutingRule::new("*", Transport::Unix).unwrap())
+    }
+
+    fn routed(host: &str) -> (Transport, Option<PathBuf>) {
+        let policy = policy();
+        let rule = policy.route(host).unwrap();
+        (rule.transport(), rule.socket_path().map(Path::to_path_buf))
+    }
+
+    #[test]
+    fn test_onion_rule() {
+        assert_eq!(routed("abcdef.onion"), (Transport::Tor, None));
+        assert_eq!(routed("Deep.Sub.ABCDEF.ONION"), (Transport::Tor, None));
+        // The glob needs the dot
+        assert_eq!(routed("onion"), (Transport::Unix, None));
+    }
+
+    #[test]
+    fn test_cidr_rules() {
+        assert_eq!(routed("10.1.2.3"), (Transport::Tcp, None));
+        assert_eq!(routed("10.255.255.255"), (Transport::Tcp, None));
+        assert_eq!(routed("11.0.0.1"), (Transport::Unix, None));
+        assert_eq!(routed("[fd12::1]"), (Transport::Tcp, None));
+        assert_eq!(routed("fe80::1"), (Transport::Unix, None));
+        // Names are not resolved
+        assert_eq!(routed("ten.example"), (Transport::Unix, None));
+    }
+
+    #[test]
+    fn test_first_match_and_fallback() {
+        assert_eq!(routed("db.internal"), (Transport::Unix, Some(PathBuf::from("/run/db.sock"))));
+        assert_eq!(routed("api.internal"), (Transport::Unix, None));
+
+        let empty = RoutingPolicy::new();
+        assert!(empty.route("example.com").is_none());
+
+        // An earlier catch-all shadows later rules
+        let shadowed = RoutingPolicy::new()
+            .with_rule(RoutingRule::new("*", Transport::Tcp).unwrap())
+            .with_rule(RoutingRule::new("*.onion", Transport::Tor).unwrap());
+        assert_eq!(shadowed.route("x.onion").unwrap().transport(), Transport::Tcp);
+    }
+
+    #[test]
+    fn test_patterns() {
+        assert!(HostPattern::parse("api-*.example.*").unwrap().matches("api-v2.example.org"));
+        assert!(!HostPattern::parse("api-*.example.*").unwrap().matches("web.example.org"));
+        assert!(HostPattern::parse("0.0.0.0/0").unwrap().matches("192.168.1.1"));
+        assert!(!HostPattern::parse("0.0.0.0/0").unwrap().matches("::1"));
+
+        for bad in ["", "10.0.0.0/33", "10.0.0/8", "::/129", "10.0.0.0/x"] {
+            assert!(matches!(HostPattern::parse(bad), Err(TransportError::ConfigError(_))), "{:?}", bad);
+        }
+    }
+}
@@ -0,0 +1,154 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Default locations for sockets
+//!
+//! Sockets live in a per-user directory when one is available:
+//!
+//! 1. `$XDG_RUNTIME_DIR/rigging`, if `XDG_RUNTIME_DIR` is set to an absolute
+//!    path and the `rigging` directory exists or can be created (mode 0700)
+//! 2. [`FALLBACK_SOCKET_DIR`] otherwise
+//!
+//! The Corsair socket defaults to [`TOR_SOCKET_NAME`] in that directory;
+//! [`tor_socket_from_env`] also honours `RIGGING_TOR_SOCKET` and
+//! `CORSAIR_SOCKET`, for both Tor routing and the direct connector.
+//!
+//! The directory is chosen each time a default is built, so it follows
+//! changes to the environment. Explicit configuration (such as
+//! [`ComposedConfig::from_env`](crate::composed::ComposedConfig::from_env)
+//! or [`TorConnector::with_socket`](crate::tor_connector::TorConnector::with_socket))
+//! always takes precedence.
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// Socket directory used when there is no usable `$XDG_RUNTIME_DIR`
+pub const FALLBACK_SOCKET_DIR: &str = "/tmp/servo-sockets";
+
+/// Subdirectory of `$XDG_RUNTIME_DIR` holding Rigging's sockets
+pub const RUNTIME_SUBDIR: &str = "rigging";
+
+/// Environment variable naming the per-user runtime directory
+pub const ENV_XDG_RUNTIME_DIR: &str = "XDG_RUNTIME_DIR";
+
+/// File name of the Corsair socket in the socket directory
+pub const TOR_SOCKET_NAME: &str = "corsair.sock";
+
+/// Corsair's own variable naming its socket, read when
+/// [`ENV_TOR_SOCKET`](crate::composed::ENV_TOR_SOCKET) is unset
+pub const ENV_CORSAIR_SOCKET: &str = "CORSAIR_SOCKET";
+
+/// The default socket directory
+pub fn default_socket_dir() -> PathBuf {
+    socket_dir_from(|name| std::env::var_os(name))
+}
+
+/// [`default_socket_dir`], reading variables through `env`
+pub(crate) fn socket_dir_from(env: impl Fn(&str) -> Option<OsString>) -> PathBuf {
+    env(ENV_XDG_RUNTIME_DIR)
+        .map(PathBuf::from)
+        .as_deref()
+        .and_then(socket_dir_in)
+        .unwrap_or_else(|| PathBuf::from(FALLBACK_SOCKET_DIR))
+}
+
+/// Default path to the Corsair socket
+///
+/// [`TOR_SOCKET_NAME`] in the [default socket directory](default_socket_dir).
+pub fn default_tor_socket() -> PathBuf {
+    default_socket_dir().join(TOR_SOCKET_NAME)
+}
+
+/// The Corsair socket, honouring the environment
+///
+/// `RIGGING_TOR_SOCKET`, else `CORSAIR_SOCKET`, else
+/// [`default_tor_socket`]. Empty variables count as unset.
+pub fn tor_socket_from_env() -> PathBuf {
+    tor_socket_from(|name| std::env::var_os(name))
+}
+
+/// [`tor_socket_from_env`], reading variables through `env`
+pub(crate) fn tor_socket_from(env: impl Fn(&str) -> Option<OsString>) -> PathBuf {
+    [crate::composed::ENV_TOR_SOCKET, ENV_CORSAIR_SOCKET]
+        .into_iter()
+        .find_map(|name| env(name).filter(|value| !value.is_empty()))
+        .map(PathBuf::from)
+        .unwrap_or_else(|| socket_dir_from(&env).join(TOR_SOCKET_NAME))
+}
+
+/// `<runtime_dir>/rigging`, creating it if needed; `None` if unusable
+fn socket_dir_in(runtime_dir: &Path) -> Option<PathBuf> {
+    if !runtime_dir.is_absolute() {
+        return None;
+    }
+    let dir = runtime_dir.join(RUNTIME_SUBDIR);
+    if dir.is_dir() {
+        return Some(dir);
+    }
+
+    let mut builder = std::fs::DirBuilder::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    match builder.create(&dir) {
+        Ok(()) => Some(dir),
+        // Created by someone else in the meantime
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists && dir.is_dir() => Some(dir),
+        Err(e) => {
+            log::debug!("Cannot use {} for sockets: {}", dir.display(), e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_socket_dir_follows_xdg_runtime_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let runtime = tmp.path();
+        let runtime_var = runtime.as_os_str().to_owned();
+        let env = |name: &str| (name == ENV_XDG_RUNTIME_DIR).then(|| runtime_var.clone());
+
+        let dir = socket_dir_from(env);
+        assert_eq!(dir, runtime.join("rigging"));
+        assert!(dir.is_dir());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&dir).unwrap().permissions().mode() & 0o777, 0o700);
+        }
+        let config = crate::composed::ComposedConfig::from_lookup(env);
+        assert_eq!(config.socket_dir, Some(dir.clone()));
+        assert_eq!(config.tor_socket, Some(dir.join("corsair.sock")));
+        assert_eq!(tor_socket_from(env), dir.join("corsair.sock"));
+
+        assert_eq!(socket_dir_from(|_| None), PathBuf::from(FALLBACK_SOCKET_DIR));
+        #[cfg(feature = "tor")]
+        assert_eq!(tor_socket_from(|_| None), PathBuf::from(crate::tor_connector::DEFAULT_TOR_SOCKET));
+
+        // Relative paths are ignored, as the XDG spec requires
+        assert_eq!(socket_dir_from(|_| Some("relative/run".into())), PathBuf::from(FALLBACK_SOCKET_DIR));
+    }
+
+    #[test]
+    fn test_unusable_runtime_dir_falls_back() {
+        let tmp = tempfile::tempdir().unwrap();
+        let runtime = tmp.path();
+        // Existing directory is reused as is
+        std::fs::create_dir(runtime.join("rigging")).unwrap();
+        assert_eq!(socket_dir_in(runtime), Some(runtime.join("rigging")));
+
+        // A file in the way, or a missing runtime dir, cannot be used
+        let blocked = runtime.join("blocked");
+        std::fs::create_dir(&blocked).unwrap();
+        std::fs::write(blocked.join("rigging"), b"").unwrap();
+        assert_eq!(socket_dir_in(&blocked), None);
+        assert_eq!(socket_dir_in(&blocked.join("missing")), None);
+    }
+}
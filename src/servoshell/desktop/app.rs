@@ -9,6 +9,7 @@ use std::rc::Rc;
 use std::time::Instant;
 use std::{env, fs};
 
+use log::debug;
 use servo::protocol_handler::ProtocolRegistry;
 use servo::Opts;
 use servo::Preferences;
@@ -86,16 +87,7 @@ impl App {
         }
 
         let mut protocol_registry = ProtocolRegistry::default();
-        let _ = protocol_registry.register(
-            "urlinfo",
-            protocols::urlinfo::UrlInfoProtocolHander::default(),
-        );
-        let _ =
-            protocol_registry.register("servo", protocols::servo::ServoProtocolHandler::default());
-        let _ = protocol_registry.register(
-            "resource",
-            protocols::resource::ResourceProtocolHandler::default(),
-        );
+        protocols::register_custom_protocols(&mut protocol_registry);
 
         let servo_builder = ServoBuilder::default()
             .opts(self.opts.clone())
@@ -141,13 +133,11 @@ impl App {
             return headless_window::Window::new(&self.servoshell_preferences);
         };
 
-        headed_window::Window::new(
-            &self.servoshell_preferences,
-            active_event_loop,
-            self.event_loop_proxy
-                .clone()
-                .expect("Should always have event loop proxy in headed mode."),
-            url,
+        debug_assert!(self.event_loop_proxy.is_some());
+        debug!("Opening window for {}", url);
+        Rc::new(
+            headed_window::Window::new(active_event_loop, &self.servoshell_preferences)
+                .expect("Failed to create window"),
         )
     }
 
@@ -176,12 +166,7 @@ impl ApplicationHandler<AppEvent> for App {
         window_event: WindowEvent,
     ) {
         let now = Instant::now();
-        trace_winit_event(
-            window_event,
-            "@{:?} (+{:?}) {window_event:?}",
-            now - self.t_start,
-            now - self.t
-        );
+        trace_winit_event(&window_event, window_id, now - self.t_start);
         self.t = now;
 
         {
@@ -234,11 +219,7 @@ impl ApplicationHandler<AppEvent> for App {
 fn load_userscripts(userscripts_directory: Option<&Path>) -> std::io::Result<Vec<UserScript>> {
     let mut userscripts = Vec::new();
     if let Some(userscripts_directory) = &userscripts_directory {
-        let mut files = std::fs::read_dir(userscripts_directory)?
-            .map(|e| e.map(|entry| entry.path()))
-            .collect::<Result<Vec<_>, _>>()?;
-        files.sort_unstable();
-        for file in files {
+        for file in crate::embed::userscript_files(userscripts_directory)? {
             userscripts.push(UserScript {
                 script: std::fs::read_to_string(&file)?,
                 source_file: Some(file),
@@ -4,25 +4,261 @@
 
 //! Minimal headed window implementation for Rigging
 //!
-//! TODO: Extract core ~300 lines from servoshell's headed_window.rs:
-//! - PlatformWindow trait implementation (~200 lines)
-//! - winit/surfman setup code (~100 lines)
-//! - Event handling code (~100 lines)
+//! The core of servoshell's headed_window.rs: a winit window, a surfman
+//! rendering surface and the `PlatformWindow` methods Servo requires.
 //!
 //! REMOVED from servoshell version:
 //! - ALL egui code (~700+ lines) - No browser chrome in Rigging
 //! - Dialog display logic - No browser dialogs
 //! - Toolbar/URL bar integration - Applications provide their own UI
+//! - Keyboard and mouse forwarding (still to be ported)
 //!
 //! See servoshell/desktop/headed_window.rs (1,350 lines) for full implementation.
 
-// Placeholder - to be implemented
+#![deny(clippy::panic)]
+#![deny(clippy::unwrap_used)]
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use euclid::{Point2D, Scale, Size2D};
+use log::debug;
+use servo::{
+    DeviceIndependentIntRect, DeviceIndependentPixel, convert_rect_to_css_pixel,
+};
+use servo::{DeviceIntPoint, DeviceIntRect, DeviceIntSize, DevicePixel};
+use servo::{RenderingContext, ScreenGeometry, WebView, WindowRenderingContext};
+use winit::dpi::{LogicalSize, PhysicalPosition, PhysicalSize};
+use winit::event::WindowEvent;
+use winit::event_loop::ActiveEventLoop;
+use winit::raw_window_handle::{HasDisplayHandle, HasWindowHandle};
+use winit::window::Fullscreen;
+
+use crate::embed::EmbedError;
+use crate::servoshell::running_app_state::RunningAppState;
+use crate::servoshell::stubs::ServoShellPreferences;
+use crate::servoshell::window::{MIN_WINDOW_INNER_SIZE, PlatformWindow, ServoShellWindow, ServoShellWindowId};
+
 pub struct Window {
-    // TODO: Add minimal window fields
+    winit_window: winit::window::Window,
+    screen_size: Size2D<u32, DevicePixel>,
+    fullscreen: Cell<bool>,
+    device_pixel_ratio_override: Option<f32>,
+    rendering_context: Rc<WindowRenderingContext>,
 }
 
 impl Window {
-    pub fn new() -> Self {
-        todo!("Extract headed_window implementation from servoshell")
+    /// Open a window at the preferred size and create its rendering surface
+    pub fn new(
+        event_loop: &ActiveEventLoop,
+        servoshell_preferences: &ServoShellPreferences,
+    ) -> Result<Self, EmbedError> {
+        let size = servoshell_preferences.initial_window_size;
+        let window_attributes = winit::window::Window::default_attributes()
+            .with_title("Rigging".to_string())
+            .with_inner_size(LogicalSize::new(size.width, size.height))
+            .with_min_inner_size(LogicalSize::new(
+                MIN_WINDOW_INNER_SIZE.width,
+                MIN_WINDOW_INNER_SIZE.height,
+            ))
+            // Shown once the surface exists, to avoid a flash of garbage
+            .with_visible(false);
+
+        let winit_window = event_loop
+            .create_window(window_attributes)
+            .map_err(|e| EmbedError::WindowFailed(e.to_string()))?;
+
+        let screen_size = winit_window
+            .current_monitor()
+            .map(|monitor| monitor.size())
+            .map_or(Size2D::new(size.width * 2, size.height * 2), |screen| {
+                Size2D::new(screen.width, screen.height)
+            });
+
+        let display_handle = event_loop
+            .display_handle()
+            .map_err(|e| EmbedError::WindowFailed(format!("No display handle: {}", e)))?;
+        let window_handle = winit_window
+            .window_handle()
+            .map_err(|e| EmbedError::WindowFailed(format!("No window handle: {}", e)))?;
+        let rendering_context =
+            WindowRenderingContext::new(display_handle, window_handle, winit_window.inner_size())
+                .map_err(|e| EmbedError::InitFailed(format!("Failed to create surface: {:?}", e)))?;
+        rendering_context
+            .make_current()
+            .map_err(|e| EmbedError::InitFailed(format!("Failed to make surface current: {:?}", e)))?;
+
+        winit_window.set_visible(true);
+
+        Ok(Window {
+            winit_window,
+            screen_size,
+            fullscreen: Cell::new(false),
+            device_pixel_ratio_override: servoshell_preferences.device_pixel_ratio_override,
+            rendering_context: Rc::new(rendering_context),
+        })
+    }
+
+    /// Resize the rendering surface and webviews to a new inner size
+    fn handle_resize(&self, window: &ServoShellWindow, new_size: PhysicalSize<u32>) {
+        if new_size.width == 0 || new_size.height == 0 {
+            // Minimized; keep the last surface
+            return;
+        }
+        debug!("Window resized to {}x{}", new_size.width, new_size.height);
+        self.rendering_context.resize(new_size);
+        for (_, webview) in window.webviews() {
+            webview.resize(new_size);
+        }
+        window.set_needs_repaint();
+    }
+}
+
+impl PlatformWindow for Window {
+    fn id(&self) -> ServoShellWindowId {
+        let id: u64 = self.winit_window.id().into();
+        id.into()
+    }
+
+    fn screen_geometry(&self) -> ScreenGeometry {
+        let size = self.screen_size.to_i32();
+        let origin = self
+            .winit_window
+            .outer_position()
+            .map_or(Point2D::zero(), |position| Point2D::new(position.x, position.y));
+        let outer_size = self.winit_window.outer_size();
+        ScreenGeometry {
+            size,
+            available_size: size,
+            window_rect: DeviceIntRect::from_origin_and_size(
+                origin,
+                DeviceIntSize::new(outer_size.width as i32, outer_size.height as i32),
+            ),
+        }
+    }
+
+    fn device_hidpi_scale_factor(&self) -> Scale<f32, DeviceIndependentPixel, DevicePixel> {
+        Scale::new(self.winit_window.scale_factor() as f32)
+    }
+
+    fn hidpi_scale_factor(&self) -> Scale<f32, DeviceIndependentPixel, DevicePixel> {
+        self.device_pixel_ratio_override
+            .map(Scale::new)
+            .unwrap_or_else(|| self.device_hidpi_scale_factor())
+    }
+
+    fn get_fullscreen(&self) -> bool {
+        self.fullscreen.get()
+    }
+
+    fn set_fullscreen(&self, state: bool) {
+        if self.fullscreen.get() != state {
+            self.winit_window
+                .set_fullscreen(state.then(|| Fullscreen::Borderless(None)));
+        }
+        self.fullscreen.set(state);
+    }
+
+    fn handle_winit_window_event(
+        &self,
+        _: Rc<RunningAppState>,
+        window: &ServoShellWindow,
+        event: WindowEvent,
+    ) {
+        match event {
+            WindowEvent::RedrawRequested => window.repaint_webviews(),
+            WindowEvent::Resized(new_size) => self.handle_resize(window, new_size),
+            WindowEvent::ScaleFactorChanged { .. } => window.hidpi_scale_factor_changed(),
+            WindowEvent::CloseRequested => window.schedule_close(),
+            _ => {}
+        }
+    }
+
+    fn request_repaint(&self, _: &ServoShellWindow) {
+        self.winit_window.request_redraw();
+    }
+
+    fn request_resize(&self, webview: &WebView, new_size: DeviceIntSize) -> Option<DeviceIntSize> {
+        // Do not let the window size get smaller than `MIN_WINDOW_INNER_SIZE` or larger
+        // than twice the screen size.
+        let new_size = new_size.clamp(MIN_WINDOW_INNER_SIZE, self.screen_size.to_i32() * 2);
+        let requested = PhysicalSize::new(new_size.width as u32, new_size.height as u32);
+
+        // `None` means the resize is asynchronous and will arrive as a `Resized` event
+        let applied = self.winit_window.request_inner_size(requested)?;
+        self.rendering_context.resize(applied);
+        webview.resize(applied);
+        Some(DeviceIntSize::new(applied.width as i32, applied.height as i32))
+    }
+
+    fn set_position(&self, point: DeviceIntPoint) {
+        self.winit_window
+            .set_outer_position(PhysicalPosition::new(point.x, point.y));
+    }
+
+    #[cfg(feature = "webxr")]
+    fn new_glwindow(
+        &self,
+        _event_loop: &winit::event_loop::ActiveEventLoop,
+    ) -> Rc<dyn servo::webxr::glwindow::GlWindow> {
+        unimplemented!()
+    }
+
+    fn rendering_context(&self) -> Rc<dyn RenderingContext> {
+        self.rendering_context.clone()
+    }
+
+    fn window_rect(&self) -> DeviceIndependentIntRect {
+        convert_rect_to_css_pixel(self.screen_geometry().window_rect, self.hidpi_scale_factor())
+    }
+
+    fn maximize(&self, _: &WebView) {
+        self.winit_window.set_maximized(true);
+    }
+
+    fn focused(&self) -> bool {
+        self.winit_window.has_focus()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use winit::application::ApplicationHandler;
+    use winit::event_loop::EventLoop;
+    use winit::window::WindowId;
+
+    /// Opens one window when the event loop starts, then exits
+    struct SmokeTest {
+        result: Option<Result<(), EmbedError>>,
+    }
+
+    impl ApplicationHandler for SmokeTest {
+        fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+            let result = Window::new(event_loop, &ServoShellPreferences::default()).map(|window| {
+                assert!(window.rendering_context().size2d().width > 0);
+                drop(window);
+            });
+            self.result = Some(result);
+            event_loop.exit();
+        }
+
+        fn window_event(&mut self, _: &ActiveEventLoop, _: WindowId, _: WindowEvent) {}
+    }
+
+    #[test]
+    #[ignore = "requires a display"]
+    fn test_create_and_destroy_window() {
+        let mut builder = EventLoop::builder();
+        #[cfg(all(unix, not(target_os = "macos")))]
+        {
+            use winit::platform::x11::EventLoopBuilderExtX11;
+            builder.with_any_thread(true);
+        }
+        let event_loop = builder.build().expect("event loop");
+
+        let mut app = SmokeTest { result: None };
+        event_loop.run_app(&mut app).expect("event loop run");
+        assert!(matches!(app.result, Some(Ok(()))));
     }
 }
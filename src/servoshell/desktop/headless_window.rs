@@ -62,6 +62,22 @@ impl Window {
 
         Rc::new(window)
     }
+
+    /// Current size of the offscreen surface in device pixels
+    pub fn size(&self) -> DeviceIntSize {
+        self.inner_size.get()
+    }
+
+    /// Read back the rendered surface as RGBA8, top row first
+    ///
+    /// Returns an empty vector if the surface cannot be read.
+    pub fn read_pixels(&self) -> Vec<u8> {
+        let rect = DeviceIntRect::from_origin_and_size(Point2D::zero(), self.inner_size.get());
+        self.rendering_context
+            .read_to_image(rect)
+            .map(|image| image.into_raw())
+            .unwrap_or_default()
+    }
 }
 
 impl PlatformWindow for Window {
@@ -161,3 +177,24 @@ impl PlatformWindow for Window {
         true
     }
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[ignore = "requires a display or GPU for the GL rendering context"]
+    fn test_blank_surface_dimensions() {
+        let preferences = ServoShellPreferences {
+            headless: true,
+            initial_window_size: Size2D::new(64, 48),
+            ..ServoShellPreferences::default()
+        };
+        let window = Window::new(&preferences);
+        window.rendering_context.make_current().unwrap();
+
+        assert_eq!(window.size(), DeviceIntSize::new(64, 48));
+        assert_eq!(window.read_pixels().len(), 64 * 48 * 4);
+    }
+}
@@ -2,46 +2,231 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
-//! Stub for protocol registration (TODO: port from servoshell)
+//! Custom protocol handlers (ported from servoshell)
+//!
+//! - `resource:` serves files from the resources directory
+//! - `servo:` serves the built-in welcome and error pages
+//! - `urlinfo:` describes the URL it was given, for debugging
 
-use servo::protocol_handler::ProtocolRegistry;
+use std::future::Future;
+use std::pin::Pin;
 
-// TODO: Port these protocol handlers from servoshell
+use headers::{ContentType, HeaderMapExt};
+use mime_guess::Mime;
+use ::servo::protocol_handler::{ProtocolRegistry, Request, ResourceFetchTiming, Response, ResponseBody};
+
+/// Future returned by `ProtocolHandler::load`
+type LoadFuture = Pin<Box<dyn Future<Output = Response> + Send>>;
+
+/// Build a complete response with the given content type and body
+fn response_with_body(request: &Request, mime: Mime, body: Vec<u8>) -> LoadFuture {
+    let mut response = Response::new(
+        request.current_url(),
+        ResourceFetchTiming::new(request.timing_type()),
+    );
+    response.headers.typed_insert(ContentType::from(mime));
+    *response.body.lock().unwrap() = ResponseBody::Done(body);
+    Box::pin(std::future::ready(response))
+}
+
+fn error_response(message: &'static str) -> LoadFuture {
+    Box::pin(std::future::ready(Response::network_internal_error(message)))
+}
 
 pub mod urlinfo {
-    use servo::protocol_handler::ProtocolHandler;
+    use ::servo::protocol_handler::{DoneChannel, FetchContext, ProtocolHandler, Request};
+
+    use super::{LoadFuture, response_with_body};
 
+    /// Replies with a plain-text description of the requested URL
     #[derive(Default)]
     pub struct UrlInfoProtocolHander;
 
     impl ProtocolHandler for UrlInfoProtocolHander {
-        // Stub implementation
+        fn load(
+            &self,
+            request: &mut Request,
+            _done_chan: &mut DoneChannel,
+            _context: &FetchContext,
+        ) -> LoadFuture {
+            let url = request.current_url();
+            let content = format!(
+                "Full URL: {}\nScheme: {}\nPath: {}\nQuery: {:?}\n",
+                url,
+                url.scheme(),
+                url.path(),
+                url.query()
+            );
+            response_with_body(request, mime_guess::mime::TEXT_PLAIN_UTF_8, content.into_bytes())
+        }
     }
 }
 
 pub mod servo {
-    use servo::protocol_handler::ProtocolHandler;
+    use ::servo::protocol_handler::{DoneChannel, FetchContext, ProtocolHandler, Request};
+
+    use super::{LoadFuture, error_response, response_with_body};
+
+    const WELCOME_PAGE: &str = "<!DOCTYPE html>\n<html><head><title>Rigging</title></head>\
+        <body><h1>Rigging</h1><p>The embedded browser is running.</p></body></html>\n";
 
+    /// Serves `servo:welcome` (also `servo:newtab`) and `servo:error?<message>`
     #[derive(Default)]
     pub struct ServoProtocolHandler;
 
+    /// Render a built-in page, or `None` if `path` is not one
+    pub(crate) fn page(path: &str, query: Option<&str>) -> Option<String> {
+        match path {
+            "welcome" | "newtab" => Some(WELCOME_PAGE.to_string()),
+            "error" => {
+                let message = query
+                    .map(|query| percent_decode(query))
+                    .unwrap_or_else(|| "Unknown error".to_string());
+                Some(format!(
+                    "<!DOCTYPE html>\n<html><head><title>Error</title></head>\
+                     <body><h1>Page failed to load</h1><p>{}</p></body></html>\n",
+                    escape_html(&message)
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    fn percent_decode(input: &str) -> String {
+        url::form_urlencoded::parse(input.as_bytes())
+            .map(|(key, value)| if value.is_empty() { key } else { value })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn escape_html(text: &str) -> String {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    }
+
     impl ProtocolHandler for ServoProtocolHandler {
-        // Stub implementation
+        fn is_fetchable(&self) -> bool {
+            true
+        }
+
+        fn load(
+            &self,
+            request: &mut Request,
+            _done_chan: &mut DoneChannel,
+            _context: &FetchContext,
+        ) -> LoadFuture {
+            let url = request.current_url();
+            match page(url.path(), url.query()) {
+                Some(html) => {
+                    response_with_body(request, mime_guess::mime::TEXT_HTML_UTF_8, html.into_bytes())
+                }
+                None => error_response("Unknown servo: page"),
+            }
+        }
     }
 }
 
 pub mod resource {
-    use servo::protocol_handler::ProtocolHandler;
+    use std::path::{Component, Path, PathBuf};
+
+    use mime_guess::Mime;
+    use ::servo::protocol_handler::{DoneChannel, FetchContext, ProtocolHandler, Request};
 
+    use super::{LoadFuture, error_response, response_with_body};
+
+    /// Overrides the directory `resource:` URLs are served from
+    pub const ENV_RESOURCES_DIR: &str = "RIGGING_RESOURCES_DIR";
+
+    /// Serves files below the resources directory
+    ///
+    /// The directory is `RIGGING_RESOURCES_DIR` if set, otherwise
+    /// `resources/` next to the executable. Paths that would escape it are
+    /// rejected.
     #[derive(Default)]
     pub struct ResourceProtocolHandler;
 
+    /// Directory `resource:` URLs are served from
+    pub fn resources_dir() -> PathBuf {
+        if let Some(dir) = std::env::var_os(ENV_RESOURCES_DIR) {
+            return PathBuf::from(dir);
+        }
+        std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(|dir| dir.join("resources")))
+            .unwrap_or_else(|| PathBuf::from("resources"))
+    }
+
+    /// Map a URL path to a file in `dir` and its content type
+    pub(crate) fn resolve(dir: &Path, url_path: &str) -> Result<(PathBuf, Mime), &'static str> {
+        let relative = Path::new(url_path.trim_start_matches('/'));
+        if !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+            return Err("Invalid path");
+        }
+
+        let file_path = dir.join(relative);
+        if !file_path.is_file() {
+            return Err("Resource not found");
+        }
+        let mime = mime_guess::from_path(&file_path).first_or_octet_stream();
+        Ok((file_path, mime))
+    }
+
     impl ProtocolHandler for ResourceProtocolHandler {
-        // Stub implementation
+        fn load(
+            &self,
+            request: &mut Request,
+            _done_chan: &mut DoneChannel,
+            _context: &FetchContext,
+        ) -> LoadFuture {
+            let url = request.current_url();
+            let (file_path, mime) = match resolve(&resources_dir(), url.path()) {
+                Ok(resolved) => resolved,
+                Err(message) => return error_response(message),
+            };
+            match std::fs::read(&file_path) {
+                Ok(body) => response_with_body(request, mime, body),
+                Err(_) => error_response("Opening file failed"),
+            }
+        }
     }
 }
 
-/// Register custom protocols (stub implementation)
-pub fn register_custom_protocols(_registry: &mut ProtocolRegistry) {
-    // TODO: Port protocol registration from servoshell
+/// Register the `urlinfo`, `servo` and `resource` protocols
+pub fn register_custom_protocols(registry: &mut ProtocolRegistry) {
+    let _ = registry.register("urlinfo", urlinfo::UrlInfoProtocolHander);
+    let _ = registry.register("servo", servo::ServoProtocolHandler);
+    let _ = registry.register("resource", resource::ResourceProtocolHandler);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resource_resolves_known_asset() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        std::fs::create_dir_all(dir.join("css")).unwrap();
+        std::fs::write(dir.join("css/style.css"), "body {}").unwrap();
+
+        let (path, mime) = resource::resolve(dir, "/css/style.css").unwrap();
+        assert_eq!(path, dir.join("css/style.css"));
+        assert_eq!(mime, mime_guess::mime::TEXT_CSS);
+
+        assert!(resource::resolve(dir, "/css/missing.css").is_err());
+        assert!(resource::resolve(dir, "/../etc/passwd").is_err());
+        assert!(resource::resolve(dir, "/css").is_err());
+    }
+
+    #[test]
+    fn test_servo_pages() {
+        assert!(servo::page("welcome", None).unwrap().contains("<h1>Rigging</h1>"));
+        assert!(servo::page("newtab", None).is_some());
+        assert!(servo::page("missing", None).is_none());
+
+        let error = servo::page("error", Some("reason=%3Cb%3Ebad%3C%2Fb%3E")).unwrap();
+        assert!(error.contains("&lt;b&gt;bad&lt;/b&gt;"));
+    }
 }
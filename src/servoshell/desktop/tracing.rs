@@ -2,12 +2,112 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
-//! Stub for event tracing (TODO: port from servoshell)
+//! Event loop tracing
+//!
+//! With the `event-tracing` feature, window events are logged at `trace`
+//! level under the [`TARGET`] log target, e.g. with
+//! `RUST_LOG=rigging::winit_events=trace`. The logger's filter is checked
+//! first, so nothing is formatted unless that target is enabled. Without the
+//! feature, [`trace_winit_event`] compiles to nothing.
+
+use std::time::Duration;
 
 use winit::event::WindowEvent;
 use winit::window::WindowId;
 
-/// Trace winit event (stub implementation)
-pub fn trace_winit_event(_event: &WindowEvent, _window_id: WindowId) {
-    // TODO: Port tracing from servoshell if needed
+/// Log target for window events
+pub const TARGET: &str = "rigging::winit_events";
+
+/// Log a window event, if event tracing is enabled
+#[cfg(feature = "event-tracing")]
+pub fn trace_winit_event(event: &WindowEvent, window_id: WindowId, elapsed: Duration) {
+    if !log::log_enabled!(target: TARGET, log::Level::Trace) {
+        return;
+    }
+    if let Some(description) = describe(event) {
+        log::trace!(target: TARGET, "@{:?} {:?} {}", elapsed, window_id, description);
+    }
+}
+
+/// Log a window event, if event tracing is enabled
+#[cfg(not(feature = "event-tracing"))]
+#[inline(always)]
+pub fn trace_winit_event(_event: &WindowEvent, _window_id: WindowId, _elapsed: Duration) {}
+
+/// Summarise the events worth tracing; others return `None`
+#[cfg(feature = "event-tracing")]
+fn describe(event: &WindowEvent) -> Option<String> {
+    let description = match event {
+        WindowEvent::Resized(size) => format!("Resized {}x{}", size.width, size.height),
+        WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+            format!("ScaleFactorChanged {}", scale_factor)
+        }
+        WindowEvent::Focused(focused) => format!("Focused {}", focused),
+        WindowEvent::KeyboardInput { event, .. } => {
+            format!("KeyboardInput {:?} {:?}", event.state, event.logical_key)
+        }
+        WindowEvent::MouseInput { state, button, .. } => {
+            format!("MouseInput {:?} {:?}", state, button)
+        }
+        WindowEvent::MouseWheel { delta, .. } => format!("MouseWheel {:?}", delta),
+        WindowEvent::CursorMoved { position, .. } => {
+            format!("CursorMoved {:.0},{:.0}", position.x, position.y)
+        }
+        WindowEvent::CursorEntered { .. } => "CursorEntered".to_string(),
+        WindowEvent::CursorLeft { .. } => "CursorLeft".to_string(),
+        WindowEvent::CloseRequested => "CloseRequested".to_string(),
+        WindowEvent::Destroyed => "Destroyed".to_string(),
+        _ => return None,
+    };
+    Some(description)
+}
+
+#[cfg(all(test, feature = "event-tracing"))]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use winit::dpi::PhysicalSize;
+
+    /// Records every message logged to `TARGET`
+    struct CapturingLogger {
+        records: Mutex<Vec<String>>,
+    }
+
+    impl log::Log for CapturingLogger {
+        fn enabled(&self, metadata: &log::Metadata) -> bool {
+            metadata.target() == TARGET
+        }
+
+        fn log(&self, record: &log::Record) {
+            if self.enabled(record.metadata()) {
+                self.records.lock().unwrap().push(record.args().to_string());
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: CapturingLogger = CapturingLogger {
+        records: Mutex::new(Vec::new()),
+    };
+
+    #[test]
+    fn test_resize_is_traced() {
+        log::set_logger(&LOGGER).unwrap();
+        log::set_max_level(log::LevelFilter::Trace);
+
+        // SAFETY: only used as an identifier in the log message
+        let window_id = unsafe { WindowId::dummy() };
+        trace_winit_event(
+            &WindowEvent::Resized(PhysicalSize::new(640, 480)),
+            window_id,
+            Duration::from_millis(5),
+        );
+        // Not worth tracing
+        trace_winit_event(&WindowEvent::Occluded(false), window_id, Duration::ZERO);
+
+        let records = LOGGER.records.lock().unwrap();
+        assert_eq!(records.len(), 1);
+        assert!(records[0].contains("Resized 640x480"));
+    }
 }
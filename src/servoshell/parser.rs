@@ -2,12 +2,196 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
-//! Stub for URL parsing (TODO: port from servoshell)
+//! Turning user input into URLs Servo can load
+//!
+//! Input goes through Rigging's `TransportUrl` first, so transport-aware
+//! URLs such as `http::unix///tmp/app.sock/` work wherever a URL is typed.
 
+use std::path::{Path, PathBuf};
+
+use log::warn;
 use servo::ServoUrl;
 
-/// Get default URL (stub implementation)
-pub fn get_default_url() -> ServoUrl {
-    // TODO: Port URL parsing from servoshell
-    ServoUrl::parse("about:blank").unwrap()
+use crate::servoshell::stubs::ServoShellPreferences;
+use crate::types::Transport;
+use crate::TransportUrl;
+
+/// Search engine used for input that is not a URL; `%s` is the query
+pub const SEARCH_PAGE: &str = "https://duckduckgo.com/html/?q=%s";
+
+/// Get the URL to open at startup
+///
+/// Uses `ServoShellPreferences::url` (passed as `url_opt`): an existing
+/// file relative to `cwd` becomes a `file:` URL, anything else goes through
+/// [`parse_url_or_search`]. Without a URL this is the homepage, or
+/// `about:blank` if there is none.
+pub fn get_default_url(
+    url_opt: Option<&str>,
+    cwd: impl AsRef<Path>,
+    exists: impl FnOnce(&PathBuf) -> bool,
+    preferences: &ServoShellPreferences,
+) -> ServoUrl {
+    let Some(input) = url_opt else {
+        return preferences
+            .homepage
+            .as_deref()
+            .map_or_else(blank_url, parse_url_or_search);
+    };
+
+    let path = cwd.as_ref().join(input);
+    if exists(&path) {
+        if let Ok(url) = ServoUrl::from_file_path(&path) {
+            return url;
+        }
+    }
+
+    parse_url_or_search(input)
+}
+
+/// Turn user input into a URL, falling back to a search
+///
+/// Transport-aware URLs are converted to a form Servo can load: Unix socket
+/// URLs are served through a localhost [`UnixProxy`](crate::proxy::UnixProxy)
+/// (one per socket, kept for the life of the process) and given as its login
+/// URL, so Servo picks up the proxy's cookie; Tor and TCP URLs
+/// become plain `http(s)://` URLs routed by the patched network stack. Bare
+/// domains get `https://`, and anything else is searched for.
+pub fn parse_url_or_search(input: &str) -> ServoUrl {
+    let input = input.trim();
+
+    if input.contains("::") {
+        match TransportUrl::parse(input) {
+            Ok(url) => match servo_url_for(&url) {
+                Some(servo_url) => return servo_url,
+                None => warn!("Cannot load {} in Servo", input),
+            },
+            Err(e) => warn!("Invalid transport URL {}: {}", input, e),
+        }
+    } else if let Some(url) = try_as_url(input).or_else(|| try_as_domain(input)) {
+        return url;
+    }
+
+    search_url(input)
+}
+
+/// Convert a transport URL to the URL Servo should load
+fn servo_url_for(url: &TransportUrl) -> Option<ServoUrl> {
+    match url.transport() {
+        #[cfg(feature = "unix")]
+        Transport::Unix => proxied_unix_url(url),
+        Transport::Tcp | Transport::Tor => Some(ServoUrl::from_url(url.url().clone())),
+        _ => None,
+    }
+}
+
+/// Route a Unix socket URL through a shared localhost proxy
+#[cfg(feature = "unix")]
+fn proxied_unix_url(url: &TransportUrl) -> Option<ServoUrl> {
+    use std::collections::HashMap;
+    use std::sync::{Mutex, OnceLock};
+
+    use crate::proxy::UnixProxy;
+    use crate::UnixConnector;
+
+    static PROXIES: OnceLock<Mutex<HashMap<PathBuf, UnixProxy>>> = OnceLock::new();
+
+    let socket = PathBuf::from(url.unix_socket_path()?);
+    let mut proxies = PROXIES.get_or_init(Default::default).lock().ok()?;
+    if !proxies.contains_key(&socket) {
+        let proxy = UnixProxy::start(UnixConnector::new(&socket))
+            .inspect_err(|e| warn!("Failed to start proxy for {}: {}", socket.display(), e))
+            .ok()?;
+        proxies.insert(socket.clone(), proxy);
+    }
+    ServoUrl::parse(&proxies[&socket].login_url_for(url)).ok()
+}
+
+/// Accept input that already has a scheme Servo understands
+fn try_as_url(input: &str) -> Option<ServoUrl> {
+    let url = ServoUrl::parse(input).ok()?;
+    // "localhost:8080" parses with "localhost" as the scheme
+    matches!(
+        url.scheme(),
+        "http" | "https" | "file" | "data" | "about" | "resource" | "servo" | "urlinfo"
+    )
+    .then_some(url)
+}
+
+/// Accept input that looks like a domain (`example.com`, `example.com/path`)
+fn try_as_domain(input: &str) -> Option<ServoUrl> {
+    let host = input.split(['/', '?', '#']).next()?;
+    let is_domain_like = !input.contains(char::is_whitespace)
+        && !host.starts_with('.')
+        && !host.ends_with('.')
+        && (host.contains('.') || host.starts_with("localhost"));
+    if !is_domain_like {
+        return None;
+    }
+    ServoUrl::parse(&format!("https://{}", input)).ok()
+}
+
+fn search_url(query: &str) -> ServoUrl {
+    let encoded: String = url::form_urlencoded::byte_serialize(query.as_bytes()).collect();
+    ServoUrl::parse(&SEARCH_PAGE.replace("%s", &encoded)).unwrap_or_else(|_| blank_url())
+}
+
+fn blank_url() -> ServoUrl {
+    ServoUrl::parse("about:blank").expect("about:blank is a valid URL")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "unix")]
+    #[test]
+    fn test_parse_unix_transport_url() {
+        let url = parse_url_or_search("http::unix///tmp/rigging-parser.sock/app?tab=1");
+        assert_eq!(url.scheme(), "http");
+        assert_eq!(url.host_str(), Some("127.0.0.1"));
+        // The login URL sets the proxy's cookie, then redirects to /app
+        assert!(url.path().starts_with("/.rigging-login/"));
+        assert!(url.path().ends_with("/app"));
+        assert_eq!(url.query(), Some("tab=1"));
+
+        // The same socket reuses the same proxy
+        let again = parse_url_or_search("http::unix///tmp/rigging-parser.sock/other");
+        assert_eq!(again.port(), url.port());
+    }
+
+    #[test]
+    fn test_parse_bare_domain() {
+        assert_eq!(parse_url_or_search("example.com").as_str(), "https://example.com/");
+        assert_eq!(
+            parse_url_or_search("example.com/docs?x=1").as_str(),
+            "https://example.com/docs?x=1"
+        );
+        assert_eq!(parse_url_or_search("http://localhost:8080/").as_str(), "http://localhost:8080/");
+    }
+
+    #[test]
+    fn test_parse_search_phrase() {
+        let url = parse_url_or_search("rust unix sockets");
+        assert_eq!(url.host_str(), Some("duckduckgo.com"));
+        assert_eq!(url.query(), Some("q=rust+unix+sockets"));
+    }
+
+    #[test]
+    fn test_default_url() {
+        let preferences = ServoShellPreferences::default();
+        assert_eq!(get_default_url(None, "/", |_| false, &preferences).as_str(), "about:blank");
+
+        let with_homepage = ServoShellPreferences {
+            homepage: Some("http://localhost/home".to_string()),
+            ..ServoShellPreferences::default()
+        };
+        assert_eq!(
+            get_default_url(None, "/", |_| false, &with_homepage).as_str(),
+            "http://localhost/home"
+        );
+        assert_eq!(
+            get_default_url(Some("index.html"), "/srv", |_| true, &preferences).as_str(),
+            "file:///srv/index.html"
+        );
+    }
 }
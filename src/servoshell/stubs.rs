@@ -4,6 +4,13 @@
 
 //! Temporary stubs for servoshell features not yet ported
 
+use std::path::PathBuf;
+
+use euclid::Size2D;
+use servo::DeviceIndependentPixel;
+
+use crate::embed::BrowserConfig;
+
 /// Stub for gamepad support
 #[derive(Default)]
 pub struct GamepadSupport;
@@ -12,13 +19,63 @@ pub struct GamepadSupport;
 #[derive(Default)]
 pub struct WebDriverEmbedderControls;
 
-/// Stub for servoshell preferences
-#[derive(Default, Clone)]
+/// Servoshell preferences, usually built from a `BrowserConfig`
+///
+/// Only the in-process Servo engine reads these. The Servo subprocess
+/// backend passes `BrowserConfig` to Servo as command line flags instead
+/// (see `servo_backend::build_command`), and ignores `zoom`, `homepage` and
+/// `userscripts_directory`.
+#[derive(Clone)]
 pub struct ServoShellPreferences {
     pub url: Option<String>,
     pub width: Option<u32>,
     pub height: Option<u32>,
     pub headless: bool,
+    /// Initial window (or headless surface) size
+    pub initial_window_size: Size2D<u32, DeviceIndependentPixel>,
+    /// Overrides the screen's device pixel ratio
+    pub device_pixel_ratio_override: Option<f32>,
+    /// Overrides the reported screen size
+    pub screen_size_override: Option<Size2D<u32, DeviceIndependentPixel>>,
+    /// Custom user agent (None = Servo's default)
+    pub user_agent: Option<String>,
+    /// Whether to start the devtools server
+    pub devtools: bool,
+    /// Directory of scripts injected into every page
+    pub userscripts_directory: Option<PathBuf>,
+    /// Page opened for new tabs and when no URL is given
+    pub homepage: Option<String>,
+    /// Initial page zoom factor
+    pub zoom: f32,
+    /// Save a screenshot of the first stable frame here
+    pub output_image_path: Option<PathBuf>,
+    /// Exit once the page has rendered a stable frame
+    pub exit_after_stable_image: bool,
+    /// Port for the WebDriver server (None = disabled)
+    pub webdriver_port: Option<u16>,
+}
+
+impl Default for ServoShellPreferences {
+    fn default() -> Self {
+        Self {
+            url: None,
+            width: None,
+            height: None,
+            headless: false,
+            // Same default as servoshell
+            initial_window_size: Size2D::new(1024, 740),
+            device_pixel_ratio_override: None,
+            screen_size_override: None,
+            user_agent: None,
+            devtools: false,
+            userscripts_directory: None,
+            homepage: None,
+            zoom: 1.0,
+            output_image_path: None,
+            exit_after_stable_image: false,
+            webdriver_port: None,
+        }
+    }
 }
 
 impl ServoShellPreferences {
@@ -26,3 +83,62 @@ impl ServoShellPreferences {
         Self::default()
     }
 }
+
+impl From<&BrowserConfig> for ServoShellPreferences {
+    fn from(config: &BrowserConfig) -> Self {
+        Self {
+            url: Some(config.url.clone()),
+            width: Some(config.width),
+            height: Some(config.height),
+            headless: config.headless,
+            initial_window_size: Size2D::new(config.width, config.height),
+            device_pixel_ratio_override: config.device_pixel_ratio,
+            screen_size_override: None,
+            user_agent: config.user_agent.clone(),
+            devtools: config.devtools,
+            userscripts_directory: config.userscripts_dir.clone(),
+            homepage: config.homepage.clone(),
+            zoom: config.zoom,
+            output_image_path: config.screenshot_path.clone(),
+            // Screenshot runs exit once the image has been saved
+            exit_after_stable_image: config.screenshot_path.is_some(),
+            webdriver_port: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preferences_from_browser_config() {
+        let config = BrowserConfig::new("http://localhost/")
+            .with_size(800, 600)
+            .with_device_pixel_ratio(2.0)
+            .with_user_agent("Rigging/1.0")
+            .with_devtools(true)
+            .with_userscripts_dir("/etc/rigging/scripts")
+            .with_homepage("http://localhost/home")
+            .with_zoom(1.25)
+            .with_screenshot("/tmp/shot.png");
+
+        let preferences = ServoShellPreferences::from(&config);
+
+        assert_eq!(preferences.url.as_deref(), Some("http://localhost/"));
+        assert_eq!(preferences.width, Some(800));
+        assert_eq!(preferences.height, Some(600));
+        assert!(preferences.headless);
+        assert_eq!(preferences.initial_window_size, Size2D::new(800, 600));
+        assert_eq!(preferences.device_pixel_ratio_override, Some(2.0));
+        assert_eq!(preferences.screen_size_override, None);
+        assert_eq!(preferences.user_agent.as_deref(), Some("Rigging/1.0"));
+        assert!(preferences.devtools);
+        assert_eq!(preferences.userscripts_directory, Some(PathBuf::from("/etc/rigging/scripts")));
+        assert_eq!(preferences.homepage.as_deref(), Some("http://localhost/home"));
+        assert_eq!(preferences.zoom, 1.25);
+        assert_eq!(preferences.output_image_path, Some(PathBuf::from("/tmp/shot.png")));
+        assert!(preferences.exit_after_stable_image);
+        assert_eq!(preferences.webdriver_port, None);
+    }
+}
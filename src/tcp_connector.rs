@@ -5,24 +5,45 @@
 //! TCP connector for HTTP clients
 //!
 //! Standard TCP/IP connector, mainly for completeness in the transport abstraction.
+//! It can also tunnel through an HTTP proxy with `CONNECT`; see
+//! [`TcpConnector::via_http_proxy`].
 
-use crate::types::TransportError;
+use crate::connector::{cancellable, AsyncReadWrite, Connector};
+use crate::deadline;
+use crate::metrics::observe_connect;
+use crate::redact::RedactionPolicy;
+use crate::types::{PeerInfo, Transport, TransportError};
+use crate::TransportUrl;
 use futures::future::BoxFuture;
 use hyper::Uri;
+use std::net::SocketAddr;
 use std::pin::Pin;
 use std::task::{Context, Poll};
-use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use tokio::net::TcpStream;
+use tokio_util::sync::CancellationToken;
 use tower_service::Service;
 
 /// A stream type that wraps TCP connections
 pub struct TcpConnection {
     stream: TcpStream,
+    peer: Option<std::net::SocketAddr>,
 }
 
 impl TcpConnection {
     pub fn new(stream: TcpStream) -> Self {
-        Self { stream }
+        let peer = stream.peer_addr().ok();
+        Self { stream, peer }
+    }
+
+    /// Remote address, as recorded when the connection was made
+    pub fn peer_info(&self) -> PeerInfo {
+        self.peer.map_or(PeerInfo::Unknown, PeerInfo::Tcp)
+    }
+
+    /// Split into owned read and write halves that can move to separate tasks
+    pub fn into_split(self) -> (tokio::net::tcp::OwnedReadHalf, tokio::net::tcp::OwnedWriteHalf) {
+        self.stream.into_split()
     }
 }
 
@@ -94,27 +115,191 @@ impl hyper::rt::Write for TcpConnection {
     }
 }
 
+/// Longest response head accepted from an HTTP proxy
+const MAX_PROXY_RESPONSE_LEN: usize = 8 * 1024;
+
 /// TCP connector for Hyper HTTP clients
 #[derive(Clone, Default)]
-pub struct TcpConnector;
+pub struct TcpConnector {
+    /// HTTP proxy to tunnel through, if any
+    http_proxy: Option<HttpProxy>,
+    /// How hosts appear in connect logs
+    redaction: RedactionPolicy,
+}
+
+/// A direct TCP connector, so code written when `TcpConnector` was a unit
+/// struct still builds
+#[allow(non_upper_case_globals)]
+#[deprecated(note = "use `TcpConnector::new()`")]
+pub const TcpConnector: TcpConnector = TcpConnector {
+    http_proxy: None,
+    redaction: RedactionPolicy::Truncate,
+};
 
 impl TcpConnector {
     /// Create a new TCP connector
     pub fn new() -> Self {
-        Self
+        Self::default()
+    }
+
+    /// Create a TCP connector that tunnels through an HTTP proxy
+    ///
+    /// Each connect goes to `proxy`, asks it for `CONNECT host:port` and,
+    /// once it answers `200`, returns the tunnel as an ordinary connection.
+    /// `auth` is a username and password, sent with Basic authentication.
+    /// A `407` reply fails with [`TransportError::AuthenticationFailed`],
+    /// any other `4xx` with [`TransportError::HttpProxyError`] (not worth
+    /// retrying) and a `5xx` with [`TransportError::ConnectionRefused`].
+    pub fn via_http_proxy(proxy: SocketAddr, auth: Option<(String, String)>) -> Self {
+        let authorization = auth.map(|(user, password)| {
+            format!("Basic {}", crate::base64::encode(format!("{}:{}", user, password).as_bytes()))
+        });
+        Self {
+            http_proxy: Some(HttpProxy { addr: proxy, authorization }),
+            redaction: RedactionPolicy::default(),
+        }
+    }
+
+    /// Set how hosts appear in connect logs
+    pub fn with_redaction(mut self, redaction: RedactionPolicy) -> Self {
+        self.redaction = redaction;
+        self
+    }
+
+    /// The HTTP proxy connections are tunnelled through, if any
+    pub fn http_proxy(&self) -> Option<SocketAddr> {
+        self.http_proxy.as_ref().map(|proxy| proxy.addr)
     }
 
     /// Connect to a host:port
     pub async fn connect(&self, host: &str, port: u16) -> Result<TcpConnection, TransportError> {
-        let addr = format!("{}:{}", host, port);
-        let stream = TcpStream::connect(&addr)
-            .await
-            .map_err(TransportError::Io)?;
+        observe_connect(Transport::Tcp, self.open(host, port)).await
+    }
 
+    /// Connect to a host:port, giving up when `token` is cancelled
+    pub async fn connect_with_cancel(
+        &self,
+        host: &str,
+        port: u16,
+        token: CancellationToken,
+    ) -> Result<TcpConnection, TransportError> {
+        cancellable(&token, self.connect(host, port)).await
+    }
+
+    /// Open a stream to host:port, directly or through the proxy
+    async fn open(&self, host: &str, port: u16) -> Result<TcpConnection, TransportError> {
+        self.redaction.log_connect(Transport::Tcp, host, port);
+        let stream = match &self.http_proxy {
+            Some(proxy) => proxy.tunnel(host, port).await?,
+            None => TcpStream::connect(format!("{}:{}", host, port))
+                .await
+                .map_err(TransportError::Io)?,
+        };
         Ok(TcpConnection::new(stream))
     }
 }
 
+/// An HTTP proxy reached with `CONNECT`
+#[derive(Clone)]
+struct HttpProxy {
+    addr: SocketAddr,
+    /// `Proxy-Authorization` header value
+    authorization: Option<String>,
+}
+
+impl HttpProxy {
+    /// Ask the proxy for a tunnel to host:port
+    async fn tunnel(&self, host: &str, port: u16) -> Result<TcpStream, TransportError> {
+        let mut stream = TcpStream::connect(self.addr).await.map_err(TransportError::Io)?;
+
+        let authority = format!("{}:{}", host, port);
+        let mut request = format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n", authority);
+        if let Some(authorization) = &self.authorization {
+            request.push_str(&format!("Proxy-Authorization: {}\r\n", authorization));
+        }
+        request.push_str("\r\n");
+        stream.write_all(request.as_bytes()).await?;
+
+        let head = read_response_head(&mut stream).await?;
+        let status_line = head.lines().next().unwrap_or_default();
+        let status = status_line
+            .strip_prefix("HTTP/1.")
+            .and_then(|rest| rest.split_whitespace().nth(1))
+            .and_then(|code| code.parse::<u16>().ok())
+            .ok_or_else(|| {
+                TransportError::ConnectionFailed(format!("HTTP proxy {} sent a malformed reply", self.addr))
+            })?;
+
+        match status {
+            200..=299 => Ok(stream),
+            407 => {
+                let challenge = head
+                    .lines()
+                    .filter_map(|line| line.split_once(':'))
+                    .find(|(name, _)| name.trim().eq_ignore_ascii_case("proxy-authenticate"))
+                    .map(|(_, value)| format!(" ({})", value.trim()))
+                    .unwrap_or_default();
+                let problem = if self.authorization.is_some() {
+                    "rejected the credentials"
+                } else {
+                    "requires credentials"
+                };
+                Err(TransportError::AuthenticationFailed(format!(
+                    "HTTP proxy {} {}{}",
+                    self.addr, problem, challenge
+                )))
+            }
+            400..=499 => Err(TransportError::HttpProxyError(format!(
+                "HTTP proxy {} refused CONNECT {}: {}",
+                self.addr, authority, status_line
+            ))),
+            _ => Err(TransportError::ConnectionRefused(format!(
+                "HTTP proxy {} failed CONNECT {}: {}",
+                self.addr, authority, status_line
+            ))),
+        }
+    }
+}
+
+/// Read a response head up to its blank line, leaving tunnelled data unread
+///
+/// Bytes are peeked first and only the head is consumed, so anything the
+/// proxy sends through the tunnel right behind its reply stays in the stream.
+async fn read_response_head(stream: &mut TcpStream) -> Result<String, TransportError> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    loop {
+        let peeked = stream.peek(&mut buf).await?;
+        if peeked == 0 {
+            return Err(TransportError::ConnectionFailed("HTTP proxy closed the connection".to_string()));
+        }
+
+        // The blank line may straddle two reads
+        let start = head.len().saturating_sub(2);
+        head.extend_from_slice(&buf[..peeked]);
+        let end = head_end(&head[start..]).map(|end| start + end);
+        let unread = head.len() - end.unwrap_or(head.len());
+        head.truncate(head.len() - unread);
+        stream.read_exact(&mut buf[..peeked - unread]).await?;
+
+        if end.is_some() {
+            return Ok(String::from_utf8_lossy(&head).into_owned());
+        }
+        if head.len() >= MAX_PROXY_RESPONSE_LEN {
+            return Err(TransportError::ConnectionFailed("HTTP proxy reply too long".to_string()));
+        }
+    }
+}
+
+/// Offset just past the blank line ending a response head, if present
+fn head_end(bytes: &[u8]) -> Option<usize> {
+    (0..bytes.len()).find_map(|i| match &bytes[i..] {
+        [b'\n', b'\n', ..] => Some(i + 2),
+        [b'\n', b'\r', b'\n', ..] => Some(i + 3),
+        _ => None,
+    })
+}
+
 impl Service<Uri> for TcpConnector {
     type Response = TcpConnection;
     type Error = TransportError;
@@ -125,7 +310,8 @@ impl Service<Uri> for TcpConnector {
     }
 
     fn call(&mut self, uri: Uri) -> Self::Future {
-        Box::pin(async move {
+        let connector = self.clone();
+        deadline::bounded(async move {
             let host = uri.host().ok_or_else(|| {
                 TransportError::InvalidUrl("No host in URI".to_string())
             })?;
@@ -138,12 +324,157 @@ impl Service<Uri> for TcpConnector {
                 }
             });
 
-            let addr = format!("{}:{}", host, port);
-            let stream = TcpStream::connect(&addr)
-                .await
-                .map_err(TransportError::Io)?;
+            connector.connect(host, port).await
+        })
+    }
+}
+
+impl Connector for TcpConnector {
+    fn allows_url(&self, url: &TransportUrl) -> bool {
+        url.transport() == Transport::Tcp
+    }
 
-            Ok(TcpConnection::new(stream))
+    fn connect<'a>(
+        &'a self,
+        url: &'a TransportUrl,
+    ) -> BoxFuture<'a, Result<Box<dyn AsyncReadWrite>, TransportError>> {
+        Box::pin(async move {
+            let host = url.host_str().ok_or_else(|| {
+                TransportError::InvalidUrl("No host".to_string())
+            })?;
+            let conn = TcpConnector::connect(self, host, url.port_or_default()).await?;
+            Ok(Box::new(conn) as Box<dyn AsyncReadWrite>)
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, BufReader};
+    use tokio::net::TcpListener;
+
+    /// `Proxy-Authorization` the mock proxy accepts (user `alice`, password `s3cret`)
+    const CREDENTIALS: &str = "Basic YWxpY2U6czNjcmV0";
+
+    /// Mock HTTP proxy: tunnels to `allowed.test` only, echoing the tunnel
+    ///
+    /// With `require_auth`, requests without [`CREDENTIALS`] get a `407`.
+    async fn mock_proxy(require_auth: bool) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                tokio::spawn(async move {
+                    let (reader, mut writer) = stream.into_split();
+                    let mut reader = BufReader::new(reader);
+                    let mut head = Vec::new();
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).await.unwrap();
+                        if line.trim_end().is_empty() {
+                            break;
+                        }
+                        head.push(line.trim_end().to_string());
+                    }
+
+                    let authorized = head.contains(&format!("Proxy-Authorization: {}", CREDENTIALS));
+                    if head[0] != "CONNECT allowed.test:8080 HTTP/1.1" {
+                        writer.write_all(b"HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\n\r\n").await.unwrap();
+                    } else if require_auth && !authorized {
+                        let reply = "HTTP/1.1 407 Proxy Authentication Required\r\n\
+                                     Proxy-Authenticate: Basic realm=\"corp\"\r\nContent-Length: 0\r\n\r\n";
+                        writer.write_all(reply.as_bytes()).await.unwrap();
+                    } else {
+                        // Tunnelled bytes may arrive with the reply itself
+                        writer.write_all(b"HTTP/1.1 200 Connection established\r\n\r\nhello").await.unwrap();
+                        tokio::io::copy(&mut reader, &mut writer).await.unwrap();
+                    }
+                });
+            }
+        });
+        addr
+    }
+
+    #[test]
+    fn test_head_end() {
+        assert_eq!(head_end(b"HTTP/1.1 200 OK\r\n\r\nhello"), Some(19));
+        assert_eq!(head_end(b"HTTP/1.1 200 OK\n\nhello"), Some(17));
+        assert_eq!(head_end(b"HTTP/1.1 200 OK\r\nVia: x\r\n"), None);
+        assert_eq!(head_end(b"\n\r\n"), Some(3));
+    }
+
+    #[allow(deprecated)]
+    #[test]
+    fn test_unit_struct_construction() {
+        let connector: TcpConnector = TcpConnector;
+        assert_eq!(connector.http_proxy(), None);
+    }
+
+    #[tokio::test]
+    async fn test_http_proxy_tunnel() {
+        let proxy = mock_proxy(false).await;
+        let connector = TcpConnector::via_http_proxy(proxy, None);
+        assert_eq!(connector.http_proxy(), Some(proxy));
+
+        let mut conn = connector.connect("allowed.test", 8080).await.unwrap();
+        assert_eq!(conn.peer_info(), PeerInfo::Tcp(proxy));
+
+        // Nothing sent after the reply head is lost
+        let mut greeting = [0u8; 5];
+        conn.read_exact(&mut greeting).await.unwrap();
+        assert_eq!(&greeting, b"hello");
+
+        conn.write_all(b"ping").await.unwrap();
+        let mut buf = [0u8; 4];
+        conn.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+
+        // hyper connects through the same tunnel
+        let mut service = connector.clone();
+        let mut conn = service.call(Uri::from_static("http://allowed.test:8080/")).await.unwrap();
+        conn.read_exact(&mut greeting).await.unwrap();
+        assert_eq!(&greeting, b"hello");
+    }
+
+    #[tokio::test]
+    async fn test_http_proxy_auth_challenge() {
+        let proxy = mock_proxy(true).await;
+
+        let err = TcpConnector::via_http_proxy(proxy, None).connect("allowed.test", 8080).await.err().unwrap();
+        match err {
+            TransportError::AuthenticationFailed(msg) => {
+                assert!(msg.contains("requires credentials"), "{}", msg);
+                assert!(msg.contains("realm=\"corp\""), "{}", msg);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        let wrong = Some(("alice".to_string(), "guess".to_string()));
+        let err = TcpConnector::via_http_proxy(proxy, wrong).connect("allowed.test", 8080).await.err().unwrap();
+        assert!(matches!(err, TransportError::AuthenticationFailed(ref msg) if msg.contains("rejected")));
+
+        let right = Some(("alice".to_string(), "s3cret".to_string()));
+        let mut conn = TcpConnector::via_http_proxy(proxy, right).connect("allowed.test", 8080).await.unwrap();
+        let mut greeting = [0u8; 5];
+        conn.read_exact(&mut greeting).await.unwrap();
+        assert_eq!(&greeting, b"hello");
+    }
+
+    #[tokio::test]
+    async fn test_http_proxy_refusal() {
+        let proxy = mock_proxy(false).await;
+        let connector = TcpConnector::via_http_proxy(proxy, None);
+
+        let err = connector.connect("blocked.test", 443).await.err().unwrap();
+        assert!(!err.is_retryable());
+        match err {
+            TransportError::HttpProxyError(msg) => {
+                assert!(msg.contains("CONNECT blocked.test:443"), "{}", msg);
+                assert!(msg.contains("403 Forbidden"), "{}", msg);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
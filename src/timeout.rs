@@ -0,0 +1,242 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Per-operation read and write timeouts
+//!
+//! [`TimeoutStream`] wraps any stream and fails a read or write that makes
+//! no progress for too long with [`std::io::ErrorKind::TimedOut`], which
+//! hyper reports as a connection error. The clock starts when an operation
+//! first has to wait and resets whenever it completes, so a slow but live
+//! peer is not cut off.
+//!
+//! ```rust,ignore
+//! let conn = connector
+//!     .connect("http::unix///tmp/app.sock/")
+//!     .await?
+//!     .with_io_timeout(Duration::from_secs(30), Duration::from_secs(10));
+//! ```
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::time::Sleep;
+
+/// A stream whose reads and writes time out
+pub struct TimeoutStream<S> {
+    inner: S,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+    read_timer: Option<Pin<Box<Sleep>>>,
+    write_timer: Option<Pin<Box<Sleep>>>,
+}
+
+impl<S> TimeoutStream<S> {
+    /// Wrap `inner` with no timeouts set
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            read_timeout: None,
+            write_timeout: None,
+            read_timer: None,
+            write_timer: None,
+        }
+    }
+
+    /// Fail reads that wait longer than `timeout` for data
+    pub fn with_read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    /// Fail writes, flushes and shutdowns that wait longer than `timeout`
+    pub fn with_write_timeout(mut self, timeout: Duration) -> Self {
+        self.write_timeout = Some(timeout);
+        self
+    }
+
+    /// The read timeout, if set
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.read_timeout
+    }
+
+    /// The write timeout, if set
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.write_timeout
+    }
+
+    /// The wrapped stream
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// The wrapped stream, mutably
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Unwrap the stream
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+/// Resolve a pending operation against its timer
+///
+/// Completed operations clear the timer; pending ones start it if needed
+/// and fail once it fires.
+fn check<T>(
+    poll: Poll<std::io::Result<T>>,
+    timeout: Option<Duration>,
+    timer: &mut Option<Pin<Box<Sleep>>>,
+    cx: &mut Context<'_>,
+    operation: &str,
+) -> Poll<std::io::Result<T>> {
+    if poll.is_ready() {
+        *timer = None;
+        return poll;
+    }
+    let Some(timeout) = timeout else {
+        return Poll::Pending;
+    };
+    let sleep = timer.get_or_insert_with(|| Box::pin(tokio::time::sleep(timeout)));
+    match sleep.as_mut().poll(cx) {
+        Poll::Ready(()) => {
+            *timer = None;
+            Poll::Ready(Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                format!("{} timed out after {:?}", operation, timeout),
+            )))
+        }
+        Poll::Pending => Poll::Pending,
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for TimeoutStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        let poll = Pin::new(&mut this.inner).poll_read(cx, buf);
+        check(poll, this.read_timeout, &mut this.read_timer, cx, "read")
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for TimeoutStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        let poll = Pin::new(&mut this.inner).poll_write(cx, buf);
+        check(poll, this.write_timeout, &mut this.write_timer, cx, "write")
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        let poll = Pin::new(&mut this.inner).poll_flush(cx);
+        check(poll, this.write_timeout, &mut this.write_timer, cx, "flush")
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        let poll = Pin::new(&mut this.inner).poll_shutdown(cx);
+        check(poll, this.write_timeout, &mut this.write_timer, cx, "shutdown")
+    }
+}
+
+impl<S: AsyncRead + Unpin> hyper::rt::Read for TimeoutStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        mut buf: hyper::rt::ReadBufCursor<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        let mut read_buf = tokio::io::ReadBuf::uninit(unsafe { buf.as_mut() });
+        match AsyncRead::poll_read(self, cx, &mut read_buf) {
+            Poll::Ready(Ok(())) => {
+                let filled = read_buf.filled().len();
+                unsafe { buf.advance(filled) };
+                Poll::Ready(Ok(()))
+            }
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> hyper::rt::Write for TimeoutStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, std::io::Error>> {
+        AsyncWrite::poll_write(self, cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), std::io::Error>> {
+        AsyncWrite::poll_flush(self, cx)
+    }
+
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        AsyncWrite::poll_shutdown(self, cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn test_read_times_out_on_stalled_peer() {
+        let (ours, mut peer) = tokio::io::duplex(64);
+        let mut stream = TimeoutStream::new(ours).with_read_timeout(Duration::from_millis(50));
+
+        // One byte arrives, then nothing
+        peer.write_all(b"x").await.unwrap();
+        let mut buf = [0u8; 8];
+        assert_eq!(stream.read(&mut buf).await.unwrap(), 1);
+
+        let err = stream.read(&mut buf).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+
+        // The stream is still usable once the peer catches up
+        peer.write_all(b"y").await.unwrap();
+        assert_eq!(stream.read(&mut buf).await.unwrap(), 1);
+        assert_eq!(buf[0], b'y');
+    }
+
+    #[tokio::test]
+    async fn test_write_times_out_when_peer_stops_reading() {
+        let (ours, _peer) = tokio::io::duplex(8);
+        let mut stream = TimeoutStream::new(ours).with_write_timeout(Duration::from_millis(50));
+
+        let err = stream.write_all(&[0u8; 16]).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+    }
+
+    #[tokio::test]
+    async fn test_no_timeout_by_default() {
+        let (ours, mut peer) = tokio::io::duplex(64);
+        let mut stream = TimeoutStream::new(ours);
+        assert_eq!(stream.read_timeout(), None);
+
+        let read = async {
+            let mut buf = [0u8; 1];
+            stream.read_exact(&mut buf).await.unwrap();
+        };
+        let late_write = async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            peer.write_all(b"z").await.unwrap();
+        };
+        tokio::join!(read, late_write);
+    }
+}
@@ -0,0 +1,464 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! TLS over TCP (requires the `tls` feature)
+//!
+//! Explicit `https::tcp//host:port/` and `wss::tcp//...` URLs are dialed
+//! with [`TlsTcpConnector`], which performs a rustls handshake and checks
+//! the server certificate against the URL's host. Implicit `https://` URLs
+//! keep using the plain TCP connector, so callers that layer their own TLS
+//! (e.g. Servo's network stack) are unaffected.
+//!
+//! [`TlsTcpConnector::handshake`] layers the same TLS client over any
+//! other stream, e.g. a Unix socket whose peer demands a client
+//! certificate. The name sent as SNI and checked against the certificate
+//! is the URL's [`tls_server_name`](TransportUrl::tls_server_name) unless
+//! overridden with [`TlsTcpConnector::with_server_name`], so a socket that
+//! fronts `api.example.com` can be verified as `api.example.com`.
+//!
+//! ```rust,ignore
+//! let mut roots = rustls::RootCertStore::empty();
+//! roots.add(internal_ca)?;
+//! let config = ComposedConfig {
+//!     tls: Some(TlsTcpConnector::with_root_store(roots)),
+//!     ..ComposedConfig::default()
+//! };
+//! let conn = ComposedConnector::with_config(config)
+//!     .connect("https::tcp//internal.example:8443/")
+//!     .await?;
+//! ```
+
+use crate::connector::{cancellable, AsyncReadWrite, Connector};
+use crate::deadline;
+use crate::metrics::observe_connect;
+use crate::redact::RedactionPolicy;
+use crate::types::{PeerInfo, Transport, TransportError};
+use crate::TransportUrl;
+use futures::future::BoxFuture;
+use hyper::Uri;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use rustls::{ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::TlsConnector;
+use tokio_util::sync::CancellationToken;
+use tower_service::Service;
+
+/// A TLS connection over TCP or another transport's stream
+pub struct TlsConnection {
+    stream: TlsStream<Box<dyn AsyncReadWrite>>,
+    peer: PeerInfo,
+}
+
+impl TlsConnection {
+    /// Wrap an established TLS stream, with no peer info recorded yet
+    pub fn new(stream: TlsStream<Box<dyn AsyncReadWrite>>) -> Self {
+        Self {
+            stream,
+            peer: PeerInfo::Unknown,
+        }
+    }
+
+    /// Record what the underlying stream is connected to
+    pub fn with_peer_info(mut self, peer: PeerInfo) -> Self {
+        self.peer = peer;
+        self
+    }
+
+    /// What the underlying stream is connected to
+    pub fn peer_info(&self) -> PeerInfo {
+        self.peer.clone()
+    }
+
+    /// ALPN protocol agreed during the handshake, if any
+    pub fn alpn_protocol(&self) -> Option<&[u8]> {
+        self.stream.get_ref().1.alpn_protocol()
+    }
+}
+
+impl AsyncRead for TlsConnection {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for TlsConnection {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}
+
+impl hyper::rt::Read for TlsConnection {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        mut buf: hyper::rt::ReadBufCursor<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        let mut read_buf = tokio::io::ReadBuf::uninit(unsafe { buf.as_mut() });
+        match Pin::new(&mut self.get_mut().stream).poll_read(cx, &mut read_buf) {
+            Poll::Ready(Ok(())) => {
+                let filled = read_buf.filled().len();
+                unsafe { buf.advance(filled) };
+                Poll::Ready(Ok(()))
+            }
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl hyper::rt::Write for TlsConnection {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, std::io::Error>> {
+        Pin::new(&mut self.get_mut().stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), std::io::Error>> {
+        Pin::new(&mut self.get_mut().stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        Pin::new(&mut self.get_mut().stream).poll_shutdown(cx)
+    }
+}
+
+/// Crypto provider used for every client config built here
+fn provider() -> Arc<CryptoProvider> {
+    Arc::new(rustls::crypto::aws_lc_rs::default_provider())
+}
+
+/// How the server certificate is checked
+#[derive(Clone)]
+enum Verification {
+    Roots(Arc<RootCertStore>),
+    Insecure,
+}
+
+/// Client certificate chain and key presented for mutual TLS
+#[derive(Clone)]
+struct ClientAuth {
+    cert_chain: Vec<CertificateDer<'static>>,
+    key: Arc<PrivateKeyDer<'static>>,
+}
+
+/// Build a client config for the given verification and client auth
+fn client_config(
+    verification: &Verification,
+    client_auth: Option<&ClientAuth>,
+) -> Result<Arc<ClientConfig>, TransportError> {
+    let provider = provider();
+    let builder = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .expect("default protocol versions are supported");
+    let builder = match verification {
+        Verification::Roots(roots) => builder.with_root_certificates(roots.clone()),
+        Verification::Insecure => builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(NoCertificateVerification { provider })),
+    };
+    let config = match client_auth {
+        Some(auth) => builder
+            .with_client_auth_cert(auth.cert_chain.clone(), auth.key.clone_key())
+            .map_err(|e| TransportError::TlsError(format!("Invalid client certificate: {}", e)))?,
+        None => builder.with_no_client_auth(),
+    };
+    Ok(Arc::new(config))
+}
+
+/// TLS connector that dials TCP and performs a rustls handshake
+#[derive(Clone)]
+pub struct TlsTcpConnector {
+    config: Arc<ClientConfig>,
+    /// Kept to rebuild `config` when client auth is added; `None` for a
+    /// caller-supplied config
+    verification: Option<Verification>,
+    /// SNI and certificate name used instead of the connect target
+    server_name: Option<String>,
+    /// How hosts appear in connect logs
+    redaction: RedactionPolicy,
+}
+
+impl TlsTcpConnector {
+    /// Create a connector trusting the Mozilla root store (`webpki-roots`)
+    pub fn new() -> Self {
+        Self::with_root_store(RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+        })
+    }
+
+    /// Create a connector trusting only the given roots
+    pub fn with_root_store(roots: RootCertStore) -> Self {
+        Self::with_verification(Verification::Roots(Arc::new(roots)))
+    }
+
+    /// Create a connector from a complete rustls client config
+    ///
+    /// Client authentication must then be configured on `config` itself.
+    pub fn with_client_config(config: Arc<ClientConfig>) -> Self {
+        Self {
+            config,
+            verification: None,
+            server_name: None,
+            redaction: RedactionPolicy::default(),
+        }
+    }
+
+    /// Create a connector that accepts any server certificate
+    ///
+    /// Handshake signatures are still checked, but the certificate chain and
+    /// host name are not. Only for tests against throwaway certificates.
+    pub fn danger_accept_invalid_certs() -> Self {
+        Self::with_verification(Verification::Insecure)
+    }
+
+    fn with_verification(verification: Verification) -> Self {
+        Self {
+            config: client_config(&verification, None).expect("config without client auth is valid"),
+            verification: Some(verification),
+            server_name: None,
+            redaction: RedactionPolicy::default(),
+        }
+    }
+
+    /// Present a client certificate chain and key for mutual TLS
+    ///
+    /// `cert_chain` starts with the client's own certificate. Fails if the
+    /// key does not match the certificate or is of an unsupported type, or
+    /// if the connector was built from a caller-supplied rustls config.
+    pub fn with_client_auth(
+        mut self,
+        cert_chain: Vec<CertificateDer<'static>>,
+        key: PrivateKeyDer<'static>,
+    ) -> Result<Self, TransportError> {
+        let verification = self.verification.as_ref().ok_or_else(|| {
+            TransportError::TlsError("Client auth must be set on a custom rustls config directly".to_string())
+        })?;
+        let auth = ClientAuth {
+            cert_chain,
+            key: Arc::new(key),
+        };
+        self.config = client_config(verification, Some(&auth))?;
+        Ok(self)
+    }
+
+    /// Always present `name` as SNI and verify the certificate against it
+    ///
+    /// For services reached through a socket, proxy or IP address whose
+    /// certificate is issued for a public host name.
+    pub fn with_server_name(mut self, name: impl Into<String>) -> Self {
+        self.server_name = Some(name.into());
+        self
+    }
+
+    /// Set how hosts appear in connect logs
+    pub fn with_redaction(mut self, redaction: RedactionPolicy) -> Self {
+        self.redaction = redaction;
+        self
+    }
+
+    /// The server name override, if any
+    pub fn server_name(&self) -> Option<&str> {
+        self.server_name.as_deref()
+    }
+
+    /// The rustls client config used for handshakes
+    pub fn client_config(&self) -> &Arc<ClientConfig> {
+        &self.config
+    }
+
+    /// Connect to host:port and perform the TLS handshake
+    ///
+    /// The certificate is validated against `host`, or the name set with
+    /// [`with_server_name`](Self::with_server_name).
+    pub async fn connect(&self, host: &str, port: u16) -> Result<TlsConnection, TransportError> {
+        observe_connect(Transport::Tcp, async {
+            self.redaction.log_connect(Transport::Tcp, host, port);
+            let tcp = TcpStream::connect(format!("{}:{}", host, port)).await?;
+            let peer = tcp.peer_addr().map_or(PeerInfo::Unknown, PeerInfo::Tcp);
+            let conn = self
+                .handshake(self.server_name().unwrap_or(host), tcp)
+                .await?
+                .with_peer_info(peer);
+            log::debug!("TLS connection established to {}:{}", self.redaction.host(host), port);
+            Ok(conn)
+        })
+        .await
+    }
+
+    /// Connect to host:port, giving up when `token` is cancelled
+    pub async fn connect_with_cancel(
+        &self,
+        host: &str,
+        port: u16,
+        token: CancellationToken,
+    ) -> Result<TlsConnection, TransportError> {
+        cancellable(&token, self.connect(host, port)).await
+    }
+
+    /// Perform the TLS handshake for `url` over an already-open stream
+    ///
+    /// Uses the server name override, falling back to
+    /// [`TransportUrl::tls_server_name`].
+    pub async fn handshake_url<S>(&self, url: &TransportUrl, stream: S) -> Result<TlsConnection, TransportError>
+    where
+        S: AsyncReadWrite + 'static,
+    {
+        let name = self.server_name().or_else(|| url.tls_server_name()).ok_or_else(|| {
+            TransportError::TlsError(format!("No TLS server name for {}; set one with with_server_name", url))
+        })?;
+        self.handshake(name, stream).await
+    }
+
+    /// Perform the TLS handshake over an already-open stream
+    ///
+    /// `server_name` is sent as SNI and checked against the certificate,
+    /// regardless of any override set with `with_server_name`.
+    /// Handshake failures, including a peer rejecting the client
+    /// certificate, are reported as [`TransportError::TlsError`].
+    pub async fn handshake<S>(&self, server_name: &str, stream: S) -> Result<TlsConnection, TransportError>
+    where
+        S: AsyncReadWrite + 'static,
+    {
+        // Url and Uri keep IPv6 hosts bracketed; the server name must not be
+        let name = server_name.trim_start_matches('[').trim_end_matches(']');
+        let server_name = ServerName::try_from(name.to_string())
+            .map_err(|e| TransportError::InvalidUrl(format!("Invalid TLS server name {:?}: {}", name, e)))?;
+
+        let stream = TlsConnector::from(self.config.clone())
+            .connect(server_name, Box::new(stream) as Box<dyn AsyncReadWrite>)
+            .await
+            .map_err(|e| TransportError::TlsError(e.to_string()))?;
+        Ok(TlsConnection::new(stream))
+    }
+}
+
+impl Default for TlsTcpConnector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for TlsTcpConnector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TlsTcpConnector").finish_non_exhaustive()
+    }
+}
+
+impl Service<Uri> for TlsTcpConnector {
+    type Response = TlsConnection;
+    type Error = TransportError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let connector = self.clone();
+        deadline::bounded(async move {
+            let host = uri.host().ok_or_else(|| {
+                TransportError::InvalidUrl("No host in URI".to_string())
+            })?;
+            connector.connect(host, uri.port_u16().unwrap_or(443)).await
+        })
+    }
+}
+
+impl Connector for TlsTcpConnector {
+    fn allows_url(&self, url: &TransportUrl) -> bool {
+        url.transport() == Transport::Tcp
+    }
+
+    fn connect<'a>(
+        &'a self,
+        url: &'a TransportUrl,
+    ) -> BoxFuture<'a, Result<Box<dyn AsyncReadWrite>, TransportError>> {
+        Box::pin(async move {
+            let host = url.host_str().ok_or_else(|| {
+                TransportError::InvalidUrl("No host".to_string())
+            })?;
+            let conn = TlsTcpConnector::connect(self, host, url.port_or_default()).await?;
+            Ok(Box::new(conn) as Box<dyn AsyncReadWrite>)
+        })
+    }
+}
+
+/// Verifier for [`TlsTcpConnector::danger_accept_invalid_certs`]
+#[derive(Debug)]
+struct NoCertificateVerification {
+    provider: Arc<CryptoProvider>,
+}
+
+impl ServerCertVerifier for NoCertificateVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider.signature_verification_algorithms.supported_schemes()
+    }
+}
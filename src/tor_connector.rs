@@ -2,31 +2,97 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
-//! Tor connector via Corsair daemon
+//! Tor connector via Corsair daemon or a Tor SOCKS port
 //!
-//! This connector communicates with the Corsair Tor daemon over a Unix
-//! domain socket using a simple binary IPC protocol (not SOCKS5).
+//! The connector reaches Tor through a [`TorEndpoint`]:
 //!
-//! # Protocol
+//! - `TorEndpoint::Unix` - the Corsair Tor daemon's Unix domain socket,
+//!   using a simple binary IPC protocol (not SOCKS5)
+//! - `TorEndpoint::Tcp` - a standard Tor SOCKS5 port such as
+//!   `127.0.0.1:9050`; host names are sent to Tor unresolved
 //!
-//! 1. Client sends ConnectRequest (host, port) - bincode serialized, length-prefixed
+//! # Corsair protocol
+//!
+//! 1. Client sends ConnectRequest (host, port, optional isolation tag and onion client authorization key) -
+//!    bincode serialized, length-prefixed
 //! 2. Server responds with ConnectResponse (success/error)
 //! 3. If successful, bidirectional data relay begins
+//!
+//! A Corsair shared between users can require authentication. With
+//! [`TorConnector::with_auth`] or [`TorConnector::with_auth_cookie`], every
+//! new Corsair socket first sends an [`AuthRequest`] and waits for an
+//! [`AuthResponse`], framed the same way, before anything else.
+//!
+//! With [`TorConnector::with_multiplexing`], connects share one Corsair
+//! socket instead; see [`tor_mux`](crate::tor_mux).
 
-use crate::types::TransportError;
+use crate::connector::{cancellable, AsyncReadWrite, Connector};
+use crate::deadline;
+use crate::metrics::observe_connect;
+use crate::redact::RedactionPolicy;
+use crate::tor_mux::{self, MuxSession, MuxStream};
+use crate::types::{PeerInfo, Transport, TransportError};
+use crate::TransportUrl;
 use futures::future::BoxFuture;
 use hyper::Uri;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
-use tokio::net::UnixStream;
+use tokio::net::{TcpStream, UnixStream};
+use tokio_util::sync::CancellationToken;
 use tower_service::Service;
 
-/// Default path to the Corsair (Tor daemon) socket
+/// Path to the Corsair (Tor daemon) socket when there is no usable
+/// `$XDG_RUNTIME_DIR`; see [`default_tor_socket`]
 pub const DEFAULT_TOR_SOCKET: &str = "/tmp/servo-sockets/corsair.sock";
 
+pub use crate::runtime_dir::{default_tor_socket, TOR_SOCKET_NAME};
+
+/// Default address of a system Tor's SOCKS port
+pub const DEFAULT_TOR_SOCKS_ADDR: &str = "127.0.0.1:9050";
+
+/// Corsair authentication protocol version sent in [`AuthRequest`]
+pub const AUTH_VERSION: u32 = 1;
+
+/// Exact size of a Corsair auth cookie file, as for Tor's control cookie
+pub const AUTH_COOKIE_LEN: usize = 32;
+
+/// Length of a v3 onion address, without `.onion`
+const ONION_V3_ADDRESS_LEN: usize = 56;
+
+/// Length of a base32 x25519 client authorization key
+const CLIENT_AUTH_KEY_LEN: usize = 52;
+
+/// Where the connector reaches Tor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TorEndpoint {
+    /// Corsair daemon socket (Corsair binary protocol)
+    Unix(PathBuf),
+    /// Tor SOCKS5 port (e.g. `127.0.0.1:9050`)
+    Tcp(SocketAddr),
+}
+
+impl TorEndpoint {
+    /// The default system Tor SOCKS port, `127.0.0.1:9050`
+    pub fn system_tor() -> Self {
+        TorEndpoint::Tcp(DEFAULT_TOR_SOCKS_ADDR.parse().expect("default SOCKS address is valid"))
+    }
+}
+
+impl std::fmt::Display for TorEndpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TorEndpoint::Unix(path) => write!(f, "{}", path.display()),
+            TorEndpoint::Tcp(addr) => write!(f, "{}", addr),
+        }
+    }
+}
+
 /// Request to connect to a remote host through Tor
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConnectRequest {
@@ -34,6 +100,14 @@ pub struct ConnectRequest {
     pub host: String,
     /// Target port
     pub port: u16,
+    /// Stream isolation tag; connections with different tags never share a
+    /// circuit. Kept last so older Corsair versions, which ignore trailing
+    /// bytes, still read the request (without isolating).
+    pub isolation: Option<String>,
+    /// Base32 x25519 private key for an onion service that requires client
+    /// authorization. Only set for `.onion` hosts; after `isolation` for the
+    /// same reason.
+    pub client_auth_key: Option<String>,
 }
 
 /// Response to a connection request
@@ -45,14 +119,113 @@ pub struct ConnectResponse {
     pub error: Option<String>,
 }
 
+/// Credentials sent to Corsair before any other message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthRequest {
+    /// Authentication protocol version the client speaks
+    pub version: u32,
+    /// Shared token or cookie file contents
+    pub token: Vec<u8>,
+}
+
+/// Corsair's answer to an [`AuthRequest`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthResponse {
+    /// Whether the credentials were accepted
+    pub success: bool,
+    /// Reason for rejecting them
+    pub error: Option<String>,
+}
+
+/// Where the Corsair credentials come from
+#[derive(Clone)]
+enum TorAuth {
+    Token(Vec<u8>),
+    /// Read on every connect, so a regenerated cookie is picked up
+    Cookie(PathBuf),
+}
+
+/// Stream to Corsair or to the Tor SOCKS port
+enum TorStream {
+    Unix(UnixStream),
+    Tcp(TcpStream),
+    Mux(MuxStream),
+}
+
+impl AsyncRead for TorStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            TorStream::Unix(stream) => Pin::new(stream).poll_read(cx, buf),
+            TorStream::Tcp(stream) => Pin::new(stream).poll_read(cx, buf),
+            TorStream::Mux(stream) => Pin::new(stream).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for TorStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        match self.get_mut() {
+            TorStream::Unix(stream) => Pin::new(stream).poll_write(cx, buf),
+            TorStream::Tcp(stream) => Pin::new(stream).poll_write(cx, buf),
+            TorStream::Mux(stream) => Pin::new(stream).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            TorStream::Unix(stream) => Pin::new(stream).poll_flush(cx),
+            TorStream::Tcp(stream) => Pin::new(stream).poll_flush(cx),
+            TorStream::Mux(stream) => Pin::new(stream).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            TorStream::Unix(stream) => Pin::new(stream).poll_shutdown(cx),
+            TorStream::Tcp(stream) => Pin::new(stream).poll_shutdown(cx),
+            TorStream::Mux(stream) => Pin::new(stream).poll_shutdown(cx),
+        }
+    }
+}
+
 /// A connection through the Tor network
 pub struct TorConnection {
-    stream: UnixStream,
+    stream: TorStream,
+    host: String,
+    port: u16,
 }
 
 impl TorConnection {
-    fn new(stream: UnixStream) -> Self {
-        Self { stream }
+    fn new(stream: TorStream, host: &str, port: u16) -> Self {
+        Self {
+            stream,
+            host: host.to_string(),
+            port,
+        }
+    }
+
+    /// Host and port asked of Tor
+    pub fn peer_info(&self) -> PeerInfo {
+        PeerInfo::Tor {
+            host: self.host.clone(),
+            port: self.port,
+        }
+    }
+
+    /// Split into read and write halves that can move to separate tasks
+    ///
+    /// Multiplexed streams have no owned split, so the halves share the
+    /// connection through tokio's [`split`](tokio::io::split) lock.
+    pub fn into_split(self) -> (tokio::io::ReadHalf<Self>, tokio::io::WriteHalf<Self>) {
+        tokio::io::split(self)
     }
 }
 
@@ -124,74 +297,332 @@ impl hyper::rt::Write for TorConnection {
     }
 }
 
-/// Tor connector that communicates with Corsair daemon via binary IPC
+/// Whether connects share one Corsair socket
+enum MuxState {
+    /// Not negotiated yet, or the last session failed
+    Untried,
+    Active(Arc<MuxSession>),
+    /// Corsair declined; use one socket per connect
+    Unsupported,
+}
+
+/// Tor connector that reaches Tor through Corsair or a SOCKS5 port
 #[derive(Clone)]
 pub struct TorConnector {
-    /// Path to the Corsair socket
-    socket_path: PathBuf,
+    /// Corsair socket or Tor SOCKS port
+    endpoint: TorEndpoint,
+    /// Shared Corsair session, if multiplexing (shared by clones)
+    mux: Option<Arc<tokio::sync::Mutex<MuxState>>>,
+    /// Corsair credentials, if it requires them
+    auth: Option<TorAuth>,
+    /// Client authorization keys by onion address (`<56 chars>.onion`)
+    client_auth: HashMap<String, String>,
+    /// How hosts appear in logs
+    redaction: RedactionPolicy,
 }
 
 impl TorConnector {
-    /// Create a new Tor connector with default socket path
+    /// Create a new Tor connector with the [default socket path](default_tor_socket)
     pub fn new() -> Self {
-        Self {
-            socket_path: PathBuf::from(DEFAULT_TOR_SOCKET),
-        }
+        Self::with_endpoint(TorEndpoint::Unix(default_tor_socket()))
+    }
+
+    /// Create a Tor connector for the socket named by the environment
+    ///
+    /// Uses `RIGGING_TOR_SOCKET`, then `CORSAIR_SOCKET`, then the
+    /// [default socket path](default_tor_socket), as
+    /// [`ComposedConfig::from_env`](crate::composed::ComposedConfig::from_env) does.
+    pub fn from_env() -> Self {
+        Self::with_socket(crate::runtime_dir::tor_socket_from_env())
     }
 
     /// Create a Tor connector with custom socket path
     pub fn with_socket<P: AsRef<Path>>(socket_path: P) -> Self {
+        Self::with_endpoint(TorEndpoint::Unix(socket_path.as_ref().to_path_buf()))
+    }
+
+    /// Create a Tor connector using a Tor SOCKS5 port
+    pub fn with_socks_addr(addr: SocketAddr) -> Self {
+        Self::with_endpoint(TorEndpoint::Tcp(addr))
+    }
+
+    /// Create a Tor connector for any endpoint
+    pub fn with_endpoint(endpoint: TorEndpoint) -> Self {
         Self {
-            socket_path: socket_path.as_ref().to_path_buf(),
+            endpoint,
+            mux: None,
+            auth: None,
+            client_auth: HashMap::new(),
+            redaction: RedactionPolicy::default(),
+        }
+    }
+
+    /// Set how hosts appear in logs (onion labels are truncated by default)
+    pub fn with_redaction(mut self, redaction: RedactionPolicy) -> Self {
+        self.redaction = redaction;
+        self
+    }
+
+    /// Carry all connects over one Corsair socket
+    ///
+    /// The capability is negotiated on first connect. If Corsair does not
+    /// support it, this connector (and its clones) fall back to one socket
+    /// per connect. Has no effect for a SOCKS port.
+    pub fn with_multiplexing(mut self, enabled: bool) -> Self {
+        self.mux = enabled.then(|| Arc::new(tokio::sync::Mutex::new(MuxState::Untried)));
+        self
+    }
+
+    /// Authenticate to Corsair with a shared token
+    ///
+    /// Has no effect for a SOCKS port.
+    pub fn with_auth<T: AsRef<[u8]>>(mut self, token: T) -> Self {
+        self.auth = Some(TorAuth::Token(token.as_ref().to_vec()));
+        self
+    }
+
+    /// Authenticate to Corsair with the contents of a cookie file
+    ///
+    /// The file must hold exactly [`AUTH_COOKIE_LEN`] bytes. It is read on
+    /// every new Corsair socket, so a cookie rewritten when Corsair restarts
+    /// is picked up. Has no effect for a SOCKS port.
+    pub fn with_auth_cookie<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.auth = Some(TorAuth::Cookie(path.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Authorize to the v3 onion service `onion` with a client key
+    ///
+    /// `onion` is the service address, with or without `.onion`; `key` is the
+    /// base32 x25519 private key (the last field of a Tor `.auth_private`
+    /// file). The key is sent to Corsair with connects to that service and
+    /// its subdomains only. A SOCKS port cannot carry keys; configure Tor's
+    /// `ClientOnionAuthDir` for it instead.
+    pub fn with_client_auth(mut self, onion: &str, key: &str) -> Result<Self, TransportError> {
+        let address = onion.trim_end_matches('.').to_ascii_lowercase();
+        let address = address.strip_suffix(".onion").unwrap_or(&address);
+        if address.len() != ONION_V3_ADDRESS_LEN || !is_base32(address) {
+            return Err(TransportError::ConfigError(format!("{:?} is not a v3 onion address", onion)));
         }
+        let key = key.trim().to_ascii_uppercase();
+        if key.len() != CLIENT_AUTH_KEY_LEN || !is_base32(&key) {
+            return Err(TransportError::ConfigError(format!(
+                "client authorization key for {} must be {} base32 characters",
+                onion, CLIENT_AUTH_KEY_LEN
+            )));
+        }
+        self.client_auth.insert(format!("{}.onion", address), key);
+        Ok(self)
     }
 
-    /// Get the socket path
+    /// Get the endpoint
+    pub fn endpoint(&self) -> &TorEndpoint {
+        &self.endpoint
+    }
+
+    /// Get the Corsair socket path (`None` for a SOCKS port)
+    pub fn corsair_socket(&self) -> Option<&Path> {
+        match &self.endpoint {
+            TorEndpoint::Unix(path) => Some(path),
+            TorEndpoint::Tcp(_) => None,
+        }
+    }
+
+    /// Get the Corsair socket path
+    ///
+    /// Empty when the connector uses a SOCKS port.
+    #[deprecated(note = "use `endpoint()` or `corsair_socket()`, which also cover SOCKS ports")]
     pub fn socket_path(&self) -> &Path {
-        &self.socket_path
+        self.corsair_socket().unwrap_or(Path::new(""))
     }
 
     /// Check if the Tor daemon is available
+    ///
+    /// For Corsair this checks that the socket exists; for a SOCKS port,
+    /// that it accepts connections.
     pub async fn is_available(&self) -> bool {
-        self.socket_path.exists()
+        match &self.endpoint {
+            TorEndpoint::Unix(path) => path.exists(),
+            TorEndpoint::Tcp(addr) => TcpStream::connect(addr).await.is_ok(),
+        }
+    }
+
+    /// Check that the Tor endpoint accepts connections
+    ///
+    /// Opens and immediately closes a connection without issuing a connect
+    /// request.
+    pub async fn probe(&self) -> Result<(), TransportError> {
+        self.open().await.map(drop)
     }
 
     /// Connect to a host through Tor
     pub async fn connect(&self, host: &str, port: u16) -> Result<TorConnection, TransportError> {
-        // Connect to Corsair daemon
-        let mut stream = UnixStream::connect(&self.socket_path)
-            .await
-            .map_err(|_| TransportError::TorNotAvailable)?;
+        observe_connect(Transport::Tor, self.establish(host, port, None)).await
+    }
 
-        // Send connection request using binary protocol
-        self.send_connect_request(&mut stream, host, port).await?;
+    /// Connect through Tor on circuits reserved for `isolation`
+    ///
+    /// Connections with different tags never share a circuit. Corsair gets
+    /// the tag in its connect request; a SOCKS port gets it as the
+    /// username and password, which Tor isolates on by default.
+    pub async fn connect_isolated(
+        &self,
+        host: &str,
+        port: u16,
+        isolation: &str,
+    ) -> Result<TorConnection, TransportError> {
+        observe_connect(Transport::Tor, self.establish(host, port, Some(isolation))).await
+    }
+
+    async fn establish(
+        &self,
+        host: &str,
+        port: u16,
+        isolation: Option<&str>,
+    ) -> Result<TorConnection, TransportError> {
+        self.redaction.log_connect(Transport::Tor, host, port);
+        let request = self.connect_request(host, port, isolation);
+        if let Some(session) = self.mux_session().await? {
+            let stream = session.open(&request).await?;
+            log::debug!(
+                "Tor stream opened to {}:{} via shared session on {}",
+                self.redaction.host(host),
+                port,
+                self.endpoint
+            );
+            return Ok(TorConnection::new(TorStream::Mux(stream), host, port));
+        }
 
-        // Read response
-        let response = self.read_connect_response(&mut stream).await?;
+        let mut stream = self.open().await?;
+
+        match &mut stream {
+            TorStream::Unix(unix) => {
+                // Send connection request using binary protocol
+                self.send_connect_request(unix, &request).await?;
+
+                // Read response
+                let response = self.read_connect_response(unix).await?;
+
+                if !response.success {
+                    return Err(TransportError::ConnectionFailed(
+                        response.error.unwrap_or_else(|| "Unknown error".to_string()),
+                    ));
+                }
+            }
+            TorStream::Tcp(tcp) => socks5_connect(tcp, host, port, isolation).await?,
+            TorStream::Mux(_) => unreachable!("open() does not multiplex"),
+        }
+
+        log::debug!("Tor connection established to {}:{} via {}", self.redaction.host(host), port, self.endpoint);
+        Ok(TorConnection::new(stream, host, port))
+    }
+
+    /// Connect to a host through Tor, giving up when `token` is cancelled
+    ///
+    /// Circuit building can take many seconds; cancelling closes the
+    /// connection to the Tor endpoint immediately.
+    pub async fn connect_with_cancel(
+        &self,
+        host: &str,
+        port: u16,
+        token: CancellationToken,
+    ) -> Result<TorConnection, TransportError> {
+        cancellable(&token, self.connect(host, port)).await
+    }
+
+    /// The shared Corsair session, negotiating one if needed
+    ///
+    /// `None` when not multiplexing or Corsair does not support it.
+    async fn mux_session(&self) -> Result<Option<Arc<MuxSession>>, TransportError> {
+        let (Some(mux), TorEndpoint::Unix(_)) = (&self.mux, &self.endpoint) else {
+            return Ok(None);
+        };
+        let mut state = mux.lock().await;
+        match &*state {
+            MuxState::Active(session) if !session.is_closed() => return Ok(Some(session.clone())),
+            MuxState::Unsupported => return Ok(None),
+            _ => {}
+        }
+
+        let TorStream::Unix(mut stream) = self.open().await? else {
+            unreachable!("Corsair endpoints are Unix sockets");
+        };
+        if !tor_mux::negotiate(&mut stream).await? {
+            log::debug!("Corsair at {} does not multiplex; using a socket per connect", self.endpoint);
+            *state = MuxState::Unsupported;
+            return Ok(None);
+        }
+        let session = Arc::new(MuxSession::start(stream));
+        *state = MuxState::Active(session.clone());
+        Ok(Some(session))
+    }
+
+    /// Connect to the Tor endpoint itself, authenticating to Corsair if needed
+    async fn open(&self) -> Result<TorStream, TransportError> {
+        let mut stream = match &self.endpoint {
+            TorEndpoint::Unix(path) => UnixStream::connect(path).await.map(TorStream::Unix),
+            TorEndpoint::Tcp(addr) => TcpStream::connect(addr).await.map(TorStream::Tcp),
+        }
+        .map_err(|_| TransportError::TorNotAvailable)?;
+
+        if let (TorStream::Unix(unix), Some(auth)) = (&mut stream, &self.auth) {
+            self.authenticate(unix, auth).await?;
+        }
+        Ok(stream)
+    }
+
+    /// Send credentials to Corsair and check they were accepted
+    async fn authenticate(&self, stream: &mut UnixStream, auth: &TorAuth) -> Result<(), TransportError> {
+        let token = match auth {
+            TorAuth::Token(token) => token.clone(),
+            TorAuth::Cookie(path) => read_auth_cookie(path).await?,
+        };
+        let request = AuthRequest { version: AUTH_VERSION, token };
+        let data = bincode::serialize(&request)
+            .map_err(|e| TransportError::ConnectionFailed(format!("Serialize error: {}", e)))?;
+        stream.write_all(&(data.len() as u32).to_be_bytes()).await?;
+        stream.write_all(&data).await?;
+        stream.flush().await?;
+
+        let mut len_buf = [0u8; 4];
+        stream.read_exact(&mut len_buf).await?;
+        let len = u32::from_be_bytes(len_buf) as usize;
+        if len > 64 * 1024 {
+            return Err(TransportError::ConnectionFailed("Response too large".to_string()));
+        }
+        let mut data = vec![0u8; len];
+        stream.read_exact(&mut data).await?;
+        let response: AuthResponse = bincode::deserialize(&data)
+            .map_err(|e| TransportError::ConnectionFailed(format!("Deserialize error: {}", e)))?;
 
         if !response.success {
-            return Err(TransportError::ConnectionFailed(
-                response.error.unwrap_or_else(|| "Unknown error".to_string()),
+            return Err(TransportError::AuthenticationFailed(
+                response.error.unwrap_or_else(|| "credentials rejected by Corsair".to_string()),
             ));
         }
+        log::debug!("Authenticated to Corsair at {}", self.endpoint);
+        Ok(())
+    }
 
-        log::debug!("Tor connection established to {}:{}", host, port);
-        Ok(TorConnection::new(stream))
+    /// Corsair connect request for `host:port`, with the client
+    /// authorization key if `host` is an onion service that has one
+    fn connect_request(&self, host: &str, port: u16, isolation: Option<&str>) -> ConnectRequest {
+        let client_auth_key = onion_address(host).and_then(|onion| self.client_auth.get(&onion)).cloned();
+        ConnectRequest {
+            host: host.to_string(),
+            port,
+            isolation: isolation.map(str::to_string),
+            client_auth_key,
+        }
     }
 
     /// Send a connection request to Corsair
     async fn send_connect_request(
         &self,
         stream: &mut UnixStream,
-        host: &str,
-        port: u16,
+        request: &ConnectRequest,
     ) -> Result<(), TransportError> {
-        let request = ConnectRequest {
-            host: host.to_string(),
-            port,
-        };
-
-        let data = bincode::serialize(&request)
+        let data = bincode::serialize(request)
             .map_err(|e| TransportError::ConnectionFailed(format!("Serialize error: {}", e)))?;
 
         let len = (data.len() as u32).to_be_bytes();
@@ -231,6 +662,121 @@ impl TorConnector {
     }
 }
 
+/// The onion service address of `host` (`<address>.onion`, lowercased),
+/// dropping any subdomain; `None` for other hosts
+fn onion_address(host: &str) -> Option<String> {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    let service = host.strip_suffix(".onion")?.rsplit('.').next()?;
+    Some(format!("{}.onion", service))
+}
+
+fn is_base32(s: &str) -> bool {
+    s.bytes().all(|b| matches!(b.to_ascii_uppercase(), b'A'..=b'Z' | b'2'..=b'7'))
+}
+
+/// Read a Corsair auth cookie, which must be exactly [`AUTH_COOKIE_LEN`] bytes
+async fn read_auth_cookie(path: &Path) -> Result<Vec<u8>, TransportError> {
+    let unreadable = |e: std::io::Error| {
+        TransportError::ConfigError(format!("cannot read auth cookie {}: {}", path.display(), e))
+    };
+    let file = tokio::fs::File::open(path).await.map_err(unreadable)?;
+    // Read one byte past the expected size so an oversized file is caught
+    // without loading all of it
+    let mut cookie = Vec::with_capacity(AUTH_COOKIE_LEN + 1);
+    file.take(AUTH_COOKIE_LEN as u64 + 1).read_to_end(&mut cookie).await.map_err(unreadable)?;
+    if cookie.len() != AUTH_COOKIE_LEN {
+        return Err(TransportError::ConfigError(format!(
+            "auth cookie {} must be {} bytes",
+            path.display(),
+            AUTH_COOKIE_LEN
+        )));
+    }
+    Ok(cookie)
+}
+
+/// SOCKS5 CONNECT (RFC 1928)
+///
+/// The host is always sent as a domain name so Tor resolves it, which is
+/// required for `.onion` addresses and avoids DNS leaks. An isolation tag
+/// is sent as username/password credentials (RFC 1929); otherwise no
+/// authentication is offered.
+async fn socks5_connect(
+    stream: &mut TcpStream,
+    host: &str,
+    port: u16,
+    isolation: Option<&str>,
+) -> Result<(), TransportError> {
+    if host.is_empty() || host.len() > 255 {
+        return Err(TransportError::Socks5Error(format!("invalid host name length for {:?}", host)));
+    }
+
+    // Greeting: version 5, one method, "no authentication" or "username/password"
+    let method = if isolation.is_some() { 0x02 } else { 0x00 };
+    stream.write_all(&[0x05, 0x01, method]).await?;
+    let mut choice = [0u8; 2];
+    stream.read_exact(&mut choice).await?;
+    if choice != [0x05, method] {
+        return Err(TransportError::Socks5Error("proxy requires authentication".to_string()));
+    }
+
+    if let Some(tag) = isolation {
+        if tag.is_empty() || tag.len() > 255 {
+            return Err(TransportError::Socks5Error(format!("invalid isolation tag length for {:?}", tag)));
+        }
+        let mut auth = vec![0x01, tag.len() as u8];
+        auth.extend_from_slice(tag.as_bytes());
+        auth.push(tag.len() as u8);
+        auth.extend_from_slice(tag.as_bytes());
+        stream.write_all(&auth).await?;
+        let mut status = [0u8; 2];
+        stream.read_exact(&mut status).await?;
+        if status[1] != 0x00 {
+            return Err(TransportError::Socks5Error("proxy rejected the isolation credentials".to_string()));
+        }
+    }
+
+    // CONNECT to a domain name
+    let mut request = vec![0x05, 0x01, 0x00, 0x03, host.len() as u8];
+    request.extend_from_slice(host.as_bytes());
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await?;
+    stream.flush().await?;
+
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply).await?;
+    if reply[0] != 0x05 {
+        return Err(TransportError::Socks5Error("not a SOCKS5 proxy".to_string()));
+    }
+    if reply[1] != 0x00 {
+        return Err(TransportError::Socks5Error(socks5_reply_message(reply[1]).to_string()));
+    }
+
+    // Skip the bound address
+    let addr_len = match reply[3] {
+        0x01 => 4,
+        0x04 => 16,
+        0x03 => stream.read_u8().await? as usize,
+        other => return Err(TransportError::Socks5Error(format!("unknown address type {}", other))),
+    };
+    let mut bound = vec![0u8; addr_len + 2];
+    stream.read_exact(&mut bound).await?;
+    Ok(())
+}
+
+fn socks5_reply_message(code: u8) -> &'static str {
+    match code {
+        0x01 => "general failure",
+        0x02 => "connection not allowed by ruleset",
+        0x03 => "network unreachable",
+        0x04 => "host unreachable",
+        0x05 => "connection refused",
+        0x06 => "TTL expired",
+        0x07 => "command not supported",
+        0x08 => "address type not supported",
+        _ => "unknown error",
+    }
+}
+
 impl Default for TorConnector {
     fn default() -> Self {
         Self::new()
@@ -247,8 +793,8 @@ impl Service<Uri> for TorConnector {
     }
 
     fn call(&mut self, uri: Uri) -> Self::Future {
-        let socket_path = self.socket_path.clone();
-        Box::pin(async move {
+        let connector = self.clone();
+        deadline::bounded(async move {
             let host = uri.host().ok_or_else(|| {
                 TransportError::InvalidUrl("No host in URI".to_string())
             })?;
@@ -261,8 +807,447 @@ impl Service<Uri> for TorConnector {
                 }
             });
 
-            let connector = TorConnector { socket_path };
             connector.connect(host, port).await
         })
     }
 }
+
+impl Connector for TorConnector {
+    fn allows_url(&self, url: &TransportUrl) -> bool {
+        url.requires_tor()
+    }
+
+    fn connect<'a>(
+        &'a self,
+        url: &'a TransportUrl,
+    ) -> BoxFuture<'a, Result<Box<dyn AsyncReadWrite>, TransportError>> {
+        Box::pin(async move {
+            let host = url.host_str().ok_or_else(|| {
+                TransportError::InvalidUrl("No host".to_string())
+            })?;
+            let conn = TorConnector::connect(self, host, url.port_or_default()).await?;
+            Ok(Box::new(conn) as Box<dyn AsyncReadWrite>)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_endpoint_constructors() {
+        let corsair = TorConnector::with_socket("/run/corsair.sock");
+        assert_eq!(corsair.endpoint(), &TorEndpoint::Unix(PathBuf::from("/run/corsair.sock")));
+        assert_eq!(corsair.corsair_socket(), Some(Path::new("/run/corsair.sock")));
+        #[allow(deprecated)]
+        let socket_path = corsair.socket_path();
+        assert_eq!(socket_path, Path::new("/run/corsair.sock"));
+
+        let system = TorConnector::with_endpoint(TorEndpoint::system_tor());
+        assert_eq!(system.endpoint(), &TorEndpoint::Tcp("127.0.0.1:9050".parse().unwrap()));
+        assert_eq!(system.corsair_socket(), None);
+        assert_eq!(system.endpoint().to_string(), "127.0.0.1:9050");
+    }
+
+    /// Minimal SOCKS5 proxy: checks the credentials (the isolation tag)
+    /// and CONNECT request, then echoes
+    async fn mock_socks5(reply_code: u8, isolation: Option<&'static str>) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let method = if isolation.is_some() { 0x02 } else { 0x00 };
+            let mut greeting = [0u8; 3];
+            stream.read_exact(&mut greeting).await.unwrap();
+            assert_eq!(greeting, [0x05, 0x01, method]);
+            stream.write_all(&[0x05, method]).await.unwrap();
+
+            if let Some(tag) = isolation {
+                let mut auth = vec![0u8; 3 + 2 * tag.len()];
+                stream.read_exact(&mut auth).await.unwrap();
+                let len = tag.len() as u8;
+                assert_eq!(auth, [&[0x01, len][..], tag.as_bytes(), &[len], tag.as_bytes()].concat());
+                stream.write_all(&[0x01, 0x00]).await.unwrap();
+            }
+
+            let mut header = [0u8; 5];
+            stream.read_exact(&mut header).await.unwrap();
+            assert_eq!(&header[..4], &[0x05, 0x01, 0x00, 0x03]);
+            let mut target = vec![0u8; header[4] as usize + 2];
+            stream.read_exact(&mut target).await.unwrap();
+            assert_eq!(&target[..header[4] as usize], b"example.onion");
+            assert_eq!(&target[header[4] as usize..], &80u16.to_be_bytes());
+
+            stream.write_all(&[0x05, reply_code, 0x00, 0x01, 0, 0, 0, 0, 0, 0]).await.unwrap();
+            let (mut reader, mut writer) = stream.split();
+            let _ = tokio::io::copy(&mut reader, &mut writer).await;
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_connect_via_socks_port() {
+        let connector = TorConnector::with_socks_addr(mock_socks5(0x00, None).await);
+        assert!(connector.is_available().await);
+
+        let mut conn = connector.connect("example.onion", 80).await.unwrap();
+        assert_eq!(conn.peer_info(), PeerInfo::Tor { host: "example.onion".to_string(), port: 80 });
+        conn.write_all(b"ping").await.unwrap();
+        let mut buf = [0u8; 4];
+        conn.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+    }
+
+    #[tokio::test]
+    async fn test_connect_isolated_via_socks_port() {
+        let connector = TorConnector::with_socks_addr(mock_socks5(0x00, Some("tab-7")).await);
+        let mut conn = connector.connect_isolated("example.onion", 80, "tab-7").await.unwrap();
+        conn.write_all(b"ping").await.unwrap();
+        let mut buf = [0u8; 4];
+        conn.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+    }
+
+    #[tokio::test]
+    async fn test_socks_error_reply() {
+        let connector = TorConnector::with_socks_addr(mock_socks5(0x04, None).await);
+        let err = connector.connect("example.onion", 80).await.err().unwrap();
+        assert!(matches!(err, TransportError::Socks5Error(ref m) if m == "host unreachable"));
+    }
+
+    #[tokio::test]
+    async fn test_connect_via_corsair_socket() {
+        let (_dir, socket) = corsair_socket();
+        let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut len = [0u8; 4];
+            stream.read_exact(&mut len).await.unwrap();
+            let mut request = vec![0u8; u32::from_be_bytes(len) as usize];
+            stream.read_exact(&mut request).await.unwrap();
+            let request: ConnectRequest = bincode::deserialize(&request).unwrap();
+            assert_eq!((request.host.as_str(), request.port), ("example.onion", 80));
+
+            let response = bincode::serialize(&ConnectResponse { success: true, error: None }).unwrap();
+            stream.write_all(&(response.len() as u32).to_be_bytes()).await.unwrap();
+            stream.write_all(&response).await.unwrap();
+        });
+
+        let connector = TorConnector::with_endpoint(TorEndpoint::Unix(socket));
+        assert!(connector.is_available().await);
+        assert!(connector.connect("example.onion", 80).await.is_ok());
+    }
+
+    const ONION: &str = "vww6ybal4bd7szmgncyruucpgfkqahzddi37ktceo3ah7ngmcopnpyyd.onion";
+    const CLIENT_KEY: &str = "GBSWY3DPEHPK3PXPGBSWY3DPEHPK3PXPGBSWY3DPEHPK3PXPGBSQ";
+
+    #[test]
+    fn test_client_auth_key_only_sent_for_onion_hosts() {
+        let connector = TorConnector::new().with_client_auth(ONION, &CLIENT_KEY.to_lowercase()).unwrap();
+
+        let request = connector.connect_request(ONION, 80, None);
+        let data = bincode::serialize(&request).unwrap();
+        let decoded: ConnectRequest = bincode::deserialize(&data).unwrap();
+        assert_eq!(decoded.client_auth_key.as_deref(), Some(CLIENT_KEY));
+        assert!(data.windows(CLIENT_KEY.len()).any(|w| w == CLIENT_KEY.as_bytes()));
+
+        // Subdomains belong to the same service
+        let request = connector.connect_request(&format!("www.{}", ONION.to_uppercase()), 443, Some("tab"));
+        assert_eq!(request.client_auth_key.as_deref(), Some(CLIENT_KEY));
+
+        for host in ["example.com", "other7ybal4bd7szmgncyruucpgfkqahzddi37ktceo3ah7ngmcopnpyyd.onion"] {
+            let data = bincode::serialize(&connector.connect_request(host, 80, None)).unwrap();
+            let decoded: ConnectRequest = bincode::deserialize(&data).unwrap();
+            assert_eq!(decoded.client_auth_key, None);
+            assert!(!data.windows(CLIENT_KEY.len()).any(|w| w == CLIENT_KEY.as_bytes()));
+        }
+    }
+
+    #[test]
+    fn test_client_auth_rejects_malformed_input() {
+        let short_onion = TorConnector::new().with_client_auth("example.onion", CLIENT_KEY);
+        assert!(matches!(short_onion, Err(TransportError::ConfigError(_))));
+        let bad_key = TorConnector::new().with_client_auth(ONION, "not-a-key");
+        assert!(matches!(bad_key, Err(TransportError::ConfigError(_))));
+        // The address may be given without `.onion`
+        assert!(TorConnector::new().with_client_auth(ONION.trim_end_matches(".onion"), CLIENT_KEY).is_ok());
+    }
+
+    fn corsair_socket() -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("corsair.sock");
+        (dir, socket)
+    }
+
+    async fn read_message(stream: &mut UnixStream) -> Option<Vec<u8>> {
+        let mut len = [0u8; 4];
+        stream.read_exact(&mut len).await.ok()?;
+        let mut data = vec![0u8; u32::from_be_bytes(len) as usize];
+        stream.read_exact(&mut data).await.ok()?;
+        Some(data)
+    }
+
+    async fn write_message<T: Serialize>(stream: &mut UnixStream, message: &T) {
+        let data = bincode::serialize(message).unwrap();
+        stream.write_all(&(data.len() as u32).to_be_bytes()).await.unwrap();
+        stream.write_all(&data).await.unwrap();
+    }
+
+    /// Corsair requiring `expected` as its auth token
+    fn mock_authed_corsair(name: &str, expected: &'static [u8]) -> PathBuf {
+        let socket = corsair_socket(name);
+        let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let auth: AuthRequest = bincode::deserialize(&read_message(&mut stream).await.unwrap())
+                    .unwrap();
+                assert_eq!(auth.version, AUTH_VERSION);
+                if auth.token != expected {
+                    let error = Some("bad token".to_string());
+                    write_message(&mut stream, &AuthResponse { success: false, error }).await;
+                    continue;
+                }
+                write_message(&mut stream, &AuthResponse { success: true, error: None }).await;
+
+                let request: ConnectRequest = bincode::deserialize(&read_message(&mut stream).await.unwrap())
+                    .unwrap();
+                assert_eq!(request.host, "example.onion");
+                write_message(&mut stream, &ConnectResponse { success: true, error: None }).await;
+            }
+        });
+        socket
+    }
+
+    #[tokio::test]
+    async fn test_auth_token() {
+        let socket = mock_authed_corsair("auth-token", b"s3cret");
+
+        let connector = TorConnector::with_socket(&socket).with_auth("s3cret");
+        assert!(connector.connect("example.onion", 80).await.is_ok());
+
+        let wrong = TorConnector::with_socket(&socket).with_auth("guess");
+        let err = wrong.connect("example.onion", 80).await.err().unwrap();
+        assert!(matches!(err, TransportError::AuthenticationFailed(ref m) if m == "bad token"), "{}", err);
+        assert!(!err.is_retryable());
+    }
+
+    #[tokio::test]
+    async fn test_auth_cookie_file() {
+        const COOKIE: &[u8; AUTH_COOKIE_LEN] = b"0123456789abcdef0123456789abcdef";
+        let socket = mock_authed_corsair("auth-cookie", COOKIE);
+        let cookie = socket.with_file_name("control_auth_cookie");
+
+        std::fs::write(&cookie, COOKIE).unwrap();
+        let connector = TorConnector::with_socket(&socket).with_auth_cookie(&cookie);
+        assert!(connector.connect("example.onion", 80).await.is_ok());
+
+        // The file is re-read, so a rotated cookie is used on the next connect
+        std::fs::write(&cookie, [0u8; AUTH_COOKIE_LEN]).unwrap();
+        let err = connector.connect("example.onion", 80).await.err().unwrap();
+        assert!(matches!(err, TransportError::AuthenticationFailed(_)), "{}", err);
+
+        for bad in [&COOKIE[..31], &[b'x'; AUTH_COOKIE_LEN + 1][..]] {
+            std::fs::write(&cookie, bad).unwrap();
+            let err = connector.connect("example.onion", 80).await.err().unwrap();
+            assert!(matches!(err, TransportError::ConfigError(_)), "{}", err);
+        }
+
+        let missing = TorConnector::with_socket(&socket).with_auth_cookie(socket.with_file_name("missing"));
+        assert!(matches!(missing.connect("example.onion", 80).await, Err(TransportError::ConfigError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_multiplexed_streams_share_one_socket() {
+        use crate::tor_mux::{read_frame, write_frame, Frame, FRAME_DATA, FRAME_OPEN, FRAME_OPENED};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let (_dir, socket) = corsair_socket();
+        let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+        let accepted = Arc::new(AtomicUsize::new(0));
+        let count = accepted.clone();
+
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                count.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let hello: tor_mux::Hello = bincode::deserialize(&read_message(&mut stream).await.unwrap()).unwrap();
+                    assert!(hello.multiplex);
+                    write_message(&mut stream, &tor_mux::HelloResponse { version: hello.version, multiplex: true }).await;
+
+                    // Open both streams before sending anything on either
+                    let mut hosts = HashMap::new();
+                    while hosts.len() < 2 {
+                        let frame = read_frame(&mut stream).await.unwrap();
+                        assert_eq!(frame.kind, FRAME_OPEN);
+                        let request: ConnectRequest = bincode::deserialize(&frame.payload).unwrap();
+                        hosts.insert(frame.stream, request.host);
+                    }
+                    for id in hosts.keys() {
+                        let ok = bincode::serialize(&ConnectResponse { success: true, error: None }).unwrap();
+                        write_frame(&mut stream, &Frame { stream: *id, kind: FRAME_OPENED, payload: ok }).await.unwrap();
+                    }
+
+                    // Greet in reverse id order, then echo until closed
+                    let mut ids: Vec<_> = hosts.keys().copied().collect();
+                    ids.sort_unstable_by(|a, b| b.cmp(a));
+                    for id in ids {
+                        let payload = format!("hi {}", hosts[&id]).into_bytes();
+                        write_frame(&mut stream, &Frame { stream: id, kind: FRAME_DATA, payload }).await.unwrap();
+                    }
+                    while let Ok(frame) = read_frame(&mut stream).await {
+                        if frame.kind == FRAME_DATA {
+                            write_frame(&mut stream, &frame).await.unwrap();
+                        }
+                    }
+                });
+            }
+        });
+
+        let connector = TorConnector::with_socket(&socket).with_multiplexing(true);
+        let (first, second) = tokio::join!(
+            connector.connect("aaaa.onion", 80),
+            connector.connect("bbbb.onion", 80)
+        );
+        let (mut first, mut second) = (first.unwrap(), second.unwrap());
+
+        let mut buf = [0u8; 13];
+        second.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hi bbbb.onion");
+        first.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hi aaaa.onion");
+
+        first.write_all(b"one").await.unwrap();
+        second.write_all(b"two").await.unwrap();
+        let mut echo = [0u8; 3];
+        second.read_exact(&mut echo).await.unwrap();
+        assert_eq!(&echo, b"two");
+        first.read_exact(&mut echo).await.unwrap();
+        assert_eq!(&echo, b"one");
+
+        assert_eq!(accepted.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_slow_corsair_throttles_multiplexed_writes() {
+        use crate::tor_mux::{read_frame, write_frame, Frame, FRAME_CLOSE, FRAME_DATA, FRAME_OPENED};
+        use std::time::Duration;
+
+        const TOTAL: usize = 16 * 1024 * 1024;
+
+        let (_dir, socket) = corsair_socket();
+        let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+        let resume = Arc::new(tokio::sync::Notify::new());
+        let start_reading = resume.clone();
+        let (received_tx, received_rx) = tokio::sync::oneshot::channel();
+
+        // Opens the stream, then reads nothing more until told to
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let hello: tor_mux::Hello = bincode::deserialize(&read_message(&mut stream).await.unwrap()).unwrap();
+            write_message(&mut stream, &tor_mux::HelloResponse { version: hello.version, multiplex: true }).await;
+            let open = read_frame(&mut stream).await.unwrap();
+            let ok = bincode::serialize(&ConnectResponse { success: true, error: None }).unwrap();
+            write_frame(&mut stream, &Frame { stream: open.stream, kind: FRAME_OPENED, payload: ok }).await.unwrap();
+
+            start_reading.notified().await;
+            let mut received = 0;
+            loop {
+                let frame = read_frame(&mut stream).await.unwrap();
+                match frame.kind {
+                    FRAME_DATA => received += frame.payload.len(),
+                    FRAME_CLOSE => break,
+                    _ => {}
+                }
+            }
+            let _ = received_tx.send(received);
+        });
+
+        let connector = TorConnector::with_socket(&socket).with_multiplexing(true);
+        let mut conn = connector.connect("slow.onion", 80).await.unwrap();
+        let writer = tokio::spawn(async move {
+            conn.write_all(&vec![7u8; TOTAL]).await.unwrap();
+            conn.shutdown().await.unwrap();
+        });
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(!writer.is_finished(), "writes should wait while Corsair is not reading");
+
+        resume.notify_one();
+        tokio::time::timeout(Duration::from_secs(10), writer).await.unwrap().unwrap();
+        assert_eq!(received_rx.await.unwrap(), TOTAL);
+    }
+
+    #[tokio::test]
+    async fn test_multiplexing_falls_back_for_old_corsair() {
+        let (_dir, socket) = corsair_socket();
+        let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+        let (hellos_tx, mut hellos_rx) = tokio::sync::mpsc::unbounded_channel();
+
+        // Answers ConnectRequests and hangs up on anything else
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let hellos_tx = hellos_tx.clone();
+                tokio::spawn(async move {
+                    let data = read_message(&mut stream).await.unwrap();
+                    let Ok(request) = bincode::deserialize::<ConnectRequest>(&data) else {
+                        hellos_tx.send(()).unwrap();
+                        return;
+                    };
+                    assert_eq!(request.port, 80);
+                    write_message(&mut stream, &ConnectResponse { success: true, error: None }).await;
+                });
+            }
+        });
+
+        let connector = TorConnector::with_socket(&socket).with_multiplexing(true);
+        assert!(connector.connect("example.onion", 80).await.is_ok());
+        assert!(connector.clone().connect("example.onion", 80).await.is_ok());
+
+        // Negotiated once, then remembered
+        hellos_rx.recv().await.unwrap();
+        assert!(hellos_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_connect_with_cancel_closes_socket() {
+        use std::time::Duration;
+
+        // A SOCKS port that accepts but never answers the greeting
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let connector = TorConnector::with_socks_addr(listener.local_addr().unwrap());
+        let token = CancellationToken::new();
+
+        let connect = connector.connect_with_cancel("example.onion", 80, token.clone());
+        let stalled_proxy = async {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut greeting = [0u8; 3];
+            stream.read_exact(&mut greeting).await.unwrap();
+            token.cancel();
+            // The connector hangs up once cancelled
+            let mut rest = Vec::new();
+            tokio::time::timeout(Duration::from_secs(2), stream.read_to_end(&mut rest))
+                .await
+                .unwrap()
+                .unwrap();
+        };
+
+        let (result, ()) = tokio::join!(connect, stalled_proxy);
+        assert!(matches!(result, Err(TransportError::Cancelled)));
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_endpoints() {
+        let missing = TorConnector::with_socket("/nonexistent/rigging/corsair.sock");
+        assert!(!missing.is_available().await);
+        assert!(matches!(missing.probe().await, Err(TransportError::TorNotAvailable)));
+
+        // Bind then drop to find a closed port
+        let closed = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+        let refused = TorConnector::with_socks_addr(closed);
+        assert!(!refused.is_available().await);
+    }
+}
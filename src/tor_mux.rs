@@ -0,0 +1,501 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Many Tor streams over one Corsair socket
+//!
+//! [`TorConnector::with_multiplexing`] keeps a single connection to Corsair
+//! and carries every connect over it instead of opening a socket per
+//! request.
+//!
+//! # Negotiation
+//!
+//! The client opens the shared connection and sends a [`Hello`],
+//! length-prefixed and bincode serialized like a `ConnectRequest`. A Corsair
+//! that can multiplex answers with a [`HelloResponse`] whose `multiplex` is
+//! set. Anything else (a closed socket, an error response, a response
+//! without the capability) means it cannot, and the connector goes back to
+//! one socket per connect.
+//!
+//! # Framing
+//!
+//! After negotiation both sides exchange frames:
+//!
+//! ```text
+//! stream id (u32 BE) | kind (u8) | payload length (u32 BE) | payload
+//! ```
+//!
+//! - [`FRAME_OPEN`] - client to Corsair; payload is a `ConnectRequest`
+//! - [`FRAME_OPENED`] - Corsair to client; payload is a `ConnectResponse`
+//! - [`FRAME_DATA`] - stream bytes, either direction
+//! - [`FRAME_CLOSE`] - empty; the sender writes nothing more on the stream
+//!
+//! Stream ids are chosen by the client, starting at 1. Once they wrap, ids
+//! of streams still open are skipped.
+//!
+//! # Backpressure
+//!
+//! Frames for Corsair wait in a queue of [`OUTGOING_QUEUE`] frames shared
+//! by every stream, so writes stall once Corsair stops reading. Frames from
+//! Corsair wait in a queue of [`INCOMING_QUEUE`] frames per stream; while
+//! one is full the session stops reading the socket, so a stream nobody
+//! reads eventually holds up the others too.
+//!
+//! [`TorConnector::with_multiplexing`]: crate::tor_connector::TorConnector::with_multiplexing
+
+use crate::tor_connector::{ConnectRequest, ConnectResponse};
+use crate::types::TransportError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::pin::Pin;
+use futures::task::AtomicWaker;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{ready, Context, Poll};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::UnixStream;
+use tokio::sync::mpsc;
+use tokio_util::sync::PollSender;
+
+/// Multiplexing protocol version sent in [`Hello`]
+pub const MUX_VERSION: u32 = 1;
+
+/// Open a stream (client to Corsair)
+pub const FRAME_OPEN: u8 = 1;
+
+/// Result of opening a stream (Corsair to client)
+pub const FRAME_OPENED: u8 = 2;
+
+/// Stream data
+pub const FRAME_DATA: u8 = 3;
+
+/// End of a stream's data in one direction
+pub const FRAME_CLOSE: u8 = 4;
+
+/// Largest frame payload accepted or sent
+pub const MAX_FRAME_PAYLOAD: usize = 64 * 1024;
+
+/// Frames waiting to be written to Corsair, across all streams
+pub const OUTGOING_QUEUE: usize = 32;
+
+/// Frames from Corsair waiting to be read, per stream
+pub const INCOMING_QUEUE: usize = 16;
+
+/// Sent by the client to ask for multiplexing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Hello {
+    /// Protocol version the client speaks
+    pub version: u32,
+    /// Whether the client wants to multiplex streams
+    pub multiplex: bool,
+}
+
+/// Corsair's answer to a [`Hello`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HelloResponse {
+    /// Protocol version Corsair speaks
+    pub version: u32,
+    /// Whether Corsair will multiplex streams on this connection
+    pub multiplex: bool,
+}
+
+/// One frame on a multiplexed connection
+#[derive(Debug)]
+pub(crate) struct Frame {
+    pub stream: u32,
+    pub kind: u8,
+    pub payload: Vec<u8>,
+}
+
+/// Read one frame
+pub(crate) async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R) -> std::io::Result<Frame> {
+    let mut header = [0u8; 9];
+    reader.read_exact(&mut header).await?;
+    let stream = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+    let kind = header[4];
+    let len = u32::from_be_bytes([header[5], header[6], header[7], header[8]]) as usize;
+    if len > MAX_FRAME_PAYLOAD {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("frame of {} bytes exceeds the {} byte limit", len, MAX_FRAME_PAYLOAD),
+        ));
+    }
+    let mut payload = vec![0u8; len];
+    reader.read_exact(&mut payload).await?;
+    Ok(Frame { stream, kind, payload })
+}
+
+/// Write one frame
+pub(crate) async fn write_frame<W: AsyncWrite + Unpin>(writer: &mut W, frame: &Frame) -> std::io::Result<()> {
+    let mut header = [0u8; 9];
+    header[..4].copy_from_slice(&frame.stream.to_be_bytes());
+    header[4] = frame.kind;
+    header[5..].copy_from_slice(&(frame.payload.len() as u32).to_be_bytes());
+    writer.write_all(&header).await?;
+    writer.write_all(&frame.payload).await?;
+    writer.flush().await
+}
+
+/// Ask Corsair for multiplexing, returning whether it agreed
+///
+/// Failures to read an answer mean the daemon predates multiplexing, so
+/// only failures to send are errors.
+pub(crate) async fn negotiate(stream: &mut UnixStream) -> Result<bool, TransportError> {
+    let hello = bincode::serialize(&Hello {
+        version: MUX_VERSION,
+        multiplex: true,
+    })
+    .map_err(|e| TransportError::ConnectionFailed(format!("Serialize error: {}", e)))?;
+    stream.write_all(&(hello.len() as u32).to_be_bytes()).await?;
+    stream.write_all(&hello).await?;
+    stream.flush().await?;
+
+    let mut len = [0u8; 4];
+    if stream.read_exact(&mut len).await.is_err() {
+        return Ok(false);
+    }
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_FRAME_PAYLOAD {
+        return Ok(false);
+    }
+    let mut data = vec![0u8; len];
+    if stream.read_exact(&mut data).await.is_err() {
+        return Ok(false);
+    }
+    Ok(bincode::deserialize::<HelloResponse>(&data)
+        .map(|response| response.multiplex && response.version == MUX_VERSION)
+        .unwrap_or(false))
+}
+
+type StreamTable = Arc<Mutex<HashMap<u32, mpsc::Sender<Frame>>>>;
+
+/// A frame for the writer task, with the token reporting when it is written
+type Queued = (Frame, WriteToken);
+
+/// Frames a stream has queued that the writer task has not written yet
+#[derive(Default)]
+struct Unwritten {
+    count: AtomicUsize,
+    /// Set once a queued frame was dropped instead of written
+    failed: AtomicBool,
+    waker: AtomicWaker,
+}
+
+/// Travels with a queued frame and updates its stream's [`Unwritten`]
+struct WriteToken {
+    unwritten: Arc<Unwritten>,
+    written: bool,
+}
+
+impl Drop for WriteToken {
+    fn drop(&mut self) {
+        if !self.written {
+            self.unwritten.failed.store(true, Ordering::SeqCst);
+        }
+        self.unwritten.count.fetch_sub(1, Ordering::SeqCst);
+        self.unwritten.waker.wake();
+    }
+}
+
+/// A negotiated connection to Corsair carrying many streams
+pub(crate) struct MuxSession {
+    outgoing: mpsc::Sender<Queued>,
+    streams: StreamTable,
+    next_id: AtomicU32,
+    closed: Arc<AtomicBool>,
+}
+
+impl MuxSession {
+    /// Take over a connection on which [`negotiate`] succeeded
+    pub fn start(stream: UnixStream) -> Self {
+        let (mut reader, mut writer) = stream.into_split();
+        let (outgoing, mut queued) = mpsc::channel::<Queued>(OUTGOING_QUEUE);
+        let streams: StreamTable = Arc::default();
+        let closed = Arc::new(AtomicBool::new(false));
+
+        let writer_closed = closed.clone();
+        tokio::spawn(async move {
+            while let Some((frame, mut token)) = queued.recv().await {
+                if let Err(e) = write_frame(&mut writer, &frame).await {
+                    log::debug!("Corsair session write failed: {}", e);
+                    break;
+                }
+                token.written = true;
+            }
+            writer_closed.store(true, Ordering::SeqCst);
+        });
+
+        let reader_streams = streams.clone();
+        let reader_closed = closed.clone();
+        tokio::spawn(async move {
+            loop {
+                let frame = match read_frame(&mut reader).await {
+                    Ok(frame) => frame,
+                    Err(e) => {
+                        log::debug!("Corsair session closed: {}", e);
+                        break;
+                    }
+                };
+                let sender = {
+                    let mut table = reader_streams.lock().unwrap_or_else(|e| e.into_inner());
+                    match frame.kind {
+                        FRAME_CLOSE => table.remove(&frame.stream),
+                        _ => table.get(&frame.stream).cloned(),
+                    }
+                };
+                // Waits while the stream's queue is full
+                if let Some(sender) = sender {
+                    let _ = sender.send(frame).await;
+                }
+            }
+            reader_closed.store(true, Ordering::SeqCst);
+            // Dropping the senders tells every open stream the session is gone
+            reader_streams.lock().unwrap_or_else(|e| e.into_inner()).clear();
+        });
+
+        Self {
+            outgoing,
+            streams,
+            next_id: AtomicU32::new(1),
+            closed,
+        }
+    }
+
+    /// Whether the shared connection has failed
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst) || self.outgoing.is_closed()
+    }
+
+    /// Open a stream as asked by `request`
+    pub async fn open(&self, request: &ConnectRequest) -> Result<MuxStream, TransportError> {
+        let (sender, incoming) = mpsc::channel(INCOMING_QUEUE);
+        let id = self.register(sender)?;
+
+        // Dropping the stream on any error below closes it on Corsair's side
+        let mut stream = MuxStream {
+            id,
+            incoming,
+            outgoing: PollSender::new(self.outgoing.clone()),
+            unwritten: Arc::default(),
+            streams: self.streams.clone(),
+            pending: Vec::new(),
+            offset: 0,
+            read_closed: false,
+            write_closed: false,
+        };
+
+        let request = bincode::serialize(request)
+            .map_err(|e| TransportError::ConnectionFailed(format!("Serialize error: {}", e)))?;
+        stream.send(FRAME_OPEN, request).await.map_err(|_| TransportError::TorNotAvailable)?;
+
+        let opened = stream.incoming.recv().await.ok_or(TransportError::TorNotAvailable)?;
+        if opened.kind != FRAME_OPENED {
+            return Err(TransportError::ConnectionFailed(format!(
+                "unexpected frame kind {} while opening a stream",
+                opened.kind
+            )));
+        }
+        let response: ConnectResponse = bincode::deserialize(&opened.payload)
+            .map_err(|e| TransportError::ConnectionFailed(format!("Deserialize error: {}", e)))?;
+        if !response.success {
+            // Corsair has already dropped the stream
+            stream.write_closed = true;
+            return Err(TransportError::ConnectionFailed(
+                response.error.unwrap_or_else(|| "Unknown error".to_string()),
+            ));
+        }
+
+        Ok(stream)
+    }
+
+    /// Add a stream to the table under the next free id
+    ///
+    /// If every id is taken the session is marked closed, so the next
+    /// connect negotiates a fresh one.
+    fn register(&self, sender: mpsc::Sender<Frame>) -> Result<u32, TransportError> {
+        let mut table = self.streams.lock().unwrap_or_else(|e| e.into_inner());
+        if table.len() >= u32::MAX as usize {
+            self.closed.store(true, Ordering::SeqCst);
+            return Err(TransportError::ConnectionFailed(
+                "no free stream ids on the Corsair session".to_string(),
+            ));
+        }
+        loop {
+            // Wraps past u32::MAX; 0 is never used
+            let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+            if id != 0 && !table.contains_key(&id) {
+                table.insert(id, sender);
+                return Ok(id);
+            }
+        }
+    }
+}
+
+/// One stream on a [`MuxSession`]
+///
+/// Writes are queued to the session's writer task; `poll_write` waits for
+/// room in the queue and `poll_flush` until the stream's queued frames have
+/// been written to the socket.
+pub(crate) struct MuxStream {
+    id: u32,
+    incoming: mpsc::Receiver<Frame>,
+    outgoing: PollSender<Queued>,
+    unwritten: Arc<Unwritten>,
+    streams: StreamTable,
+    pending: Vec<u8>,
+    offset: usize,
+    read_closed: bool,
+    write_closed: bool,
+}
+
+impl MuxStream {
+    /// A frame on this stream, counted as unwritten until the writer is done
+    fn queued(&self, kind: u8, payload: Vec<u8>) -> Queued {
+        self.unwritten.count.fetch_add(1, Ordering::SeqCst);
+        let token = WriteToken {
+            unwritten: self.unwritten.clone(),
+            written: false,
+        };
+        (Frame { stream: self.id, kind, payload }, token)
+    }
+
+    /// Queue a frame, waiting for room
+    async fn send(&self, kind: u8, payload: Vec<u8>) -> std::io::Result<()> {
+        let sender = self.outgoing.get_ref().ok_or_else(session_closed)?;
+        sender.send(self.queued(kind, payload)).await.map_err(|_| session_closed())
+    }
+
+    /// Queue a frame once [`PollSender::poll_reserve`] makes room
+    fn poll_send(&mut self, cx: &mut Context<'_>, kind: u8, payload: &[u8]) -> Poll<std::io::Result<()>> {
+        ready!(self.outgoing.poll_reserve(cx)).map_err(|_| session_closed())?;
+        let frame = self.queued(kind, payload.to_vec());
+        Poll::Ready(self.outgoing.send_item(frame).map_err(|_| session_closed()))
+    }
+
+    /// Wait until every frame queued so far has been written
+    fn poll_written(&self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let unwritten = &self.unwritten;
+        if unwritten.count.load(Ordering::SeqCst) > 0 {
+            unwritten.waker.register(cx.waker());
+            if unwritten.count.load(Ordering::SeqCst) > 0 {
+                return Poll::Pending;
+            }
+        }
+        if unwritten.failed.load(Ordering::SeqCst) {
+            return Poll::Ready(Err(session_closed()));
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+fn session_closed() -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::BrokenPipe, "Corsair session closed")
+}
+
+impl AsyncRead for MuxStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if this.offset < this.pending.len() {
+                let n = buf.remaining().min(this.pending.len() - this.offset);
+                buf.put_slice(&this.pending[this.offset..this.offset + n]);
+                this.offset += n;
+                return Poll::Ready(Ok(()));
+            }
+            if this.read_closed {
+                return Poll::Ready(Ok(()));
+            }
+            match this.incoming.poll_recv(cx) {
+                Poll::Ready(Some(frame)) => match frame.kind {
+                    FRAME_DATA => {
+                        this.pending = frame.payload;
+                        this.offset = 0;
+                    }
+                    FRAME_CLOSE => this.read_closed = true,
+                    _ => {}
+                },
+                Poll::Ready(None) => {
+                    return Poll::Ready(Err(std::io::Error::new(
+                        std::io::ErrorKind::ConnectionReset,
+                        "Corsair session closed",
+                    )))
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl AsyncWrite for MuxStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        if this.write_closed {
+            return Poll::Ready(Err(std::io::Error::new(
+                std::io::ErrorKind::BrokenPipe,
+                "stream is shut down",
+            )));
+        }
+        let n = buf.len().min(MAX_FRAME_PAYLOAD);
+        ready!(this.poll_send(cx, FRAME_DATA, &buf[..n]))?;
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        self.poll_written(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        if !this.write_closed {
+            ready!(this.poll_send(cx, FRAME_CLOSE, &[]))?;
+            this.write_closed = true;
+        }
+        this.poll_written(cx)
+    }
+}
+
+impl Drop for MuxStream {
+    fn drop(&mut self) {
+        if !self.write_closed {
+            if let Some(sender) = self.outgoing.get_ref().cloned() {
+                let close = self.queued(FRAME_CLOSE, Vec::new());
+                if let Err(mpsc::error::TrySendError::Full(close)) = sender.try_send(close) {
+                    // Send it once there is room, without blocking the dropping task
+                    if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+                        runtime.spawn(async move {
+                            let _ = sender.send(close).await;
+                        });
+                    }
+                }
+            }
+        }
+        self.streams.lock().unwrap_or_else(|e| e.into_inner()).remove(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_stream_ids_skip_open_streams_when_wrapping() {
+        let (client, _corsair) = UnixStream::pair().unwrap();
+        let session = MuxSession::start(client);
+        let (open, _incoming) = mpsc::channel(INCOMING_QUEUE);
+        session.streams.lock().unwrap().insert(1, open.clone());
+        session.next_id.store(u32::MAX, Ordering::SeqCst);
+
+        assert_eq!(session.register(open.clone()).unwrap(), u32::MAX);
+        // 0 is skipped, and 1 is still open
+        assert_eq!(session.register(open).unwrap(), 2);
+        assert_eq!(session.streams.lock().unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,179 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Connection spans (requires the `tracing` feature to record anything)
+//!
+//! With the `tracing` feature, every [`ComposedConnector::connect`] and
+//! [`connect_url`] runs inside an INFO-level `connect` span, so spans opened
+//! by the connectors underneath, and any the caller opens around the
+//! request, nest under it. Its fields describe where the connection goes, as
+//! [`ComposedConnector::resolve`] would report it:
+//!
+//! - `transport` - [`Transport::as_str`] of the transport dialed
+//! - `host`, `port` - the target, for network transports
+//! - `socket_path` - the Unix socket, for local connections
+//! - `daemon` - the Corsair socket, Tor SOCKS port or SAM bridge, if any
+//! - `outcome` - `"ok"` or `"error"`, recorded before the span closes
+//! - `error` - the [`TransportError`] message, on failure
+//!
+//! Without the feature the hook is empty and compiles away.
+//!
+//! [`ComposedConnector::connect`]: crate::composed::ComposedConnector::connect
+//! [`connect_url`]: crate::composed::ComposedConnector::connect_url
+//! [`ComposedConnector::resolve`]: crate::composed::ComposedConnector::resolve
+//! [`Transport::as_str`]: crate::types::Transport::as_str
+
+use crate::composed::ResolutionPlan;
+use crate::types::TransportError;
+use std::future::Future;
+
+/// Name of the span around each connect
+pub const CONNECT_SPAN: &str = "connect";
+
+/// Run `connect` in a `connect` span describing `plan`
+///
+/// `plan` is only called when spans are recorded.
+#[inline]
+pub(crate) async fn instrument_connect<T>(
+    plan: impl FnOnce() -> Option<ResolutionPlan>,
+    connect: impl Future<Output = Result<T, TransportError>>,
+) -> Result<T, TransportError> {
+    #[cfg(feature = "tracing")]
+    {
+        use crate::composed::ResolvedEndpoint;
+        use ::tracing::field::{display, Empty};
+        use ::tracing::Instrument;
+
+        let span = ::tracing::info_span!(
+            "connect",
+            transport = Empty,
+            host = Empty,
+            port = Empty,
+            socket_path = Empty,
+            daemon = Empty,
+            outcome = Empty,
+            error = Empty,
+        );
+        if let Some(plan) = plan() {
+            span.record("transport", plan.transport.as_str());
+            match &plan.endpoint {
+                ResolvedEndpoint::Socket(path) => {
+                    span.record("socket_path", display(path.display()));
+                }
+                ResolvedEndpoint::Host { host, port } => {
+                    span.record("host", host.as_str());
+                    span.record("port", port);
+                }
+                ResolvedEndpoint::Custom => {}
+            }
+            if let Some(daemon) = &plan.daemon {
+                span.record("daemon", daemon.as_str());
+            }
+        }
+
+        let result = connect.instrument(span.clone()).await;
+        match &result {
+            Ok(_) => {
+                span.record("outcome", "ok");
+            }
+            Err(e) => {
+                span.record("outcome", "error");
+                span.record("error", display(e));
+            }
+        }
+        result
+    }
+    #[cfg(not(feature = "tracing"))]
+    {
+        let _ = plan;
+        connect.await
+    }
+}
+
+#[cfg(all(test, feature = "tracing", feature = "unix"))]
+mod tests {
+    use super::*;
+    use crate::composed::ComposedConnector;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::Subscriber;
+    use tracing_subscriber::layer::{Context, SubscriberExt};
+    use tracing_subscriber::registry::LookupSpan;
+    use tracing_subscriber::Layer;
+
+    /// Field values of a span, as recorded so far
+    #[derive(Default)]
+    struct Fields(HashMap<String, String>);
+
+    impl Visit for Fields {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.insert(field.name().to_string(), value.to_string());
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0.insert(field.name().to_string(), format!("{:?}", value));
+        }
+    }
+
+    /// Layer collecting the fields of each closed `connect` span
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<HashMap<String, String>>>>);
+
+    impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for Capture {
+        fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+            let mut fields = Fields::default();
+            attrs.record(&mut fields);
+            ctx.span(id).unwrap().extensions_mut().insert(fields);
+        }
+
+        fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+            if let Some(fields) = ctx.span(id).unwrap().extensions_mut().get_mut::<Fields>() {
+                values.record(fields);
+            }
+        }
+
+        fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+            let span = ctx.span(&id).unwrap();
+            if span.name() == CONNECT_SPAN {
+                let fields = span.extensions_mut().remove::<Fields>().unwrap_or_default();
+                self.0.lock().unwrap().push(fields.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_connect_span_fields() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("app.sock");
+        let _listener = std::os::unix::net::UnixListener::bind(&socket).unwrap();
+
+        let capture = Capture::default();
+        let subscriber = tracing_subscriber::registry().with(capture.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+            runtime.block_on(async {
+                let connector = ComposedConnector::new();
+                let url = format!("http::unix//{}/api", socket.display());
+                connector.connect(&url).await.unwrap();
+                connector.connect("http::unix///nonexistent/rigging-trace.sock/").await.unwrap_err();
+            });
+        });
+
+        let spans = capture.0.lock().unwrap();
+        assert_eq!(spans.len(), 2);
+
+        let ok = &spans[0];
+        assert_eq!(ok["transport"], "unix");
+        assert_eq!(ok["socket_path"], socket.display().to_string());
+        assert_eq!(ok["outcome"], "ok");
+        assert!(!ok.contains_key("host") && !ok.contains_key("error"));
+
+        let failed = &spans[1];
+        assert_eq!(failed["socket_path"], "/nonexistent/rigging-trace.sock");
+        assert_eq!(failed["outcome"], "error");
+        assert_eq!(failed["error"], TransportError::SocketPathNotFound.to_string());
+    }
+}
@@ -8,15 +8,50 @@
 //!
 //! ```text
 //! http::unix///tmp/app.sock/api/data    # Unix socket
+//! http::unix///run/control:/api/data  # Unix socket without an extension
 //! http::tcp//localhost:8080             # Explicit TCP
 //! http::tor//example.onion              # Tor network
 //! ```
 
 use crate::types::{Transport, TransportError};
-use url::Url;
+use std::path::PathBuf;
+use url::{Position, Url};
+
+/// Options for [`TransportUrl::parse_with_options`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Downgrade `https`/`wss` to `http`/`ws` on Unix sockets and named
+    /// pipes (default `true`)
+    ///
+    /// Turn this off for TLS-terminating services behind a socket: the
+    /// scheme stays secure and a composed connector performs the handshake
+    /// over the socket.
+    pub downgrade_local_tls: bool,
+}
+
+impl ParseOptions {
+    /// Default options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set whether local sockets downgrade secure schemes
+    pub fn with_downgrade_local_tls(mut self, downgrade: bool) -> Self {
+        self.downgrade_local_tls = downgrade;
+        self
+    }
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            downgrade_local_tls: true,
+        }
+    }
+}
 
 /// A URL with explicit transport information
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TransportUrl {
     /// The underlying URL (normalized)
     url: Url,
@@ -50,14 +85,44 @@ impl TransportUrl {
     /// let url = TransportUrl::parse("http::tor//example.onion/").unwrap();
     /// ```
     pub fn parse(url_str: &str) -> Result<Self, TransportError> {
+        Self::parse_with_options(url_str, ParseOptions::default())
+    }
+
+    /// Parse a transport-aware URL with non-default options
+    ///
+    /// ```
+    /// use rigging::transport_url::{ParseOptions, TransportUrl};
+    ///
+    /// let options = ParseOptions::new().with_downgrade_local_tls(false);
+    /// let url = TransportUrl::parse_with_options("https::unix///run/api.sock/", options).unwrap();
+    /// assert_eq!(url.scheme(), "https");
+    /// ```
+    pub fn parse_with_options(url_str: &str, options: ParseOptions) -> Result<Self, TransportError> {
         // Check for transport specification: scheme::transport//...
         if let Some((scheme_transport, rest)) = url_str.split_once("//") {
             if let Some((scheme, transport_str)) = scheme_transport.split_once("::") {
                 // Explicit transport specified
-                let transport = Transport::from_str(transport_str)
-                    .ok_or_else(|| TransportError::InvalidTransport(transport_str.to_string()))?;
+                if transport_str.contains("::") {
+                    return Err(TransportError::InvalidTransport(format!(
+                        "multiple transport prefixes in `{}` (expected scheme::transport//)",
+                        url_str
+                    )));
+                }
+                if transport_str.is_empty() {
+                    return Err(TransportError::InvalidTransport(format!(
+                        "missing transport after `::` in `{}`",
+                        url_str
+                    )));
+                }
+                if scheme.is_empty() {
+                    return Err(TransportError::InvalidUrl(format!(
+                        "missing scheme before `::` in `{}`",
+                        url_str
+                    )));
+                }
+                let transport = Transport::try_from(transport_str)?;
 
-                return Self::parse_with_transport(scheme, transport, rest);
+                return Self::parse_with_transport(scheme, transport, rest, options);
             }
         }
 
@@ -86,12 +151,13 @@ impl TransportUrl {
         scheme: &str,
         transport: Transport,
         rest: &str,
+        options: ParseOptions,
     ) -> Result<Self, TransportError> {
         match transport {
-            Transport::Unix => Self::parse_unix_url(scheme, rest),
-            Transport::NamedPipe => Self::parse_named_pipe_url(scheme, rest),
+            Transport::Unix => Self::parse_unix_url(scheme, rest, options),
+            Transport::NamedPipe => Self::parse_named_pipe_url(scheme, rest, options),
             Transport::Tor => Self::parse_tor_url(scheme, rest),
-            Transport::Tcp | Transport::Ssh | Transport::Quic => {
+            Transport::Tcp | Transport::I2p | Transport::Ssh | Transport::Quic | Transport::Memory => {
                 // Standard URL format
                 let full_url = format!("{}://{}", scheme, rest);
                 let url = Url::parse(&full_url)
@@ -109,13 +175,17 @@ impl TransportUrl {
         }
     }
 
-    fn parse_unix_url(scheme: &str, rest: &str) -> Result<Self, TransportError> {
+    fn parse_unix_url(scheme: &str, rest: &str, options: ParseOptions) -> Result<Self, TransportError> {
         // Unix socket URL format:
         // http::unix//relative/path.sock         -> relative path
         // http::unix///absolute/path.sock        -> absolute path (note 3 slashes)
         // http::unix///tmp/app.sock/api/data     -> socket path + URL path
+        // http::unix///run/control:/api/data     -> extensionless socket + URL path
+        // http::unix//@app/api/data              -> abstract socket (Linux) + URL path
 
-        let (socket_path, url_path) = if rest.starts_with('/') {
+        let (socket_path, url_path) = if rest.starts_with('@') {
+            Self::extract_abstract_name(rest)
+        } else if rest.starts_with('/') {
             // Absolute path: ///tmp/app.sock or ///tmp/app.sock/api
             // rest is "/tmp/app.sock/..." - keep the leading slash for absolute paths
             Self::extract_socket_path(rest)
@@ -125,11 +195,7 @@ impl TransportUrl {
         };
 
         // Downgrade HTTPS to HTTP for local sockets (TLS not needed)
-        let effective_scheme = match scheme {
-            "https" => "http",
-            "wss" => "ws",
-            other => other,
-        };
+        let effective_scheme = local_scheme(scheme, options);
 
         // Create a localhost URL for the URL parsing
         let url_string = format!("{}://localhost{}", effective_scheme, url_path);
@@ -146,7 +212,7 @@ impl TransportUrl {
         })
     }
 
-    fn parse_named_pipe_url(scheme: &str, rest: &str) -> Result<Self, TransportError> {
+    fn parse_named_pipe_url(scheme: &str, rest: &str, options: ParseOptions) -> Result<Self, TransportError> {
         // Named pipe URL format (Windows):
         // http::pipe//\\.\pipe\myapp           -> named pipe
         // http::pipe//myapp                    -> shorthand for \\.\pipe\myapp
@@ -168,11 +234,7 @@ impl TransportUrl {
             "/".to_string()
         };
 
-        let effective_scheme = match scheme {
-            "https" => "http",
-            "wss" => "ws",
-            other => other,
-        };
+        let effective_scheme = local_scheme(scheme, options);
 
         let url_string = format!("{}://localhost{}", effective_scheme, url_path);
         let url = Url::parse(&url_string)
@@ -204,23 +266,63 @@ impl TransportUrl {
     }
 
     /// Extract socket path from URL path, separating socket file from URL path
+    ///
+    /// The query and fragment always belong to the URL path.
     fn extract_socket_path(path: &str) -> (String, String) {
+        let split = path.find(|c: char| c == '?' || c == '#').unwrap_or(path.len());
+        let (path, suffix) = path.split_at(split);
+
+        let (socket_path, url_path) = Self::split_socket_path(path);
+        (socket_path.to_string(), format!("{}{}", url_path, suffix))
+    }
+
+    /// Split `@name/path` into the abstract socket name (with its `@`) and
+    /// the URL path; abstract names have no extension or file to go by, so
+    /// the name ends at the first `/`
+    fn extract_abstract_name(rest: &str) -> (String, String) {
+        let end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+        let (name, url_path) = rest.split_at(end);
+        let url_path = if url_path.starts_with('/') {
+            url_path.to_string()
+        } else {
+            format!("/{}", url_path)
+        };
+        (name.to_string(), url_path)
+    }
+
+    /// Split a URL path into the socket path and the URL path
+    ///
+    /// A `:` followed by `/` (or ending the path) separates the two, as in
+    /// `/run/control:/api`; otherwise the socket ends with its extension.
+    /// Without either the whole path is the socket. The file system is
+    /// never consulted, so parsing does not depend on what exists on disk.
+    fn split_socket_path(path: &str) -> (&str, &str) {
+        let separator = path.find(":/").or_else(|| path.strip_suffix(':').map(str::len));
+        if let Some(idx) = separator {
+            let url_path = &path[idx + 1..];
+            return (&path[..idx], if url_path.is_empty() { "/" } else { url_path });
+        }
+
         // Look for common socket file extensions
         for ext in &[".sock", ".socket", ".sk"] {
             if let Some(idx) = path.find(ext) {
                 let end_idx = idx + ext.len();
-                let socket_path = &path[..end_idx];
                 let url_path = if end_idx < path.len() {
                     &path[end_idx..]
                 } else {
                     "/"
                 };
-                return (socket_path.to_string(), url_path.to_string());
+                return (&path[..end_idx], url_path);
             }
         }
 
-        // No extension found - assume entire path is socket
-        (path.to_string(), "/".to_string())
+        // No extension - assume entire path is socket
+        (path, "/")
+    }
+
+    /// Start building a URL from its parts
+    pub fn builder() -> TransportUrlBuilder {
+        TransportUrlBuilder::new()
     }
 
     /// Get the transport type
@@ -274,6 +376,101 @@ impl TransportUrl {
         self.url.path()
     }
 
+    /// Copy of this URL with a canonical request path
+    ///
+    /// Collapses repeated slashes and resolves `.` and `..` segments (never
+    /// above the root), keeping a trailing slash. Only the request path is
+    /// rewritten: the Unix socket or pipe path, query and fragment are left
+    /// untouched.
+    pub fn normalized(&self) -> TransportUrl {
+        let mut normalized = self.clone();
+        if !self.url.cannot_be_a_base() {
+            normalized.url.set_path(&normalize_path(self.url.path()));
+        }
+        normalized
+    }
+
+    /// Resolve a relative reference such as `../v2/items?page=2`
+    ///
+    /// The reference is applied to the inner URL with [`url::Url::join`];
+    /// the transport and socket or pipe path are kept. For local transports
+    /// a reference that changes the scheme or host is rejected, as is any
+    /// transport-aware reference. Use [`resolve`](Self::resolve) for link
+    /// and redirect targets that may leave the socket.
+    pub fn join(&self, reference: &str) -> Result<TransportUrl, TransportError> {
+        if is_transport_aware(reference) {
+            return Err(TransportError::InvalidUrl(format!(
+                "cannot join transport-aware URL {:?}",
+                reference
+            )));
+        }
+
+        let resolved = self
+            .url
+            .join(reference)
+            .map_err(|e| TransportError::InvalidUrl(e.to_string()))?;
+        if self.is_local() && resolved.origin() != self.url.origin() {
+            return Err(TransportError::InvalidUrl(format!(
+                "{:?} would leave the {} socket",
+                reference,
+                self.transport.display_name()
+            )));
+        }
+        let mut joined = self.clone();
+        joined.url = resolved;
+        Ok(joined)
+    }
+
+    /// Resolve a link or redirect target against this URL
+    ///
+    /// Transport-aware (`scheme::transport//...`) and absolute URLs for
+    /// another origin are parsed as they are, so they can switch transports.
+    /// Relative references, and absolute URLs naming this URL's own origin
+    /// (a server behind a Unix socket sees itself as `http://localhost`),
+    /// keep the current transport and socket. Before following a redirect
+    /// to the result, see [`check_redirect`](Self::check_redirect).
+    pub fn resolve(&self, reference: &str) -> Result<TransportUrl, TransportError> {
+        if is_transport_aware(reference) {
+            return TransportUrl::parse(reference);
+        }
+
+        let resolved = self
+            .url
+            .join(reference)
+            .map_err(|e| TransportError::InvalidUrl(e.to_string()))?;
+        if resolved.origin() != self.url.origin() {
+            return TransportUrl::parse(resolved.as_str());
+        }
+        let mut joined = self.clone();
+        joined.url = resolved;
+        Ok(joined)
+    }
+
+    /// Check that a redirect from this URL to `target` is safe to follow
+    ///
+    /// A redirect may not leave Tor or I2P, which would reveal the client's
+    /// address, nor move from a network transport onto a Unix socket, named
+    /// pipe or in-memory transport, which would let a remote server reach
+    /// local services. Fails with [`TransportError::RedirectDenied`].
+    pub fn check_redirect(&self, target: &TransportUrl) -> Result<(), TransportError> {
+        let (from, to) = (self.effective_transport(), target.effective_transport());
+        let leaves_anonymity = from.is_anonymous() && to != from;
+        let enters_local = to.is_local() && !from.is_local();
+        if leaves_anonymity || enters_local {
+            return Err(TransportError::RedirectDenied { from, to });
+        }
+        Ok(())
+    }
+
+    /// The transport, counting `.onion` hosts as Tor
+    fn effective_transport(&self) -> Transport {
+        if self.requires_tor() {
+            Transport::Tor
+        } else {
+            self.transport
+        }
+    }
+
     /// Get the full URL as string
     pub fn as_str(&self) -> &str {
         self.url.as_str()
@@ -284,16 +481,76 @@ impl TransportUrl {
         self.unix_socket_path.as_deref()
     }
 
+    /// Get Unix socket path (if applicable) as an owned path
+    ///
+    /// An `@name` abstract socket comes back as the path `@name`, as
+    /// [`UnixConnector`](crate::unix_connector::UnixConnector) expects it.
+    pub fn unix_socket_pathbuf(&self) -> Option<PathBuf> {
+        self.unix_socket_path.as_deref().map(PathBuf::from)
+    }
+
+    /// Whether the Unix socket is in the Linux abstract namespace
+    ///
+    /// Abstract sockets are written `@name` and have no file on disk.
+    pub fn is_abstract_socket(&self) -> bool {
+        self.unix_socket_path.as_deref().is_some_and(|p| p.starts_with('@'))
+    }
+
     /// Get named pipe path (if applicable, Windows)
     pub fn named_pipe_path(&self) -> Option<&str> {
         self.named_pipe_path.as_deref()
     }
 
+    /// Get named pipe path (if applicable, Windows) as an owned path
+    pub fn named_pipe_pathbuf(&self) -> Option<PathBuf> {
+        self.named_pipe_path.as_deref().map(PathBuf::from)
+    }
+
     /// Check if this is a local-only URL (Unix socket or named pipe)
     pub fn is_local(&self) -> bool {
         self.transport.is_local()
     }
 
+    /// Check if the URL asked for a secure scheme (`https` or `wss`)
+    ///
+    /// Based on the original scheme, so it stays true for Unix socket URLs
+    /// whose effective scheme was downgraded to `http`/`ws`.
+    pub fn is_secure(&self) -> bool {
+        matches!(self.original_scheme.as_str(), "https" | "wss")
+    }
+
+    /// Check if the scheme was downgraded at parse time
+    ///
+    /// True for `https`/`wss` URLs on Unix sockets and named pipes, which are
+    /// connected as `http`/`ws` since the transport never leaves the machine,
+    /// unless parsed with [`ParseOptions::downgrade_local_tls`] off.
+    /// Together with [`is_secure`](Self::is_secure) this tells a secure
+    /// local connection apart from a plain one.
+    pub fn was_downgraded(&self) -> bool {
+        self.url.scheme() != self.original_scheme
+    }
+
+    /// Name a TLS client should send as SNI and verify the certificate against
+    ///
+    /// The URL's host for `https`/`wss` URLs, even when the connection rides
+    /// a Unix socket via the socket map or goes through Tor. `None` for
+    /// insecure schemes and for explicit socket or pipe URLs, whose host is
+    /// only a `localhost` placeholder; supply the name separately for those.
+    pub fn tls_server_name(&self) -> Option<&str> {
+        if !self.is_secure() || matches!(self.transport, Transport::Unix | Transport::NamedPipe) {
+            return None;
+        }
+        // Url keeps IPv6 hosts bracketed
+        self.url
+            .host_str()
+            .map(|host| host.trim_start_matches('[').trim_end_matches(']'))
+    }
+
+    /// Check if this is a WebSocket URL (`ws` or `wss`)
+    pub fn is_websocket(&self) -> bool {
+        matches!(self.url.scheme(), "ws" | "wss")
+    }
+
     /// Check if this URL requires Tor
     pub fn requires_tor(&self) -> bool {
         self.transport == Transport::Tor ||
@@ -301,20 +558,225 @@ impl TransportUrl {
     }
 }
 
+/// Effective scheme for a Unix socket or named pipe URL
+fn local_scheme(scheme: &str, options: ParseOptions) -> &str {
+    match scheme {
+        "https" if options.downgrade_local_tls => "http",
+        "wss" if options.downgrade_local_tls => "ws",
+        other => other,
+    }
+}
+
+/// Whether `reference` is written `scheme::transport//...`
+fn is_transport_aware(reference: &str) -> bool {
+    reference.split_once("//").is_some_and(|(prefix, _)| prefix.contains("::"))
+}
+
+/// Collapse empty and `.` segments and resolve `..` in an absolute path
+fn normalize_path(path: &str) -> String {
+    let mut segments: Vec<&str> = Vec::new();
+    let mut trailing_slash = false;
+    for segment in path.split('/') {
+        trailing_slash = matches!(segment, "" | "." | "..");
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+
+    let mut normalized = format!("/{}", segments.join("/"));
+    if trailing_slash && !segments.is_empty() {
+        normalized.push('/');
+    }
+    normalized
+}
+
+/// Builds a [`TransportUrl`] from discrete parts
+///
+/// Without [`transport`](Self::transport) the URL is implicit, as if parsed
+/// from a plain `scheme://host/` string (so `.onion` hosts still use Tor).
+/// Its `Display` form parses back to the same URL.
+///
+/// ```
+/// use rigging::{Transport, TransportUrl};
+///
+/// let url = TransportUrl::builder()
+///     .transport(Transport::Unix)
+///     .socket_path("/tmp/app.sock")
+///     .path("/api/items")
+///     .query("page=2")
+///     .build()
+///     .unwrap();
+/// assert_eq!(url.to_string(), "http::unix///tmp/app.sock/api/items?page=2");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TransportUrlBuilder {
+    transport: Option<Transport>,
+    scheme: Option<String>,
+    host: Option<String>,
+    port: Option<u16>,
+    socket_path: Option<String>,
+    path: Option<String>,
+    query: Option<String>,
+}
+
+impl TransportUrlBuilder {
+    /// Create an empty builder (scheme `http`, path `/`)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use an explicit transport
+    pub fn transport(mut self, transport: Transport) -> Self {
+        self.transport = Some(transport);
+        self
+    }
+
+    /// Set the scheme (default `http`)
+    pub fn scheme(mut self, scheme: impl Into<String>) -> Self {
+        self.scheme = Some(scheme.into());
+        self
+    }
+
+    /// Set the host (not valid for Unix sockets)
+    pub fn host(mut self, host: impl Into<String>) -> Self {
+        self.host = Some(host.into());
+        self
+    }
+
+    /// Set the port (not valid for Unix sockets)
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    /// Set the socket path (Unix transport only)
+    pub fn socket_path(mut self, path: impl Into<String>) -> Self {
+        self.socket_path = Some(path.into());
+        self
+    }
+
+    /// Set the request path (default `/`)
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// Set the query string, without the leading `?`
+    pub fn query(mut self, query: impl Into<String>) -> Self {
+        self.query = Some(query.into());
+        self
+    }
+
+    /// Validate the parts and build the URL
+    pub fn build(self) -> Result<TransportUrl, TransportError> {
+        let scheme = self.scheme.as_deref().unwrap_or("http");
+        let transport = self.transport.unwrap_or(Transport::Tcp);
+        let path = match self.path.as_deref() {
+            None | Some("") => "/".to_string(),
+            Some(p) if p.starts_with('/') => p.to_string(),
+            Some(p) => format!("/{}", p),
+        };
+
+        if transport != Transport::Unix && self.socket_path.is_some() {
+            return Err(TransportError::InvalidUrl(format!(
+                "socket_path is only valid for the Unix transport, not {}",
+                transport.display_name()
+            )));
+        }
+
+        match transport {
+            Transport::Unix => {
+                if self.host.is_some() || self.port.is_some() {
+                    return Err(TransportError::InvalidUrl(
+                        "Unix socket URLs take a socket_path, not a host or port".to_string(),
+                    ));
+                }
+                let socket_path = self
+                    .socket_path
+                    .filter(|p| !p.is_empty())
+                    .ok_or_else(|| TransportError::InvalidUrl("Unix socket URLs need a socket_path".to_string()))?;
+
+                // Same downgrade as parsing
+                let effective_scheme = local_scheme(scheme, ParseOptions::default());
+                let mut url = Url::parse(&format!("{}://localhost/", effective_scheme))
+                    .map_err(|e| TransportError::InvalidUrl(e.to_string()))?;
+                url.set_path(&path);
+                url.set_query(self.query.as_deref());
+
+                Ok(TransportUrl {
+                    original_scheme: scheme.to_string(),
+                    url,
+                    transport,
+                    explicit_transport: true,
+                    unix_socket_path: Some(socket_path),
+                    named_pipe_path: None,
+                })
+            }
+            Transport::NamedPipe => Err(TransportError::InvalidUrl(
+                "Named pipe URLs cannot be built from parts; use TransportUrl::parse".to_string(),
+            )),
+            _ => {
+                let host = self
+                    .host
+                    .filter(|h| !h.is_empty())
+                    .ok_or_else(|| TransportError::InvalidUrl(format!("{} URLs need a host", transport.display_name())))?;
+
+                let mut url = Url::parse(&format!("{}://localhost/", scheme))
+                    .map_err(|e| TransportError::InvalidUrl(e.to_string()))?;
+                url.set_host(Some(&host))
+                    .map_err(|e| TransportError::InvalidUrl(format!("Invalid host {:?}: {}", host, e)))?;
+                url.set_port(self.port)
+                    .map_err(|_| TransportError::InvalidUrl(format!("Scheme {} cannot have a port", scheme)))?;
+                url.set_path(&path);
+                url.set_query(self.query.as_deref());
+
+                let explicit_transport = self.transport.is_some();
+                let transport = if !explicit_transport && host.ends_with(".onion") {
+                    Transport::Tor
+                } else {
+                    transport
+                };
+
+                Ok(TransportUrl {
+                    original_scheme: scheme.to_string(),
+                    url,
+                    transport,
+                    explicit_transport,
+                    unix_socket_path: None,
+                    named_pipe_path: None,
+                })
+            }
+        }
+    }
+}
+
 impl std::fmt::Display for TransportUrl {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if self.explicit_transport {
             match self.transport {
                 Transport::Unix => {
                     if let Some(ref socket) = self.unix_socket_path {
-                        write!(f, "{}::unix//{}{}", self.original_scheme, socket, self.url.path())
+                        let path = &self.url[Position::BeforePath..];
+                        // Sockets without an extension need the separator to parse back
+                        let separator = if socket.starts_with('@')
+                            || Self::extract_socket_path(&format!("{}{}", socket, path)).0 == *socket
+                        {
+                            ""
+                        } else {
+                            ":"
+                        };
+                        write!(f, "{}::unix//{}{}{}", self.original_scheme, socket, separator, path)
                     } else {
                         write!(f, "{}", self.url)
                     }
                 }
                 Transport::NamedPipe => {
                     if let Some(ref pipe) = self.named_pipe_path {
-                        write!(f, "{}::pipe//{}{}", self.original_scheme, pipe, self.url.path())
+                        write!(f, "{}::pipe//{}{}", self.original_scheme, pipe, &self.url[Position::BeforePath..])
                     } else {
                         write!(f, "{}", self.url)
                     }
@@ -333,6 +795,58 @@ impl std::fmt::Display for TransportUrl {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_join() {
+        let base = TransportUrl::parse("http::unix///tmp/app.sock/api/").unwrap();
+
+        let joined = base.join("../v2/items?page=2").unwrap();
+        assert_eq!(joined.to_string(), "http::unix///tmp/app.sock/v2/items?page=2");
+        assert_eq!(joined.unix_socket_path(), Some("/tmp/app.sock"));
+        assert_eq!(joined.transport(), Transport::Unix);
+
+        assert_eq!(base.join("items/1").unwrap().path(), "/api/items/1");
+        assert_eq!(base.join("/health").unwrap().to_string(), "http::unix///tmp/app.sock/health");
+
+        let joined = base.join("?page=3").unwrap();
+        assert_eq!(joined.path(), "/api/");
+        assert_eq!(joined.url.query(), Some("page=3"));
+
+        // The socket's own origin may be spelled out, but not changed
+        assert_eq!(base.join("http://localhost/home").unwrap().path(), "/home");
+        for escape in ["https://localhost/", "http://example.com/", "//example.com/x", "http::tcp//example.com/"] {
+            assert!(matches!(base.join(escape), Err(TransportError::InvalidUrl(_))), "{}", escape);
+        }
+
+        // Network transports keep their transport even for another host
+        let tor = TransportUrl::parse("http::tor//abc.onion/a/b").unwrap();
+        assert_eq!(tor.join("c").unwrap().to_string(), "http::tor//abc.onion/a/c");
+        assert_eq!(tor.join("http://def.onion/").unwrap().transport(), Transport::Tor);
+    }
+
+    #[test]
+    fn test_resolve() {
+        let base = TransportUrl::parse("http::unix///tmp/app.sock/api/items?page=2").unwrap();
+
+        let resolved = base.resolve("../login?next=1").unwrap();
+        assert_eq!(resolved.to_string(), "http::unix///tmp/app.sock/login?next=1");
+        assert_eq!(resolved.unix_socket_path(), Some("/tmp/app.sock"));
+
+        // The server's own origin stays on the socket
+        let resolved = base.resolve("http://localhost/home").unwrap();
+        assert_eq!(resolved.to_string(), "http::unix///tmp/app.sock/home");
+
+        let resolved = base.resolve("http://example.com/").unwrap();
+        assert_eq!(resolved.transport(), Transport::Tcp);
+        assert!(!resolved.is_explicit_transport());
+
+        let resolved = base.resolve("http::tor//example.onion/").unwrap();
+        assert_eq!(resolved.transport(), Transport::Tor);
+
+        let tcp = TransportUrl::parse("https://example.com/a/b").unwrap();
+        assert_eq!(tcp.resolve("c").unwrap().as_str(), "https://example.com/a/c");
+        assert_eq!(tcp.resolve("//abc.onion/").unwrap().transport(), Transport::Tor);
+    }
+
     #[test]
     fn test_standard_url() {
         let url = TransportUrl::parse("https://example.com/path").unwrap();
@@ -350,6 +864,23 @@ mod tests {
         assert_eq!(url.path(), "/api/data");
     }
 
+    #[test]
+    fn test_typed_socket_and_pipe_paths() {
+        for input in ["http::unix///tmp/app.sock/api", "http::unix//var/run/app.sock", "http::unix//@app/"] {
+            let url = TransportUrl::parse(input).unwrap();
+            assert_eq!(url.unix_socket_pathbuf().as_deref(), url.unix_socket_path().map(std::path::Path::new));
+            assert!(url.named_pipe_pathbuf().is_none());
+        }
+
+        let url = TransportUrl::parse("http::pipe//myapp/api").unwrap();
+        assert_eq!(url.named_pipe_pathbuf(), Some(PathBuf::from(r"\\.\pipe\myapp")));
+        assert_eq!(url.named_pipe_pathbuf().as_deref(), url.named_pipe_path().map(std::path::Path::new));
+        assert!(url.unix_socket_pathbuf().is_none());
+
+        let url = TransportUrl::parse("http://example.com/").unwrap();
+        assert!(url.unix_socket_pathbuf().is_none() && url.named_pipe_pathbuf().is_none());
+    }
+
     #[test]
     fn test_unix_socket_relative() {
         let url = TransportUrl::parse("http::unix//var/run/app.sock").unwrap();
@@ -357,6 +888,55 @@ mod tests {
         assert_eq!(url.unix_socket_path(), Some("var/run/app.sock"));
     }
 
+    #[test]
+    fn test_unix_socket_with_query() {
+        let url = TransportUrl::parse("http::unix///tmp/app.sock/api?x=1#top").unwrap();
+        assert_eq!(url.unix_socket_path(), Some("/tmp/app.sock"));
+        assert_eq!(url.path(), "/api");
+        assert_eq!(url.url().query(), Some("x=1"));
+        assert_eq!(url.url().fragment(), Some("top"));
+        assert_eq!(url.to_string(), "http::unix///tmp/app.sock/api?x=1#top");
+
+        let url = TransportUrl::parse("http::unix///tmp/app.sock?x=1").unwrap();
+        assert_eq!(url.unix_socket_path(), Some("/tmp/app.sock"));
+        assert_eq!(url.path(), "/");
+        assert_eq!(url.url().query(), Some("x=1"));
+    }
+
+    #[test]
+    fn test_unix_socket_extensionless_with_path() {
+        let url = TransportUrl::parse("http::unix///run/control:/api/v1").unwrap();
+        assert_eq!(url.unix_socket_path(), Some("/run/control"));
+        assert_eq!(url.path(), "/api/v1");
+        assert_eq!(url.to_string(), "http::unix///run/control:/api/v1");
+
+        let url = TransportUrl::parse("http::unix///run/control:?verbose=1").unwrap();
+        assert_eq!(url.unix_socket_path(), Some("/run/control"));
+        assert_eq!(url.path(), "/");
+        assert_eq!(url.url().query(), Some("verbose=1"));
+        assert_eq!(url.to_string(), "http::unix///run/control:/?verbose=1");
+
+        // The separator also works after an extension
+        let url = TransportUrl::parse("http::unix///tmp/app.sock:/api").unwrap();
+        assert_eq!(url.unix_socket_path(), Some("/tmp/app.sock"));
+        assert_eq!(url.path(), "/api");
+        assert_eq!(url.to_string(), "http::unix///tmp/app.sock/api");
+    }
+
+    #[test]
+    fn test_unix_socket_extensionless_without_separator() {
+        // Parsing never looks at the disk, so the whole path is the socket
+        let url = TransportUrl::parse("http::unix///run/rigging/control").unwrap();
+        assert_eq!(url.unix_socket_path(), Some("/run/rigging/control"));
+        assert_eq!(url.path(), "/");
+        assert_eq!(url.to_string(), "http::unix///run/rigging/control:/");
+
+        // A colon inside a directory name is not a separator
+        let url = TransportUrl::parse("http::unix///run/user:1000/app.sock/api").unwrap();
+        assert_eq!(url.unix_socket_path(), Some("/run/user:1000/app.sock"));
+        assert_eq!(url.path(), "/api");
+    }
+
     #[test]
     fn test_https_downgrade_for_unix() {
         let url = TransportUrl::parse("https::unix///tmp/app.sock").unwrap();
@@ -364,6 +944,249 @@ mod tests {
         assert_eq!(url.original_scheme(), "https");
     }
 
+    #[test]
+    fn test_parse_options_downgrade_local_tls() {
+        let keep = ParseOptions::new().with_downgrade_local_tls(false);
+        assert!(ParseOptions::default().downgrade_local_tls);
+
+        let url = TransportUrl::parse_with_options("https::unix///tmp/app.sock/api", ParseOptions::default()).unwrap();
+        assert_eq!(url.scheme(), "http");
+        assert!(url.was_downgraded());
+
+        let url = TransportUrl::parse_with_options("https::unix///tmp/app.sock/api", keep).unwrap();
+        assert_eq!(url.scheme(), "https");
+        assert!(url.is_secure());
+        assert!(!url.was_downgraded());
+        assert_eq!(url.unix_socket_path(), Some("/tmp/app.sock"));
+        assert_eq!(url.path(), "/api");
+        assert_eq!(url.port_or_default(), 443);
+        assert_eq!(url.to_string(), "https::unix///tmp/app.sock/api");
+
+        let url = TransportUrl::parse_with_options("wss::unix///tmp/app.sock/ws", keep).unwrap();
+        assert_eq!(url.scheme(), "wss");
+        let url = TransportUrl::parse_with_options(r"https::pipe//myapp/api", keep).unwrap();
+        assert_eq!(url.scheme(), "https");
+
+        // Other transports never downgrade
+        let url = TransportUrl::parse_with_options("https::tcp//example.com/", ParseOptions::default()).unwrap();
+        assert_eq!(url.scheme(), "https");
+    }
+
+    #[test]
+    fn test_secure_and_downgraded() {
+        let url = TransportUrl::parse("https::unix///tmp/app.sock/").unwrap();
+        assert!(url.is_secure());
+        assert!(url.was_downgraded());
+
+        let url = TransportUrl::parse("http::unix///tmp/app.sock/").unwrap();
+        assert!(!url.is_secure());
+        assert!(!url.was_downgraded());
+
+        let url = TransportUrl::parse("http://example.com/").unwrap();
+        assert!(!url.is_secure());
+        assert!(!url.was_downgraded());
+
+        for https in ["https://example.com/", "https::tcp//example.com:8443/"] {
+            let url = TransportUrl::parse(https).unwrap();
+            assert!(url.is_secure());
+            assert!(!url.was_downgraded());
+            assert_eq!(url.scheme(), "https");
+        }
+    }
+
+    #[test]
+    fn test_wss_downgrade_for_unix() {
+        let url = TransportUrl::parse("wss::unix///tmp/app.sock/ws").unwrap();
+        assert_eq!(url.transport(), Transport::Unix);
+        assert_eq!(url.unix_socket_path(), Some("/tmp/app.sock"));
+        assert_eq!(url.path(), "/ws");
+        assert_eq!(url.scheme(), "ws"); // Downgraded
+        assert_eq!(url.original_scheme(), "wss");
+        assert!(url.is_secure());
+        assert!(url.is_websocket());
+        assert_eq!(url.to_string(), "wss::unix///tmp/app.sock/ws");
+    }
+
+    #[test]
+    fn test_websocket_over_tor_and_tcp() {
+        let url = TransportUrl::parse("wss::tor//example.onion/ws").unwrap();
+        assert_eq!(url.transport(), Transport::Tor);
+        assert_eq!(url.port_or_default(), 443);
+        assert!(url.is_secure());
+        assert!(url.is_websocket());
+
+        let url = TransportUrl::parse("ws://localhost/chat").unwrap();
+        assert_eq!(url.port_or_default(), 80);
+        assert!(!url.is_secure());
+        assert!(url.is_websocket());
+
+        assert!(!TransportUrl::parse("http://localhost/").unwrap().is_websocket());
+    }
+
+    #[test]
+    fn test_tls_server_name() {
+        let url = TransportUrl::parse("https://api.example.com/v1").unwrap();
+        assert_eq!(url.tls_server_name(), Some("api.example.com"));
+
+        let url = TransportUrl::parse("wss::tor//example.onion/ws").unwrap();
+        assert_eq!(url.tls_server_name(), Some("example.onion"));
+
+        let url = TransportUrl::parse("https::tcp//[::1]:8443/").unwrap();
+        assert_eq!(url.tls_server_name(), Some("::1"));
+
+        // Plain HTTP needs no name; socket URLs only have a placeholder host
+        assert_eq!(TransportUrl::parse("http://api.example.com/").unwrap().tls_server_name(), None);
+        assert_eq!(TransportUrl::parse("https::unix///tmp/app.sock/").unwrap().tls_server_name(), None);
+    }
+
+    #[test]
+    fn test_normalized_collapses_slashes() {
+        let url = TransportUrl::parse("http::unix///tmp/app.sock/api//data").unwrap();
+        assert_eq!(url.path(), "/api//data");
+        let normalized = url.normalized();
+        assert_eq!(normalized.path(), "/api/data");
+        assert_eq!(normalized.unix_socket_path(), Some("/tmp/app.sock"));
+
+        let url = TransportUrl::parse("http://example.com//api///items//?q=1").unwrap();
+        let normalized = url.normalized();
+        assert_eq!(normalized.path(), "/api/items/");
+        assert_eq!(normalized.url().query(), Some("q=1"));
+    }
+
+    #[test]
+    fn test_normalized_dot_segments() {
+        // The socket path keeps its own dot segments
+        let url = TransportUrl::parse("http::unix//./run/app.sock/api/./v1/../v2/").unwrap();
+        let normalized = url.normalized();
+        assert_eq!(normalized.path(), "/api/v2/");
+        assert_eq!(normalized.unix_socket_path(), Some("./run/app.sock"));
+
+        assert_eq!(normalize_path("/a/./b/."), "/a/b/");
+        assert_eq!(normalize_path("/a/b/.."), "/a/");
+        assert_eq!(normalize_path("/../../etc//passwd"), "/etc/passwd");
+        assert_eq!(normalize_path("//"), "/");
+        assert_eq!(normalize_path("/"), "/");
+    }
+
+    /// Display of `url` parses back to an identical URL
+    fn assert_round_trip(url: &TransportUrl) {
+        let reparsed = TransportUrl::parse(&url.to_string()).unwrap();
+        assert_eq!(reparsed.to_string(), url.to_string());
+        assert_eq!(reparsed.transport(), url.transport());
+        assert_eq!(reparsed.is_explicit_transport(), url.is_explicit_transport());
+        assert_eq!(reparsed.unix_socket_path(), url.unix_socket_path());
+        assert_eq!(reparsed.url(), url.url());
+    }
+
+    #[test]
+    fn test_malformed_transport_prefix() {
+        let double = TransportUrl::parse("http::unix::tor///tmp/x.sock").unwrap_err();
+        assert!(matches!(double, TransportError::InvalidTransport(ref msg)
+            if msg.contains("multiple transport prefixes") && msg.contains("http::unix::tor///tmp/x.sock")));
+
+        let empty_transport = TransportUrl::parse("http:://example.com/").unwrap_err();
+        assert!(matches!(empty_transport, TransportError::InvalidTransport(ref msg)
+            if msg.contains("missing transport")));
+
+        let empty_scheme = TransportUrl::parse("::unix///tmp/app.sock").unwrap_err();
+        assert!(matches!(empty_scheme, TransportError::InvalidUrl(ref msg)
+            if msg.contains("missing scheme")));
+    }
+
+    #[test]
+    fn test_abstract_socket_round_trip() {
+        let original = TransportUrl::parse("http::unix//@rigging-app/api/items?page=2").unwrap();
+        assert!(original.is_abstract_socket());
+        assert_eq!(original.unix_socket_path(), Some("@rigging-app"));
+        assert_eq!(original.path(), "/api/items");
+        assert_eq!(original.to_string(), "http::unix//@rigging-app/api/items?page=2");
+        assert_eq!(TransportUrl::parse(&original.to_string()).unwrap(), original);
+
+        let bare = TransportUrl::parse("http::unix//@rigging-app").unwrap();
+        assert_eq!(bare.unix_socket_path(), Some("@rigging-app"));
+        assert_eq!(bare.path(), "/");
+        assert_eq!(TransportUrl::parse(&bare.to_string()).unwrap(), bare);
+
+        let file = TransportUrl::parse("http::unix///tmp/app.sock/api").unwrap();
+        assert!(!file.is_abstract_socket());
+    }
+
+    #[test]
+    fn test_builder_unix() {
+        let url = TransportUrl::builder()
+            .transport(Transport::Unix)
+            .scheme("https")
+            .socket_path("/tmp/app.sock")
+            .path("api/items")
+            .query("page=2")
+            .build()
+            .unwrap();
+        assert_eq!(url.unix_socket_path(), Some("/tmp/app.sock"));
+        assert_eq!(url.scheme(), "http"); // Downgraded, as when parsed
+        assert_eq!(url.path(), "/api/items");
+        assert_eq!(url.to_string(), "https::unix///tmp/app.sock/api/items?page=2");
+        assert_round_trip(&url);
+    }
+
+    #[test]
+    fn test_builder_tcp() {
+        let url = TransportUrl::builder()
+            .transport(Transport::Tcp)
+            .host("127.0.0.1")
+            .port(8080)
+            .path("/status")
+            .build()
+            .unwrap();
+        assert_eq!(url.to_string(), "http::tcp//127.0.0.1:8080/status");
+        assert_round_trip(&url);
+
+        // Without a transport the URL is implicit
+        let url = TransportUrl::builder().scheme("https").host("example.com").build().unwrap();
+        assert!(!url.is_explicit_transport());
+        assert_eq!(url.to_string(), "https://example.com/");
+        assert_round_trip(&url);
+    }
+
+    #[test]
+    fn test_builder_tor() {
+        let url = TransportUrl::builder()
+            .transport(Transport::Tor)
+            .host("example.onion")
+            .path("/index.html")
+            .build()
+            .unwrap();
+        assert_eq!(url.to_string(), "http::tor//example.onion/index.html");
+        assert_round_trip(&url);
+
+        let url = TransportUrl::builder().host("example.onion").build().unwrap();
+        assert_eq!(url.transport(), Transport::Tor);
+        assert_round_trip(&url);
+    }
+
+    #[test]
+    fn test_builder_rejects_invalid_combinations() {
+        let invalid = [
+            TransportUrl::builder().host("example.com").socket_path("/tmp/app.sock"),
+            TransportUrl::builder().transport(Transport::Unix).host("example.com").socket_path("/tmp/app.sock"),
+            TransportUrl::builder().transport(Transport::Unix),
+            TransportUrl::builder().transport(Transport::Tcp),
+            TransportUrl::builder().host("exa mple.com"),
+        ];
+        for builder in invalid {
+            assert!(matches!(builder.clone().build(), Err(TransportError::InvalidUrl(_))), "{:?}", builder);
+        }
+    }
+
+    #[test]
+    fn test_memory_url() {
+        let url = TransportUrl::parse("http::memory//test-server/api").unwrap();
+        assert_eq!(url.transport(), Transport::Memory);
+        assert_eq!(url.host_str(), Some("test-server"));
+        assert_eq!(url.path(), "/api");
+        assert!(url.is_local());
+        assert_eq!(url.to_string(), "http::memory//test-server/api");
+    }
+
     #[test]
     fn test_onion_auto_tor() {
         let url = TransportUrl::parse("http://example.onion/").unwrap();
@@ -371,6 +1194,25 @@ mod tests {
         assert!(!url.is_explicit_transport()); // Auto-detected
     }
 
+    #[test]
+    fn test_i2p_urls() {
+        let url = TransportUrl::parse("http::i2p//foo.b32.i2p/").unwrap();
+        assert_eq!(url.transport(), Transport::I2p);
+        assert!(url.is_explicit_transport());
+        assert_eq!(url.host_str(), Some("foo.b32.i2p"));
+        assert_eq!(url.to_string(), "http::i2p//foo.b32.i2p/");
+        assert!(!url.requires_tor());
+
+        let url = TransportUrl::parse("https::i2p//forum.i2p:8443/latest").unwrap();
+        assert_eq!(url.transport(), Transport::I2p);
+        assert_eq!(url.port_or_default(), 8443);
+        assert_eq!(url.path(), "/latest");
+
+        // Only explicit URLs use I2P; plain .i2p hosts are left to TCP (e.g. an HTTP proxy)
+        let url = TransportUrl::parse("http://forum.i2p/").unwrap();
+        assert_eq!(url.transport(), Transport::Tcp);
+    }
+
     #[test]
     fn test_explicit_tor() {
         let url = TransportUrl::parse("http::tor//example.com/").unwrap();
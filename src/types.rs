@@ -5,10 +5,18 @@
 //! Transport types and error definitions
 
 use std::fmt;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
 use thiserror::Error;
 
 /// Supported transport protocols
+///
+/// New transports may be added, so matches need a wildcard arm.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum Transport {
     /// Standard TCP/IP connection
     Tcp,
@@ -18,22 +26,61 @@ pub enum Transport {
     NamedPipe,
     /// Tor anonymity network (via SOCKS5)
     Tor,
+    /// I2P anonymity network (via a SAMv3 bridge)
+    I2p,
     /// SSH tunnel
     Ssh,
     /// QUIC/HTTP3
     Quic,
+    /// In-process loopback for tests (`test-util` feature)
+    Memory,
 }
 
 impl Transport {
+    /// Every transport, in declaration order
+    pub const ALL: [Transport; 8] = [
+        Transport::Tcp,
+        Transport::Unix,
+        Transport::NamedPipe,
+        Transport::Tor,
+        Transport::I2p,
+        Transport::Ssh,
+        Transport::Quic,
+        Transport::Memory,
+    ];
+
+    /// Check if this build can connect over the transport
+    ///
+    /// Follows the `tcp`, `unix`, `tor`, `i2p` and `test-util` features.
+    /// Named pipes, SSH and QUIC have no connector yet and are never
+    /// compiled in.
+    pub fn is_compiled(&self) -> bool {
+        match self {
+            Transport::Tcp => cfg!(feature = "tcp"),
+            Transport::Unix => cfg!(feature = "unix"),
+            Transport::Tor => cfg!(feature = "tor"),
+            Transport::I2p => cfg!(feature = "i2p"),
+            Transport::Memory => cfg!(feature = "test-util"),
+            Transport::NamedPipe | Transport::Ssh | Transport::Quic => false,
+        }
+    }
+
     /// Parse transport from string
+    ///
+    /// Names and aliases are matched case-insensitively. Returns `None` for
+    /// unknown names; use `Transport::try_from` or `str::parse` to get a
+    /// [`TransportError::InvalidTransport`] carrying the input instead.
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s.to_lowercase().as_str() {
-            "tcp" => Some(Transport::Tcp),
-            "unix" | "uds" => Some(Transport::Unix),
+            "tcp" | "direct" => Some(Transport::Tcp),
+            "unix" | "uds" | "local" => Some(Transport::Unix),
             "pipe" | "namedpipe" => Some(Transport::NamedPipe),
             "tor" | "onion" => Some(Transport::Tor),
+            "i2p" => Some(Transport::I2p),
             "ssh" => Some(Transport::Ssh),
             "quic" | "http3" => Some(Transport::Quic),
+            "memory" | "mem" => Some(Transport::Memory),
             _ => None,
         }
     }
@@ -45,19 +92,68 @@ impl Transport {
             Transport::Unix => "unix",
             Transport::NamedPipe => "pipe",
             Transport::Tor => "tor",
+            Transport::I2p => "i2p",
             Transport::Ssh => "ssh",
             Transport::Quic => "quic",
+            Transport::Memory => "memory",
         }
     }
 
     /// Check if this transport is local-only (no network)
     pub fn is_local(&self) -> bool {
-        matches!(self, Transport::Unix | Transport::NamedPipe)
+        matches!(self, Transport::Unix | Transport::NamedPipe | Transport::Memory)
     }
 
     /// Check if this transport provides anonymity
     pub fn is_anonymous(&self) -> bool {
-        matches!(self, Transport::Tor)
+        matches!(self, Transport::Tor | Transport::I2p)
+    }
+
+    /// Check if this transport needs a separate local daemon to be running
+    ///
+    /// Tor connections go through the Corsair daemon's control socket and
+    /// I2P connections through the router's SAM bridge; every other
+    /// transport connects directly.
+    pub fn requires_daemon(&self) -> bool {
+        match self {
+            Transport::Tor | Transport::I2p => true,
+            Transport::Tcp
+            | Transport::Unix
+            | Transport::NamedPipe
+            | Transport::Ssh
+            | Transport::Quic
+            | Transport::Memory => false,
+        }
+    }
+
+    /// Check if the transport itself encrypts traffic, whatever the URL scheme
+    ///
+    /// Tor, I2P, SSH and QUIC always encrypt. TCP is only encrypted when TLS is
+    /// layered on top (an `https`/`wss` URL), so it reports `false`; see
+    /// `TransportUrl::is_secure`. Local transports never leave the machine
+    /// and are not encrypted.
+    pub fn is_encrypted_by_default(&self) -> bool {
+        match self {
+            Transport::Tor | Transport::I2p | Transport::Ssh | Transport::Quic => true,
+            Transport::Tcp | Transport::Unix | Transport::NamedPipe | Transport::Memory => false,
+        }
+    }
+
+    /// Check if a connection can carry data in both directions at once
+    ///
+    /// Needed for WebSockets and other long-lived streaming protocols. All
+    /// current transports are full-duplex byte streams.
+    pub fn supports_bidirectional_streaming(&self) -> bool {
+        match self {
+            Transport::Tcp
+            | Transport::Unix
+            | Transport::NamedPipe
+            | Transport::Tor
+            | Transport::I2p
+            | Transport::Ssh
+            | Transport::Quic
+            | Transport::Memory => true,
+        }
     }
 
     /// Display name for UI
@@ -67,12 +163,35 @@ impl Transport {
             Transport::Unix => "Unix Socket",
             Transport::NamedPipe => "Named Pipe",
             Transport::Tor => "Tor Network",
+            Transport::I2p => "I2P Network",
             Transport::Ssh => "SSH Tunnel",
             Transport::Quic => "QUIC/HTTP3",
+            Transport::Memory => "In-Memory",
         }
     }
 }
 
+/// Transports this build can connect over (see [`Transport::is_compiled`])
+pub fn available_transports() -> Vec<Transport> {
+    Transport::ALL.into_iter().filter(Transport::is_compiled).collect()
+}
+
+impl TryFrom<&str> for Transport {
+    type Error = TransportError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        Transport::from_str(s).ok_or_else(|| TransportError::InvalidTransport(s.to_string()))
+    }
+}
+
+impl FromStr for Transport {
+    type Err = TransportError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Transport::try_from(s)
+    }
+}
+
 impl fmt::Display for Transport {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.as_str())
@@ -131,14 +250,54 @@ impl TransportChain {
         self.transports.len()
     }
 
+    /// Iterate over the transports, outermost first
+    pub fn iter(&self) -> std::slice::Iter<'_, Transport> {
+        self.transports.iter()
+    }
+
+    /// Iterate over the transports, innermost first
+    pub fn iter_rev(&self) -> std::iter::Rev<std::slice::Iter<'_, Transport>> {
+        self.transports.iter().rev()
+    }
+
+    /// Check if the chain includes `transport`
+    pub fn contains(&self, transport: Transport) -> bool {
+        self.transports.contains(&transport)
+    }
+
+    /// Check that the chain describes a connection that can be built
+    ///
+    /// Rejects empty chains, chains that repeat a transport, and chains where
+    /// a local-only transport (Unix socket, named pipe) is not the innermost
+    /// hop, since nothing can be layered inside a local connection.
+    pub fn validate(&self) -> Result<(), TransportError> {
+        if self.transports.is_empty() {
+            return Err(TransportError::InvalidChain("chain is empty".to_string()));
+        }
+
+        for (i, transport) in self.transports.iter().enumerate() {
+            if self.transports[..i].contains(transport) {
+                return Err(TransportError::InvalidChain(format!(
+                    "{} appears more than once in {}",
+                    transport, self
+                )));
+            }
+            if transport.is_local() && i + 1 < self.transports.len() {
+                return Err(TransportError::InvalidChain(format!(
+                    "{} must be the last hop in {}",
+                    transport, self
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
     /// Parse a chain from a string like "tor+unix" or "ssh+tcp"
     pub fn parse(s: &str) -> Result<Self, TransportError> {
         let transports: Result<Vec<_>, _> = s
             .split('+')
-            .map(|part| {
-                Transport::from_str(part.trim())
-                    .ok_or_else(|| TransportError::InvalidTransport(part.to_string()))
-            })
+            .map(|part| Transport::try_from(part.trim()))
             .collect();
 
         Ok(Self {
@@ -147,6 +306,24 @@ impl TransportChain {
     }
 }
 
+impl IntoIterator for TransportChain {
+    type Item = Transport;
+    type IntoIter = std::vec::IntoIter<Transport>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.transports.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a TransportChain {
+    type Item = &'a Transport;
+    type IntoIter = std::slice::Iter<'a, Transport>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.transports.iter()
+    }
+}
+
 impl Default for TransportChain {
     fn default() -> Self {
         Self::single(Transport::Tcp)
@@ -160,12 +337,45 @@ impl fmt::Display for TransportChain {
     }
 }
 
+/// What a connection is connected to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PeerInfo {
+    /// Remote TCP address (for TLS over TCP too)
+    Tcp(SocketAddr),
+    /// Unix socket path (`@name` for an abstract socket)
+    Unix(PathBuf),
+    /// Host and port reached through Tor
+    Tor { host: String, port: u16 },
+    /// Host and port reached through I2P
+    I2p { host: String, port: u16 },
+    /// Not recorded, e.g. for connections from registered connectors
+    Unknown,
+}
+
+impl fmt::Display for PeerInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PeerInfo::Tcp(addr) => write!(f, "tcp {}", addr),
+            PeerInfo::Unix(path) => write!(f, "unix {}", path.display()),
+            PeerInfo::Tor { host, port } => write!(f, "tor {}:{}", host, port),
+            PeerInfo::I2p { host, port } => write!(f, "i2p {}:{}", host, port),
+            PeerInfo::Unknown => write!(f, "unknown peer"),
+        }
+    }
+}
+
 /// Errors that can occur during transport operations
+///
+/// New variants may be added, so matches need a wildcard arm.
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum TransportError {
     #[error("Invalid transport: {0}")]
     InvalidTransport(String),
 
+    #[error("Invalid transport chain: {0}")]
+    InvalidChain(String),
+
     #[error("Invalid URL: {0}")]
     InvalidUrl(String),
 
@@ -181,6 +391,9 @@ pub enum TransportError {
     #[error("Socket path not found")]
     SocketPathNotFound,
 
+    #[error("Connection refused: {0}")]
+    ConnectionRefused(String),
+
     #[error("Named pipe not found: {0}")]
     NamedPipeNotFound(String),
 
@@ -189,6 +402,175 @@ pub enum TransportError {
 
     #[error("SOCKS5 error: {0}")]
     Socks5Error(String),
+
+    #[error("HTTP proxy error: {0}")]
+    HttpProxyError(String),
+
+    #[error("Authentication failed: {0}")]
+    AuthenticationFailed(String),
+
+    #[error("Invalid header: {0}")]
+    InvalidHeader(String),
+
+    #[error("Timed out: {0}")]
+    Timeout(String),
+
+    #[error("Connection cancelled")]
+    Cancelled,
+
+    #[error("Connection limit reached: {0}")]
+    LimitReached(String),
+
+    #[error("TLS error: {0}")]
+    TlsError(String),
+
+    #[error("Configuration error: {0}")]
+    ConfigError(String),
+
+    #[error("Too many redirects (limit {0})")]
+    TooManyRedirects(usize),
+
+    #[error("Redirect loop at {0}")]
+    RedirectLoop(String),
+
+    #[error("Redirect from {from} to {to} not followed")]
+    RedirectDenied { from: Transport, to: Transport },
+
+    #[error("Transport chain hop {index} ({transport}) failed: {source}")]
+    ChainHopFailed {
+        /// Position of the failing hop in the chain (0 = outermost)
+        index: usize,
+        /// Transport of the failing hop
+        transport: Transport,
+        /// Underlying failure
+        source: Box<TransportError>,
+    },
+
+    #[error("All {} endpoints failed: {}", attempts.len(), format_attempts(attempts))]
+    AllEndpointsFailed {
+        /// Each endpoint tried, in order, with the error it produced
+        attempts: Vec<(String, TransportError)>,
+    },
+
+    #[error("Failed after {attempts} attempt(s): {source}")]
+    RetryFailed {
+        /// Number of connection attempts made
+        attempts: u32,
+        /// Error from the final attempt
+        source: Box<TransportError>,
+    },
+}
+
+impl TransportError {
+    /// The error from the last endpoint attempted, if this is an aggregate
+    pub fn last_attempt(&self) -> Option<&TransportError> {
+        match self {
+            TransportError::AllEndpointsFailed { attempts } => attempts.last().map(|(_, e)| e),
+            _ => None,
+        }
+    }
+
+    /// Whether the same operation might succeed if tried again later
+    ///
+    /// True for failures caused by the peer not being up yet or dropping the
+    /// connection (refused, missing socket, reset, timeouts). Malformed URLs,
+    /// missing features and protocol errors are permanent, as is
+    /// [`TransportError::ConnectionFailed`], which reports failures such as
+    /// a malformed reply or a path that is not a socket.
+    pub fn is_retryable(&self) -> bool {
+        use std::io::ErrorKind;
+
+        match self {
+            TransportError::Io(e) => matches!(
+                e.kind(),
+                ErrorKind::ConnectionRefused
+                    | ErrorKind::ConnectionReset
+                    | ErrorKind::ConnectionAborted
+                    | ErrorKind::NotFound
+                    | ErrorKind::TimedOut
+                    | ErrorKind::Interrupted
+                    | ErrorKind::WouldBlock
+                    | ErrorKind::AddrNotAvailable
+            ),
+            TransportError::SocketPathNotFound
+            | TransportError::ConnectionRefused(_)
+            | TransportError::Timeout(_)
+            | TransportError::LimitReached(_)
+            | TransportError::TorNotAvailable
+            | TransportError::NamedPipeNotFound(_) => true,
+            TransportError::ChainHopFailed { source, .. } => source.is_retryable(),
+            TransportError::AllEndpointsFailed { attempts } => {
+                attempts.iter().any(|(_, e)| e.is_retryable())
+            }
+            _ => false,
+        }
+    }
+}
+
+impl TransportError {
+    /// Copy this error, e.g. to cache a failure and hand it to several callers
+    ///
+    /// Every variant is reproduced exactly except [`TransportError::Io`]:
+    /// `std::io::Error` cannot be cloned, so the copy keeps its
+    /// [`ErrorKind`](std::io::ErrorKind) and message but drops any inner
+    /// error value and OS error code. That is why `TransportError` has no
+    /// `Clone` impl: the loss should be visible where the copy is made.
+    pub fn clone_lossy(&self) -> TransportError {
+        match self {
+            TransportError::InvalidTransport(s) => TransportError::InvalidTransport(s.clone()),
+            TransportError::InvalidChain(s) => TransportError::InvalidChain(s.clone()),
+            TransportError::InvalidUrl(s) => TransportError::InvalidUrl(s.clone()),
+            TransportError::ConnectionFailed(s) => TransportError::ConnectionFailed(s.clone()),
+            TransportError::NotAvailable(s) => TransportError::NotAvailable(s.clone()),
+            TransportError::Io(e) => {
+                TransportError::Io(std::io::Error::new(e.kind(), e.to_string()))
+            }
+            TransportError::SocketPathNotFound => TransportError::SocketPathNotFound,
+            TransportError::ConnectionRefused(s) => TransportError::ConnectionRefused(s.clone()),
+            TransportError::NamedPipeNotFound(s) => TransportError::NamedPipeNotFound(s.clone()),
+            TransportError::TorNotAvailable => TransportError::TorNotAvailable,
+            TransportError::Socks5Error(s) => TransportError::Socks5Error(s.clone()),
+            TransportError::HttpProxyError(s) => TransportError::HttpProxyError(s.clone()),
+            TransportError::AuthenticationFailed(s) => TransportError::AuthenticationFailed(s.clone()),
+            TransportError::InvalidHeader(s) => TransportError::InvalidHeader(s.clone()),
+            TransportError::Timeout(s) => TransportError::Timeout(s.clone()),
+            TransportError::Cancelled => TransportError::Cancelled,
+            TransportError::LimitReached(s) => TransportError::LimitReached(s.clone()),
+            TransportError::TlsError(s) => TransportError::TlsError(s.clone()),
+            TransportError::ConfigError(s) => TransportError::ConfigError(s.clone()),
+            TransportError::TooManyRedirects(max) => TransportError::TooManyRedirects(*max),
+            TransportError::RedirectLoop(s) => TransportError::RedirectLoop(s.clone()),
+            TransportError::RedirectDenied { from, to } => TransportError::RedirectDenied { from: *from, to: *to },
+            TransportError::ChainHopFailed {
+                index,
+                transport,
+                source,
+            } => TransportError::ChainHopFailed {
+                index: *index,
+                transport: *transport,
+                source: Box::new(source.clone_lossy()),
+            },
+            TransportError::AllEndpointsFailed { attempts } => TransportError::AllEndpointsFailed {
+                attempts: attempts
+                    .iter()
+                    .map(|(url, e)| (url.clone(), e.clone_lossy()))
+                    .collect(),
+            },
+            TransportError::RetryFailed { attempts, source } => TransportError::RetryFailed {
+                attempts: *attempts,
+                source: Box::new(source.clone_lossy()),
+            },
+        }
+    }
+}
+
+/// Render fallback attempts as "url (error); url (error)"
+fn format_attempts(attempts: &[(String, TransportError)]) -> String {
+    attempts
+        .iter()
+        .map(|(url, err)| format!("{} ({})", url, err))
+        .collect::<Vec<_>>()
+        .join("; ")
 }
 
 #[cfg(test)]
@@ -204,6 +586,31 @@ mod tests {
         assert_eq!(Transport::from_str("invalid"), None);
     }
 
+    #[test]
+    fn test_transport_aliases() {
+        assert_eq!(Transport::from_str("local"), Some(Transport::Unix));
+        assert_eq!(Transport::from_str("direct"), Some(Transport::Tcp));
+        assert_eq!(Transport::from_str("Onion"), Some(Transport::Tor));
+        assert_eq!(Transport::from_str("NamedPipe"), Some(Transport::NamedPipe));
+        assert_eq!(Transport::from_str("HTTP3"), Some(Transport::Quic));
+        assert_eq!(Transport::from_str("mem"), Some(Transport::Memory));
+        assert_eq!("LOCAL".parse::<Transport>().unwrap(), Transport::Unix);
+        assert_eq!(Transport::try_from("Direct").unwrap(), Transport::Tcp);
+    }
+
+    #[test]
+    fn test_transport_parse_error_carries_input() {
+        let err = Transport::try_from("carrier-pigeon").unwrap_err();
+        assert!(matches!(&err, TransportError::InvalidTransport(s) if s == "carrier-pigeon"));
+        assert_eq!(err.to_string(), "Invalid transport: carrier-pigeon");
+
+        let err = "".parse::<Transport>().unwrap_err();
+        assert!(matches!(err, TransportError::InvalidTransport(s) if s.is_empty()));
+
+        let err = TransportChain::parse("tor+bogus").unwrap_err();
+        assert!(matches!(err, TransportError::InvalidTransport(s) if s == "bogus"));
+    }
+
     #[test]
     fn test_transport_chain_parse() {
         let chain = TransportChain::parse("tor+unix").unwrap();
@@ -218,11 +625,176 @@ mod tests {
         assert_eq!(chain.to_string(), "tor+unix");
     }
 
+    #[test]
+    fn test_transport_chain_iter() {
+        let chain = TransportChain::parse("ssh+tor+unix").unwrap();
+
+        let outward: Vec<_> = chain.iter().copied().collect();
+        assert_eq!(outward, [Transport::Ssh, Transport::Tor, Transport::Unix]);
+        let inward: Vec<_> = chain.iter_rev().copied().collect();
+        assert_eq!(inward, [Transport::Unix, Transport::Tor, Transport::Ssh]);
+
+        let mut hops = Vec::new();
+        for transport in &chain {
+            hops.push(*transport);
+        }
+        assert_eq!(hops, outward);
+        assert_eq!(chain.clone().into_iter().collect::<Vec<_>>(), outward);
+
+        assert!(chain.contains(Transport::Tor));
+        assert!(chain.contains(Transport::Unix));
+        assert!(!chain.contains(Transport::Tcp));
+    }
+
+    #[test]
+    fn test_transport_chain_validate() {
+        assert!(TransportChain::parse("tor+unix").unwrap().validate().is_ok());
+        assert!(TransportChain::parse("ssh+tcp").unwrap().validate().is_ok());
+
+        let duplicate = TransportChain::parse("tor+tor").unwrap().validate();
+        assert!(matches!(duplicate, Err(TransportError::InvalidChain(_))));
+
+        let local_outer = TransportChain::parse("unix+tor").unwrap().validate();
+        assert!(matches!(local_outer, Err(TransportError::InvalidChain(_))));
+
+        let empty = TransportChain::new(Vec::new()).validate();
+        assert!(matches!(empty, Err(TransportError::InvalidChain(_))));
+    }
+
+    #[test]
+    fn test_error_is_retryable() {
+        use std::io::{Error, ErrorKind};
+
+        assert!(TransportError::Io(Error::from(ErrorKind::ConnectionRefused)).is_retryable());
+        assert!(TransportError::Io(Error::from(ErrorKind::NotFound)).is_retryable());
+        assert!(TransportError::Timeout("connect".to_string()).is_retryable());
+        assert!(!TransportError::Io(Error::from(ErrorKind::PermissionDenied)).is_retryable());
+        assert!(!TransportError::InvalidUrl("bad".to_string()).is_retryable());
+        assert!(!TransportError::NotAvailable("tor".to_string()).is_retryable());
+        assert!(!TransportError::ConnectionFailed("Deserialize error".to_string()).is_retryable());
+
+        let hop = TransportError::ChainHopFailed {
+            index: 0,
+            transport: Transport::Unix,
+            source: Box::new(TransportError::Io(Error::from(ErrorKind::ConnectionRefused))),
+        };
+        assert!(hop.is_retryable());
+    }
+
+    #[test]
+    fn test_error_clone_keeps_message_and_kind() {
+        use std::io::{Error, ErrorKind};
+
+        let refused = || TransportError::Io(Error::new(ErrorKind::ConnectionRefused, "refused"));
+        let errors = vec![
+            TransportError::InvalidTransport("x".to_string()),
+            TransportError::InvalidChain("x".to_string()),
+            TransportError::InvalidUrl("x".to_string()),
+            TransportError::ConnectionFailed("x".to_string()),
+            TransportError::NotAvailable("x".to_string()),
+            refused(),
+            TransportError::SocketPathNotFound,
+            TransportError::ConnectionRefused("x".to_string()),
+            TransportError::NamedPipeNotFound("x".to_string()),
+            TransportError::TorNotAvailable,
+            TransportError::Socks5Error("x".to_string()),
+            TransportError::HttpProxyError("x".to_string()),
+            TransportError::AuthenticationFailed("x".to_string()),
+            TransportError::InvalidHeader("x".to_string()),
+            TransportError::Timeout("x".to_string()),
+            TransportError::Cancelled,
+            TransportError::LimitReached("x".to_string()),
+            TransportError::TlsError("x".to_string()),
+            TransportError::ConfigError("x".to_string()),
+            TransportError::TooManyRedirects(5),
+            TransportError::RedirectLoop("x".to_string()),
+            TransportError::RedirectDenied {
+                from: Transport::Tor,
+                to: Transport::Tcp,
+            },
+            TransportError::ChainHopFailed {
+                index: 1,
+                transport: Transport::Tor,
+                source: Box::new(refused()),
+            },
+            TransportError::AllEndpointsFailed {
+                attempts: vec![("http://a/".to_string(), refused())],
+            },
+            TransportError::RetryFailed {
+                attempts: 3,
+                source: Box::new(refused()),
+            },
+        ];
+        for error in &errors {
+            let copy = error.clone_lossy();
+            assert_eq!(copy.to_string(), error.to_string());
+            assert_eq!(copy.is_retryable(), error.is_retryable());
+        }
+
+        match refused().clone_lossy() {
+            TransportError::Io(e) => assert_eq!(e.kind(), ErrorKind::ConnectionRefused),
+            other => panic!("unexpected {:?}", other),
+        }
+        // `?` still converts io errors
+        let convert = || -> Result<(), TransportError> { Err(Error::from(ErrorKind::NotFound))? };
+        let converted = convert();
+        assert!(matches!(converted, Err(TransportError::Io(_))));
+    }
+
+    #[test]
+    fn test_transport_capabilities() {
+        let all = [
+            Transport::Tcp,
+            Transport::Unix,
+            Transport::NamedPipe,
+            Transport::Tor,
+            Transport::I2p,
+            Transport::Ssh,
+            Transport::Quic,
+            Transport::Memory,
+        ];
+        for transport in all {
+            // (local, anonymous, daemon, encrypted, bidirectional); a new
+            // variant will not compile until it is added here
+            let expected = match transport {
+                Transport::Tcp => (false, false, false, false, true),
+                Transport::Unix => (true, false, false, false, true),
+                Transport::NamedPipe => (true, false, false, false, true),
+                Transport::Tor => (false, true, true, true, true),
+                Transport::I2p => (false, true, true, true, true),
+                Transport::Ssh => (false, false, false, true, true),
+                Transport::Quic => (false, false, false, true, true),
+                Transport::Memory => (true, false, false, false, true),
+            };
+            let actual = (
+                transport.is_local(),
+                transport.is_anonymous(),
+                transport.requires_daemon(),
+                transport.is_encrypted_by_default(),
+                transport.supports_bidirectional_streaming(),
+            );
+            assert_eq!(actual, expected, "capabilities of {}", transport);
+        }
+    }
+
+    #[test]
+    fn test_available_transports() {
+        let available = available_transports();
+        #[cfg(feature = "tcp")]
+        assert!(available.contains(&Transport::Tcp));
+        #[cfg(feature = "unix")]
+        assert!(available.contains(&Transport::Unix));
+        assert!(!available.contains(&Transport::Quic));
+        assert!(available.iter().all(Transport::is_compiled));
+        assert_eq!(Transport::Tor.is_compiled(), cfg!(feature = "tor"));
+    }
+
     #[test]
     fn test_transport_is_local() {
         assert!(Transport::Unix.is_local());
         assert!(Transport::NamedPipe.is_local());
         assert!(!Transport::Tcp.is_local());
         assert!(!Transport::Tor.is_local());
+        assert!(!Transport::I2p.is_local());
     }
 }
@@ -7,24 +7,74 @@
 //! Provides a Hyper-compatible connector for making HTTP requests
 //! over Unix domain sockets.
 
-use crate::types::TransportError;
+use crate::connector::{cancellable, AsyncReadWrite, Connector};
+use crate::deadline;
+use crate::metrics::observe_connect;
+use crate::redact::RedactionPolicy;
+use crate::types::{PeerInfo, Transport, TransportError};
+use crate::TransportUrl;
 use futures::future::BoxFuture;
 use hyper::Uri;
+use std::os::unix::io::{FromRawFd, RawFd};
 use std::path::{Path, PathBuf};
 use std::pin::Pin;
+#[cfg(any(test, feature = "test-util"))]
+use std::sync::{Arc, Mutex};
 use std::task::{Context, Poll};
-use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
 use tokio::net::UnixStream;
+use tokio_util::sync::CancellationToken;
 use tower_service::Service;
 
 /// A stream type that wraps Unix socket connections
 pub struct UnixConnection {
     stream: UnixStream,
+    path: Option<PathBuf>,
 }
 
 impl UnixConnection {
     pub fn new(stream: UnixStream) -> Self {
-        Self { stream }
+        // Accepted connections usually have an unnamed peer
+        let path = stream
+            .peer_addr()
+            .ok()
+            .and_then(|addr| addr.as_pathname().map(Path::to_path_buf));
+        Self { stream, path }
+    }
+
+    /// Wrap a connected standard library stream, e.g. one inherited from a parent process
+    ///
+    /// Switches the stream to non-blocking mode, so must be called within a Tokio runtime.
+    pub fn from_std(stream: std::os::unix::net::UnixStream) -> Result<Self, TransportError> {
+        stream.set_nonblocking(true)?;
+        Ok(Self::new(UnixStream::from_std(stream)?))
+    }
+
+    fn with_path(stream: UnixStream, path: &Path) -> Self {
+        Self {
+            stream,
+            path: Some(path.to_path_buf()),
+        }
+    }
+
+    /// Socket path connected to
+    pub fn peer_info(&self) -> PeerInfo {
+        self.path.clone().map_or(PeerInfo::Unknown, PeerInfo::Unix)
+    }
+
+    /// Shut down the write side, signalling end of input to the peer
+    ///
+    /// The read side stays open for the peer's response.
+    pub async fn shutdown_write(&mut self) -> std::io::Result<()> {
+        AsyncWriteExt::shutdown(&mut self.stream).await
+    }
+
+    /// Split into owned read and write halves that can move to separate tasks
+    ///
+    /// Dropping the write half shuts down the write side, as
+    /// [`shutdown_write`](Self::shutdown_write) does.
+    pub fn into_split(self) -> (tokio::net::unix::OwnedReadHalf, tokio::net::unix::OwnedWriteHalf) {
+        self.stream.into_split()
     }
 }
 
@@ -110,6 +160,11 @@ impl hyper::rt::Write for UnixConnection {
 pub struct UnixConnector {
     /// Path to the Unix socket
     socket_path: PathBuf,
+    /// How the socket path appears in connect logs
+    redaction: RedactionPolicy,
+    /// Stream handed to the first connect instead of dialing, shared by clones
+    #[cfg(any(test, feature = "test-util"))]
+    preconnected: Option<Arc<Mutex<Option<UnixStream>>>>,
 }
 
 impl UnixConnector {
@@ -117,21 +172,147 @@ impl UnixConnector {
     pub fn new<P: AsRef<Path>>(socket_path: P) -> Self {
         Self {
             socket_path: socket_path.as_ref().to_path_buf(),
+            redaction: RedactionPolicy::default(),
+            #[cfg(any(test, feature = "test-util"))]
+            preconnected: None,
+        }
+    }
+
+    /// Set how the socket path appears in connect logs
+    pub fn with_redaction(mut self, redaction: RedactionPolicy) -> Self {
+        self.redaction = redaction;
+        self
+    }
+
+    /// A connector that returns `stream` instead of dialing (test-only)
+    ///
+    /// The first [`connect`](Self::connect) or `Service::call`, on this
+    /// connector or any clone, returns `stream`; later ones fail with
+    /// [`TransportError::ConnectionFailed`]. Nothing touches the filesystem,
+    /// so the other end of a [`UnixStream::pair`] can be served in-process.
+    /// Only available in tests and with the `test-util` feature.
+    #[cfg(any(test, feature = "test-util"))]
+    pub fn preconnected(stream: UnixStream) -> Self {
+        Self {
+            socket_path: PathBuf::new(),
+            redaction: RedactionPolicy::default(),
+            preconnected: Some(Arc::new(Mutex::new(Some(stream)))),
         }
     }
 
+    /// Take the preconnected stream, if this connector was given one
+    fn take_preconnected(&self) -> Option<Result<UnixConnection, TransportError>> {
+        #[cfg(any(test, feature = "test-util"))]
+        if let Some(slot) = &self.preconnected {
+            let stream = slot.lock().unwrap_or_else(|e| e.into_inner()).take();
+            return Some(stream.map(UnixConnection::new).ok_or_else(|| {
+                TransportError::ConnectionFailed("preconnected stream already used".to_string())
+            }));
+        }
+        None
+    }
+
     /// Get the socket path
     pub fn socket_path(&self) -> &Path {
         &self.socket_path
     }
 
+    /// Wrap an already-connected socket, such as one passed by socket activation
+    ///
+    /// The descriptor must be a Unix domain stream socket; anything else is
+    /// rejected with [`TransportError::InvalidTransport`] and left open for the
+    /// caller. On success the returned connection owns it.
+    ///
+    /// # Safety
+    ///
+    /// `fd` must be an open descriptor that nothing else owns or will close.
+    pub unsafe fn from_raw_fd(fd: RawFd) -> Result<UnixConnection, TransportError> {
+        check_unix_stream(fd)?;
+        UnixConnection::from_std(std::os::unix::net::UnixStream::from_raw_fd(fd))
+    }
+
     /// Connect to the Unix socket
     pub async fn connect(&self) -> Result<UnixConnection, TransportError> {
-        let stream = UnixStream::connect(&self.socket_path)
-            .await
-            .map_err(TransportError::Io)?;
+        observe_connect(Transport::Unix, async {
+            if let Some(result) = self.take_preconnected() {
+                return result;
+            }
+            self.redaction.log_socket_connect(&self.socket_path);
+            check_socket_type(&self.socket_path)?;
+            let stream = connect_stream(&self.socket_path)
+                .await
+                .map_err(|e| connect_error(&self.socket_path, e))?;
+
+            Ok(UnixConnection::with_path(stream, &self.socket_path))
+        })
+        .await
+    }
+
+    /// Connect to the Unix socket, giving up when `token` is cancelled
+    pub async fn connect_with_cancel(
+        &self,
+        token: CancellationToken,
+    ) -> Result<UnixConnection, TransportError> {
+        cancellable(&token, self.connect()).await
+    }
+}
+
+/// Connect to a socket file, or on Linux to an `@name` abstract socket
+async fn connect_stream(path: &Path) -> std::io::Result<UnixStream> {
+    #[cfg(target_os = "linux")]
+    if let Some(name) = path.to_str().and_then(|p| p.strip_prefix('@')) {
+        use std::os::linux::net::SocketAddrExt;
+
+        let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+        let stream = std::os::unix::net::UnixStream::connect_addr(&addr)?;
+        stream.set_nonblocking(true)?;
+        return UnixStream::from_std(stream);
+    }
+    UnixStream::connect(path).await
+}
 
-        Ok(UnixConnection::new(stream))
+/// Reject a path that exists but is not a socket
+///
+/// Connecting to a directory or regular file fails with a bare "connection
+/// refused", which would be mistaken for a stale socket. Missing paths are
+/// left to the connect to report, and abstract socket names have no file.
+fn check_socket_type(path: &Path) -> Result<(), TransportError> {
+    use std::os::unix::fs::FileTypeExt;
+
+    #[cfg(target_os = "linux")]
+    if path.to_str().is_some_and(|p| p.starts_with('@')) {
+        return Ok(());
+    }
+    let Ok(metadata) = std::fs::metadata(path) else {
+        return Ok(());
+    };
+    let file_type = metadata.file_type();
+    let kind = if file_type.is_socket() {
+        return Ok(());
+    } else if file_type.is_dir() {
+        "a directory"
+    } else if file_type.is_file() {
+        "a regular file"
+    } else if file_type.is_fifo() {
+        "a named pipe (FIFO)"
+    } else {
+        "a device or other special file"
+    };
+    Err(TransportError::ConnectionFailed(format!("{} is {}, not a socket", path.display(), kind)))
+}
+
+/// Map a failed connect to `path` onto a typed error
+///
+/// A missing socket file means the server has not started; a socket file
+/// that refuses connections was left behind by a server that is gone.
+fn connect_error(path: &Path, e: std::io::Error) -> TransportError {
+    match e.kind() {
+        std::io::ErrorKind::NotFound => TransportError::SocketPathNotFound,
+        std::io::ErrorKind::ConnectionRefused if path.exists() => TransportError::ConnectionRefused(
+            format!("stale socket {} (nothing is listening)", path.display()),
+        ),
+        std::io::ErrorKind::ConnectionRefused => TransportError::SocketPathNotFound,
+        _ => TransportError::Io(e),
     }
 }
 
@@ -145,13 +326,23 @@ impl Service<Uri> for UnixConnector {
     }
 
     fn call(&mut self, _uri: Uri) -> Self::Future {
-        let socket_path = self.socket_path.clone();
-        Box::pin(async move {
-            let stream = UnixStream::connect(&socket_path)
-                .await
-                .map_err(TransportError::Io)?;
+        let connector = self.clone();
+        deadline::bounded(async move { connector.connect().await })
+    }
+}
 
-            Ok(UnixConnection::new(stream))
+impl Connector for UnixConnector {
+    fn allows_url(&self, url: &TransportUrl) -> bool {
+        url.transport() == Transport::Unix
+    }
+
+    fn connect<'a>(
+        &'a self,
+        _url: &'a TransportUrl,
+    ) -> BoxFuture<'a, Result<Box<dyn AsyncReadWrite>, TransportError>> {
+        Box::pin(async move {
+            let conn = UnixConnector::connect(self).await?;
+            Ok(Box::new(conn) as Box<dyn AsyncReadWrite>)
         })
     }
 }
@@ -160,11 +351,16 @@ impl Service<Uri> for UnixConnector {
 ///
 /// Maps hostnames to Unix socket paths for transparent routing.
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct SocketMapping {
     /// Default socket directory
     pub socket_dir: Option<PathBuf>,
     /// Explicit hostname to socket path mappings
     mappings: std::collections::HashMap<String, PathBuf>,
+    /// File the mapping was loaded from, for [`reload`](Self::reload)
+    #[cfg_attr(feature = "serde", serde(skip))]
+    source: Option<PathBuf>,
 }
 
 impl SocketMapping {
@@ -184,6 +380,14 @@ impl SocketMapping {
         self.mappings.insert(host.into(), path.as_ref().to_path_buf());
     }
 
+    /// Get the explicitly mapped socket path for a hostname
+    ///
+    /// Unlike [`get_socket_path`](Self::get_socket_path), this does not fall
+    /// back to the default socket directory.
+    pub fn mapped_path(&self, host: &str) -> Option<&Path> {
+        self.mappings.get(host).map(PathBuf::as_path)
+    }
+
     /// Get socket path for a hostname
     pub fn get_socket_path(&self, host: &str) -> Option<PathBuf> {
         // Check explicit mappings first
@@ -207,6 +411,88 @@ impl SocketMapping {
         }
         mapping
     }
+
+    /// Load mappings from a TOML file
+    ///
+    /// Each `host = "/path/to.sock"` entry maps a hostname; the optional
+    /// `socket_dir` key sets the default socket directory:
+    ///
+    /// ```toml
+    /// socket_dir = "/run/myapp"
+    /// api = "/run/api/api.sock"
+    /// "db.internal" = "/var/run/db.sock"
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn from_toml_file<P: AsRef<Path>>(path: P) -> Result<Self, TransportError> {
+        let mut mapping = Self::new();
+        mapping.source = Some(path.as_ref().to_path_buf());
+        mapping.reload()?;
+        Ok(mapping)
+    }
+
+    /// Re-read the file this mapping was loaded from
+    ///
+    /// On error the current mappings are left unchanged.
+    #[cfg(feature = "serde")]
+    pub fn reload(&mut self) -> Result<(), TransportError> {
+        let path = self.source.clone().ok_or_else(|| {
+            TransportError::ConfigError("socket mapping was not loaded from a file".to_string())
+        })?;
+        let invalid = |message: String| TransportError::ConfigError(format!("{}: {}", path.display(), message));
+
+        let contents = std::fs::read_to_string(&path).map_err(|e| invalid(e.to_string()))?;
+        let table: toml::Table = contents.parse().map_err(|e: toml::de::Error| invalid(e.to_string()))?;
+
+        let mut socket_dir = None;
+        let mut mappings = std::collections::HashMap::new();
+        for (key, value) in table {
+            let value = value
+                .as_str()
+                .ok_or_else(|| invalid(format!("`{}` must be a path string", key)))?;
+            if key == "socket_dir" {
+                socket_dir = Some(PathBuf::from(value));
+            } else {
+                mappings.insert(key, PathBuf::from(value));
+            }
+        }
+
+        self.socket_dir = socket_dir;
+        self.mappings = mappings;
+        Ok(())
+    }
+}
+
+/// Check that `fd` is a Unix domain stream socket
+fn check_unix_stream(fd: RawFd) -> Result<(), TransportError> {
+    let mut sock_type: libc::c_int = 0;
+    let mut len = std::mem::size_of::<libc::c_int>() as libc::socklen_t;
+    // SAFETY: sock_type and len are valid for writes of the sizes given
+    let rc = unsafe {
+        libc::getsockopt(fd, libc::SOL_SOCKET, libc::SO_TYPE, (&mut sock_type as *mut libc::c_int).cast(), &mut len)
+    };
+    if rc != 0 {
+        return Err(TransportError::InvalidTransport(format!(
+            "fd {} is not a socket: {}",
+            fd,
+            std::io::Error::last_os_error()
+        )));
+    }
+    if sock_type != libc::SOCK_STREAM {
+        return Err(TransportError::InvalidTransport(format!("fd {} is not a stream socket", fd)));
+    }
+
+    // SAFETY: an all-zero sockaddr_storage is a valid value
+    let mut addr: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+    let mut len = std::mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+    // SAFETY: addr and len are valid for writes of the sizes given
+    let rc = unsafe { libc::getsockname(fd, (&mut addr as *mut libc::sockaddr_storage).cast(), &mut len) };
+    if rc != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    if libc::c_int::from(addr.ss_family) != libc::AF_UNIX {
+        return Err(TransportError::InvalidTransport(format!("fd {} is not a Unix domain socket", fd)));
+    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -233,6 +519,246 @@ mod tests {
         );
     }
 
+    fn test_socket() -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("app.sock");
+        (dir, socket)
+    }
+
+    #[tokio::test]
+    async fn test_from_raw_fd_wraps_connected_socket() {
+        use std::io::{Read, Write};
+        use std::os::unix::io::IntoRawFd;
+        use tokio::io::AsyncReadExt;
+
+        let (ours, mut peer) = std::os::unix::net::UnixStream::pair().unwrap();
+        let mut conn = unsafe { UnixConnector::from_raw_fd(ours.into_raw_fd()) }.unwrap();
+        assert_eq!(conn.peer_info(), PeerInfo::Unknown);
+
+        conn.write_all(b"ping").await.unwrap();
+        let mut buf = [0u8; 4];
+        peer.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"ping");
+
+        peer.write_all(b"pong").unwrap();
+        conn.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"pong");
+    }
+
+    #[tokio::test]
+    async fn test_from_raw_fd_rejects_non_stream_sockets() {
+        use std::os::unix::io::AsRawFd;
+
+        let (datagram, _peer) = std::os::unix::net::UnixDatagram::pair().unwrap();
+        let err = unsafe { UnixConnector::from_raw_fd(datagram.as_raw_fd()) }.unwrap_err();
+        assert!(matches!(err, TransportError::InvalidTransport(ref msg) if msg.contains("not a stream socket")));
+
+        let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let err = unsafe { UnixConnector::from_raw_fd(tcp.as_raw_fd()) }.unwrap_err();
+        assert!(matches!(err, TransportError::InvalidTransport(ref msg) if msg.contains("not a Unix domain socket")));
+
+        let file = std::fs::File::open("/dev/null").unwrap();
+        let err = unsafe { UnixConnector::from_raw_fd(file.as_raw_fd()) }.unwrap_err();
+        assert!(matches!(err, TransportError::InvalidTransport(ref msg) if msg.contains("not a socket")));
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_write_keeps_read_side() {
+        use tokio::io::AsyncReadExt;
+
+        let (ours, mut peer) = UnixStream::pair().unwrap();
+        let mut conn = UnixConnection::new(ours);
+        conn.write_all(b"request").await.unwrap();
+        conn.shutdown_write().await.unwrap();
+
+        // The peer sees end of input, then answers
+        let mut request = Vec::new();
+        peer.read_to_end(&mut request).await.unwrap();
+        assert_eq!(request, b"request");
+        peer.write_all(b"response").await.unwrap();
+        drop(peer);
+
+        let mut response = Vec::new();
+        conn.read_to_end(&mut response).await.unwrap();
+        assert_eq!(response, b"response");
+    }
+
+    #[tokio::test]
+    async fn test_into_split_full_duplex() {
+        use tokio::io::AsyncReadExt;
+
+        let (ours, peer) = UnixStream::pair().unwrap();
+        let (mut read_half, mut write_half) = UnixConnection::new(ours).into_split();
+        let (mut peer_read, mut peer_write) = peer.into_split();
+
+        // Each direction runs in its own task
+        let writer = tokio::spawn(async move {
+            write_half.write_all(&[1u8; 64 * 1024]).await.unwrap();
+        });
+        let reader = tokio::spawn(async move {
+            let mut received = Vec::new();
+            read_half.read_to_end(&mut received).await.unwrap();
+            received
+        });
+
+        let mut upstream = vec![0u8; 64 * 1024];
+        let (read, ()) = tokio::join!(
+            peer_read.read_exact(&mut upstream),
+            async {
+                peer_write.write_all(&[2u8; 64 * 1024]).await.unwrap();
+                peer_write.shutdown().await.unwrap();
+            }
+        );
+        read.unwrap();
+        assert!(upstream.iter().all(|&b| b == 1));
+
+        writer.await.unwrap();
+        let received = reader.await.unwrap();
+        assert_eq!(received.len(), 64 * 1024);
+        assert!(received.iter().all(|&b| b == 2));
+    }
+
+    #[tokio::test]
+    async fn test_missing_socket() {
+        let (_dir, path) = test_socket();
+        let connector = UnixConnector::new(path);
+        let err = connector.connect().await.err().unwrap();
+        assert!(matches!(err, TransportError::SocketPathNotFound));
+        assert!(err.is_retryable());
+    }
+
+    #[tokio::test]
+    async fn test_stale_socket() {
+        let (_dir, path) = test_socket();
+        // Dropping a std listener leaves its socket file behind
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        assert!(path.exists());
+
+        let connector = UnixConnector::new(&path);
+        let err = connector.connect().await.err().unwrap();
+        assert!(matches!(err, TransportError::ConnectionRefused(ref msg) if msg.contains("stale socket")));
+        assert!(err.is_retryable());
+    }
+
+    #[tokio::test]
+    async fn test_wrong_file_type() {
+        let (_dir, path) = test_socket();
+        let dir = path.parent().unwrap();
+
+        let err = UnixConnector::new(dir).connect().await.err().unwrap();
+        assert!(
+            matches!(err, TransportError::ConnectionFailed(ref msg) if msg.ends_with("is a directory, not a socket")),
+            "{:?}",
+            err
+        );
+        // A typo in the path will not fix itself
+        assert!(!err.is_retryable());
+
+        std::fs::write(&path, b"not a socket").unwrap();
+        let mut connector = UnixConnector::new(&path);
+        let err = connector.call(Uri::from_static("http://localhost/")).await.err().unwrap();
+        assert!(
+            matches!(err, TransportError::ConnectionFailed(ref msg) if msg.ends_with("is a regular file, not a socket")),
+            "{:?}",
+            err
+        );
+        assert!(!err.is_retryable());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_socket_mapping_from_toml_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sockets.toml");
+        std::fs::write(
+            &path,
+            "socket_dir = \"/run/apps\"\napi = \"/run/api/api.sock\"\n\"db.internal\" = \"/var/run/db.sock\"\n",
+        )
+        .unwrap();
+
+        let mut mapping = SocketMapping::from_toml_file(&path).unwrap();
+        assert_eq!(mapping.get_socket_path("api"), Some(PathBuf::from("/run/api/api.sock")));
+        assert_eq!(mapping.get_socket_path("db.internal"), Some(PathBuf::from("/var/run/db.sock")));
+        assert_eq!(mapping.get_socket_path("web"), Some(PathBuf::from("/run/apps/web.sock")));
+        assert_eq!(mapping.mapped_path("web"), None);
+
+        std::fs::write(&path, "api = \"/tmp/api.sock\"\n").unwrap();
+        mapping.reload().unwrap();
+        assert_eq!(mapping.get_socket_path("api"), Some(PathBuf::from("/tmp/api.sock")));
+        assert_eq!(mapping.get_socket_path("db.internal"), None);
+        assert_eq!(mapping.socket_dir, None);
+
+        // A bad edit keeps the last good mappings
+        std::fs::write(&path, "api = 3\n").unwrap();
+        assert!(matches!(mapping.reload(), Err(TransportError::ConfigError(_))));
+        assert_eq!(mapping.get_socket_path("api"), Some(PathBuf::from("/tmp/api.sock")));
+
+        assert!(SocketMapping::new().reload().is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_abstract_socket() {
+        use std::os::linux::net::SocketAddrExt;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let name = format!("rigging-test-{}", std::process::id());
+        let addr = std::os::unix::net::SocketAddr::from_abstract_name(&name).unwrap();
+        let listener = std::os::unix::net::UnixListener::bind_addr(&addr).unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let listener = tokio::net::UnixListener::from_std(listener).unwrap();
+
+        let url = TransportUrl::parse(&format!("http::unix//@{}/", name)).unwrap();
+        let connector = UnixConnector::new(url.unix_socket_path().unwrap());
+        let (conn, accepted) = tokio::join!(connector.connect(), listener.accept());
+        let mut conn = conn.ok().unwrap();
+        let (mut peer, _) = accepted.unwrap();
+
+        conn.write_all(b"ping").await.unwrap();
+        let mut buf = [0u8; 4];
+        peer.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+
+        drop(listener);
+        let err = connector.connect().await.err().unwrap();
+        assert!(matches!(err, TransportError::SocketPathNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_preconnected_pair_drives_hyper_client() {
+        use http_body_util::{BodyExt, Empty, Full};
+        use hyper::body::Bytes;
+
+        let (client_end, server_end) = UnixStream::pair().unwrap();
+        tokio::spawn(async move {
+            let service = hyper::service::service_fn(|req: hyper::Request<hyper::body::Incoming>| async move {
+                let body = format!("{} {}", req.method(), req.uri().path());
+                Ok::<_, std::convert::Infallible>(hyper::Response::new(Full::new(Bytes::from(body))))
+            });
+            let io = hyper_util::rt::TokioIo::new(server_end);
+            let _ = hyper::server::conn::http1::Builder::new().serve_connection(io, service).await;
+        });
+
+        let mut connector = UnixConnector::preconnected(client_end);
+        let conn = connector.clone().call(Uri::from_static("http://localhost/")).await.unwrap();
+        let (mut sender, connection) = hyper::client::conn::http1::handshake(conn).await.unwrap();
+        tokio::spawn(connection);
+
+        let request = hyper::Request::get("/api/items")
+            .header(hyper::header::HOST, "localhost")
+            .body(Empty::<Bytes>::new())
+            .unwrap();
+        let response = sender.send_request(request).await.unwrap();
+        assert_eq!(response.status(), hyper::StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(&body[..], b"GET /api/items");
+
+        // The stream is handed out once, to whichever clone asks first
+        let err = connector.call(Uri::from_static("http://localhost/")).await.err().unwrap();
+        assert!(matches!(err, TransportError::ConnectionFailed(_)));
+        assert!(connector.connect().await.is_err());
+    }
+
     #[test]
     fn test_socket_mapping_from_env() {
         let mapping = SocketMapping::from_env_string("app1:/tmp/app1.sock,app2:/var/run/app2.sock");
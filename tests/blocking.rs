@@ -0,0 +1,45 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Integration test: blocking GET over a Unix socket
+
+#![cfg(all(feature = "blocking", feature = "unix"))]
+
+use http_body_util::Full;
+use hyper::body::Bytes;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use std::convert::Infallible;
+
+#[test]
+fn test_blocking_get_over_unix_socket() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("app.sock");
+
+    // The server runs on its own runtime; the caller has none
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let listener = runtime.block_on(async { tokio::net::UnixListener::bind(&socket) }).unwrap();
+    runtime.spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let service = service_fn(|req: Request<hyper::body::Incoming>| async move {
+            let query = req.uri().query().unwrap_or("");
+            let body = format!("{{\"path\":\"{}\",\"query\":\"{}\"}}", req.uri().path(), query);
+            Ok::<_, Infallible>(Response::new(Full::new(Bytes::from(body))))
+        });
+        http1::Builder::new()
+            .serve_connection(TokioIo::new(stream), service)
+            .await
+            .unwrap();
+    });
+
+    let url = format!("http::unix//{}/api/status?verbose=1", socket.display());
+    let (status, body) = rigging::blocking::get(&url).unwrap();
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, br#"{"path":"/api/status","query":"verbose=1"}"#);
+
+    let missing = format!("http::unix//{}/", dir.path().join("missing.sock").display());
+    assert!(rigging::blocking::get(&missing).is_err());
+}
@@ -0,0 +1,137 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Integration test: one-shot async GETs over Unix sockets and TCP
+
+#![cfg(all(feature = "unix", feature = "tcp"))]
+
+use http_body_util::Full;
+use hyper::body::Bytes;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use rigging::composed::ComposedConnector;
+use rigging::types::TransportError;
+use std::convert::Infallible;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// Answer with the request's Host header and path; `/large` sends 64 KiB
+async fn serve<S>(stream: S)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let service = service_fn(|req: Request<hyper::body::Incoming>| async move {
+        let response = if req.uri().path() == "/large" {
+            Response::new(Full::new(Bytes::from(vec![b'x'; 64 * 1024])))
+        } else {
+            let host = req.headers().get(hyper::header::HOST).and_then(|h| h.to_str().ok()).unwrap_or("");
+            let body = format!("{} {}", host, req.uri());
+            Response::builder()
+                .status(StatusCode::ACCEPTED)
+                .header("x-served-by", "rigging-test")
+                .body(Full::new(Bytes::from(body)))
+                .unwrap()
+        };
+        Ok::<_, Infallible>(response)
+    });
+    let _ = http1::Builder::new().serve_connection(TokioIo::new(stream), service).await;
+}
+
+#[tokio::test]
+async fn test_fetch_over_unix_socket() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("app.sock");
+    let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(serve(stream));
+        }
+    });
+
+    let connector = ComposedConnector::new();
+    let url = format!("http::unix//{}/api/status?verbose=1", socket.display());
+    let (status, headers, body) = connector.fetch(&url).await.unwrap();
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert_eq!(headers["x-served-by"], "rigging-test");
+    assert_eq!(body, b"localhost /api/status?verbose=1");
+
+    // The body limit applies however the length is known
+    let large = format!("http::unix//{}/large", socket.display());
+    assert_eq!(connector.fetch(&large).await.unwrap().2.len(), 64 * 1024);
+    let err = connector.fetch_with_limit(&large, 1024).await.unwrap_err();
+    assert!(matches!(err, TransportError::ConnectionFailed(ref msg) if msg.contains("exceeds 1024 bytes")));
+}
+
+#[tokio::test]
+async fn test_fetch_over_tcp() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(serve(stream));
+        }
+    });
+
+    let connector = ComposedConnector::new();
+    let (status, _, body) = connector.fetch(&format!("http://127.0.0.1:{}/items", port)).await.unwrap();
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert_eq!(body, format!("127.0.0.1:{} /items", port).as_bytes());
+
+    let err = connector.fetch_with_limit(&format!("http://127.0.0.1:{}/large", port), 10).await.unwrap_err();
+    assert!(matches!(err, TransportError::ConnectionFailed(_)));
+}
+
+#[tokio::test]
+async fn test_send_streams_large_body_over_unix_socket() {
+    use http_body_util::BodyExt;
+    use hyper::header::{CONTENT_LENGTH, TRANSFER_ENCODING};
+    use hyper::{HeaderMap, Method};
+
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("echo.sock");
+    let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+
+    // Echo the body back as it arrives, reporting how it was framed
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let service = service_fn(|req: Request<hyper::body::Incoming>| async move {
+                let framing = match req.headers().get(TRANSFER_ENCODING) {
+                    Some(encoding) => encoding.to_str().unwrap().to_string(),
+                    None => format!("length {}", req.headers()[CONTENT_LENGTH].to_str().unwrap()),
+                };
+                let response = Response::builder()
+                    .header("x-method", req.method().as_str())
+                    .header("x-framing", framing)
+                    .body(req.into_body())
+                    .unwrap();
+                Ok::<_, Infallible>(response)
+            });
+            tokio::spawn(http1::Builder::new().serve_connection(TokioIo::new(stream), service));
+        }
+    });
+
+    let payload: Vec<u8> = (0..5 * 1024 * 1024u32).map(|i| (i % 251) as u8).collect();
+    let connector = ComposedConnector::new();
+    let url = format!("http::unix//{}/upload", socket.display());
+
+    let response = connector
+        .send(&url, Method::POST, HeaderMap::new(), std::io::Cursor::new(payload.clone()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["x-method"], "POST");
+    assert_eq!(response.headers()["x-framing"], "chunked");
+    let echoed = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(echoed.len(), payload.len());
+    assert!(echoed == payload);
+
+    // A known length is sent as is, without chunking
+    let mut headers = HeaderMap::new();
+    headers.insert(CONTENT_LENGTH, "11".parse().unwrap());
+    let response = connector.send(&url, Method::PUT, headers, &b"hello world"[..]).await.unwrap();
+    assert_eq!(response.headers()["x-framing"], "length 11");
+    let echoed = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(&echoed[..], b"hello world");
+}
@@ -0,0 +1,56 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Integration test: HTTP/2 with prior knowledge over a Unix socket
+
+#![cfg(all(feature = "http2", feature = "unix"))]
+
+use http_body_util::{BodyExt, Empty, Full};
+use hyper::body::Bytes;
+use hyper::server::conn::http2;
+use hyper::service::service_fn;
+use hyper::{Request, Response, Version};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use rigging::composed::ComposedConnector;
+use rigging::TransportUrl;
+use std::convert::Infallible;
+use tokio::net::UnixListener;
+
+#[tokio::test]
+async fn test_h2_prior_knowledge_over_unix_socket() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("rpc.sock");
+    let listener = UnixListener::bind(&socket).unwrap();
+
+    // Only one connection is accepted, so every request must share it
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let service = service_fn(|req: Request<hyper::body::Incoming>| async move {
+            let body = format!("{:?} {}", req.version(), req.uri().path());
+            Ok::<_, Infallible>(Response::new(Full::new(Bytes::from(body))))
+        });
+        http2::Builder::new(TokioExecutor::new())
+            .serve_connection(TokioIo::new(stream), service)
+            .await
+            .unwrap();
+    });
+
+    let url = TransportUrl::parse(&format!("http::unix//{}/", socket.display())).unwrap();
+    let sender = ComposedConnector::new().connect_h2::<Empty<Bytes>>(&url).await.unwrap();
+
+    let requests = ["/a", "/b", "/c"].map(|path| {
+        let mut sender = sender.clone();
+        async move {
+            let request = Request::get(format!("http://localhost{}", path))
+                .body(Empty::new())
+                .unwrap();
+            let response = sender.send_request(request).await.unwrap();
+            assert_eq!(response.version(), Version::HTTP_2);
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            String::from_utf8(body.to_vec()).unwrap()
+        }
+    });
+    let bodies = futures::future::join_all(requests).await;
+    assert_eq!(bodies, ["HTTP/2.0 /a", "HTTP/2.0 /b", "HTTP/2.0 /c"]);
+}
@@ -0,0 +1,49 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Integration test: hyper client over a Unix socket transport
+
+#![cfg(feature = "unix")]
+
+use http_body_util::{BodyExt, Full};
+use hyper::body::Bytes;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Request, Response};
+use hyper_util::rt::TokioIo;
+use rigging::composed::{ComposedConnector, HyperClient};
+use rigging::TransportUrl;
+use std::convert::Infallible;
+use tokio::net::UnixListener;
+
+#[tokio::test]
+async fn test_get_over_unix_socket() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("app.sock");
+    let listener = UnixListener::bind(&socket).unwrap();
+
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let service = service_fn(|req: Request<hyper::body::Incoming>| async move {
+            let body = format!("hello from {}", req.uri().path());
+            Ok::<_, Infallible>(Response::new(Full::new(Bytes::from(body))))
+        });
+        http1::Builder::new()
+            .serve_connection(TokioIo::new(stream), service)
+            .await
+            .unwrap();
+    });
+
+    let url = TransportUrl::parse(&format!("http::unix//{}/", socket.display())).unwrap();
+    let client: HyperClient<Full<Bytes>> = ComposedConnector::new().into_hyper_client(&url).unwrap();
+
+    let response = client
+        .get("http://localhost/greeting".parse().unwrap())
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(&body[..], b"hello from /greeting");
+}
@@ -0,0 +1,212 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Integration test: following redirects across transports
+
+#![cfg(all(feature = "unix", feature = "tcp"))]
+
+use http_body_util::{BodyExt, Full};
+use hyper::body::Bytes;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use rigging::composed::ComposedConnector;
+use rigging::redirect::{follow_redirects, follow_redirects_with_options, RedirectOptions, TRANSPORT_LOCATION};
+use rigging::{Transport, TransportError, TransportUrl};
+use std::convert::Infallible;
+use std::path::Path;
+
+/// Answer `routes` (path, status, header, target) and 200 with the path otherwise
+fn respond(path: &str, routes: &[(&str, StatusCode, &str, String)]) -> Response<Full<Bytes>> {
+    match routes.iter().find(|(from, ..)| *from == path) {
+        Some((_, status, header, target)) => Response::builder()
+            .status(*status)
+            .header(*header, target.as_str())
+            .body(Full::default())
+            .unwrap(),
+        None => Response::new(Full::new(Bytes::from(format!("reached {}", path)))),
+    }
+}
+
+/// Serve `routes` over TCP, returning the listener's address
+async fn serve_tcp(routes: Vec<(&'static str, StatusCode, &'static str, String)>) -> std::net::SocketAddr {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let routes = routes.clone();
+            let service = service_fn(move |req: Request<hyper::body::Incoming>| {
+                let response = respond(req.uri().path(), &routes);
+                async move { Ok::<_, Infallible>(response) }
+            });
+            tokio::spawn(http1::Builder::new().serve_connection(TokioIo::new(stream), service));
+        }
+    });
+    addr
+}
+
+async fn serve_unix(socket: &Path, routes: Vec<(&'static str, StatusCode, &'static str, String)>) {
+    let listener = tokio::net::UnixListener::bind(socket).unwrap();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let routes = routes.clone();
+            let service = service_fn(move |req: Request<hyper::body::Incoming>| {
+                let response = respond(req.uri().path(), &routes);
+                async move { Ok::<_, Infallible>(response) }
+            });
+            tokio::spawn(http1::Builder::new().serve_connection(TokioIo::new(stream), service));
+        }
+    });
+}
+
+async fn body(response: Response<hyper::body::Incoming>) -> String {
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    String::from_utf8(bytes.to_vec()).unwrap()
+}
+
+#[tokio::test]
+async fn test_same_transport_redirect() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("app.sock");
+    serve_unix(
+        &socket,
+        vec![
+            ("/start", StatusCode::FOUND, "location", "/middle?step=1".to_string()),
+            ("/middle", StatusCode::SEE_OTHER, "location", "http://localhost/end".to_string()),
+            ("/loop-a", StatusCode::FOUND, "location", "/loop-b".to_string()),
+            ("/loop-b", StatusCode::FOUND, "location", "/loop-a".to_string()),
+        ],
+    )
+    .await;
+    let url = |path: &str| TransportUrl::parse(&format!("http::unix//{}{}", socket.display(), path)).unwrap();
+    let connector = ComposedConnector::new();
+
+    let (last, response) = follow_redirects(&connector, url("/start"), 5).await.unwrap();
+    assert_eq!(last, url("/end"));
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(body(response).await, "reached /end");
+
+    let err = follow_redirects(&connector, url("/start"), 1).await.unwrap_err();
+    assert!(matches!(err, TransportError::TooManyRedirects(1)));
+
+    let err = follow_redirects(&connector, url("/loop-a"), 10).await.unwrap_err();
+    assert!(matches!(err, TransportError::RedirectLoop(ref at) if at.ends_with("/loop-a")), "{}", err);
+}
+
+#[tokio::test]
+async fn test_cross_transport_redirect() {
+    let dir = tempfile::tempdir().unwrap();
+    let (front, back) = (dir.path().join("front.sock"), dir.path().join("back.sock"));
+
+    let tcp = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let tcp_url = format!("http://{}/landing", tcp.local_addr().unwrap());
+    tokio::spawn(async move {
+        let (stream, _) = tcp.accept().await.unwrap();
+        let service = service_fn(|req: Request<hyper::body::Incoming>| async move {
+            Ok::<_, Infallible>(respond(req.uri().path(), &[]))
+        });
+        http1::Builder::new().serve_connection(TokioIo::new(stream), service).await.unwrap();
+    });
+
+    serve_unix(
+        &front,
+        vec![
+            ("/to-tcp", StatusCode::FOUND, "location", tcp_url.clone()),
+            (
+                "/to-socket",
+                StatusCode::TEMPORARY_REDIRECT,
+                TRANSPORT_LOCATION,
+                format!("http::unix//{}/backend", back.display()),
+            ),
+        ],
+    )
+    .await;
+    serve_unix(&back, Vec::new()).await;
+    let front_url = |path: &str| TransportUrl::parse(&format!("http::unix//{}{}", front.display(), path)).unwrap();
+    let connector = ComposedConnector::new();
+
+    let (last, response) = follow_redirects(&connector, front_url("/to-tcp"), 5).await.unwrap();
+    assert_eq!(last.transport(), Transport::Tcp);
+    assert_eq!(last.as_str(), tcp_url);
+    assert_eq!(body(response).await, "reached /landing");
+
+    let (last, response) = follow_redirects(&connector, front_url("/to-socket"), 5).await.unwrap();
+    assert_eq!(last.unix_socket_path(), Some(back.to_str().unwrap()));
+    assert_eq!(body(response).await, "reached /backend");
+}
+
+#[tokio::test]
+async fn test_redirect_from_network_to_socket_is_refused() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("admin.sock");
+    serve_unix(&socket, Vec::new()).await;
+    let addr = serve_tcp(vec![(
+        "/bounce",
+        StatusCode::FOUND,
+        TRANSPORT_LOCATION,
+        format!("http::unix//{}/admin", socket.display()),
+    )])
+    .await;
+    let start = TransportUrl::parse(&format!("http://{}/bounce", addr)).unwrap();
+    let connector = ComposedConnector::new();
+
+    let err = follow_redirects(&connector, start.clone(), 5).await.unwrap_err();
+    assert!(
+        matches!(err, TransportError::RedirectDenied { from: Transport::Tcp, to: Transport::Unix }),
+        "{}",
+        err
+    );
+
+    let options = RedirectOptions::new().with_unsafe_transport_change(true);
+    let (last, response) = follow_redirects_with_options(&connector, start, 5, options).await.unwrap();
+    assert_eq!(last.transport(), Transport::Unix);
+    assert_eq!(body(response).await, "reached /admin");
+}
+
+/// Stands in for Tor by dialing a local TCP server
+struct FakeTor(std::net::SocketAddr);
+
+impl rigging::connector::Connector for FakeTor {
+    fn allows_url(&self, url: &TransportUrl) -> bool {
+        url.requires_tor()
+    }
+
+    fn connect<'a>(
+        &'a self,
+        _url: &'a TransportUrl,
+    ) -> futures::future::BoxFuture<'a, Result<Box<dyn rigging::connector::AsyncReadWrite>, TransportError>> {
+        Box::pin(async move {
+            let stream = tokio::net::TcpStream::connect(self.0).await?;
+            Ok(Box::new(stream) as Box<dyn rigging::connector::AsyncReadWrite>)
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_redirect_leaving_tor_is_refused() {
+    let clearnet = serve_tcp(Vec::new()).await;
+    let onion = serve_tcp(vec![(
+        "/leave",
+        StatusCode::FOUND,
+        "location",
+        format!("http://{}/exposed", clearnet),
+    )])
+    .await;
+    let mut connector = ComposedConnector::new();
+    connector.register(Transport::Tor, Box::new(FakeTor(onion)));
+    let start = TransportUrl::parse("http::tor//rigging4test.onion/leave").unwrap();
+
+    let err = follow_redirects(&connector, start.clone(), 5).await.unwrap_err();
+    assert!(
+        matches!(err, TransportError::RedirectDenied { from: Transport::Tor, to: Transport::Tcp }),
+        "{}",
+        err
+    );
+
+    let options = RedirectOptions::new().with_unsafe_transport_change(true);
+    let (last, response) = follow_redirects_with_options(&connector, start, 5, options).await.unwrap();
+    assert_eq!(last.transport(), Transport::Tcp);
+    assert_eq!(body(response).await, "reached /exposed");
+}
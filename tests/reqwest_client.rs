@@ -0,0 +1,45 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Integration test: reqwest client over a Unix socket transport
+
+#![cfg(feature = "reqwest")]
+
+use http_body_util::Full;
+use hyper::body::Bytes;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Request, Response};
+use hyper_util::rt::TokioIo;
+use rigging::composed::ComposedConnector;
+use rigging::reqwest_client::TransportClient;
+use rigging::TransportUrl;
+use std::convert::Infallible;
+use tokio::net::UnixListener;
+
+#[tokio::test]
+async fn test_reqwest_get_over_unix_socket() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("app.sock");
+    let listener = UnixListener::bind(&socket).unwrap();
+
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let service = service_fn(|req: Request<hyper::body::Incoming>| async move {
+                let body = format!("hello from {}", req.uri().path());
+                Ok::<_, Infallible>(Response::new(Full::new(Bytes::from(body))))
+            });
+            tokio::spawn(http1::Builder::new().serve_connection(TokioIo::new(stream), service));
+        }
+    });
+
+    let url = TransportUrl::parse(&format!("http::unix//{}/", socket.display())).unwrap();
+    let client = TransportClient::new(&ComposedConnector::new(), &url).unwrap();
+    assert_eq!(client.base_url().host_str(), Some("127.0.0.1"));
+
+    let response = client.get("/greeting").unwrap().send().await.unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(response.text().await.unwrap(), "hello from /greeting");
+}
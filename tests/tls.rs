@@ -0,0 +1,308 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Integration test: TLS and mutual TLS against self-signed certificates
+
+#![cfg(feature = "tls")]
+
+use rigging::composed::{ComposedConfig, ComposedConnector, Connection};
+use rigging::tls_connector::TlsTcpConnector;
+use rigging::types::TransportError;
+use rigging::transport_url::ParseOptions;
+use rigging::{PeerInfo, TransportUrl};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use rustls::server::WebPkiClientVerifier;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+
+/// A self-signed certificate and its key
+fn self_signed(name: &str) -> (CertificateDer<'static>, PrivateKeyDer<'static>) {
+    let certified = rcgen::generate_simple_self_signed(vec![name.to_string()]).unwrap();
+    let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(certified.key_pair.serialize_der()));
+    (certified.cert.der().clone(), key)
+}
+
+/// Acceptor for `name`, requiring a certificate signed by `client_root`
+/// if given; returns the acceptor and the server certificate
+fn acceptor(name: &str, client_root: Option<CertificateDer<'static>>) -> (TlsAcceptor, CertificateDer<'static>) {
+    let provider = Arc::new(rustls::crypto::aws_lc_rs::default_provider());
+    let (cert, key) = self_signed(name);
+
+    let builder = rustls::ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .unwrap();
+    let builder = match client_root {
+        Some(root) => {
+            let mut roots = rustls::RootCertStore::empty();
+            roots.add(root).unwrap();
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                .build()
+                .unwrap();
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+    let config = builder.with_single_cert(vec![cert.clone()], key).unwrap();
+    (TlsAcceptor::from(Arc::new(config)), cert)
+}
+
+/// Complete the handshake on `stream` and echo one 5-byte message
+async fn serve_echo<S>(acceptor: TlsAcceptor, stream: S)
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    // Handshakes rejected by either side fail here; that is expected
+    if let Ok(mut tls) = acceptor.accept(stream).await {
+        let mut buf = [0u8; 5];
+        if tls.read_exact(&mut buf).await.is_ok() {
+            let _ = tls.write_all(&buf).await;
+            let _ = tls.flush().await;
+        }
+    }
+}
+
+/// Start a TLS echo server on TCP, returning its port and certificate
+async fn tls_echo_server_with(client_root: Option<CertificateDer<'static>>) -> (u16, CertificateDer<'static>) {
+    let (acceptor, cert) = acceptor("localhost", client_root);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        while let Ok((tcp, _)) = listener.accept().await {
+            tokio::spawn(serve_echo(acceptor.clone(), tcp));
+        }
+    });
+    (port, cert)
+}
+
+/// Start a TLS echo server for "localhost", returning its port and certificate
+async fn tls_echo_server() -> (u16, CertificateDer<'static>) {
+    tls_echo_server_with(None).await
+}
+
+fn trusting(cert: CertificateDer<'static>) -> rustls::RootCertStore {
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add(cert).unwrap();
+    roots
+}
+
+async fn assert_echo(mut conn: Connection) {
+    conn.write_all(b"hello").await.unwrap();
+    conn.flush().await.unwrap();
+    let mut buf = [0u8; 5];
+    conn.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"hello");
+}
+
+#[tokio::test]
+async fn test_tls_with_custom_root() {
+    let (port, cert) = tls_echo_server().await;
+
+    let connector = ComposedConnector::with_config(ComposedConfig {
+        tls: Some(TlsTcpConnector::with_root_store(trusting(cert))),
+        ..ComposedConfig::default()
+    });
+    let conn = connector
+        .connect(&format!("https::tcp//localhost:{}/", port))
+        .await
+        .unwrap();
+    assert!(matches!(conn, Connection::Tls(_)));
+    assert!(matches!(conn.peer_info(), PeerInfo::Tcp(addr) if addr.port() == port));
+    assert_echo(conn).await;
+}
+
+#[tokio::test]
+async fn test_tls_rejects_untrusted_certificate() {
+    let (port, _) = tls_echo_server().await;
+
+    // The default Mozilla roots do not include the self-signed certificate
+    let result = ComposedConnector::new()
+        .connect(&format!("https::tcp//localhost:{}/", port))
+        .await;
+    assert!(matches!(result, Err(TransportError::TlsError(_))));
+}
+
+#[tokio::test]
+async fn test_tls_host_name_must_match() {
+    let (port, cert) = tls_echo_server().await;
+
+    // The certificate names "localhost", not the IP address
+    let connector = TlsTcpConnector::with_root_store(trusting(cert));
+    let result = connector.connect("127.0.0.1", port).await;
+    assert!(matches!(result, Err(TransportError::TlsError(_))));
+}
+
+#[tokio::test]
+async fn test_tls_without_verification() {
+    let (port, _) = tls_echo_server().await;
+
+    let connector = ComposedConnector::with_config(ComposedConfig {
+        tls: Some(TlsTcpConnector::danger_accept_invalid_certs()),
+        ..ComposedConfig::default()
+    });
+    let conn = connector
+        .connect(&format!("https::tcp//127.0.0.1:{}/", port))
+        .await
+        .unwrap();
+    assert_echo(conn).await;
+}
+
+#[tokio::test]
+async fn test_implicit_https_stays_plain_tcp() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let (conn, _) = tokio::join!(
+        ComposedConnector::new().connect(&format!("https://127.0.0.1:{}/", port)),
+        listener.accept()
+    );
+    assert!(matches!(conn.unwrap(), Connection::Tcp(_)));
+}
+
+#[tokio::test]
+async fn test_mutual_tls_requires_client_certificate() {
+    let (client_cert, client_key) = self_signed("client");
+    let (port, server_cert) = tls_echo_server_with(Some(client_cert.clone())).await;
+    let url = format!("https::tcp//localhost:{}/", port);
+
+    let with_cert = TlsTcpConnector::with_root_store(trusting(server_cert.clone()))
+        .with_client_auth(vec![client_cert], client_key)
+        .unwrap();
+    let connector = ComposedConnector::with_config(ComposedConfig {
+        tls: Some(with_cert),
+        ..ComposedConfig::default()
+    });
+    assert_echo(connector.connect(&url).await.unwrap()).await;
+
+    // Under TLS 1.3 the server rejects the missing certificate after the
+    // client's handshake completes, so the failure surfaces on first read
+    let connector = ComposedConnector::with_config(ComposedConfig {
+        tls: Some(TlsTcpConnector::with_root_store(trusting(server_cert))),
+        ..ComposedConfig::default()
+    });
+    let rejected = match connector.connect(&url).await {
+        Err(e) => matches!(e, TransportError::TlsError(_)),
+        Ok(mut conn) => {
+            let _ = conn.write_all(b"hello").await;
+            let mut buf = [0u8; 5];
+            conn.read_exact(&mut buf).await.is_err()
+        }
+    };
+    assert!(rejected);
+}
+
+#[test]
+fn test_client_auth_needs_builtin_config() {
+    let (client_cert, client_key) = self_signed("client");
+    let custom = TlsTcpConnector::new().client_config().clone();
+    let result = TlsTcpConnector::with_client_config(custom).with_client_auth(vec![client_cert], client_key);
+    assert!(matches!(result, Err(TransportError::TlsError(_))));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_mutual_tls_over_unix_socket() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("tls.sock");
+
+    let (client_cert, client_key) = self_signed("client");
+    let (acceptor, server_cert) = acceptor("localhost", Some(client_cert.clone()));
+    let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        serve_echo(acceptor, stream).await;
+    });
+
+    let connector = TlsTcpConnector::with_root_store(trusting(server_cert))
+        .with_client_auth(vec![client_cert], client_key)
+        .unwrap();
+    let unix = tokio::net::UnixStream::connect(&socket).await.unwrap();
+    let mut conn = connector.handshake("localhost", unix).await.unwrap();
+
+    conn.write_all(b"hello").await.unwrap();
+    conn.flush().await.unwrap();
+    let mut buf = [0u8; 5];
+    conn.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"hello");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_sni_carries_public_host_over_unix_socket() {
+    use tokio::net::UnixStream;
+
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("api.sock");
+
+    let (acceptor, cert) = acceptor("api.example.com", None);
+    let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+    let (sni_tx, mut sni_rx) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            if let Ok(mut tls) = acceptor.accept(stream).await {
+                let sni = tls.get_ref().1.server_name().map(str::to_string);
+                sni_tx.send(sni).unwrap();
+                let _ = tls.write_all(b"ok").await;
+                let _ = tls.flush().await;
+            }
+        }
+    });
+
+    // A mapped host keeps its public name even though the stream is a socket
+    let connector = TlsTcpConnector::with_root_store(trusting(cert));
+    let url = TransportUrl::parse("https://api.example.com/v1").unwrap();
+    let stream = UnixStream::connect(&socket).await.unwrap();
+    let mut conn = connector.handshake_url(&url, stream).await.unwrap();
+    let mut buf = [0u8; 2];
+    conn.read_exact(&mut buf).await.unwrap();
+    assert_eq!(sni_rx.recv().await.unwrap().as_deref(), Some("api.example.com"));
+
+    // An explicit socket URL has no public name unless one is configured
+    let url = TransportUrl::parse(&format!("https::unix//{}/v1", socket.display())).unwrap();
+    let stream = UnixStream::connect(&socket).await.unwrap();
+    let result = connector.handshake_url(&url, stream).await;
+    assert!(matches!(result, Err(TransportError::TlsError(_))));
+
+    let connector = connector.with_server_name("api.example.com");
+    let stream = UnixStream::connect(&socket).await.unwrap();
+    let mut conn = connector.handshake_url(&url, stream).await.unwrap();
+    conn.read_exact(&mut buf).await.unwrap();
+    assert_eq!(sni_rx.recv().await.unwrap().as_deref(), Some("api.example.com"));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_https_unix_url_without_downgrade_uses_tls() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("tls.sock");
+
+    let (acceptor, cert) = acceptor("localhost", None);
+    let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(serve_echo(acceptor.clone(), stream));
+        }
+    });
+
+    let connector = ComposedConnector::with_config(ComposedConfig {
+        tls: Some(TlsTcpConnector::with_root_store(trusting(cert)).with_server_name("localhost")),
+        ..ComposedConfig::default()
+    });
+    let url_str = format!("https::unix//{}/", socket.display());
+
+    let options = ParseOptions::new().with_downgrade_local_tls(false);
+    let url = TransportUrl::parse_with_options(&url_str, options).unwrap();
+    assert_eq!(url.scheme(), "https");
+    let conn = connector.connect_url(&url).await.unwrap();
+    assert!(matches!(conn, Connection::Tls(_)));
+    assert!(matches!(conn.peer_info(), PeerInfo::Unix(ref path) if path == &socket));
+    assert_echo(conn).await;
+
+    // Downgraded by default: plain bytes over the socket, no handshake
+    let url = TransportUrl::parse(&url_str).unwrap();
+    assert_eq!(url.scheme(), "http");
+    let conn = connector.connect_url(&url).await.unwrap();
+    assert!(matches!(conn, Connection::Unix(_)));
+}
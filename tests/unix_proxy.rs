@@ -0,0 +1,206 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Integration test: fetching Unix socket content through the localhost proxy
+
+#![cfg(feature = "unix")]
+
+use http_body_util::Full;
+use hyper::body::Bytes;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Request, Response};
+use hyper_util::rt::TokioIo;
+use rigging::proxy::UnixProxy;
+use rigging::unix_connector::SocketMapping;
+use rigging::{TransportUrl, UnixConnector};
+use std::convert::Infallible;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UnixListener};
+
+#[tokio::test]
+async fn test_proxy_serves_unix_socket_content() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("app.sock");
+    let listener = UnixListener::bind(&socket).unwrap();
+
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let service = service_fn(|req: Request<hyper::body::Incoming>| async move {
+                let body = format!("served {}", req.uri());
+                Ok::<_, Infallible>(Response::new(Full::new(Bytes::from(body))))
+            });
+            tokio::spawn(http1::Builder::new().serve_connection(TokioIo::new(stream), service));
+        }
+    });
+
+    let url = TransportUrl::parse(&format!("http::unix//{}/page?x=1", socket.display())).unwrap();
+    let proxy = UnixProxy::start(UnixConnector::new(&socket)).unwrap();
+
+    let proxied = proxy.url_for(&url);
+    assert_eq!(proxied, format!("http://{}/page?x=1", proxy.local_addr()));
+
+    // Fetch the page the way the webview would: plain HTTP over TCP
+    let request = format!(
+        "GET /page?x=1 HTTP/1.1\r\nHost: localhost\r\n{}Connection: close\r\n\r\n",
+        cookie(&proxy)
+    );
+    let response = send(&proxy, &request).await;
+
+    assert!(response.starts_with("HTTP/1.1 200"));
+    assert!(response.ends_with("served /page?x=1"));
+
+    let addr = proxy.local_addr();
+    proxy.shutdown();
+    assert!(TcpStream::connect(addr).await.is_err());
+}
+
+#[tokio::test]
+async fn test_proxy_injects_extra_headers() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("app.sock");
+    let listener = UnixListener::bind(&socket).unwrap();
+
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let service = service_fn(|req: Request<hyper::body::Incoming>| async move {
+                let header = |name: &str| {
+                    req.headers()
+                        .get(name)
+                        .and_then(|v| v.to_str().ok())
+                        .unwrap_or("missing")
+                        .to_string()
+                };
+                let body = format!("token={} cookie={}", header("x-rigging-token"), header("cookie"));
+                Ok::<_, Infallible>(Response::new(Full::new(Bytes::from(body))))
+            });
+            tokio::spawn(http1::Builder::new().serve_connection(TokioIo::new(stream), service));
+        }
+    });
+
+    let headers = vec![("X-Rigging-Token".to_string(), "secret".to_string())];
+    let proxy = UnixProxy::start_with_headers(UnixConnector::new(&socket), &headers).unwrap();
+
+    // The configured value replaces one sent by the browser, and the proxy's
+    // own cookie is taken out of the ones passed on
+    let request = format!(
+        "GET / HTTP/1.1\r\nHost: localhost\r\nX-Rigging-Token: forged\r\nCookie: a=1; rigging-proxy-{}={}; b=2\r\nConnection: close\r\n\r\n",
+        proxy.local_addr().port(),
+        proxy.token()
+    );
+    let response = send(&proxy, &request).await;
+
+    assert!(response.starts_with("HTTP/1.1 200"));
+    assert!(response.ends_with("token=secret cookie=a=1; b=2"));
+}
+
+#[test]
+fn test_proxy_rejects_invalid_headers() {
+    let headers = vec![("Bad Header".to_string(), "value".to_string())];
+    let result = UnixProxy::start_with_headers(UnixConnector::new("/tmp/unused.sock"), &headers);
+    assert!(matches!(result, Err(rigging::TransportError::InvalidHeader(_))));
+}
+
+/// Serve HTTP on `socket`, answering every request with `name` and its target
+fn serve_named(socket: &std::path::Path, name: &'static str) {
+    let listener = UnixListener::bind(socket).unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let service = service_fn(move |req: Request<hyper::body::Incoming>| async move {
+                Ok::<_, Infallible>(Response::new(Full::new(Bytes::from(format!("{} {}", name, req.uri())))))
+            });
+            tokio::spawn(http1::Builder::new().serve_connection(TokioIo::new(stream), service));
+        }
+    });
+}
+
+/// `Cookie` header line carrying the proxy's token
+fn cookie(proxy: &UnixProxy) -> String {
+    format!("Cookie: rigging-proxy-{}={}\r\n", proxy.local_addr().port(), proxy.token())
+}
+
+/// Send `request` to the proxy and return the whole response
+async fn send(proxy: &UnixProxy, request: &str) -> String {
+    let mut stream = TcpStream::connect(proxy.local_addr()).await.unwrap();
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response
+}
+
+/// Send `request` to the proxy and return the response body
+async fn fetch(proxy: &UnixProxy, request: &str) -> String {
+    let response = send(proxy, request).await;
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    response.split("\r\n\r\n").nth(1).unwrap().to_string()
+}
+
+#[tokio::test]
+async fn test_proxy_routes_by_host() {
+    let dir = tempfile::tempdir().unwrap();
+    serve_named(&dir.path().join("app.sock"), "app");
+    serve_named(&dir.path().join("api.sock"), "api");
+    serve_named(&dir.path().join("auth.sock"), "auth");
+
+    let mut mapping = SocketMapping::new();
+    mapping.add_mapping("api.local", dir.path().join("api.sock"));
+    mapping.add_mapping("auth.local", dir.path().join("auth.sock"));
+    let proxy = UnixProxy::start_with_mapping(UnixConnector::new(dir.path().join("app.sock")), mapping, &[]).unwrap();
+
+    let auth = cookie(&proxy);
+    let get = |host: &str| format!("GET /x HTTP/1.1\r\nHost: {}\r\n{}Connection: close\r\n\r\n", host, auth);
+    assert_eq!(fetch(&proxy, &get("api.local")).await, "api /x");
+    assert_eq!(fetch(&proxy, &get("auth.local:8080")).await, "auth /x");
+    // The proxy's own address goes to the primary socket
+    assert_eq!(fetch(&proxy, &get(&proxy.local_addr().to_string())).await, "app /x");
+    // Other hosts are refused, so rebound DNS names cannot reach it
+    assert!(send(&proxy, &get("other.local")).await.starts_with("HTTP/1.1 403"));
+
+    // As an HTTP proxy the target names the host, unmapped ones going to the
+    // primary socket; upstream sees origin form
+    let credentials = format!("Proxy-Authorization: {}\r\n", proxy.proxy_authorization());
+    let absolute = |target: &str| {
+        format!("GET {} HTTP/1.1\r\nHost: x\r\n{}Connection: close\r\n\r\n", target, credentials)
+    };
+    assert_eq!(fetch(&proxy, &absolute("http://auth.local/login?next=1")).await, "auth /login?next=1");
+    assert_eq!(fetch(&proxy, &absolute("http://other.local/")).await, "app /");
+}
+
+#[tokio::test]
+async fn test_proxy_requires_token() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("app.sock");
+    serve_named(&socket, "app");
+
+    let headers = vec![("Authorization".to_string(), "Bearer app-secret".to_string())];
+    let proxy = UnixProxy::start_with_headers(UnixConnector::new(&socket), &headers).unwrap();
+
+    // Without the token nothing is forwarded
+    let plain = "GET /x HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n";
+    assert!(send(&proxy, plain).await.starts_with("HTTP/1.1 403"));
+    let wrong = format!(
+        "GET /x HTTP/1.1\r\nHost: localhost\r\nCookie: rigging-proxy-{}=guess\r\nConnection: close\r\n\r\n",
+        proxy.local_addr().port()
+    );
+    assert!(send(&proxy, &wrong).await.starts_with("HTTP/1.1 403"));
+    let proxied = "GET http://localhost/x HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n";
+    let response = send(&proxy, proxied).await;
+    assert!(response.starts_with("HTTP/1.1 407"), "{}", response);
+    assert!(response.to_ascii_lowercase().contains("proxy-authenticate: basic"));
+
+    // The login URL sets the cookie and redirects to the page
+    let url = TransportUrl::parse(&format!("http::unix//{}/x?y=1", socket.display())).unwrap();
+    let login = proxy.login_url_for(&url);
+    let path = &login[format!("http://{}", proxy.local_addr()).len()..];
+    let response = send(&proxy, &format!("GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n", path)).await;
+    assert!(response.starts_with("HTTP/1.1 303"), "{}", response);
+    assert!(response.contains("location: /x?y=1"));
+    assert!(response.contains(&format!("set-cookie: rigging-proxy-{}={};", proxy.local_addr().port(), proxy.token())));
+
+    let request = format!("GET /x HTTP/1.1\r\nHost: localhost\r\n{}Connection: close\r\n\r\n", cookie(&proxy));
+    assert_eq!(fetch(&proxy, &request).await, "app /x");
+}
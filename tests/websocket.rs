@@ -0,0 +1,82 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Integration test: WebSocket echo over a Unix socket transport
+
+#![cfg(feature = "unix")]
+
+use futures::{SinkExt, StreamExt};
+use rigging::composed::ComposedConnector;
+use rigging::TransportUrl;
+use tokio::net::UnixListener;
+use tokio_tungstenite::tungstenite::Message;
+
+#[tokio::test]
+async fn test_websocket_echo_over_unix_socket() {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("app.sock");
+    let listener = UnixListener::bind(&socket).unwrap();
+
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+        while let Some(Ok(message)) = ws.next().await {
+            if message.is_text() || message.is_binary() {
+                ws.send(message).await.unwrap();
+            }
+        }
+    });
+
+    // wss is downgraded to ws for the local socket
+    let url = TransportUrl::parse(&format!("wss::unix//{}/ws", socket.display())).unwrap();
+    let (request, connection) = ComposedConnector::new().websocket_stream(&url).await.unwrap();
+    assert_eq!(request, "ws://localhost/ws");
+
+    let (mut ws, response) = tokio_tungstenite::client_async(request, connection).await.unwrap();
+    assert_eq!(response.status(), 101);
+
+    ws.send(Message::text("hello")).await.unwrap();
+    let echoed = ws.next().await.unwrap().unwrap();
+    assert_eq!(echoed.into_text().unwrap(), "hello");
+}
+
+#[cfg(feature = "websocket")]
+#[tokio::test]
+async fn test_connect_websocket_over_unix_socket() {
+    use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("app.sock");
+    let listener = UnixListener::bind(&socket).unwrap();
+
+    let (headers_tx, headers_rx) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let check = move |request: &Request, response: Response| -> Result<Response, ErrorResponse> {
+            let header = |name: &str| {
+                request.headers().get(name).and_then(|v| v.to_str().ok()).map(str::to_string)
+            };
+            let _ = headers_tx.send((request.uri().to_string(), header("host"), header("origin")));
+            Ok(response)
+        };
+        let mut ws = tokio_tungstenite::accept_hdr_async(stream, check).await.unwrap();
+        while let Some(Ok(message)) = ws.next().await {
+            if message.is_text() || message.is_binary() {
+                ws.send(message).await.unwrap();
+            }
+        }
+    });
+
+    let url = TransportUrl::parse(&format!("ws::unix//{}/events?topic=a", socket.display())).unwrap();
+    let mut ws = ComposedConnector::new().connect_websocket(&url).await.unwrap();
+
+    let (path, host, origin) = headers_rx.await.unwrap();
+    assert_eq!(path, "/events?topic=a");
+    assert_eq!(host.as_deref(), Some("localhost"));
+    assert_eq!(origin.as_deref(), Some("http://localhost"));
+
+    ws.send(Message::text("hello")).await.unwrap();
+    let echoed = ws.next().await.unwrap().unwrap();
+    assert_eq!(echoed.into_text().unwrap(), "hello");
+}
@@ -0,0 +1,82 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Integration test: persistent cookie storage with the webview backend
+
+#![cfg(all(feature = "webview", target_os = "linux"))]
+
+use rigging::embed::{BrowserBuilder, BrowserConfig, CookieStore};
+use rigging::BrowserEvent;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::path::Path;
+use std::sync::mpsc;
+
+/// Serve pages whose inline script is the request path; cookies need an HTTP origin
+fn serve_pages() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().map_while(Result::ok) {
+            let mut request = [0u8; 4096];
+            let n = stream.read(&mut request).unwrap_or(0);
+            let request = String::from_utf8_lossy(&request[..n]);
+            let script = match request.split_whitespace().nth(1) {
+                Some("/set") => "document.cookie = 'session=abc123; max-age=3600'; console.log('set')",
+                _ => "console.log('cookies=' + document.cookie)",
+            };
+            let body = format!("<script>{}</script>", script);
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+        }
+    });
+    format!("http://{}", addr)
+}
+
+/// Load `url` with a persistent store and return its console messages
+///
+/// A screenshot path makes the browser exit once the page has loaded. Both
+/// runs happen on the test thread, since GTK may only be used from one thread.
+fn load(url: &str, store: &Path) -> Vec<String> {
+    let config = BrowserConfig::new(url)
+        .with_screenshot(store.with_extension("png"))
+        .with_cookie_store(CookieStore::Persistent(store.to_path_buf()));
+
+    let (tx, rx) = mpsc::channel();
+    BrowserBuilder::new()
+        .config(config)
+        .on_event(move |event| {
+            let _ = tx.send(event);
+        })
+        .run()
+        .unwrap();
+
+    rx.try_iter()
+        .filter_map(|event| match event {
+            BrowserEvent::ConsoleMessage { message, .. } => Some(message),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+#[ignore = "requires a display for the webview backend"]
+fn test_persistent_cookie_survives_restart() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = dir.path().join("cookies");
+    let origin = serve_pages();
+
+    assert_eq!(load(&format!("{}/set", origin), &store), vec!["set"]);
+
+    let messages = load(&format!("{}/get", origin), &store);
+    assert!(
+        messages.iter().any(|m| m.contains("session=abc123")),
+        "console after restart: {:?}",
+        messages
+    );
+}
@@ -0,0 +1,48 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Integration test: headless screenshot capture with the webview backend
+
+#![cfg(all(feature = "webview", target_os = "linux"))]
+
+use rigging::embed::{BrowserBuilder, BrowserConfig};
+use rigging::BrowserEvent;
+use std::sync::mpsc;
+
+#[test]
+#[ignore = "requires a display for the webview backend"]
+fn test_screenshot_of_solid_color_page() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("page.png");
+
+    let config = BrowserConfig::new(
+        "data:text/html,<body style='margin:0;background:rgb(255,0,0)'></body>",
+    )
+    .with_size(200, 100)
+    .with_screenshot(&path);
+
+    let (tx, rx) = mpsc::channel();
+    BrowserBuilder::new()
+        .config(config)
+        .on_event(move |event| {
+            let _ = tx.send(event);
+        })
+        .run()
+        .unwrap();
+
+    let events: Vec<_> = rx.try_iter().collect();
+    assert!(matches!(events.last(), Some(BrowserEvent::Shutdown)));
+    assert!(!events.iter().any(|event| matches!(event, BrowserEvent::Error { .. })));
+
+    let mut file = std::fs::File::open(&path).unwrap();
+    let mut image = cairo::ImageSurface::create_from_png(&mut file).unwrap();
+    assert_eq!((image.width(), image.height()), (200, 100));
+
+    // ARGB32 pixels are stored as native-endian u32s: B, G, R, A on little-endian
+    let stride = image.stride() as usize;
+    let data = image.data().unwrap();
+    let offset = 50 * stride + 100 * 4;
+    let pixel = u32::from_ne_bytes(data[offset..offset + 4].try_into().unwrap());
+    assert_eq!(pixel, 0xFFFF_0000);
+}
@@ -0,0 +1,69 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Integration test: userscripts injected by the webview backend
+
+#![cfg(all(feature = "webview", target_os = "linux"))]
+
+use rigging::embed::{BrowserBuilder, BrowserConfig};
+use rigging::BrowserEvent;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::mpsc;
+
+/// Serve an untitled page
+fn serve_page() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().map_while(Result::ok) {
+            let mut request = [0u8; 4096];
+            let _ = stream.read(&mut request);
+            let body = "<p>untitled</p>";
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+        }
+    });
+    format!("http://{}/", addr)
+}
+
+#[test]
+#[ignore = "requires a display for the webview backend"]
+fn test_userscript_sets_title() {
+    let dir = tempfile::tempdir().unwrap();
+    // Sorted order: the second script sees what the first set
+    std::fs::write(dir.path().join("01-title.js"), "document.title = 'injected';").unwrap();
+    std::fs::write(
+        dir.path().join("02-suffix.js"),
+        "window.addEventListener('DOMContentLoaded', () => { document.title += ' by userscript'; });",
+    )
+    .unwrap();
+    std::fs::write(dir.path().join("03-ignored.txt"), "document.title = 'wrong';").unwrap();
+
+    let config = BrowserConfig::new(serve_page())
+        .with_userscripts_dir(dir.path())
+        .with_screenshot(dir.path().join("shot.png"));
+
+    let (tx, rx) = mpsc::channel();
+    BrowserBuilder::new()
+        .config(config)
+        .on_event(move |event| {
+            let _ = tx.send(event);
+        })
+        .run()
+        .unwrap();
+
+    let titles: Vec<String> = rx
+        .try_iter()
+        .filter_map(|event| match event {
+            BrowserEvent::TitleChanged { title } => Some(title),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(titles.last().map(String::as_str), Some("injected by userscript"), "titles: {:?}", titles);
+}